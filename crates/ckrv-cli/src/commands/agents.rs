@@ -0,0 +1,486 @@
+//! Agents commands - manage and health-check the agents in agents.yaml.
+
+use std::path::Path;
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use ckrv_core::agent_config::{AgentConfig, AgentsFile, OpenRouterConfig};
+use ckrv_core::runner::{self, RunnerConfig, WorkflowRunner};
+use ckrv_core::secrets::{secret_reference, SecretResolver, SECRET_PREFIX};
+use ckrv_core::{AgentProfile, AgentType, AgentUsage, Config, SecretStore};
+
+use crate::ui::components::RichTable;
+use crate::ui::{Renderable, UiContext};
+
+/// Prompt sent by `ckrv agents test`; small enough to cost next to nothing.
+const PROBE_PROMPT: &str = "Reply with the single word OK and nothing else. Do not edit any files.";
+
+/// Arguments for the agents command
+#[derive(Args)]
+pub struct AgentsArgs {
+    #[command(subcommand)]
+    pub command: AgentsCommand,
+}
+
+/// Agents subcommands
+#[derive(Subcommand)]
+pub enum AgentsCommand {
+    /// List configured agents
+    List,
+    /// Check an agent's binary and credentials, then send it a tiny prompt
+    Test {
+        /// Agent ID, name or model (defaults to the default agent)
+        agent: Option<String>,
+
+        /// Run the prompt locally instead of in the Docker sandbox
+        #[arg(long)]
+        no_sandbox: bool,
+
+        /// Only check the binary and credentials
+        #[arg(long)]
+        skip_prompt: bool,
+
+        /// Seconds to wait for the agent's reply
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+    },
+    /// Add an agent, or replace one with the same ID
+    Add {
+        /// Agent ID
+        id: String,
+
+        /// Agent type, e.g. claude, codex, gemini, aider or opencode
+        #[arg(long = "type", value_parser = parse_agent_type, default_value = "claude")]
+        agent_type: AgentType,
+
+        /// Display name (defaults to the ID)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Model to request
+        #[arg(long)]
+        model: Option<String>,
+
+        /// `OpenRouter` API key or a reference like secret:openrouter-main;
+        /// plain keys are moved into the project's secret store
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// `OpenRouter` base URL
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Path to the agent binary (defaults to the type's binary)
+        #[arg(long)]
+        binary: Option<String>,
+
+        /// Capability level from 1 (cheapest) to 5 (strongest)
+        #[arg(long, default_value = "3", value_parser = clap::value_parser!(u8).range(1..=5))]
+        level: u8,
+
+        /// Make this the default agent
+        #[arg(long)]
+        default: bool,
+    },
+    /// Remove an agent
+    Remove {
+        /// Agent ID
+        id: String,
+    },
+    /// Make an agent the default
+    SetDefault {
+        /// Agent ID
+        id: String,
+    },
+}
+
+/// Row in `ckrv agents list`
+#[derive(Serialize, tabled::Tabled)]
+struct AgentRow {
+    #[tabled(rename = "ID")]
+    id: String,
+    #[tabled(rename = "Type")]
+    agent_type: String,
+    #[tabled(rename = "Model")]
+    model: String,
+    #[tabled(rename = "Level")]
+    level: u8,
+    #[tabled(rename = "Default")]
+    default: String,
+    #[tabled(rename = "Enabled")]
+    enabled: String,
+}
+
+/// Result of one health check
+#[derive(Serialize)]
+struct CheckOutput {
+    ok: bool,
+    detail: String,
+}
+
+/// Result of the test prompt
+#[derive(Serialize)]
+struct PromptOutput {
+    ok: bool,
+    sandboxed: bool,
+    latency_ms: u64,
+    timed_out: bool,
+    response: String,
+    usage: Option<AgentUsage>,
+}
+
+/// JSON output for agents test
+#[derive(Serialize)]
+struct AgentTestOutput {
+    agent: String,
+    agent_type: AgentType,
+    success: bool,
+    binary: CheckOutput,
+    credentials: CheckOutput,
+    prompt: Option<PromptOutput>,
+}
+
+fn parse_agent_type(raw: &str) -> Result<AgentType, String> {
+    serde_json::from_value(serde_json::Value::String(raw.replace('-', "_")))
+        .map_err(|_| format!("unknown agent type '{raw}'"))
+}
+
+/// Execute the agents command
+pub async fn execute(args: AgentsArgs, json: bool, ui: &UiContext) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    match args.command {
+        AgentsCommand::List => execute_list(&cwd, json, ui),
+        AgentsCommand::Test {
+            agent,
+            no_sandbox,
+            skip_prompt,
+            timeout,
+        } => {
+            execute_test(
+                &cwd,
+                agent.as_deref(),
+                !no_sandbox,
+                skip_prompt,
+                timeout,
+                json,
+            )
+            .await
+        }
+        AgentsCommand::Add {
+            id,
+            agent_type,
+            name,
+            model,
+            api_key,
+            base_url,
+            binary,
+            level,
+            default,
+        } => {
+            let stored = match api_key.as_deref() {
+                Some(key) if is_plain_key(key) => Some(store_api_key(&cwd, &id, key)?),
+                _ => None,
+            };
+            let api_key = stored.clone().or(api_key);
+            let mut agent = AgentConfig::new(&id, name.unwrap_or_else(|| id.clone()), agent_type);
+            agent.level = level;
+            agent.binary_path = binary;
+            if model.is_some() || api_key.is_some() || base_url.is_some() {
+                agent.openrouter = Some(OpenRouterConfig {
+                    api_key,
+                    model: model.unwrap_or_default(),
+                    base_url,
+                    ..Default::default()
+                });
+            }
+            edit_agents(&cwd, json, |agents| {
+                let replaced = agents.agents.iter().any(|a| a.id == id);
+                agents.agents.retain(|a| a.id != id);
+                agents.agents.push(agent);
+                if default {
+                    set_default(agents, &id);
+                }
+                let verb = if replaced { "Updated" } else { "Added" };
+                Ok(stored.map_or_else(
+                    || format!("{verb} agent '{id}'"),
+                    |reference| format!("{verb} agent '{id}'; API key stored as {reference}"),
+                ))
+            })
+        }
+        AgentsCommand::Remove { id } => edit_agents(&cwd, json, |agents| {
+            let before = agents.agents.len();
+            agents.agents.retain(|a| a.id != id);
+            if agents.agents.len() == before {
+                anyhow::bail!("No agent with ID '{id}'");
+            }
+            Ok(format!("Removed agent '{id}'"))
+        }),
+        AgentsCommand::SetDefault { id } => edit_agents(&cwd, json, |agents| {
+            if agents.by_id(&id).is_none() {
+                anyhow::bail!("No agent with ID '{id}'");
+            }
+            set_default(agents, &id);
+            Ok(format!("'{id}' is now the default agent"))
+        }),
+    }
+}
+
+fn set_default(agents: &mut AgentsFile, id: &str) {
+    for agent in &mut agents.agents {
+        agent.is_default = agent.id == id;
+    }
+}
+
+/// Whether `key` is a literal key rather than a `secret:` or `${VAR}` reference.
+fn is_plain_key(key: &str) -> bool {
+    secret_reference(key).is_none() && !key.contains("${")
+}
+
+/// Save `key` in the project's secret store and return the reference that
+/// goes into agents.yaml in its place.
+fn store_api_key(cwd: &Path, id: &str, key: &str) -> anyhow::Result<String> {
+    let name: String = format!("{id}-api-key")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect();
+    let mut store = SecretStore::open(SecretStore::project_path(cwd))?;
+    store.set(&name, key)?;
+    store.save()?;
+    Ok(format!("{SECRET_PREFIX}{name}"))
+}
+
+/// Apply `edit` to agents.yaml as written (keeping `${VAR}` references) and
+/// save it.
+fn edit_agents(
+    cwd: &Path,
+    json: bool,
+    edit: impl FnOnce(&mut AgentsFile) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let path = AgentsFile::path_for_project(cwd);
+    let mut agents = AgentsFile::load_for_project_raw(cwd)?;
+    let message = edit(&mut agents)?;
+    agents.save(&path)?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "success": true,
+                "message": message,
+                "path": path,
+            }))?
+        );
+    } else {
+        println!("✓ {message} ({})", path.display());
+    }
+    Ok(())
+}
+
+fn execute_list(cwd: &Path, json: bool, ui: &UiContext) -> anyhow::Result<()> {
+    let agents = AgentsFile::load_for_project_raw(cwd)?;
+    let rows: Vec<AgentRow> = agents
+        .agents
+        .iter()
+        .map(|agent| AgentRow {
+            id: agent.id.clone(),
+            agent_type: agent.agent_type.backend().name().to_string(),
+            model: agent.model().unwrap_or("-").to_string(),
+            level: agent.level,
+            default: if agent.is_default { "✓" } else { "" }.to_string(),
+            enabled: if agent.enabled { "✓" } else { "✗" }.to_string(),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else if rows.is_empty() {
+        println!(
+            "No agents configured in {}",
+            AgentsFile::path_for_project(cwd).display()
+        );
+        println!("Add one with: ckrv agents add <id> --type claude");
+    } else {
+        println!(
+            "{}",
+            RichTable::new(tabled::Table::new(rows)).render(&ui.theme)
+        );
+    }
+    Ok(())
+}
+
+/// Check where the profile's credentials come from, making sure a `secret:`
+/// key actually resolves.
+fn check_credentials(cwd: &Path, profile: &AgentProfile) -> CheckOutput {
+    let result = profile
+        .agent_type
+        .backend()
+        .credentials(profile)
+        .and_then(|source| match profile.openrouter_api_key.as_deref() {
+            Some(key) if secret_reference(key).is_some() => SecretResolver::new(Some(cwd))
+                .resolve(key)
+                .map(|_| source)
+                .map_err(|e| e.to_string()),
+            _ => Ok(source),
+        });
+    match result {
+        Ok(detail) => CheckOutput { ok: true, detail },
+        Err(detail) => CheckOutput { ok: false, detail },
+    }
+}
+
+async fn execute_test(
+    cwd: &Path,
+    agent_arg: Option<&str>,
+    sandboxed: bool,
+    skip_prompt: bool,
+    timeout: u64,
+    json: bool,
+) -> anyhow::Result<()> {
+    let agents = AgentsFile::load_for_project(cwd)?;
+    let profile = match agent_arg {
+        Some(arg) => agents
+            .by_model(arg)
+            .map(AgentConfig::to_profile)
+            .ok_or_else(|| anyhow::anyhow!("No agent matching '{arg}' (see `ckrv agents list`)"))?,
+        // Without any configuration, test plain Claude Code like `ckrv task` would run
+        None => agents.default_agent().map_or_else(
+            || AgentProfile::new("claude", "claude"),
+            AgentConfig::to_profile,
+        ),
+    };
+    let backend = profile.agent_type.backend();
+
+    let binary = match runner::locate_agent_binary(&profile.binary) {
+        Some(path) => CheckOutput {
+            ok: true,
+            detail: path.display().to_string(),
+        },
+        // The sandbox image ships its own agent CLIs
+        None if sandboxed => CheckOutput {
+            ok: true,
+            detail: format!("{} not on this host; using the sandbox image's", profile.binary),
+        },
+        None => CheckOutput {
+            ok: false,
+            detail: format!("{} not found in common locations or PATH", profile.binary),
+        },
+    };
+    let credentials = check_credentials(cwd, &profile);
+
+    if !json {
+        println!("Testing agent '{}' ({})", profile.id, backend.name());
+        print_check("Binary", &binary);
+        print_check("Credentials", &credentials);
+    }
+
+    let prompt = if skip_prompt || !binary.ok {
+        None
+    } else {
+        if !json {
+            println!(
+                "  … sending test prompt{}",
+                if sandboxed { " in sandbox" } else { "" }
+            );
+        }
+        let config = RunnerConfig {
+            use_sandbox: sandboxed,
+            step_timeout_secs: timeout,
+            project_root: Some(cwd.to_path_buf()),
+            container_runtime: Config::load_project(cwd)?.container_runtime,
+            ..Default::default()
+        };
+        let workdir =
+            std::env::temp_dir().join(format!("ckrv-agent-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&workdir)?;
+        let result = WorkflowRunner::new(config)
+            .probe(&profile, PROBE_PROMPT, &workdir)
+            .await;
+        let _ = std::fs::remove_dir_all(&workdir);
+
+        Some(match result {
+            Ok(probe) => PromptOutput {
+                ok: probe.success,
+                sandboxed,
+                latency_ms: probe.duration_ms,
+                timed_out: probe.timed_out,
+                response: if probe.success || probe.stderr.trim().is_empty() {
+                    probe.output.trim().to_string()
+                } else {
+                    probe.stderr.trim().to_string()
+                },
+                usage: probe.usage,
+            },
+            Err(e) => PromptOutput {
+                ok: false,
+                sandboxed,
+                latency_ms: 0,
+                timed_out: false,
+                response: e.to_string(),
+                usage: None,
+            },
+        })
+    };
+
+    let success = binary.ok && credentials.ok && prompt.as_ref().map_or(true, |p| p.ok);
+
+    if json {
+        let output = AgentTestOutput {
+            agent: profile.id.clone(),
+            agent_type: profile.agent_type,
+            success,
+            binary,
+            credentials,
+            prompt,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if let Some(ref prompt) = prompt {
+        print_prompt(prompt, timeout);
+    }
+
+    if !success {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_check(label: &str, check: &CheckOutput) {
+    let mark = if check.ok { "✓" } else { "✗" };
+    println!("  {mark} {label}: {}", check.detail);
+}
+
+fn print_prompt(prompt: &PromptOutput, timeout: u64) {
+    if prompt.timed_out {
+        println!("  ✗ Prompt: no reply within {timeout}s");
+        return;
+    }
+    let mark = if prompt.ok { "✓" } else { "✗" };
+    let reply: String = prompt
+        .response
+        .lines()
+        .next()
+        .unwrap_or("")
+        .chars()
+        .take(120)
+        .collect();
+    println!("  {mark} Prompt: {reply} ({} ms)", prompt.latency_ms);
+
+    if let Some(ref usage) = prompt.usage {
+        let mut parts = Vec::new();
+        if let Some(input) = usage.input_tokens {
+            parts.push(format!("{input} in"));
+        }
+        if let Some(output) = usage.output_tokens {
+            parts.push(format!("{output} out"));
+        }
+        if let Some(total) = usage.total_tokens.filter(|_| parts.is_empty()) {
+            parts.push(format!("{total} total"));
+        }
+        if let Some(cost) = usage.cost_usd {
+            parts.push(format!("${cost:.4}"));
+        }
+        if !parts.is_empty() {
+            println!("    Usage: {}", parts.join(", "));
+        }
+    }
+}
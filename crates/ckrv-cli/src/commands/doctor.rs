@@ -0,0 +1,127 @@
+//! Doctor command - check the container runtime used for sandboxed agents.
+
+use clap::Args;
+use serde::Serialize;
+
+use ckrv_core::Config;
+use ckrv_sandbox::docker::DEFAULT_IMAGE;
+use ckrv_sandbox::{ContainerRuntime, RuntimeSource, UserMapping, SOCKET_ENV};
+
+/// Arguments for the doctor command
+#[derive(Args)]
+pub struct DoctorArgs {}
+
+/// Result of a single check
+#[derive(Serialize)]
+struct CheckOutput {
+    ok: bool,
+    detail: String,
+}
+
+/// JSON output for doctor
+#[derive(Serialize)]
+struct DoctorOutput {
+    success: bool,
+    runtime: ContainerRuntime,
+    user_mapping: UserMapping,
+    daemon: CheckOutput,
+    image: Option<CheckOutput>,
+}
+
+/// Execute the doctor command
+pub async fn execute(_args: DoctorArgs, json: bool) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let root = ckrv_git::repo_root(&cwd).unwrap_or(cwd);
+    let config = Config::load_project(&root)?;
+
+    let runtime = ContainerRuntime::resolve(&config.container_runtime);
+    let user_mapping = runtime.user_mapping();
+    let (daemon, image) = check_daemon(&runtime).await;
+    let success = daemon.ok && image.as_ref().is_some_and(|i| i.ok);
+
+    if json {
+        let output = DoctorOutput {
+            success,
+            runtime,
+            user_mapping,
+            daemon,
+            image,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("Container runtime");
+        println!("  Runtime: {}", runtime.kind.as_str());
+        println!("  Socket: {} ({})", runtime.socket, runtime.source.describe());
+        println!("  Rootless: {}", if runtime.rootless { "yes" } else { "no" });
+        println!("  User: {}", describe_user(&runtime, &user_mapping));
+        print_check("Daemon", &daemon);
+        if let Some(ref image) = image {
+            print_check("Image", image);
+        }
+    }
+
+    if !success {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Ping the runtime and, if it answers, look for the agent image.
+async fn check_daemon(runtime: &ContainerRuntime) -> (CheckOutput, Option<CheckOutput>) {
+    let docker = match runtime.connect() {
+        Ok(docker) => docker,
+        Err(e) => return (CheckOutput { ok: false, detail: e.to_string() }, None),
+    };
+    if let Err(e) = docker.ping().await {
+        let detail = if runtime.source == RuntimeSource::Default {
+            format!(
+                "no Docker or Podman socket found; set {SOCKET_ENV} or \
+                 container_runtime.socket in .chakravarti/config.json"
+            )
+        } else {
+            format!("not reachable at {}: {e}", runtime.socket)
+        };
+        return (CheckOutput { ok: false, detail }, None);
+    }
+
+    let detail = match docker.version().await {
+        Ok(version) => {
+            let engine = version
+                .components
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| c.name)
+                .next()
+                .unwrap_or_else(|| "Engine".to_string());
+            format!("{engine} {}", version.version.unwrap_or_default()).trim().to_string()
+        }
+        Err(_) => "reachable".to_string(),
+    };
+    let daemon = CheckOutput { ok: true, detail };
+
+    let image = match docker.inspect_image(DEFAULT_IMAGE).await {
+        Ok(_) => CheckOutput { ok: true, detail: DEFAULT_IMAGE.to_string() },
+        Err(_) => CheckOutput {
+            ok: false,
+            detail: format!("{DEFAULT_IMAGE} not found; build it before running sandboxed agents"),
+        },
+    };
+    (daemon, Some(image))
+}
+
+fn describe_user(runtime: &ContainerRuntime, mapping: &UserMapping) -> String {
+    match (&mapping.user, &mapping.userns_mode) {
+        (Some(user), Some(userns)) => format!("{user} (userns {userns})"),
+        (Some(user), None) if user == "0:0" && runtime.rootless => {
+            "root (container root maps to you)".to_string()
+        }
+        (Some(user), None) if user == "0:0" => "root".to_string(),
+        (Some(user), None) => user.clone(),
+        (None, _) => "image default".to_string(),
+    }
+}
+
+fn print_check(label: &str, check: &CheckOutput) {
+    let mark = if check.ok { "✓" } else { "✗" };
+    println!("  {mark} {label}: {}", check.detail);
+}
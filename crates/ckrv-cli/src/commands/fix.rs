@@ -5,6 +5,8 @@ use std::path::PathBuf;
 use clap::Args;
 use serde::{Deserialize, Serialize};
 
+use ckrv_core::mock_agent::{MockAgent, MockMode, WorkspaceSnapshot};
+
 use crate::ui::UiContext;
 use crate::ui::Renderable;
 use crate::ui::components::Banner;
@@ -266,6 +268,19 @@ struct FixResult {
 }
 
 async fn run_claude_fix(cwd: &PathBuf, prompt: &str, json: bool) -> anyhow::Result<FixResult> {
+    let mock = MockAgent::from_env(cwd)?;
+    if let Some(ref mock) = mock {
+        if mock.mode() == MockMode::Replay {
+            let transcript = mock.replay(prompt, cwd)?;
+            return Ok(fix_result(
+                transcript.success,
+                &transcript.stdout,
+                &transcript.stderr,
+                json,
+            ));
+        }
+    }
+
     // Check if claude CLI is available
     let claude_check = std::process::Command::new("claude")
         .arg("--version")
@@ -279,6 +294,11 @@ async fn run_claude_fix(cwd: &PathBuf, prompt: &str, json: bool) -> anyhow::Resu
         });
     }
 
+    let before = match mock {
+        Some(_) => Some(WorkspaceSnapshot::capture(cwd)?),
+        None => None,
+    };
+
     // Run Claude to analyze and fix issues
     // Use -p for prompt and --dangerously-skip-permissions to allow file edits
     let output = std::process::Command::new("claude")
@@ -289,6 +309,14 @@ async fn run_claude_fix(cwd: &PathBuf, prompt: &str, json: bool) -> anyhow::Resu
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
 
+    if let (Some(mock), Some(before)) = (mock, before) {
+        mock.record(prompt, cwd, &before, &stdout, &stderr, output.status.success())?;
+    }
+
+    Ok(fix_result(output.status.success(), &stdout, &stderr, json))
+}
+
+fn fix_result(success: bool, stdout: &str, stderr: &str, json: bool) -> FixResult {
     if !json {
         // Print Claude's response
         for line in stdout.lines() {
@@ -296,24 +324,24 @@ async fn run_claude_fix(cwd: &PathBuf, prompt: &str, json: bool) -> anyhow::Resu
         }
     }
 
-    if output.status.success() {
+    if success {
         // Count how many files were modified (heuristic based on output)
         let fixes_applied = stdout.matches("Updated").count()
             + stdout.matches("Modified").count()
             + stdout.matches("Created").count()
             + stdout.matches("Fixed").count();
 
-        Ok(FixResult {
+        FixResult {
             success: true,
             fixes_applied: fixes_applied.max(1), // At least 1 if successful
             message: "Fixes applied".to_string(),
-        })
+        }
     } else {
-        Ok(FixResult {
+        FixResult {
             success: false,
             fixes_applied: 0,
             message: stderr.lines().next().unwrap_or("Unknown error").to_string(),
-        })
+        }
     }
 }
 
@@ -41,6 +41,10 @@ const ENV_EXAMPLE_TEMPLATE: &str = r#"# Chakravarti API Keys
 # Custom model endpoint (optional)
 # CKRV_MODEL_ENDPOINT=https://api.example.com/v1/chat/completions
 # CKRV_MODEL_API_KEY=...
+
+# Agent keys can be kept out of agents.yaml in the encrypted store instead:
+#   ckrv secrets set openrouter-main
+# and referenced from agents.yaml as `api_key: secret:openrouter-main`
 "#;
 
 /// Default SWE workflow
@@ -254,7 +258,10 @@ pub async fn execute(args: InitArgs, json: bool, ui: &UiContext) -> anyhow::Resu
 /// Update .gitignore to ignore secrets but not structure files
 fn update_gitignore(repo_root: &std::path::Path) -> anyhow::Result<()> {
     let gitignore_path = repo_root.join(".gitignore");
-    let secrets_pattern = ".chakravarti/secrets/.env";
+    let secrets_patterns = [
+        ".chakravarti/secrets/.env",
+        ".chakravarti/secrets/secrets.enc",
+    ];
 
     // Read existing or start fresh
     let mut content = if gitignore_path.exists() {
@@ -263,13 +270,20 @@ fn update_gitignore(repo_root: &std::path::Path) -> anyhow::Result<()> {
         String::new()
     };
 
-    // Check if pattern already exists
-    if !content.contains(secrets_pattern) {
+    // Add whichever patterns are missing
+    let missing: Vec<&str> = secrets_patterns
+        .into_iter()
+        .filter(|pattern| !content.lines().any(|line| line.trim() == *pattern))
+        .collect();
+    if !missing.is_empty() {
         if !content.is_empty() && !content.ends_with('\n') {
             content.push('\n');
         }
         content.push_str("\n# Chakravarti secrets (API keys)\n");
-        content.push_str(".chakravarti/secrets/.env\n");
+        for pattern in missing {
+            content.push_str(pattern);
+            content.push('\n');
+        }
         std::fs::write(&gitignore_path, content)?;
     }
 
@@ -1,7 +1,9 @@
 //! CLI command modules.
 
+pub mod agents;
 pub mod cloud;
 pub mod diff;
+pub mod doctor;
 pub mod fix;
 pub mod init;
 pub mod logs;
@@ -10,12 +12,14 @@ pub mod promote;
 pub mod pull;
 pub mod report;
 pub mod run;
+pub mod secrets;
 pub mod spec;
 pub mod spec_structs;
 pub mod status;
 pub mod task;
 pub mod ui;
 pub mod verify;
+pub mod workflow;
 
 /// Emit a JSON value to stdout if requested.
 pub fn emit_json<T: serde::Serialize>(val: T, json: bool) {
@@ -1,15 +1,22 @@
 //! Plan command - generate execution plan using Claude Code in Docker.
 //!
 //! This command analyzes tasks.yaml and creates plan.yaml
-//! using Claude Code running inside a Docker container.
+//! using Claude Code running inside a Docker container, or through the
+//! model API when `generation` in config.json allows it.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Args;
 use anyhow::Context;
 
-use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
+use ckrv_core::Config;
+use ckrv_metrics::PricingCatalog;
+use ckrv_model::{PromptBudget, PromptSection};
+use ckrv_sandbox::{
+    ContainerRuntime, DefaultAllowList, DockerSandbox, ExecuteConfig, RuntimeConfig, Sandbox,
+};
 
+use crate::prompts::{price_note, prompt_budget, warn_if_trimmed, AGENT_MODEL};
 use crate::ui::UiContext;
 use crate::ui::Renderable;
 use crate::ui::components::Banner;
@@ -24,6 +31,10 @@ pub struct PlanArgs {
     /// Force regeneration even if plan.yaml already exists.
     #[arg(long, short)]
     pub force: bool,
+
+    /// Call the model API even when a cached response exists.
+    #[arg(long)]
+    pub no_cache: bool,
 }
 
 /// Execute the plan command.
@@ -100,15 +111,30 @@ pub async fn execute(args: PlanArgs, json: bool, ui: &UiContext) -> anyhow::Resu
         String::new()
     };
 
-    // Build the planning prompt
-    let prompt = build_planning_prompt(&tasks_content, &spec_content);
+    let root = ckrv_git::repo_root(&cwd).unwrap_or_else(|_| cwd.clone());
+    let config = Config::load_project(&root)?;
+    let pricing = PricingCatalog::load(&root);
+    if let Some(router) = crate::generation::api_router(&config, &root, !args.no_cache).await? {
+        let model = crate::generation::model(&router, &config);
+        let prompt = budgeted_planning_prompt(&root, &model, &tasks_content, &spec_content, &pricing, RESPOND_WITH_PLAN);
+        let spec_id = spec_dir.file_name().and_then(|n| n.to_str()).unwrap_or("unknown");
+        let metrics = crate::generation::GenerationMetrics::start(&root, spec_id, "plan");
+        let result = crate::generation::complete(&router, &config, &prompt, json).await;
+        metrics.save(&router, result.success);
+        if !result.success {
+            anyhow::bail!("Planning failed: {}", result.error);
+        }
+        std::fs::write(&plan_path, crate::prompts::strip_yaml_fences(result.output.trim()))?;
+    } else {
+        let prompt = budgeted_planning_prompt(&root, AGENT_MODEL, &tasks_content, &spec_content, &pricing, SAVE_PLAN);
 
-    if !json {
-        println!("🐳 Starting planning in Docker container...");
-    }
+        if !json {
+            println!("🐳 Starting planning in Docker container...");
+        }
 
-    // Execute planning in Docker (mounts ~/.claude for auth)
-    execute_planning_docker(&spec_dir, &prompt, json).await?;
+        // Execute planning in Docker (mounts ~/.claude for auth)
+        execute_planning_docker(&spec_dir, &prompt, &config.container_runtime, json).await?;
+    }
 
     if !json && plan_path.exists() {
         println!("\n✅ Plan generated successfully!");
@@ -119,8 +145,86 @@ pub async fn execute(args: PlanArgs, json: bool, ui: &UiContext) -> anyhow::Resu
     Ok(())
 }
 
+/// Closing instruction when an agent writes the plan itself.
+const SAVE_PLAN: &str = "Save your output as `plan.yaml` in the current directory.";
+
+/// Closing instruction when the plan comes back as the model's reply.
+const RESPOND_WITH_PLAN: &str = "Respond with the contents of `plan.yaml` only, without commentary.";
+
+/// The planning prompt with the tasks and spec fitted to `model`'s context
+/// window. The tasks claim the budget before the spec.
+fn budgeted_planning_prompt(
+    root: &Path,
+    model: &str,
+    tasks_yaml: &str,
+    spec_yaml: &str,
+    pricing: &PricingCatalog,
+    output: &str,
+) -> String {
+    let mut budget = prompt_budget(root, model, &build_planning_prompt("", "", pricing, output));
+    let (tasks, spec) = fit_tasks_and_spec(&mut budget, tasks_yaml, spec_yaml);
+    build_planning_prompt(&tasks, &spec, pricing, output)
+}
+
+/// Fit `tasks.yaml` and the spec to `budget`. Each task is its own section,
+/// so when they don't all fit the oldest are summarized, then dropped,
+/// first; the spec gets what is left.
+fn fit_tasks_and_spec(budget: &mut PromptBudget, tasks_yaml: &str, spec_yaml: &str) -> (String, String) {
+    let Some(mut sections) = task_sections(tasks_yaml) else {
+        // Not the usual shape: send it whole and let it be truncated
+        let fitted = budget.fit(&[
+            PromptSection::new("tasks.yaml", tasks_yaml).with_priority(1),
+            PromptSection::new("spec.yaml", spec_yaml),
+        ]);
+        warn_if_trimmed(&fitted);
+        return (fitted.sections[0].text.clone(), fitted.sections[1].text.clone());
+    };
+
+    sections.push(PromptSection::new("spec.yaml", spec_yaml).with_priority(-1));
+    let mut fitted = budget.fit(&sections);
+    warn_if_trimmed(&fitted);
+    let spec = fitted.sections.pop().map(|s| s.text).unwrap_or_default();
+    (format!("tasks:\n{}", fitted.join("")), spec)
+}
+
+/// One section per task in `tasks.yaml`, each a YAML list item with a
+/// summary of its ID, title and complexity. Later tasks have higher
+/// priority. `None` if the file has no `tasks` list.
+fn task_sections(tasks_yaml: &str) -> Option<Vec<PromptSection>> {
+    let file: serde_yaml::Value = serde_yaml::from_str(tasks_yaml).ok()?;
+    let tasks = file.get("tasks")?.as_sequence()?;
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let label = task
+                .get("id")
+                .and_then(serde_yaml::Value::as_str)
+                .map_or_else(|| format!("task {}", i + 1), String::from);
+            let summary: serde_yaml::Mapping = ["id", "title", "complexity"]
+                .into_iter()
+                .filter_map(|key| Some((key.into(), task.get(key)?.clone())))
+                .collect();
+            let text = serde_yaml::to_string(&[task]).ok()?;
+            let summary = serde_yaml::to_string(&[summary]).ok()?;
+            Some(
+                PromptSection::new(label, text)
+                    .with_summary(summary)
+                    .with_priority(i64::try_from(i).unwrap_or(i64::MAX)),
+            )
+        })
+        .collect()
+}
+
 /// Build the planning prompt from tasks and spec
-fn build_planning_prompt(tasks_yaml: &str, spec_yaml: &str) -> String {
+fn build_planning_prompt(
+    tasks_yaml: &str,
+    spec_yaml: &str,
+    pricing: &PricingCatalog,
+    output: &str,
+) -> String {
+    let minimax = price_note(pricing, "minimax/minimax-m2.1");
+    let glm = price_note(pricing, "z-ai/glm-4.7");
     format!(r#"You are an expert software architect. Analyze these development tasks and create an execution plan.
 
 ## CONTEXT
@@ -133,8 +237,8 @@ fn build_planning_prompt(tasks_yaml: &str, spec_yaml: &str) -> String {
 1. Group related tasks into logical batches
 2. Determine dependencies between batches
 3. Assign the best AI model for each batch based on complexity:
-   - 'minimax/minimax-m2.1' for simple/standard tasks
-   - 'z-ai/glm-4.7' for medium complexity
+   - 'minimax/minimax-m2.1'{minimax} for simple/standard tasks
+   - 'z-ai/glm-4.7'{glm} for medium complexity
    - 'claude' for high complexity or security-critical tasks
 
 ## OUTPUT FORMAT
@@ -168,14 +272,20 @@ batches:
     reasoning: "Depends on setup for project structure."
 ```
 
-IMPORTANT: Save your output as `plan.yaml` in the current directory.
-"#, spec_yaml = spec_yaml, tasks_yaml = tasks_yaml)
+IMPORTANT: {output}
+"#, spec_yaml = spec_yaml, tasks_yaml = tasks_yaml, output = output)
 }
 
 /// Execute planning using Docker sandbox with Claude Code
-async fn execute_planning_docker(spec_dir: &PathBuf, prompt: &str, json: bool) -> anyhow::Result<()> {
+async fn execute_planning_docker(
+    spec_dir: &PathBuf,
+    prompt: &str,
+    runtime: &RuntimeConfig,
+    json: bool,
+) -> anyhow::Result<()> {
     // Create Docker sandbox
-    let sandbox = DockerSandbox::with_defaults()
+    let runtime = ContainerRuntime::resolve(runtime);
+    let sandbox = DockerSandbox::with_runtime(DefaultAllowList::default(), &runtime)
         .context("Docker is required but not available. Please install and start Docker.")?;
     
     // Health check
@@ -245,3 +355,51 @@ async fn execute_planning_docker(spec_dir: &PathBuf, prompt: &str, json: bool) -
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKS: &str = r#"
+tasks:
+  - id: T001
+    title: "Create project structure"
+    description: "Create the directories and config files the rest of the work builds on."
+    complexity: 1
+  - id: T002
+    title: "Add the login endpoint"
+    description: "Accept a username and password, check them and return a session token."
+    complexity: 3
+  - id: T003
+    title: "Add rate limiting"
+    description: "Limit login attempts per client so passwords can't be brute forced."
+    complexity: 3
+"#;
+
+    #[test]
+    fn test_fit_tasks_summarizes_oldest_first() {
+        let mut budget = PromptBudget::new("test-model", 70);
+        let (tasks, spec) = fit_tasks_and_spec(&mut budget, TASKS, "id: demo\n");
+
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&tasks).expect("tasks stay valid YAML");
+        let parsed = parsed["tasks"].as_sequence().expect("tasks list");
+        let ids: Vec<_> = parsed.iter().filter_map(|t| t["id"].as_str()).collect();
+        assert_eq!(ids, ["T001", "T002", "T003"]);
+        assert!(parsed[0].get("description").is_none(), "oldest task is summarized");
+        assert_eq!(parsed[0]["title"], "Create project structure");
+        assert!(parsed[2].get("description").is_some(), "newest task is kept whole");
+        assert_eq!(spec, "id: demo\n", "the spec gets what the tasks leave");
+    }
+
+    #[test]
+    fn test_fit_tasks_keeps_everything_that_fits() {
+        let mut budget = PromptBudget::new("test-model", 10_000);
+        let (tasks, spec) = fit_tasks_and_spec(&mut budget, TASKS, "id: demo\n");
+
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&tasks).expect("valid YAML");
+        let original: serde_yaml::Value = serde_yaml::from_str(TASKS).expect("valid YAML");
+        assert_eq!(parsed, original);
+        assert_eq!(spec, "id: demo\n");
+        assert!(task_sections("not: [a, task, list]").is_none());
+    }
+}
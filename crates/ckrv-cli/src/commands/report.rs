@@ -3,7 +3,7 @@
 use clap::Args;
 use serde::Serialize;
 
-use ckrv_metrics::{format_ms, FileMetricsStorage, MetricsStorage};
+use ckrv_metrics::{format_ms, FileMetricsStorage, MetricsStorage, StepMetrics, ToolCallEntry};
 
 /// Arguments for the report command
 #[derive(Args)]
@@ -45,6 +45,8 @@ struct ModelTokens {
 struct CostReport {
     total_usd: f64,
     by_model: Vec<ModelCost>,
+    cache_hits: Vec<ModelTokens>,
+    cache_savings_usd: f64,
 }
 
 #[derive(Serialize)]
@@ -57,6 +59,29 @@ struct ModelCost {
 struct StepReport {
     step_id: String,
     duration_ms: u64,
+    cost_usd: Option<f64>,
+    turns: Option<u64>,
+    tool_calls: Vec<ToolCallEntry>,
+}
+
+/// One line per step: duration, then turns, cost and tool calls if known.
+fn step_line(step: &StepMetrics) -> String {
+    let mut details = vec![format_ms(step.duration_ms)];
+    if let Some(turns) = step.turns {
+        details.push(format!("{turns} turns"));
+    }
+    if let Some(cost) = step.cost_usd {
+        details.push(format!("${cost:.4}"));
+    }
+    if !step.tool_calls.is_empty() {
+        let failed = step.tool_calls.iter().filter(|call| call.failed).count();
+        details.push(if failed > 0 {
+            format!("{} tool calls, {failed} failed", step.tool_calls.len())
+        } else {
+            format!("{} tool calls", step.tool_calls.len())
+        });
+    }
+    format!("{} ({})", step.step_id, details.join(", "))
 }
 
 /// Execute the report command
@@ -103,6 +128,17 @@ pub async fn execute(args: ReportArgs, json: bool) -> anyhow::Result<()> {
                                     cost_usd: *cost,
                                 })
                                 .collect(),
+                            cache_hits: metrics
+                                .cache_hits
+                                .iter()
+                                .map(|t| ModelTokens {
+                                    model: t.model.clone(),
+                                    input: t.input_tokens,
+                                    output: t.output_tokens,
+                                    total: t.total(),
+                                })
+                                .collect(),
+                            cache_savings_usd: metrics.cache_savings_usd,
                         },
                         steps: metrics
                             .step_metrics
@@ -110,6 +146,9 @@ pub async fn execute(args: ReportArgs, json: bool) -> anyhow::Result<()> {
                             .map(|s| StepReport {
                                 step_id: s.step_id.clone(),
                                 duration_ms: s.duration_ms,
+                                cost_usd: s.cost_usd,
+                                turns: s.turns,
+                                tool_calls: s.tool_calls.clone(),
                             })
                             .collect(),
                     };
@@ -140,7 +179,15 @@ pub async fn execute(args: ReportArgs, json: bool) -> anyhow::Result<()> {
                         println!();
                         println!("  Steps:");
                         for step in &metrics.step_metrics {
-                            println!("    • {} ({})", step.step_id, format_ms(step.duration_ms));
+                            println!("    • {}", step_line(step));
+                            for call in &step.tool_calls {
+                                println!(
+                                    "        {} {}{}",
+                                    if call.failed { "✗" } else { "→" },
+                                    call.name,
+                                    call.target.as_deref().map(|t| format!(" {t}")).unwrap_or_default()
+                                );
+                            }
                         }
                     }
                     println!();
@@ -177,6 +224,14 @@ pub async fn execute(args: ReportArgs, json: bool) -> anyhow::Result<()> {
                             println!("    • {}: ${:.4}", model, cost);
                         }
                     }
+                    if !metrics.cache_hits.is_empty() {
+                        println!();
+                        println!(
+                            "  Cache hits: {} (saved ${:.4})",
+                            metrics.cache_hits.len(),
+                            metrics.cache_savings_usd
+                        );
+                    }
                     println!();
 
                     println!("═══════════════════════════════════════════════");
@@ -14,13 +14,20 @@ use tokio::process::Command as AsyncCommand;
 use std::sync::Arc;
 
 use ckrv_core::{
-    AgentTask, Workflow, WorkflowStep, OptimizeMode,
+    agent_history::dominant_language,
+    AgentHistory, AgentRunRecord, AgentTask, AgentsFile, Config, Workflow, WorkflowStep, OptimizeMode, TaskType,
     runner::{RunnerConfig, WorkflowRunner, WorkflowRunResult},
 };
 use ckrv_git::{DefaultDiffGenerator, DefaultWorktreeManager, DiffGenerator, WorktreeManager};
-use ckrv_metrics::{DefaultMetricsCollector, FileMetricsStorage, MetricsCollector, MetricsStorage};
-use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
+use ckrv_metrics::{
+    DefaultMetricsCollector, FileMetricsStorage, MetricsCollector, MetricsStorage, PricingCatalog,
+};
+use ckrv_model::PromptSection;
+use ckrv_sandbox::{ContainerRuntime, DockerSandbox, ExecuteConfig, Sandbox};
 
+use crate::prompts::{
+    conflict_hunks, price_note, prompt_budget, warn_if_trimmed, AGENT_MODEL, CONFLICT_CONTEXT_LINES,
+};
 use crate::ui::UiContext;
 use crate::ui::Renderable;
 use crate::ui::components::{Banner, RichTable, Panel};
@@ -36,9 +43,9 @@ pub struct RunArgs {
     #[arg()]
     pub spec: Option<PathBuf>,
 
-    /// Optimization strategy.
-    #[arg(short, long, value_enum, default_value = "balanced")]
-    pub optimize: OptimizeModeArg,
+    /// Optimization strategy [default: the project's `default_optimize`].
+    #[arg(short, long, value_enum)]
+    pub optimize: Option<OptimizeModeArg>,
 
     /// Override the AI model/agent to use for execution.
     #[arg(short, long)]
@@ -158,6 +165,9 @@ struct ExecutionBatch {
     /// Branch name created for this batch (for resume)
     #[serde(default)]
     branch: Option<String>,
+    /// Agent that produced this batch, after any fallbacks
+    #[serde(default)]
+    agent: Option<String>,
     
     // Enhanced Fields
     #[serde(default)]
@@ -228,6 +238,10 @@ fn serialize_plan_with_depends_on(plan: &ExecutionPlan) -> String {
         if let Some(ref branch) = batch.branch {
             output.push_str(&format!("    branch: \"{}\"\n", branch));
         }
+
+        if let Some(ref agent) = batch.agent {
+            output.push_str(&format!("    agent: \"{}\"\n", agent));
+        }
         
         output.push('\n');
     }
@@ -254,7 +268,10 @@ struct AgentModelDef {
     description: String,
 }
 
+/// Model assignment guidance for the planner, with each model's price from
+/// the project's pricing catalog.
 fn load_agent_model_instructions(cwd: &Path) -> String {
+    let pricing = PricingCatalog::load(cwd);
     let locations = [cwd.join("agent.yaml"), cwd.join(".ckrv/agent.yaml")];
     for loc in &locations {
         if loc.exists() {
@@ -262,7 +279,8 @@ fn load_agent_model_instructions(cwd: &Path) -> String {
                  if let Ok(config) = serde_yaml::from_str::<AgentConfig>(&content) {
                      let mut instructions = String::new();
                      for model in config.models {
-                         instructions.push_str(&format!("   - Use '{}' {}.\n", model.id, model.description));
+                         let price = price_note(&pricing, &model.id);
+                         instructions.push_str(&format!("   - Use '{}'{} {}.\n", model.id, price, model.description));
                      }
                      if !instructions.is_empty() {
                          return instructions;
@@ -272,9 +290,13 @@ fn load_agent_model_instructions(cwd: &Path) -> String {
         }
     }
     // Default fallback
-    r#"   - Use 'minimax/minimax-m2.1' for light/standard tasks (Level 1-3).
-   - Use 'z-ai/glm-4.7' for complex logic (Level 4).
-   - Use 'claude' (default) if high reasoning/risk required (Level 5)."#.to_string()
+    format!(
+        r#"   - Use 'minimax/minimax-m2.1'{} for light/standard tasks (Level 1-3).
+   - Use 'z-ai/glm-4.7'{} for complex logic (Level 4).
+   - Use 'claude' (default) if high reasoning/risk required (Level 5)."#,
+        price_note(&pricing, "minimax/minimax-m2.1"),
+        price_note(&pricing, "z-ai/glm-4.7"),
+    )
 }
 
 /// Execute the run command.
@@ -573,48 +595,28 @@ pub async fn execute(args: RunArgs, json: bool, ui: &UiContext) -> anyhow::Resul
             println!("Generating execution plan with Claude...");
         }
 
-        let tasks_json = serde_json::to_string_pretty(&pending_tasks)?;
         let model_instructions = load_agent_model_instructions(&cwd);
-        let prompt_base = format!(r#"### ARCHITECTURAL PLANNER
-Analyze these tasks and group them into logical execution batches.
-
-DEPENDENCY MAPPING RULES:
-1. Every batch MUST have 'id', 'name', 'task_ids', 'reasoning', 'depends_on', and assignment fields.
-2. 'depends_on' is a list of batch IDs this batch depends on.
-3. If Batch B needs code created in Batch A, Batch B MUST have `depends_on: ["batch-a-id"]`.
-4. For batches with no prerequisites, use `depends_on: []`.
-5. 'model_assignment': Assign the best model based on task complexity/risk.
-{}
-6. 'execution_strategy': "parallel" if tasks within batch don't depend on each other, else "sequential".
-
-Tasks:
-{}
-
-OUTPUT ONLY VALID YAML:
-batches:
-  - id: "foundation"
-    name: "Core Infrastructure"
-    task_ids: ["T001", "T002"]
-    depends_on: []
-    reasoning: "Standard setup."
-    model_assignment:
-      default: "minimax/minimax-m2.1"
-      overrides: {{}}
-    execution_strategy: "parallel"
-    estimated_cost: 0.01
-    estimated_time: "30s"
-  - id: "ui-components"
-    name: "Component Development"
-    task_ids: ["T003"]
-    depends_on: ["foundation"]
-    reasoning: "Depends on foundation."
-    model_assignment:
-      default: "z-ai/glm-4.7"
-      overrides: {{}}
-    execution_strategy: "sequential"
-    estimated_cost: 0.05
-    estimated_time: "2m"
-"#, model_instructions, tasks_json);
+        // Fit the tasks to the planner's context window, summarizing the
+        // oldest tasks first
+        let mut budget = prompt_budget(&cwd, AGENT_MODEL, &planner_prompt(&model_instructions, ""));
+        let sections = pending_tasks
+            .iter()
+            .enumerate()
+            .map(|(i, task)| {
+                let summary = serde_json::json!({
+                    "id": task.id,
+                    "title": task.title,
+                    "complexity": task.complexity,
+                });
+                Ok(PromptSection::new(&task.id, serde_json::to_string_pretty(task)?)
+                    .with_summary(summary.to_string())
+                    .with_priority(i64::try_from(i).unwrap_or(i64::MAX)))
+            })
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        let tasks = budget.fit(&sections);
+        warn_if_trimmed(&tasks);
+        let tasks_json = format!("[\n{}\n]", tasks.join(",\n"));
+        let prompt_base = planner_prompt(&model_instructions, &tasks_json);
 
         let plan_workflow = Workflow {
             version: "1.0".to_string(),
@@ -634,8 +636,10 @@ batches:
                              output_type: ckrv_core::OutputType::File,
                              description: Some("The generated plan yaml".to_string()),
                              filename: Some("plan.yaml".to_string()),
+                             schema: None,
                          }
                      ],
+                     resume_session: true,
                  }
             ],
         };
@@ -651,6 +655,8 @@ batches:
             agent_binary: "claude".to_string(),
             use_sandbox: true,
             keep_container: false,
+            project_root: Some(cwd.clone()),
+            container_runtime: Config::load_project(&cwd)?.container_runtime,
             ..Default::default()
         };
 
@@ -887,6 +893,13 @@ batches:
     let mut running_futures = futures::stream::FuturesUnordered::new();
 
     let exe_arc = std::sync::Arc::new(exe);
+
+    // Agent selection follows the routing rules, then how each agent did
+    // on earlier batches
+    let config = Config::load_project(&cwd)?;
+    let optimize = args.optimize.map_or_else(|| config.optimize_mode(), OptimizeMode::from);
+    let agent_history = AgentHistory::load(&cwd);
+    let mut batch_runs: std::collections::HashMap<String, BatchRun> = std::collections::HashMap::new();
     let manager_arc = std::sync::Arc::new(manager);
 
     while !pending_batches.is_empty() || !running_futures.is_empty() {
@@ -922,29 +935,39 @@ batches:
                 }
                 
                 // Intelligent Agent Selection
-                // Priority: 1. CLI Override, 2. AI Plan (model_assignment), 3. Complexity Auto-Select
-                let resolved_agent = if args.executor_model.is_none() {
-                    let mut agent_id = None;
-                    
-                    // 1. Try Plan Assignment
-                    if let Some(model_str) = &batch.model_assignment.default {
-                        agent_id = find_agent_for_model_string(&cwd, model_str);
-                        if let Some(ref id) = agent_id {
-                             println!("   🧠 Plan-selected agent '{}' for model '{}', Batch Level {}", id, model_str, max_complexity);
-                        }
-                    } 
-                    
-                    // 2. Fallback to Complexity
-                    if agent_id.is_none() {
-                         agent_id = find_best_agent_for_level(&cwd, max_complexity);
-                         if let Some(ref id) = agent_id {
-                             println!("   🧠 Auto-selecting agent '{}' for Batch Level {}", id, max_complexity);
-                         }
-                    }
-                    agent_id
+                // Priority: 1. CLI Override, 2. AI Plan (model_assignment), 3. Routing rules, 4. History-driven Auto-Select
+                let language = dominant_language(
+                    task_ids.iter().filter_map(|id| task_map.get(id)).filter_map(|t| t.file.as_deref()),
+                );
+                let agents = load_agents(&cwd);
+                let (resolved_agent, selection) = if let Some(ref model) = args.executor_model {
+                    (None, format!("set with --executor-model '{}'", model))
+                } else if let Some((id, model_str)) = batch.model_assignment.default.as_deref()
+                    .and_then(|m| find_agent_for_model_string(&cwd, m).map(|id| (id, m)))
+                {
+                    // 1. Plan Assignment
+                    println!("   🧠 Plan-selected agent '{}' for model '{}', Batch Level {}", id, model_str, max_complexity);
+                    (Some(id), format!("plan assigned model '{}'", model_str))
+                } else if let Some((model, rule)) = config.route(TaskType::Execution, optimize, Some(max_complexity), |m| agents.by_model(m).is_some()) {
+                    // 2. Routing rules in config.json
+                    let id = agents.by_model(model).map(|a| a.id.clone()).unwrap_or_default();
+                    println!("   🧠 Routing rule '{rule}' selected agent '{id}' for model '{model}', Batch Level {max_complexity}");
+                    (Some(id), format!("routing rule '{rule}' chose model '{model}'"))
+                } else if let Some(selected) = agent_history.select(&agents, max_complexity, language, optimize) {
+                    // 3. Fallback to history, then complexity
+                    println!("   🧠 Auto-selecting agent '{}' for Batch Level {}: {}", selected.agent_id, max_complexity, selected.reason);
+                    (Some(selected.agent_id), selected.reason)
                 } else {
-                    None
+                    (None, "no agents configured; using the default agent".to_string())
                 };
+                batch_runs.insert(batch_id.clone(), BatchRun {
+                    agent: resolved_agent.clone().or_else(|| args.executor_model.clone()),
+                    complexity: max_complexity,
+                    language: language.map(str::to_string),
+                    selection,
+                    started: std::time::Instant::now(),
+                    started_at: chrono::Utc::now(),
+                });
 
                 // Store plan path for status updates
                 let plan_path = tasks_path.parent().unwrap_or(&cwd).join("plan.yaml");
@@ -970,7 +993,7 @@ batches:
                     worktree_branch = worktree.branch.clone();
                     
                     // Update plan.yaml with running status and branch
-                    let _ = update_batch_status(&plan_path_clone, &batch_id_for_status, BatchStatus::Running, Some(&worktree.branch));
+                    let _ = update_batch_status(&plan_path_clone, &batch_id_for_status, BatchStatus::Running, Some(&worktree.branch), None);
                     
                     let mut cmd = AsyncCommand::new(exe.as_ref());
                     cmd.arg("task").arg(&combined_desc);
@@ -1012,6 +1035,12 @@ batches:
             if let Some(result) = running_futures.next().await {
                 match result {
                     Ok((id, name, ref wt_path_opt, Ok(branch))) => {
+                        // The agent that produced the batch, after any fallbacks
+                        let agent = record_batch_outcome(&cwd, &spec.id, &id, batch_runs.get(&id), true);
+                        if let Some(ref agent) = agent {
+                            println!("[Orchestrator] Batch '{}' produced by agent '{}'", name, agent);
+                        }
+
                         // 1. Commit changes in the worktree
                         if let Some(ref wt_path) = wt_path_opt {
                             println!("[Orchestrator] Committing changes for batch '{}'...", name);
@@ -1105,7 +1134,7 @@ batches:
                             
                             // 4. Update plan.yaml batch status
                             let plan_path = tasks_path.parent().unwrap_or(&cwd).join("plan.yaml");
-                            let _ = update_batch_status(&plan_path, &id, BatchStatus::Completed, Some(&branch));
+                            let _ = update_batch_status(&plan_path, &id, BatchStatus::Completed, Some(&branch), agent.as_deref());
                             
                             // 5. Verify merge and cleanup worktree
                             // Check if the branch is now an ancestor of HEAD (merge was successful)
@@ -1135,9 +1164,10 @@ batches:
                         completed_batches.insert(id);
                     }
                     Ok((failed_id, name, _path, Err(e))) => {
+                        record_batch_outcome(&cwd, &spec.id, &failed_id, batch_runs.get(&failed_id), false);
                         // Mark batch as failed in plan.yaml for potential retry
                         let plan_path = tasks_path.parent().unwrap_or(&cwd).join("plan.yaml");
-                        let _ = update_batch_status(&plan_path, &failed_id, BatchStatus::Failed, None);
+                        let _ = update_batch_status(&plan_path, &failed_id, BatchStatus::Failed, None, None);
                         return Err(anyhow::anyhow!("Batch '{}' failed: {}", name, e));
                     }
                     Err(e) => {
@@ -1250,7 +1280,7 @@ fn mark_tasks_complete(tasks_path: &Path, task_ids: &[String]) -> anyhow::Result
 }
 
 /// Update batch status in plan.yaml for resume capability
-fn update_batch_status(plan_path: &Path, batch_id: &str, status: BatchStatus, branch: Option<&str>) -> anyhow::Result<()> {
+fn update_batch_status(plan_path: &Path, batch_id: &str, status: BatchStatus, branch: Option<&str>, agent: Option<&str>) -> anyhow::Result<()> {
     if !plan_path.exists() {
         return Ok(());
     }
@@ -1264,6 +1294,9 @@ fn update_batch_status(plan_path: &Path, batch_id: &str, status: BatchStatus, br
             if let Some(b) = branch {
                 batch.branch = Some(b.to_string());
             }
+            if let Some(a) = agent {
+                batch.agent = Some(a.to_string());
+            }
             break;
         }
     }
@@ -1304,39 +1337,53 @@ fn get_conflicted_files(cwd: &Path) -> Vec<String> {
     }
 }
 
-/// Resolve merge conflicts using Claude Code AI
-async fn resolve_conflicts_with_ai(cwd: &Path, branch_name: &str, spec_path: Option<&Path>) -> anyhow::Result<()> {
-    let conflicted_files = get_conflicted_files(cwd);
-    
-    if conflicted_files.is_empty() {
-        return Ok(());
-    }
+/// Prompt asking the planner to batch the tasks in `tasks_json`.
+fn planner_prompt(model_instructions: &str, tasks_json: &str) -> String {
+    format!(r#"### ARCHITECTURAL PLANNER
+Analyze these tasks and group them into logical execution batches.
 
-    println!("\n🔀 Merge Conflict Detected!");
-    println!("   Conflicting files:");
-    for file in &conflicted_files {
-        println!("     • {}", file);
-    }
-    println!("\n🤖 Invoking Claude Code to resolve conflicts...\n");
+DEPENDENCY MAPPING RULES:
+1. Every batch MUST have 'id', 'name', 'task_ids', 'reasoning', 'depends_on', and assignment fields.
+2. 'depends_on' is a list of batch IDs this batch depends on.
+3. If Batch B needs code created in Batch A, Batch B MUST have `depends_on: ["batch-a-id"]`.
+4. For batches with no prerequisites, use `depends_on: []`.
+5. 'model_assignment': Assign the best model based on task complexity/risk.
+{}
+6. 'execution_strategy': "parallel" if tasks within batch don't depend on each other, else "sequential".
 
-    // Read spec context if available
-    let spec_context = if let Some(path) = spec_path {
-        std::fs::read_to_string(path).unwrap_or_default()
-    } else {
-        String::new()
-    };
+Tasks:
+{}
 
-    // Read the actual content of conflicted files so Claude can see them
-    let mut file_contents = String::new();
-    for file in &conflicted_files {
-        let file_path = cwd.join(file);
-        if let Ok(content) = std::fs::read_to_string(&file_path) {
-            file_contents.push_str(&format!("\n=== {} ===\n{}\n", file, content));
-        }
-    }
+OUTPUT ONLY VALID YAML:
+batches:
+  - id: "foundation"
+    name: "Core Infrastructure"
+    task_ids: ["T001", "T002"]
+    depends_on: []
+    reasoning: "Standard setup."
+    model_assignment:
+      default: "minimax/minimax-m2.1"
+      overrides: {{}}
+    execution_strategy: "parallel"
+    estimated_cost: 0.01
+    estimated_time: "30s"
+  - id: "ui-components"
+    name: "Component Development"
+    task_ids: ["T003"]
+    depends_on: ["foundation"]
+    reasoning: "Depends on foundation."
+    model_assignment:
+      default: "z-ai/glm-4.7"
+      overrides: {{}}
+    execution_strategy: "sequential"
+    estimated_cost: 0.05
+    estimated_time: "2m"
+"#, model_instructions, tasks_json)
+}
 
-    // Build prompt for Claude - use interactive mode so Claude can edit files
-    let prompt = format!(
+/// Prompt asking the agent to resolve the conflicts in `files`.
+fn conflict_prompt(branch: &str, spec: &str, files: &str) -> String {
+    format!(
         r#"You are resolving Git merge conflicts. Your ONLY task is to edit the conflicting files to resolve the conflicts.
 
 BRANCH BEING MERGED: {branch}
@@ -1344,7 +1391,7 @@ BRANCH BEING MERGED: {branch}
 SPEC CONTEXT (what we're building):
 {spec}
 
-CONFLICTING FILES AND THEIR CURRENT CONTENT:
+CONFLICTING FILES (conflict hunks with surrounding lines; the full files are on disk):
 {files}
 
 YOUR TASK:
@@ -1363,13 +1410,57 @@ CRITICAL RULES:
 - After editing, stage all files with: git add -A
 
 Start by editing the first conflicted file now."#,
-        branch = branch_name,
-        spec = if spec_context.is_empty() { "(No spec provided)".to_string() } else { spec_context },
-        files = file_contents
-    );
+        spec = if spec.is_empty() { "(No spec provided)" } else { spec },
+    )
+}
+
+/// Resolve merge conflicts using Claude Code AI
+async fn resolve_conflicts_with_ai(cwd: &Path, branch_name: &str, spec_path: Option<&Path>) -> anyhow::Result<()> {
+    let conflicted_files = get_conflicted_files(cwd);
+    
+    if conflicted_files.is_empty() {
+        return Ok(());
+    }
+
+    println!("\n🔀 Merge Conflict Detected!");
+    println!("   Conflicting files:");
+    for file in &conflicted_files {
+        println!("     • {}", file);
+    }
+    println!("\n🤖 Invoking Claude Code to resolve conflicts...\n");
+
+    // Read spec context if available
+    let spec_context = if let Some(path) = spec_path {
+        std::fs::read_to_string(path).unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    // Send only the conflict hunks of each file, trimmed to the agent's
+    // context window. The spec gets whatever room is left.
+    let mut budget = prompt_budget(cwd, AGENT_MODEL, &conflict_prompt(branch_name, "", ""));
+    let sections: Vec<_> = conflicted_files
+        .iter()
+        .filter_map(|file| {
+            let content = std::fs::read_to_string(cwd.join(file)).ok()?;
+            let hunks = conflict_hunks(&content, CONFLICT_CONTEXT_LINES);
+            Some(
+                PromptSection::new(file, format!("\n=== {file} ===\n{hunks}\n"))
+                    .with_summary(format!("\n=== {file} ===\n(Conflicts not shown; open the file to see them)\n")),
+            )
+        })
+        .collect();
+    let files = budget.fit(&sections);
+    let spec = budget.fit(&[PromptSection::new("spec", spec_context)]);
+    warn_if_trimmed(&files);
+    warn_if_trimmed(&spec);
+
+    // Build prompt for Claude - use interactive mode so Claude can edit files
+    let prompt = conflict_prompt(branch_name, &spec.join(""), &files.join(""));
 
     // Run Claude in Docker sandbox without -p flag so it can use tools to edit files
-    let sandbox = DockerSandbox::new(ckrv_sandbox::DefaultAllowList::default())
+    let runtime = ContainerRuntime::resolve(&Config::load_project(cwd)?.container_runtime);
+    let sandbox = DockerSandbox::with_runtime(ckrv_sandbox::DefaultAllowList::default(), &runtime)
         .map_err(|e| anyhow::anyhow!("Failed to create sandbox for conflict resolution: {}", e))?;
 
     // Use Claude in interactive mode with the prompt passed via stdin-like mechanism
@@ -1528,100 +1619,98 @@ async fn execute_cloud_job(
     Ok(())
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct OpenRouterConfigLite {
-    model: Option<String>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct AgentConfigLite {
-    id: String,
-    name: String,
-    #[serde(default)]
-    level: u8,
-    #[serde(default)]
-    enabled: bool,
-    openrouter: Option<OpenRouterConfigLite>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct AgentsFileLite {
-    agents: Vec<AgentConfigLite>,
+/// Load agents.yaml, warning (rather than failing the run) if it is invalid.
+fn load_agents(cwd: &Path) -> AgentsFile {
+    AgentsFile::load_for_project(cwd).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Ignoring invalid agents configuration");
+        AgentsFile::default()
+    })
 }
 
 /// Find an agent ID that matches a model string (e.g. "minimax/minimax-m2.1")
 fn find_agent_for_model_string(cwd: &Path, model_string: &str) -> Option<String> {
-    let agents_path = dirs::config_dir()
-        .map(|d| d.join("chakravarti").join("agents.yaml"))
-        .filter(|p| p.exists())
-        .unwrap_or_else(|| cwd.join(".chakravarti").join("agents.yaml"));
-
-    if !agents_path.exists() { return None; }
-
-    let content = match std::fs::read_to_string(agents_path) {
-        Ok(c) => c,
-        Err(_) => return None,
-    };
-
-    let config: AgentsFileLite = match serde_yaml::from_str(&content) {
-        Ok(c) => c,
-        Err(_) => return None,
-    };
-
-    config.agents.iter()
-        .filter(|a| a.enabled)
-        .find(|a| {
-            // Match ID, Name, or OpenRouter Model
-            a.id == model_string || 
-            a.name == model_string || 
-            a.openrouter.as_ref().map(|o| o.model.as_deref() == Some(model_string)).unwrap_or(false)
-        })
-        .map(|a| a.id.clone())
+    load_agents(cwd).by_model(model_string).map(|a| a.id.clone())
 }
 
-/// Find the best agent for a given complexity level.
-/// Strategy: Find the lowest level agent that is >= required level.
-/// If no agent meets the requirement, return the highest available level.
-fn find_best_agent_for_level(cwd: &Path, required_level: u8) -> Option<String> {
-    // Check global path first
-    let agents_path = dirs::config_dir()
-        .map(|d| d.join("chakravarti").join("agents.yaml"))
-        .filter(|p| p.exists())
-        .unwrap_or_else(|| cwd.join(".chakravarti").join("agents.yaml"));
-
-    if !agents_path.exists() {
-        return None;
-    }
-
-    let content = match std::fs::read_to_string(agents_path) {
-        Ok(c) => c,
-        Err(_) => return None,
-    };
+/// How a running batch's agent was chosen, for its history record.
+struct BatchRun {
+    agent: Option<String>,
+    complexity: u8,
+    language: Option<String>,
+    selection: String,
+    started: std::time::Instant,
+    /// When the batch started, to tell its task from an earlier run's.
+    started_at: chrono::DateTime<chrono::Utc>,
+}
 
-    let config: AgentsFileLite = match serde_yaml::from_str(&content) {
-        Ok(c) => c,
-        Err(_) => return None,
+/// Append a finished batch to the agent history and return the agent that
+/// produced it, after any fallbacks.
+fn record_batch_outcome(cwd: &Path, spec_id: &str, batch_id: &str, run: Option<&BatchRun>, success: bool) -> Option<String> {
+    let run = run?;
+    // The task is reused across runs; one not saved since this run started
+    // still holds the previous run's agent and cost
+    let task = AgentTask::load(cwd, &format!("{}-run", batch_id))
+        .ok()
+        .filter(|t| t.updated_at.is_some_and(|at| at >= run.started_at));
+    let agent = task.as_ref().and_then(|t| t.agent.clone()).or_else(|| run.agent.clone())?;
+
+    let record = AgentRunRecord {
+        agent: agent.clone(),
+        complexity: run.complexity,
+        language: run.language.clone(),
+        success,
+        duration_ms: u64::try_from(run.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+        cost_usd: task.and_then(|t| t.usage).and_then(|u| u.cost_usd),
+        spec: Some(spec_id.to_string()),
+        batch: Some(batch_id.to_string()),
+        selection: Some(run.selection.clone()),
+        recorded_at: chrono::Utc::now(),
     };
-
-    let enabled_agents: Vec<&AgentConfigLite> = config.agents.iter().filter(|a| a.enabled).collect();
-    if enabled_agents.is_empty() {
-        return None;
+    if let Err(e) = AgentHistory::record(cwd, &record) {
+        tracing::warn!(error = %e, "Could not record agent history");
     }
+    Some(agent)
+}
 
-    // 1. Try to find agents with level >= required
-    let mut sufficient_agents: Vec<&AgentConfigLite> = enabled_agents.iter()
-        .filter(|a| a.level >= required_level)
-        .map(|&a| a)
-        .collect();
-
-    if !sufficient_agents.is_empty() {
-        // Sort by level ascending (pick the "cheapest" one that is good enough)
-        sufficient_agents.sort_by_key(|a| a.level);
-        return Some(sufficient_agents[0].id.clone());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckrv_core::AgentUsage;
+
+    fn batch_run(agent: &str) -> BatchRun {
+        BatchRun {
+            agent: Some(agent.to_string()),
+            complexity: 3,
+            language: None,
+            selection: "test".to_string(),
+            started: std::time::Instant::now(),
+            started_at: chrono::Utc::now(),
+        }
     }
 
-    // 2. Fallback: pick the strongest available agent
-    let mut all_agents = enabled_agents;
-    all_agents.sort_by_key(|a| a.level); // Sort ascending
-    all_agents.last().map(|a| a.id.clone())
+    #[test]
+    fn test_batch_outcome_ignores_a_previous_runs_task() {
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let mut task = AgentTask::new("b1-run", "batch", "swe", dir.path().to_path_buf());
+        task.agent = Some("old-agent".to_string());
+        task.usage = Some(AgentUsage { cost_usd: Some(1.5), ..AgentUsage::default() });
+        task.updated_at = Some(chrono::Utc::now() - chrono::Duration::minutes(1));
+        task.save(dir.path()).expect("save task");
+
+        // The child died before saving, so the task is from the last run
+        let run = batch_run("new-agent");
+        let agent = record_batch_outcome(dir.path(), "spec", "b1", Some(&run), false);
+        assert_eq!(agent.as_deref(), Some("new-agent"));
+
+        // A task saved during this run is trusted
+        let run = batch_run("new-agent");
+        task.set_status(ckrv_core::AgentTaskStatus::Completed);
+        task.save(dir.path()).expect("save task");
+        let agent = record_batch_outcome(dir.path(), "spec", "b1", Some(&run), true);
+        assert_eq!(agent.as_deref(), Some("old-agent"));
+
+        let history = AgentHistory::load(dir.path());
+        let costs: Vec<_> = history.records().iter().map(|r| r.cost_usd).collect();
+        assert_eq!(costs, vec![None, Some(1.5)]);
+    }
 }
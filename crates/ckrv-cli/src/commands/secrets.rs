@@ -0,0 +1,128 @@
+//! Secrets commands - manage the encrypted store for API keys.
+//!
+//! Config files reference stored values as `secret:<name>`; they're resolved
+//! only when an agent is launched.
+
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use ckrv_core::secrets::{KeySource, SECRET_PREFIX};
+use ckrv_core::SecretStore;
+
+/// Arguments for the secrets command
+#[derive(Args)]
+pub struct SecretsArgs {
+    /// Use the global store instead of the project's
+    #[arg(long, global = true)]
+    pub global: bool,
+
+    #[command(subcommand)]
+    pub command: SecretsCommand,
+}
+
+/// Secrets subcommands
+#[derive(Subcommand)]
+pub enum SecretsCommand {
+    /// Store a secret (value is read from a prompt or stdin)
+    Set {
+        /// Secret name, referenced as secret:<name>
+        name: String,
+    },
+    /// Print a secret's value
+    Get {
+        /// Secret name
+        name: String,
+    },
+    /// List stored secret names
+    List,
+    /// Remove a secret
+    Rm {
+        /// Secret name
+        name: String,
+    },
+}
+
+/// JSON output for secrets list
+#[derive(Serialize)]
+struct SecretsListOutput {
+    store: PathBuf,
+    key_source: KeySource,
+    secrets: Vec<String>,
+}
+
+/// Execute the secrets command
+pub fn execute(args: SecretsArgs, json: bool) -> anyhow::Result<()> {
+    let path = if args.global {
+        SecretStore::global_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
+    } else {
+        SecretStore::project_path(&std::env::current_dir()?)
+    };
+    let mut store = SecretStore::open(path)?;
+
+    match args.command {
+        SecretsCommand::Set { name } => {
+            let value = read_secret_value(&name)?;
+            store.set(&name, value)?;
+            store.save()?;
+            if !json {
+                println!("✓ Stored secret '{name}' in {}", store.path().display());
+                println!("  Reference it in config as {SECRET_PREFIX}{name}");
+            }
+        }
+        SecretsCommand::Get { name } => {
+            let value = store
+                .get(&name)
+                .ok_or_else(|| ckrv_core::SecretError::NotFound(name.clone()))?;
+            println!("{value}");
+        }
+        SecretsCommand::List => {
+            let secrets: Vec<String> = store.names().map(str::to_string).collect();
+            if json {
+                let output = SecretsListOutput {
+                    store: store.path().to_path_buf(),
+                    key_source: store.key_source(),
+                    secrets,
+                };
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if secrets.is_empty() {
+                println!("No secrets in {}", store.path().display());
+            } else {
+                for name in secrets {
+                    println!("{name}");
+                }
+            }
+        }
+        SecretsCommand::Rm { name } => {
+            if !store.remove(&name) {
+                return Err(ckrv_core::SecretError::NotFound(name).into());
+            }
+            store.save()?;
+            if !json {
+                println!("✓ Removed secret '{name}'");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a secret from a hidden prompt, or from stdin when it's piped.
+fn read_secret_value(name: &str) -> anyhow::Result<String> {
+    let value = if std::io::stdin().is_terminal() {
+        eprint!("Value for '{name}': ");
+        std::io::stderr().flush()?;
+        rpassword::read_password()?
+    } else {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        buf.trim_end_matches(['\n', '\r']).to_string()
+    };
+    if value.is_empty() {
+        anyhow::bail!("Secret value cannot be empty");
+    }
+    Ok(value)
+}
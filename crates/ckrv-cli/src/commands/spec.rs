@@ -1,13 +1,24 @@
 //! Spec commands - create and manage specifications.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use clap::{Args, Subcommand};
 use serde::Serialize;
 
+use ckrv_core::mock_agent::{MockAgent, MockMode, WorkspaceSnapshot};
+use ckrv_core::Config;
+use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
+
+use crate::generation::{self, Generation, GenerationMetrics};
+
 /// Arguments for the spec command
 #[derive(Args)]
 pub struct SpecArgs {
+    /// Call the model API even when a cached response exists
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
     #[command(subcommand)]
     pub command: SpecCommand,
 }
@@ -91,22 +102,19 @@ use crate::ui::components::{RichTable, Banner};
 pub async fn execute(args: SpecArgs, json: bool, ui: &UiContext) -> anyhow::Result<()> {
     match args.command {
         SpecCommand::New { description, name } => {
-            execute_generate(&description, name.as_deref(), json).await
+            execute_generate(&description, name.as_deref(), args.no_cache, json).await
         }
         SpecCommand::Clarify { spec } => execute_clarify(spec.as_ref(), json).await,
-        SpecCommand::Design { spec, force } => execute_design(spec.as_ref(), force, json).await,
+        SpecCommand::Design { spec, force } => execute_design(spec.as_ref(), force, args.no_cache, json).await,
         SpecCommand::Init { name } => execute_init(&name, json),
-        SpecCommand::Tasks { spec, force } => execute_tasks(spec.as_ref(), force, json, ui).await,
+        SpecCommand::Tasks { spec, force } => execute_tasks(spec.as_ref(), force, args.no_cache, json, ui).await,
         SpecCommand::Validate { path } => execute_validate(path.as_ref(), json),
         SpecCommand::List => execute_list(json),
     }
 }
 
 /// Create a new spec using Claude AI from a natural language description.
-async fn execute_generate(description: &str, name: Option<&str>, json: bool) -> anyhow::Result<()> {
-    use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
-    use std::time::Duration;
-
+async fn execute_generate(description: &str, name: Option<&str>, no_cache: bool, json: bool) -> anyhow::Result<()> {
     let cwd = std::env::current_dir()?;
 
     // Check if initialized
@@ -179,41 +187,25 @@ async fn execute_generate(description: &str, name: Option<&str>, json: bool) ->
     // Build the rich prompt for Claude using the prompts module
     let prompt = crate::prompts::build_spec_prompt(description, &numbered_name);
 
-    // Run Claude in Docker sandbox
-    let result = {
-        let sandbox = DockerSandbox::new(ckrv_sandbox::DefaultAllowList::default())
-            .map_err(|e| anyhow::anyhow!("Failed to create sandbox: {}", e))?;
+    let result = generate(&prompt, &specs_dir, (&numbered_name, "new"), no_cache, json).await?;
 
-        let command = format!(
-            "claude -p {} --dangerously-skip-permissions --output-format text --tools \"\"",
-            shell_escape::escape(prompt.clone().into())
-        );
-
-        let config = ExecuteConfig::new("", specs_dir.clone())
-            .shell(&command)
-            .with_timeout(Duration::from_secs(300));
-
-        sandbox.execute(config).await
-            .map_err(|e| anyhow::anyhow!("Sandbox execution failed: {}", e))
-    }?;
-
-    if !result.success() {
+    if !result.success {
         if json {
             let output = serde_json::json!({
                 "success": false,
-                "error": format!("AI generation failed: {}", result.stderr),
+                "error": format!("AI generation failed: {}", result.error),
                 "code": "GENERATION_FAILED"
             });
             println!("{}", serde_json::to_string_pretty(&output)?);
         } else {
             eprintln!("Error: AI generation failed");
-            eprintln!("{}", result.stderr);
+            eprintln!("{}", result.error);
         }
         std::process::exit(1);
     }
 
     // Write the generated spec (strip any markdown code fences)
-    let spec_content = result.stdout.trim();
+    let spec_content = result.output.trim();
     let spec_content = crate::prompts::strip_yaml_fences(spec_content);
     
     // Validate the generated YAML before writing
@@ -296,6 +288,9 @@ async fn execute_generate(description: &str, name: Option<&str>, json: bool) ->
             "id": numbered_name,
             "branch": if branch_created { Some(&numbered_name) } else { None },
             "message": "Spec generated with AI",
+            "usage": result.usage,
+            "cached": result.cached,
+            "job_id": result.job_id,
             "spec": {
                 "user_story_count": spec_details.as_ref().map(|s| s.user_story_count()).unwrap_or(0),
                 "requirement_count": spec_details.as_ref().map(|s| s.requirement_count()).unwrap_or(0),
@@ -445,10 +440,7 @@ async fn execute_clarify(spec_path: Option<&PathBuf>, json: bool) -> anyhow::Res
 }
 
 /// Generate technical design document from a specification
-async fn execute_design(spec_path: Option<&PathBuf>, force: bool, json: bool) -> anyhow::Result<()> {
-    use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
-    use std::time::Duration;
-
+async fn execute_design(spec_path: Option<&PathBuf>, force: bool, no_cache: bool, json: bool) -> anyhow::Result<()> {
     let cwd = std::env::current_dir()?;
     
     // Resolve spec path - auto-detect from current branch if not provided
@@ -503,41 +495,25 @@ async fn execute_design(spec_path: Option<&PathBuf>, force: bool, json: bool) ->
     // Build the design prompt
     let prompt = crate::prompts::build_design_prompt(&spec_content, &spec.id);
     
-    // Run Claude in Docker sandbox
-    let result = {
-        let sandbox = DockerSandbox::new(ckrv_sandbox::DefaultAllowList::default())
-            .map_err(|e| anyhow::anyhow!("Failed to create sandbox: {}", e))?;
-
-        let command = format!(
-            "claude -p {} --dangerously-skip-permissions --output-format text --tools \"\"",
-            shell_escape::escape(prompt.clone().into())
-        );
-
-        let config = ExecuteConfig::new("", spec_folder.clone())
-            .shell(&command)
-            .with_timeout(Duration::from_secs(300));
+    let result = generate(&prompt, &spec_folder, (&spec.id, "design"), no_cache, json).await?;
 
-        sandbox.execute(config).await
-            .map_err(|e| anyhow::anyhow!("Sandbox execution failed: {}", e))
-    }?;
-
-    if !result.success() {
+    if !result.success {
         if json {
             let output = serde_json::json!({
                 "success": false,
-                "error": format!("AI generation failed: {}", result.stderr),
+                "error": format!("AI generation failed: {}", result.error),
                 "code": "GENERATION_FAILED"
             });
             println!("{}", serde_json::to_string_pretty(&output)?);
         } else {
             eprintln!("Error: AI generation failed");
-            eprintln!("{}", result.stderr);
+            eprintln!("{}", result.error);
         }
         std::process::exit(1);
     }
 
     // Write the design document
-    let design_content = result.stdout.trim();
+    let design_content = result.output.trim();
     std::fs::write(&design_path, design_content)?;
     
     // Create a basic research.md if it doesn't exist
@@ -567,7 +543,10 @@ async fn execute_design(spec_path: Option<&PathBuf>, force: bool, json: bool) ->
             "success": true,
             "design_path": design_path,
             "research_path": research_path,
-            "message": "Design generated successfully"
+            "message": "Design generated successfully",
+            "usage": result.usage,
+            "cached": result.cached,
+            "job_id": result.job_id
         });
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
@@ -740,10 +719,7 @@ fn resolve_spec_path(spec_path: Option<&PathBuf>, cwd: &std::path::Path) -> anyh
 
 /// Generate implementation tasks from a spec file.
 /// Auto-detects spec from current branch if not provided.
-async fn execute_tasks(spec_path: Option<&PathBuf>, force: bool, json: bool, ui: &UiContext) -> anyhow::Result<()> {
-    use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox};
-    use std::time::Duration;
-
+async fn execute_tasks(spec_path: Option<&PathBuf>, force: bool, no_cache: bool, json: bool, ui: &UiContext) -> anyhow::Result<()> {
     let cwd = std::env::current_dir()?;
     let is_auto_detected = spec_path.is_none();
 
@@ -975,41 +951,25 @@ Output ONLY the raw YAML content. No ```yaml fences."#,
     }
 
     // Run Claude in Docker sandbox
-    let result = {
-        let sandbox = DockerSandbox::new(ckrv_sandbox::DefaultAllowList::default())
-            .map_err(|e| anyhow::anyhow!("Failed to create sandbox: {}", e))?;
+    let result = generate(&prompt, spec_path.parent().unwrap_or(&cwd), (spec_id, "tasks"), no_cache, json).await?;
 
-        let command = format!(
-            "claude -p {} --dangerously-skip-permissions --output-format text --tools \"\"",
-            shell_escape::escape(prompt.clone().into())
-        );
-
-        let specs_dir = spec_path.parent().unwrap_or(&cwd);
-        let config = ExecuteConfig::new("", specs_dir.to_path_buf())
-            .shell(&command)
-            .with_timeout(Duration::from_secs(300));
-
-        sandbox.execute(config).await
-            .map_err(|e| anyhow::anyhow!("Sandbox execution failed: {}", e))
-    }?;
-
-    if !result.success() {
+    if !result.success {
         if json {
             let output = serde_json::json!({
                 "success": false,
-                "error": format!("AI task generation failed: {}", result.stderr),
+                "error": format!("AI task generation failed: {}", result.error),
                 "code": "GENERATION_FAILED"
             });
             println!("{}", serde_json::to_string_pretty(&output)?);
         } else {
             eprintln!("Error: AI task generation failed");
-            eprintln!("{}", result.stderr);
+            eprintln!("{}", result.error);
         }
         std::process::exit(1);
     }
 
     // Write tasks.yaml in the same folder as spec.yaml
-    let tasks_content = strip_code_fences(result.stdout.trim());
+    let tasks_content = strip_code_fences(result.output.trim());
     let spec_folder = spec_path.parent().unwrap_or(&cwd);
     let tasks_path = spec_folder.join("tasks.yaml");
     std::fs::write(&tasks_path, &tasks_content)?;
@@ -1020,7 +980,10 @@ Output ONLY the raw YAML content. No ```yaml fences."#,
             "spec_id": spec_id,
             "branch": if on_branch { Some(spec_id) } else { None },
             "tasks_path": tasks_path,
-            "message": "Tasks generated"
+            "message": "Tasks generated",
+            "usage": result.usage,
+            "cached": result.cached,
+            "job_id": result.job_id
         });
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
@@ -1037,6 +1000,88 @@ Output ONLY the raw YAML content. No ```yaml fences."#,
     Ok(())
 }
 
+/// Generate text for a prompt that needs no tool use.
+///
+/// Calls the model API through `ModelRouter` when `generation.backend` in
+/// `.chakravarti/config.json` allows it and a provider is configured,
+/// otherwise runs Claude in the Docker sandbox. With `CKRV_MOCK_AGENT` set,
+/// the response is replayed from (or recorded to) the mock agent's fixtures.
+/// `no_cache` skips the model response cache. Model API usage is saved as
+/// the metrics of job `<spec_id>-<step>` from `job`.
+async fn generate(prompt: &str, workdir: &Path, job: (&str, &str), no_cache: bool, json: bool) -> anyhow::Result<Generation> {
+    let cwd = std::env::current_dir()?;
+    let mock = MockAgent::from_env(&cwd)?;
+    if let Some(ref mock) = mock {
+        if mock.mode() == MockMode::Replay {
+            let transcript = mock.replay(prompt, workdir)?;
+            return Ok(Generation {
+                success: transcript.success,
+                output: transcript.stdout,
+                error: transcript.stderr,
+                usage: None,
+                cached: false,
+                job_id: None,
+            });
+        }
+    }
+
+    let root = ckrv_git::repo_root(&cwd).unwrap_or(cwd);
+    let config = Config::load_project(&root)?;
+    let router = generation::api_router(&config, &root, !no_cache).await?;
+
+    let before = match mock {
+        Some(_) => Some(WorkspaceSnapshot::capture(workdir)?),
+        None => None,
+    };
+    let result = match router {
+        Some(router) => {
+            let metrics = GenerationMetrics::start(&root, job.0, job.1);
+            let mut result = generation::complete(&router, &config, prompt, json).await;
+            result.job_id = metrics.save(&router, result.success);
+            result
+        }
+        None => run_spec_agent(prompt, workdir, &config.container_runtime).await?,
+    };
+
+    if let (Some(mock), Some(before)) = (mock, before) {
+        mock.record(prompt, workdir, &before, &result.output, &result.error, result.success)?;
+    }
+
+    Ok(result)
+}
+
+/// Run a prompt through Claude in the Docker sandbox (text output, no tools).
+async fn run_spec_agent(
+    prompt: &str,
+    workdir: &Path,
+    runtime: &ckrv_sandbox::RuntimeConfig,
+) -> anyhow::Result<Generation> {
+    let runtime = ckrv_sandbox::ContainerRuntime::resolve(runtime);
+    let sandbox = DockerSandbox::with_runtime(ckrv_sandbox::DefaultAllowList::default(), &runtime)
+        .map_err(|e| anyhow::anyhow!("Failed to create sandbox: {}", e))?;
+
+    let command = format!(
+        "claude -p {} --dangerously-skip-permissions --output-format text --tools \"\"",
+        shell_escape::escape(prompt.into())
+    );
+
+    let config = ExecuteConfig::new("", workdir.to_path_buf())
+        .shell(&command)
+        .with_timeout(Duration::from_secs(300));
+
+    let result = sandbox.execute(config).await
+        .map_err(|e| anyhow::anyhow!("Sandbox execution failed: {}", e))?;
+
+    Ok(Generation {
+        success: result.success(),
+        output: result.stdout,
+        error: result.stderr,
+        usage: None,
+        cached: false,
+        job_id: None,
+    })
+}
+
 /// Generate a short name from a description.
 fn generate_short_name(description: &str) -> String {
     // Extract meaningful words and create a short identifier
@@ -3,15 +3,25 @@
 //! This command initiates a multi-step workflow (like Plan -> Implement)
 //! using an AI agent in a sandboxed environment.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use clap::Args;
 use serde::{Deserialize, Serialize};
 
 use ckrv_core::{
-    runner::{RunnerConfig, WorkflowRunner},
-    AgentTask, Workflow,
+    agent_config::AgentConfigError,
+    runner::{RunnerConfig, WorkflowRunResult, WorkflowRunner},
+    AgentProfile, AgentTask, AgentType, AgentsFile, Config, EventHandler, FailureKind, JobEvent,
+    Workflow,
 };
+use ckrv_metrics::{
+    DefaultMetricsCollector, FileMetricsStorage, Metrics, MetricsCollector, MetricsStorage,
+    PricingCatalog, StepMetrics, TokenUsageEntry, ToolCallEntry,
+};
+use ckrv_model::TokenUsage;
+use ckrv_sandbox::OutputStream;
 
 use crate::ui::UiContext;
 
@@ -49,6 +59,10 @@ pub struct TaskArgs {
     /// Use existing worktree path instead of creating one.
     #[arg(long)]
     pub use_worktree: Option<PathBuf>,
+
+    /// Timeout per workflow step in seconds.
+    #[arg(long, default_value_t = 300)]
+    pub step_timeout: u64,
 }
 
 /// JSON output events for task execution.
@@ -71,6 +85,21 @@ enum TaskEvent {
         step_id: String,
         error: String,
     },
+    StepTimedOut {
+        step_id: String,
+        timeout_secs: u64,
+    },
+    Output {
+        step_id: String,
+        stream: OutputStream,
+        line: String,
+    },
+    Fallback {
+        step_id: String,
+        from: String,
+        to: String,
+        failure: FailureKind,
+    },
     Completed {
         task_id: String,
         duration_ms: u64,
@@ -94,6 +123,58 @@ fn emit_event(event: &TaskEvent, json: bool) {
     }
 }
 
+/// Prints runner progress and live agent output.
+struct TaskEventPrinter {
+    json: bool,
+    step_names: HashMap<String, String>,
+}
+
+impl EventHandler for TaskEventPrinter {
+    fn handle(&self, event: JobEvent) {
+        let event = match event {
+            JobEvent::StepStarted { step_id } => {
+                let step_name = self.step_names.get(&step_id).cloned().unwrap_or_default();
+                if !self.json {
+                    eprintln!("▶ {step_name} ({step_id})");
+                }
+                TaskEvent::StepStarted { step_id, step_name }
+            }
+            JobEvent::StepCompleted { step_id, duration_ms } => {
+                if !self.json {
+                    eprintln!("✓ {step_id} completed in {duration_ms}ms");
+                }
+                TaskEvent::StepCompleted { step_id, duration_ms }
+            }
+            JobEvent::StepFailed { step_id, error } => {
+                if !self.json {
+                    eprintln!("✗ {step_id} failed");
+                }
+                TaskEvent::StepFailed { step_id, error }
+            }
+            JobEvent::StepTimedOut { step_id, timeout_secs } => {
+                if !self.json {
+                    eprintln!("✗ {step_id} timed out after {timeout_secs}s");
+                }
+                TaskEvent::StepTimedOut { step_id, timeout_secs }
+            }
+            JobEvent::AgentOutput { step_id, stream, line } => {
+                if !self.json {
+                    eprintln!("  {line}");
+                }
+                TaskEvent::Output { step_id, stream, line }
+            }
+            JobEvent::AgentFallback { step_id, from, to, failure } => {
+                if !self.json {
+                    eprintln!("↪ {step_id}: agent '{from}' failed ({failure}), falling back to '{to}'");
+                }
+                TaskEvent::Fallback { step_id, from, to, failure }
+            }
+            _ => return,
+        };
+        emit_event(&event, self.json);
+    }
+}
+
 /// Execute the task command.
 ///
 /// # Errors
@@ -112,16 +193,16 @@ pub async fn execute(args: TaskArgs, json: bool, ui: &UiContext) -> anyhow::Resu
     let target = &args.target;
     let target_is_id = target.starts_with('T') && target.len() == 4 && target[1..].chars().all(char::is_numeric);
 
+    // Spec directory for the current branch (used for task lookup and prompt helpers)
+    let spec_dir = detect_spec_dir(&cwd);
+    let spec_id = spec_dir
+        .as_ref()
+        .and_then(|dir| dir.file_name())
+        .map_or_else(|| workflow.name.clone(), |name| name.to_string_lossy().into_owned());
+
     let (description, task_id): (String, String) = if target_is_id {
          // Auto-detect spec and look up task
-         let branch_output = std::process::Command::new("git").args(["symbolic-ref", "--short", "HEAD"]).output().ok();
-         let branch = branch_output.and_then(|o| if o.status.success() { String::from_utf8(o.stdout).ok().map(|s| s.trim().to_string()) } else { None });
-         
-         let detected_path = if let Some(b) = branch {
-             let p = cwd.join(".specs").join(&b);
-             let yaml = p.join("tasks.yaml");
-             if yaml.exists() { Some(yaml) } else { None }
-         } else { None };
+         let detected_path = spec_dir.as_ref().map(|p| p.join("tasks.yaml")).filter(|p| p.exists());
          
          let spec_task = if let Some(path) = detected_path {
               let content = std::fs::read_to_string(&path).unwrap_or_default();
@@ -216,28 +297,50 @@ pub async fn execute(args: TaskArgs, json: bool, ui: &UiContext) -> anyhow::Resu
     task.save(&cwd)?;
 
     // Load agent configuration from .chakravarti/agents.yaml
-    let (openrouter_api_key, openrouter_model, openrouter_base_url) = load_agent_config(&cwd, &args.agent);
+    let (selected_agent, agents) = load_agent_profiles(&cwd, &args.agent)?;
+    let base_agent = selected_agent.unwrap_or_else(|| {
+        let binary = AgentType::from_binary(&args.agent).backend().default_binary();
+        AgentProfile::new(&args.agent, binary)
+    });
 
     // Create runner and execute
-    // Note: agent_binary is the actual CLI binary (always "claude"),
-    // while args.agent is an agent ID used only for config lookup above
+    // Note: agent_binary is the actual CLI binary (claude unless agents.yaml sets
+    // binary_path), while args.agent is an agent ID used for config lookup above.
+    // Workflow steps may switch to any other agent in `agents`.
     let config = RunnerConfig {
-        agent_binary: "claude".to_string(),  // Always use claude binary
+        agent_binary: base_agent.binary,
         use_sandbox: !args.no_sandbox,
         keep_container: args.keep_container,
-        openrouter_api_key,
-        openrouter_model,
-        openrouter_base_url,
+        openrouter_api_key: base_agent.openrouter_api_key,
+        openrouter_model: base_agent.model,
+        openrouter_base_url: base_agent.openrouter_base_url,
+        spec_dir,
+        step_timeout_secs: args.step_timeout,
+        agent_id: Some(base_agent.id),
+        agents,
+        project_root: Some(cwd.clone()),
+        container_runtime: Config::load_project(&cwd)?.container_runtime,
         ..Default::default()
     };
 
-    let runner = WorkflowRunner::new(config);
+    let printer = TaskEventPrinter {
+        json,
+        step_names: workflow.steps.iter().map(|s| (s.id.clone(), s.name.clone())).collect(),
+    };
+    let runner = WorkflowRunner::new(config).with_event_handler(Arc::new(printer));
 
     // Run the workflow
+    let collector = DefaultMetricsCollector::new().with_pricing(PricingCatalog::load(&cwd));
+    collector.start_job(&task_id, &spec_id);
     let result = runner.run(&workflow, &mut task, &cwd).await;
 
     match result {
         Ok(run_result) => {
+            let metrics = record_metrics(&collector, &run_result);
+            if let Err(e) = FileMetricsStorage::new(cwd.join(".chakravarti")).save(&metrics) {
+                tracing::warn!(error = %e, "Could not save task metrics");
+            }
+
             emit_event(
                 &TaskEvent::Completed {
                     task_id: task_id.clone(),
@@ -256,6 +359,14 @@ pub async fn execute(args: TaskArgs, json: bool, ui: &UiContext) -> anyhow::Resu
                 } else {
                     ui.error("Task Complete", "Completed with some failures");
                 }
+                if metrics.total_tokens() > 0 || metrics.cost.total_usd > 0.0 {
+                    eprintln!(
+                        "Usage: {} tokens, ${:.4} (see `ckrv report {}`)",
+                        metrics.total_tokens(),
+                        metrics.cost.total_usd,
+                        task_id
+                    );
+                }
                 eprintln!("Results at: .ckrv/tasks/{}/", task_id);
             }
 
@@ -283,8 +394,49 @@ pub async fn execute(args: TaskArgs, json: bool, ui: &UiContext) -> anyhow::Resu
     }
 }
 
+/// Feed each step's reported usage and tool calls into `collector` and
+/// finish the job.
+fn record_metrics(collector: &DefaultMetricsCollector, run: &WorkflowRunResult) -> Metrics {
+    for result in &run.step_results {
+        let model = result
+            .model
+            .clone()
+            .or_else(|| result.agent.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut step = StepMetrics::new(&result.step_id, result.duration_ms);
+
+        if let Some(ref usage) = result.usage {
+            let tokens = TokenUsage::from(usage);
+            let (input, output) = (
+                u64::from(tokens.prompt_tokens),
+                u64::from(tokens.completion_tokens),
+            );
+            collector.record_reported_usage(&model, input, output, usage.cost_usd);
+            step.tokens = Some(TokenUsageEntry {
+                model: model.clone(),
+                input_tokens: input,
+                output_tokens: output,
+            });
+            step.cost_usd = usage.cost_usd;
+            step.turns = usage.turns;
+        }
+        step.model = Some(model);
+        step.tool_calls = result
+            .tool_calls
+            .iter()
+            .map(|call| ToolCallEntry {
+                name: call.name.clone(),
+                target: call.target.clone(),
+                failed: call.failed,
+            })
+            .collect();
+        collector.record_step(step);
+    }
+    collector.finish_job(run.success)
+}
+
 /// Load a workflow by name or path.
-fn load_workflow(name_or_path: &str, base_dir: &std::path::Path) -> Result<Workflow, anyhow::Error> {
+pub(crate) fn load_workflow(name_or_path: &str, base_dir: &std::path::Path) -> Result<Workflow, anyhow::Error> {
     // Check if it's a file path
     let path = PathBuf::from(name_or_path);
     if path.exists() {
@@ -313,6 +465,22 @@ fn load_workflow(name_or_path: &str, base_dir: &std::path::Path) -> Result<Workf
     Err(anyhow::anyhow!("Workflow '{}' not found. Create it in .ckrv/workflows/ as .yml or .yaml", name_or_path))
 }
 
+/// Find the spec directory (`.specs/<branch>`) for the current git branch.
+pub(crate) fn detect_spec_dir(cwd: &std::path::Path) -> Option<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["symbolic-ref", "--short", "HEAD"])
+        .current_dir(cwd)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let branch = String::from_utf8(output.stdout).ok()?;
+    let dir = cwd.join(".specs").join(branch.trim());
+    dir.exists().then_some(dir)
+}
+
 #[derive(Deserialize)]
 struct TaskFile {
     tasks: Vec<SpecTask>,
@@ -327,98 +495,53 @@ struct SpecTask {
     file: String,
 }
 
-/// Load agent configuration from .chakravarti/agents.yaml
-/// Returns (api_key, model, base_url) for OpenRouter if configured
-fn load_agent_config(cwd: &std::path::Path, agent_arg: &str) -> (Option<String>, Option<String>, Option<String>) {
-    // Check global path first
-    let agents_path = dirs::config_dir()
-        .map(|d| d.join("chakravarti").join("agents.yaml"))
-        .filter(|p| p.exists())
-        .unwrap_or_else(|| cwd.join(".chakravarti").join("agents.yaml"));
-    
-    if !agents_path.exists() {
-        return (None, None, None);
-    }
-
-    #[derive(Deserialize)]
-    struct AgentsFile {
-        agents: Vec<AgentEntry>,
-    }
-
-    #[derive(Deserialize)]
-    struct AgentEntry {
-        id: String,
-        #[allow(dead_code)]
-        name: String,
-        agent_type: String,
-        is_default: bool,
-        #[allow(dead_code)]
-        enabled: bool,
-        openrouter: Option<OpenRouterEntry>,
-    }
-
-    #[derive(Deserialize)]
-    struct OpenRouterEntry {
-        api_key: Option<String>,
-        model: String,
-        base_url: Option<String>,
-    }
-
-    let content = match std::fs::read_to_string(&agents_path) {
-        Ok(c) => c,
-        Err(_) => return (None, None, None),
-    };
-
-    let file: AgentsFile = match serde_yaml::from_str(&content) {
-        Ok(f) => f,
-        Err(_) => return (None, None, None),
-    };
-
-    // Helper to check if agent type is OpenRouter-compatible
-    let is_openrouter_type = |t: &str| -> bool {
-        t == "claude_openrouter" || t == "claude_open_router"
+/// Load agent profiles from agents.yaml.
+///
+/// Returns the agent selected by `--agent` (by ID or model, falling back to
+/// the default agent) together with every enabled agent, for per-step
+/// workflow overrides. An undefined `${VAR}` is an error rather than a
+/// warning, since falling back would silently run a different agent.
+pub(crate) fn load_agent_profiles(
+    cwd: &std::path::Path,
+    agent_arg: &str,
+) -> anyhow::Result<(Option<AgentProfile>, Vec<AgentProfile>)> {
+    let agents = match AgentsFile::load_for_project(cwd) {
+        Ok(agents) => agents,
+        Err(e @ AgentConfigError::Interpolation(_)) => return Err(e.into()),
+        Err(e) => {
+            tracing::warn!(error = %e, "Ignoring invalid agents configuration");
+            return Ok((None, Vec::new()));
+        }
     };
+    let profiles = agents.profiles();
 
-    // Priority 1: If agent_arg is provided and looks like an agent ID, find that specific agent
+    // Priority 1: If agent_arg is provided and names a configured agent, use it
     if !agent_arg.is_empty() && agent_arg != "claude" {
-        if let Some(agent) = file.agents.iter().find(|a| a.id == agent_arg) {
-            if is_openrouter_type(&agent.agent_type) {
-                if let Some(ref or_config) = agent.openrouter {
-                    tracing::info!(
-                        agent_id = %agent.id,
-                        model = %or_config.model,
-                        has_api_key = or_config.api_key.is_some(),
-                        "Using specified OpenRouter agent configuration"
-                    );
-                    return (
-                        or_config.api_key.clone(),
-                        Some(or_config.model.clone()),
-                        or_config.base_url.clone(),
-                    );
-                }
-            }
-        }
-    }
-
-    // Priority 2: Find the default agent with OpenRouter type
-    let default_openrouter = file.agents.iter().find(|a| {
-        a.is_default && is_openrouter_type(&a.agent_type) && a.openrouter.is_some()
-    });
-
-    if let Some(agent) = default_openrouter {
-        if let Some(ref or_config) = agent.openrouter {
+        if let Some(agent) = agents.by_model(agent_arg) {
+            let profile = agent.to_profile();
             tracing::info!(
-                model = %or_config.model,
-                has_api_key = or_config.api_key.is_some(),
-                "Using default OpenRouter agent configuration"
-            );
-            return (
-                or_config.api_key.clone(),
-                Some(or_config.model.clone()),
-                or_config.base_url.clone(),
+                agent_id = %profile.id,
+                agent_type = ?profile.agent_type,
+                model = ?profile.model,
+                has_api_key = profile.uses_openrouter(),
+                "Using specified agent configuration"
             );
+            return Ok((Some(profile), profiles));
         }
     }
 
-    (None, None, None)
+    // Priority 2: The default agent
+    let selected = agents.default_agent().map(|agent| {
+        let profile = agent.to_profile();
+        tracing::info!(
+            agent_id = %profile.id,
+            agent_type = ?profile.agent_type,
+            model = ?profile.model,
+            has_api_key = profile.uses_openrouter(),
+            "Using default agent configuration"
+        );
+        profile
+    });
+
+    Ok((selected, profiles))
 }
@@ -0,0 +1,300 @@
+//! Workflow commands - lint, preview and inspect workflow definitions.
+
+use std::collections::HashMap;
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use ckrv_core::{
+    agent_profile, prompt::PARTIALS_DIR, structured_output, AgentProfile, PromptRenderer,
+    RenderContext, StepOutputs, Workflow,
+};
+
+use super::task::{detect_spec_dir, load_agent_profiles, load_workflow};
+
+/// Arguments for the workflow command
+#[derive(Args)]
+pub struct WorkflowArgs {
+    #[command(subcommand)]
+    pub command: WorkflowCommand,
+}
+
+/// Workflow subcommands
+#[derive(Subcommand)]
+pub enum WorkflowCommand {
+    /// Check a workflow's schema and dry-render every prompt in strict mode
+    Validate {
+        /// Workflow name (from .ckrv/workflows/) or path to a YAML file
+        workflow: String,
+    },
+    /// Print the fully rendered prompt for every step
+    Render {
+        /// Workflow name (from .ckrv/workflows/) or path to a YAML file
+        workflow: String,
+
+        /// Input value as key=value (repeatable, e.g. --input description="Add login")
+        #[arg(short, long = "input", value_parser = parse_input)]
+        inputs: Vec<(String, String)>,
+    },
+    /// Print the dependencies between steps
+    Graph {
+        /// Workflow name (from .ckrv/workflows/) or path to a YAML file
+        #[arg(default_value = "swe")]
+        workflow: String,
+    },
+}
+
+/// JSON output for workflow validate command
+#[derive(Serialize)]
+struct WorkflowValidateOutput {
+    valid: bool,
+    workflow: String,
+    errors: Vec<StepErrorOutput>,
+}
+
+#[derive(Serialize)]
+struct StepErrorOutput {
+    step: Option<String>,
+    message: String,
+}
+
+/// JSON output for a rendered step prompt
+#[derive(Serialize)]
+struct RenderedStepOutput {
+    step: String,
+    prompt: String,
+}
+
+/// JSON output for a step in the dependency graph
+#[derive(Serialize)]
+struct GraphNodeOutput {
+    step: String,
+    depends_on: Vec<String>,
+}
+
+fn parse_input(raw: &str) -> Result<(String, String), String> {
+    raw.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("invalid input '{raw}': expected key=value"))
+}
+
+/// Execute the workflow command
+pub fn execute(args: WorkflowArgs, json: bool) -> anyhow::Result<()> {
+    match args.command {
+        WorkflowCommand::Validate { workflow } => execute_validate(&workflow, json),
+        WorkflowCommand::Render { workflow, inputs } => execute_render(&workflow, inputs, json),
+        WorkflowCommand::Graph { workflow } => execute_graph(&workflow, json),
+    }
+}
+
+/// Inputs the runner always provides, with placeholders for missing values.
+fn sample_inputs(inputs: Vec<(String, String)>) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = inputs.into_iter().collect();
+    let description = map
+        .get("description")
+        .cloned()
+        .unwrap_or_else(|| "<description>".to_string());
+    map.entry("description".to_string())
+        .or_insert_with(|| description.clone());
+    map.entry("prompt".to_string()).or_insert(description);
+    map
+}
+
+/// Record placeholder values for a step's declared outputs.
+fn record_placeholder_outputs(context: &mut RenderContext, workflow: &Workflow, step_id: &str) {
+    let Some(step) = workflow.get_step(step_id) else {
+        return;
+    };
+    let mut outputs = StepOutputs::new();
+    for output in &step.outputs {
+        outputs = outputs.with_output(
+            &output.name,
+            format!("<steps.{step_id}.outputs.{}>", output.name),
+        );
+    }
+    context.steps.insert(step_id.to_string(), outputs);
+}
+
+fn execute_validate(name_or_path: &str, json: bool) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let mut errors: Vec<StepErrorOutput> = Vec::new();
+
+    // Schema checks happen while loading
+    let workflow = match load_workflow(name_or_path, &cwd) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            errors.push(StepErrorOutput {
+                step: None,
+                message: e.to_string(),
+            });
+            None
+        }
+    };
+
+    if let Some(ref workflow) = workflow {
+        // Strict dry-render: each step only sees the outputs of the steps before it
+        let mut renderer = PromptRenderer::new();
+        renderer.register_placeholder_helpers();
+        if let Err(e) = renderer.load_partials(&cwd.join(PARTIALS_DIR)) {
+            errors.push(StepErrorOutput {
+                step: None,
+                message: e.to_string(),
+            });
+        }
+
+        let mut context = RenderContext::new();
+        context.set_inputs(sample_inputs(Vec::new()));
+
+        for step in &workflow.steps {
+            if let Err(e) = renderer.render(&step.prompt, &context) {
+                errors.push(StepErrorOutput {
+                    step: Some(step.id.clone()),
+                    message: e.to_string(),
+                });
+            }
+            for referenced in step.referenced_steps() {
+                if workflow.get_step(&referenced).is_none() {
+                    errors.push(StepErrorOutput {
+                        step: Some(step.id.clone()),
+                        message: format!("references unknown step '{referenced}'"),
+                    });
+                } else if !context.steps.contains_key(&referenced) {
+                    errors.push(StepErrorOutput {
+                        step: Some(step.id.clone()),
+                        message: format!("references step '{referenced}' which runs later"),
+                    });
+                }
+            }
+            record_placeholder_outputs(&mut context, workflow, &step.id);
+        }
+
+        errors.extend(check_agents(workflow, &cwd));
+    }
+
+    let valid = errors.is_empty();
+    let name = workflow
+        .as_ref()
+        .map_or_else(|| name_or_path.to_string(), |w| w.name.clone());
+
+    if json {
+        let output = WorkflowValidateOutput {
+            valid,
+            workflow: name,
+            errors,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if valid {
+        let steps = workflow.as_ref().map_or(0, |w| w.steps.len());
+        println!("✓ Workflow is valid: {name} ({steps} steps)");
+    } else {
+        eprintln!("✗ Workflow validation failed: {name}");
+        for error in &errors {
+            match &error.step {
+                Some(step) => eprintln!("  • {step}: {}", error.message),
+                None => eprintln!("  • {}", error.message),
+            }
+        }
+    }
+
+    if !valid {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Errors for agents in `defaults.tool` and step `agent` fields that are
+/// neither agents.yaml profiles nor known agent CLIs.
+fn check_agents(workflow: &Workflow, cwd: &std::path::Path) -> Vec<StepErrorOutput> {
+    let (selected, profiles) = match load_agent_profiles(cwd, "") {
+        Ok(agents) => agents,
+        Err(e) => {
+            return vec![StepErrorOutput {
+                step: None,
+                message: e.to_string(),
+            }];
+        }
+    };
+    let base = selected.unwrap_or_else(|| AgentProfile::new("claude", "claude"));
+    agent_profile::unknown_agents(workflow, &base, &profiles)
+        .into_iter()
+        .map(|unknown| StepErrorOutput {
+            message: unknown.to_string(),
+            step: unknown.step,
+        })
+        .collect()
+}
+
+fn execute_render(
+    name_or_path: &str,
+    inputs: Vec<(String, String)>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let workflow = load_workflow(name_or_path, &cwd)?;
+
+    let spec_dir = detect_spec_dir(&cwd);
+    let renderer = PromptRenderer::for_project(&cwd, &cwd, spec_dir.as_deref())?;
+
+    let mut context = RenderContext::new();
+    context.set_inputs(sample_inputs(inputs));
+
+    let mut prompts: Vec<RenderedStepOutput> = Vec::new();
+    for step in &workflow.steps {
+        let mut prompt = renderer
+            .render(&step.prompt, &context)
+            .map_err(|e| anyhow::anyhow!("Step '{}': {e}", step.id))?;
+        if let Some(instructions) = structured_output::output_instructions(&step.outputs) {
+            prompt.push_str(&instructions);
+        }
+        prompts.push(RenderedStepOutput {
+            step: step.id.clone(),
+            prompt,
+        });
+        record_placeholder_outputs(&mut context, &workflow, &step.id);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&prompts)?);
+    } else {
+        for (i, step) in prompts.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("=== {} ===", step.step);
+            println!("{}", step.prompt.trim_end());
+        }
+    }
+
+    Ok(())
+}
+
+fn execute_graph(name_or_path: &str, json: bool) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let workflow = load_workflow(name_or_path, &cwd)?;
+
+    let nodes: Vec<GraphNodeOutput> = workflow
+        .step_dependencies()
+        .into_iter()
+        .map(|(step, depends_on)| GraphNodeOutput {
+            step: step.to_string(),
+            depends_on,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&nodes)?);
+    } else {
+        println!("{}", workflow.name);
+        for node in &nodes {
+            if node.depends_on.is_empty() {
+                println!("  {}", node.step);
+            } else {
+                println!("  {} <- {}", node.step, node.depends_on.join(", "));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Text generation through the model API.
+//!
+//! `ckrv spec new/design/tasks` and `ckrv plan` only need text back, so they
+//! can call a model provider directly through `ModelRouter` instead of
+//! launching a coding agent. `generation` in `.chakravarti/config.json`
+//! selects the backend, provider and model.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ckrv_core::secrets::SecretResolver;
+use ckrv_core::{Config, GenerationBackend, ProviderKind};
+use ckrv_metrics::{DefaultMetricsCollector, FileMetricsStorage, MetricsCollector, MetricsStorage};
+use ckrv_model::{
+    openrouter, CompletionRequest, Message, ModelRouter, PricingCatalog, ResponseCache,
+    RoutingContext, StreamEvent, TaskType, TokenUsage,
+};
+use futures::StreamExt;
+
+/// How long a cached `OpenRouter` model list is used before refetching.
+const PRICING_CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Text produced by a generation step.
+pub struct Generation {
+    /// Whether generation succeeded.
+    pub success: bool,
+    /// Generated text.
+    pub output: String,
+    /// Error output when generation failed.
+    pub error: String,
+    /// Token usage, reported when the model API was called.
+    pub usage: Option<TokenUsage>,
+    /// Whether the text came from the response cache.
+    pub cached: bool,
+    /// Job ID its metrics were saved under, for `ckrv report`.
+    pub job_id: Option<String>,
+}
+
+/// Metrics for one generation through the model API, saved as job
+/// `<spec_id>-<step>` so `ckrv report` shows its tokens, cost and cache hits.
+pub struct GenerationMetrics {
+    collector: DefaultMetricsCollector,
+    dir: PathBuf,
+}
+
+impl GenerationMetrics {
+    /// Start timing generation step `step` (e.g. `design`) for `spec_id`.
+    pub fn start(root: &Path, spec_id: &str, step: &str) -> Self {
+        let collector = DefaultMetricsCollector::new().with_pricing(PricingCatalog::load(root));
+        collector.start_job(&format!("{spec_id}-{step}"), spec_id);
+        Self { collector, dir: root.join(".chakravarti") }
+    }
+
+    /// Record what `router` spent and served from its cache, and save the
+    /// metrics. Returns the job ID, or `None` if they couldn't be saved.
+    pub fn save(self, router: &ModelRouter, success: bool) -> Option<String> {
+        if let Ok(budget) = router.budget().lock() {
+            for (model, (input, output)) in &budget.tokens_by_model {
+                self.collector.record_tokens(model, *input, *output);
+            }
+            for (model, (input, output)) in &budget.cached_tokens_by_model {
+                self.collector.record_cache_hit(model, *input, *output);
+            }
+        }
+        let metrics = self.collector.finish_job(success);
+        match FileMetricsStorage::new(&self.dir).save(&metrics) {
+            Ok(()) => Some(metrics.job_id),
+            Err(e) => {
+                tracing::warn!(error = %e, "Could not save generation metrics");
+                None
+            }
+        }
+    }
+}
+
+/// The router to generate with, or `None` when generation should run the
+/// agent instead.
+///
+/// In `auto` mode the model API is used when any provider is available; a
+/// configured provider that can't be set up (e.g. a missing secret) is
+/// logged as a warning before falling back to the agent. `api` mode fails
+/// if no provider is usable. The router prices requests with the
+/// project's pricing catalog, and with `use_cache` reuses responses from the
+/// cache when `response_cache` is enabled in config.
+///
+/// # Errors
+///
+/// Returns an error in `api` mode if no usable provider is configured.
+pub async fn api_router(
+    config: &Config,
+    root: &Path,
+    use_cache: bool,
+) -> anyhow::Result<Option<ModelRouter>> {
+    let build = || -> anyhow::Result<ModelRouter> {
+        let mut secrets = SecretResolver::new(Some(root));
+        let router = ModelRouter::from_config(config, &mut secrets)?;
+        Ok(match config.generation.provider {
+            Some(ref provider) => router.with_provider(provider)?,
+            None => router,
+        })
+    };
+    let router = match config.generation.backend {
+        GenerationBackend::Agent => None,
+        GenerationBackend::Auto => match build() {
+            Ok(router) => Some(router),
+            // Nothing configured: the agent is the expected path
+            Err(e) if config.providers.is_empty() && config.generation.provider.is_none() => {
+                tracing::debug!(error = %e, "No model API available, using the agent");
+                None
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Model API unavailable, falling back to the agent");
+                None
+            }
+        },
+        GenerationBackend::Api => Some(build()?),
+    };
+    let Some(router) = router else {
+        return Ok(None);
+    };
+    refresh_openrouter_prices(config, root).await;
+    let cache = use_cache
+        .then(|| ResponseCache::from_config(root, &config.response_cache))
+        .flatten();
+    Ok(Some(router.with_pricing(PricingCatalog::load(root)).with_cache(cache)))
+}
+
+/// Refresh the cached `OpenRouter` model list when an `OpenRouter` provider
+/// is configured, so its models are priced. Failures only warn: the bundled
+/// prices still apply.
+async fn refresh_openrouter_prices(config: &Config, root: &Path) {
+    let Some(provider) = config
+        .providers
+        .values()
+        .find(|p| p.kind == ProviderKind::OpenRouter)
+    else {
+        return;
+    };
+    let base_url = provider
+        .base_url
+        .as_deref()
+        .unwrap_or(openrouter::OPENROUTER_BASE_URL)
+        .trim_end_matches('/');
+    let api_key = provider
+        .api_key
+        .as_deref()
+        .and_then(|key| SecretResolver::new(Some(root)).resolve(key).ok());
+    if let Err(e) =
+        openrouter::refresh_cache(root, base_url, api_key.as_deref(), PRICING_CACHE_MAX_AGE).await
+    {
+        tracing::warn!(error = %e, "Could not refresh OpenRouter prices");
+    }
+}
+
+/// The model generation requests go to: `generation.model`, else
+/// `planner_model`, else the router's planning choice for the project's
+/// `default_optimize` mode.
+pub fn model(router: &ModelRouter, config: &Config) -> String {
+    config.generation.model.clone()
+        .or_else(|| config.planner_model.clone())
+        .unwrap_or_else(|| router.select_model(&RoutingContext {
+            task_type: TaskType::Planning,
+            optimize: config.optimize_mode(),
+            ..RoutingContext::default()
+        }))
+}
+
+/// Stream a prompt through the model API and report token usage.
+///
+/// Interactive terminals show the latest generated line in a spinner. In
+/// JSON mode each generated line is echoed to stderr as it arrives, so
+/// callers such as the UI can relay progress live.
+pub async fn complete(
+    router: &ModelRouter,
+    config: &Config,
+    prompt: &str,
+    json: bool,
+) -> Generation {
+    let model = model(router, config);
+    let ui = crate::ui::UiContext::new(json);
+    let spinner = ui.spinner(format!("Generating with {model}"));
+
+    let request = CompletionRequest {
+        model: model.clone(),
+        messages: vec![Message::user(prompt)],
+        max_tokens: Some(config.generation.max_tokens.unwrap_or(8192)),
+        ..CompletionRequest::default()
+    };
+    let mut result = Generation { success: false, output: String::new(), error: String::new(), usage: None, cached: false, job_id: None };
+    let mut stream = match router.complete_stream(request).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            spinner.error(&format!("Generation failed with {model}"));
+            result.error = e.to_string();
+            return result;
+        }
+    };
+
+    let mut pending = String::new();
+    let mut last_line = String::new();
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(StreamEvent::Delta(text)) => {
+                pending.push_str(&text);
+                while let Some(end) = pending.find('\n') {
+                    let line: String = pending.drain(..=end).collect();
+                    let line = line.trim_end();
+                    if json {
+                        eprintln!("{line}");
+                    }
+                    if !line.trim().is_empty() {
+                        last_line = line.trim().to_string();
+                    }
+                }
+                let current = if pending.trim().is_empty() { &last_line } else { &pending };
+                let preview: String = current.trim().chars().take(60).collect();
+                spinner.set_message(&format!("Generating with {model}: {preview}"));
+            }
+            Ok(StreamEvent::Done(response)) => {
+                if response.finish_reason == "max_tokens" || response.finish_reason == "length" {
+                    result.error = "Output truncated at max_tokens; raise generation.max_tokens in config.json".to_string();
+                }
+                result.success = result.error.is_empty();
+                result.output = response.content;
+                result.usage = Some(response.usage);
+                result.cached = response.cached;
+            }
+            Err(e) => {
+                result.error = e.to_string();
+                break;
+            }
+        }
+    }
+    if json && !pending.trim().is_empty() {
+        eprintln!("{}", pending.trim_end());
+    }
+
+    match result.usage {
+        Some(ref usage) if result.success => {
+            let summary = if result.cached {
+                format!("Reused cached response from {model}")
+            } else {
+                format!(
+                    "Generated with {model} ({} in, {} out tokens)",
+                    usage.prompt_tokens, usage.completion_tokens
+                )
+            };
+            if ui.is_interactive {
+                spinner.success(&summary);
+            } else if !json {
+                eprintln!("{summary}");
+            }
+        }
+        _ => spinner.error(&format!("Generation failed with {model}")),
+    }
+    result
+}
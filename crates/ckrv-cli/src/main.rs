@@ -2,10 +2,12 @@
 //!
 //! This binary provides the `ckrv` command-line interface.
 
+use ckrv_core::secrets::RedactingWriter;
 use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 mod cloud;
 mod commands;
+mod generation;
 mod prompts;
 pub mod ui;
 
@@ -67,6 +69,22 @@ enum Commands {
     #[command(display_order = 7)]
     Fix(commands::fix::FixArgs),
 
+    /// Validate, render or graph agent workflows
+    #[command(display_order = 12)]
+    Workflow(commands::workflow::WorkflowArgs),
+
+    /// List, add and health-check coding agents
+    #[command(display_order = 13)]
+    Agents(commands::agents::AgentsArgs),
+
+    /// Manage encrypted API keys referenced as secret:<name>
+    #[command(display_order = 14)]
+    Secrets(commands::secrets::SecretsArgs),
+
+    /// Check the container runtime used for sandboxed agents
+    #[command(display_order = 15)]
+    Doctor(commands::doctor::DoctorArgs),
+
     /// Execute a workflow-based agent task
     #[command(hide = true)]
     Task(commands::task::TaskArgs),
@@ -123,7 +141,7 @@ async fn main() -> anyhow::Result<()> {
 
     tracing_subscriber::fmt()
         .with_env_filter(filter)
-        .with_writer(std::io::stderr)
+        .with_writer(|| RedactingWriter::new(std::io::stderr()))
         .init();
 
     // Initialize UI Context
@@ -135,6 +153,10 @@ async fn main() -> anyhow::Result<()> {
         Some(Commands::Plan(args)) => commands::plan::execute(args, cli.json, &ui).await,
         Some(Commands::Run(args)) => commands::run::execute(args, cli.json, &ui).await,
         Some(Commands::Task(args)) => commands::task::execute(args, cli.json, &ui).await,
+        Some(Commands::Workflow(args)) => commands::workflow::execute(args, cli.json),
+        Some(Commands::Agents(args)) => commands::agents::execute(args, cli.json, &ui).await,
+        Some(Commands::Secrets(args)) => commands::secrets::execute(args, cli.json),
+        Some(Commands::Doctor(args)) => commands::doctor::execute(args, cli.json).await,
         Some(Commands::Status(args)) => commands::status::execute(args, cli.json, &ui).await,
         Some(Commands::Diff(args)) => commands::diff::execute(args, cli.json, &ui).await,
         Some(Commands::Verify(args)) => commands::verify::execute(args, cli.json, &ui).await,
@@ -3,6 +3,8 @@
 //! This module provides prompt construction utilities for Claude Code
 //! to generate rich specifications, clarifications, and designs.
 
+use ckrv_model::{Fitted, PromptBudget};
+
 /// Embedded spec template
 pub const SPEC_TEMPLATE: &str = include_str!("templates/spec-template.yaml");
 
@@ -127,6 +129,69 @@ Output the complete markdown document now:"#,
     )
 }
 
+/// A model's prices for planning prompts, e.g. " ($0.30 in / $1.20 out per
+/// 1M tokens)", or nothing if the catalog doesn't know the model.
+pub fn price_note(pricing: &ckrv_metrics::PricingCatalog, model: &str) -> String {
+    pricing.get(model).map_or_else(String::new, |p| {
+        format!(
+            " (${:.2} in / ${:.2} out per 1M tokens)",
+            p.input_cost_per_million, p.output_cost_per_million
+        )
+    })
+}
+
+/// Model the sandboxed Claude agent runs by default, for prompt budgeting.
+pub const AGENT_MODEL: &str = "claude-sonnet-4-5";
+
+/// Lines of context kept around each conflict hunk.
+pub const CONFLICT_CONTEXT_LINES: usize = 10;
+
+/// A prompt budget for `model` from the project's pricing catalog, with room
+/// already set aside for the prompt's fixed `instructions`.
+pub fn prompt_budget(root: &std::path::Path, model: &str, instructions: &str) -> PromptBudget {
+    let mut budget = PromptBudget::for_model(&ckrv_metrics::PricingCatalog::load(root), model);
+    budget.reserve(instructions);
+    budget
+}
+
+/// Warn on stderr when budgeting left content out of a prompt.
+pub fn warn_if_trimmed(fitted: &Fitted) {
+    if let Some(warning) = fitted.warning() {
+        eprintln!("⚠️  {warning}");
+    }
+}
+
+/// The conflict hunks of a file, each with `context_lines` lines either side and
+/// a header giving its first line number. Returns the whole file if it has
+/// no conflict markers.
+pub fn conflict_hunks(content: &str, context_lines: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut start = None;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("<<<<<<<") {
+            start = Some(i);
+        } else if line.starts_with(">>>>>>>") {
+            if let Some(first) = start.take() {
+                let range = (first.saturating_sub(context_lines), (i + context_lines + 1).min(lines.len()));
+                match ranges.last_mut() {
+                    Some(last) if range.0 <= last.1 => last.1 = range.1,
+                    _ => ranges.push(range),
+                }
+            }
+        }
+    }
+    if ranges.is_empty() {
+        return content.to_string();
+    }
+
+    ranges
+        .iter()
+        .map(|&(from, to)| format!("@@ line {} @@\n{}", from + 1, lines[from..to].join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Strip markdown code fences from AI output
 pub fn strip_yaml_fences(content: &str) -> String {
     let lines: Vec<&str> = content.lines().collect();
@@ -179,6 +244,22 @@ mod tests {
         assert_eq!(result, "key: value\nanother: thing");
     }
 
+    #[test]
+    fn test_conflict_hunks_keep_only_conflicts_with_context() {
+        let mut lines: Vec<String> = (1..=40).map(|i| format!("line {i}")).collect();
+        lines.splice(19..20, ["<<<<<<< HEAD", "ours", "=======", "theirs", ">>>>>>> feature"].map(String::from));
+        let hunks = conflict_hunks(&lines.join("\n"), 2);
+
+        assert!(hunks.starts_with("@@ line 18 @@\nline 18\nline 19\n<<<<<<< HEAD"));
+        assert!(hunks.ends_with(">>>>>>> feature\nline 21\nline 22"));
+        assert!(!hunks.contains("line 1\n"));
+    }
+
+    #[test]
+    fn test_conflict_hunks_without_markers_returns_file() {
+        assert_eq!(conflict_hunks("fn main() {}", 2), "fn main() {}");
+    }
+
     #[test]
     fn test_build_spec_prompt_contains_description() {
         let prompt = build_spec_prompt("Add user authentication", "001-auth");
@@ -0,0 +1,169 @@
+//! Integration tests for `ckrv agents`.
+//!
+//! - add/list/set-default/remove edit `.chakravarti/agents.yaml`
+//! - test checks the binary and credentials, then sends a prompt
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+/// A stand-in for Aider that answers and reports usage the way Aider does.
+const FAKE_AIDER: &str =
+    "#!/bin/sh\necho OK\necho 'Tokens: 1.2k sent, 30 received. Cost: $0.01 message, $0.01 session.'\n";
+
+fn ckrv(project: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(args)
+        .current_dir(project)
+        .env("XDG_CONFIG_HOME", project.join("config"))
+        .env("OPENROUTER_API_KEY", "sk-test")
+        .output()
+        .expect("Failed to execute ckrv")
+}
+
+fn setup_project() -> TempDir {
+    let project = TempDir::new().expect("temp dir");
+    std::fs::create_dir_all(project.path().join(".chakravarti")).expect("create .chakravarti");
+    project
+}
+
+#[test]
+fn test_agents_add_list_set_default_remove() {
+    let project = setup_project();
+    let dir = project.path();
+
+    assert!(ckrv(
+        dir,
+        &[
+            "agents",
+            "add",
+            "fast",
+            "--type",
+            "codex",
+            "--model",
+            "gpt-5-mini",
+            "--level",
+            "2"
+        ]
+    )
+    .status
+    .success());
+    assert!(ckrv(
+        dir,
+        &["agents", "add", "strong", "--type", "claude", "--default"]
+    )
+    .status
+    .success());
+
+    let output = ckrv(dir, &["--json", "agents", "list"]);
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json list");
+    assert_eq!(rows.as_array().map(Vec::len), Some(2));
+    assert_eq!(rows[0]["model"], "gpt-5-mini");
+    assert_eq!(rows[1]["default"], "✓");
+
+    assert!(ckrv(dir, &["agents", "set-default", "fast"])
+        .status
+        .success());
+    let yaml = std::fs::read_to_string(dir.join(".chakravarti/agents.yaml")).expect("agents.yaml");
+    let file: serde_yaml::Value = serde_yaml::from_str(&yaml).expect("yaml");
+    assert_eq!(file["agents"][0]["is_default"], true);
+    assert_eq!(file["agents"][1]["is_default"], false);
+
+    assert!(ckrv(dir, &["agents", "remove", "strong"]).status.success());
+    assert!(!ckrv(dir, &["agents", "remove", "strong"]).status.success());
+    assert!(!ckrv(dir, &["agents", "set-default", "missing"])
+        .status
+        .success());
+}
+
+#[test]
+fn test_agents_add_moves_api_key_into_secret_store() {
+    let project = setup_project();
+    let dir = project.path();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(["agents", "add", "router", "--model", "kimi-k2", "--api-key", "sk-or-plain-123"])
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("CKRV_SECRETS_PASSPHRASE", "pw")
+        .output()
+        .expect("Failed to execute ckrv");
+    assert!(
+        output.status.success(),
+        "add failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let yaml = std::fs::read_to_string(dir.join(".chakravarti/agents.yaml")).expect("agents.yaml");
+    assert!(!yaml.contains("sk-or-plain-123"));
+    let file: serde_yaml::Value = serde_yaml::from_str(&yaml).expect("yaml");
+    assert_eq!(file["agents"][0]["openrouter"]["api_key"], "secret:router-api-key");
+
+    let store = std::fs::read_to_string(dir.join(".chakravarti/secrets/secrets.enc"))
+        .expect("secret store written");
+    assert!(!store.contains("sk-or-plain-123"));
+
+    // References are kept as given
+    assert!(ckrv(
+        dir,
+        &["agents", "add", "other", "--api-key", "secret:shared-key"]
+    )
+    .status
+    .success());
+    let yaml = std::fs::read_to_string(dir.join(".chakravarti/agents.yaml")).expect("agents.yaml");
+    let file: serde_yaml::Value = serde_yaml::from_str(&yaml).expect("yaml");
+    assert_eq!(file["agents"][1]["openrouter"]["api_key"], "secret:shared-key");
+}
+
+#[test]
+fn test_agents_test_reports_latency_and_usage() {
+    let project = setup_project();
+    let dir = project.path();
+    let agent = dir.join("fake-aider");
+    std::fs::write(&agent, FAKE_AIDER).expect("write agent");
+    std::fs::set_permissions(&agent, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    let binary = agent.display().to_string();
+    assert!(ckrv(
+        dir,
+        &["agents", "add", "aider", "--type", "aider", "--binary", &binary]
+    )
+    .status
+    .success());
+
+    let output = ckrv(dir, &["--json", "agents", "test", "aider", "--no-sandbox"]);
+    assert!(
+        output.status.success(),
+        "agents test failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json report");
+    assert_eq!(report["binary"]["ok"], true);
+    assert_eq!(report["credentials"]["detail"], "$OPENROUTER_API_KEY");
+    assert_eq!(report["prompt"]["ok"], true);
+    assert!(report["prompt"]["latency_ms"].is_u64());
+    assert_eq!(report["prompt"]["usage"]["input_tokens"], 1200);
+    assert_eq!(report["prompt"]["usage"]["output_tokens"], 30);
+}
+
+#[test]
+fn test_agents_test_fails_for_missing_binary() {
+    let project = setup_project();
+    let dir = project.path();
+    assert!(ckrv(
+        dir,
+        &["agents", "add", "ghost", "--binary", "/nonexistent/claude"]
+    )
+    .status
+    .success());
+
+    let output = ckrv(dir, &["--json", "agents", "test", "ghost", "--no-sandbox"]);
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json report");
+    assert_eq!(report["binary"]["ok"], false);
+    assert!(report["prompt"].is_null());
+}
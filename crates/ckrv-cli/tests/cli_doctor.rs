@@ -0,0 +1,75 @@
+//! Integration tests for `ckrv doctor`.
+//!
+//! - the runtime and socket come from `CKRV_CONTAINER_*`, then the project config
+//! - an unreachable daemon fails the command
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+fn ckrv(project: &Path, envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(["--json", "doctor"])
+        .current_dir(project)
+        .env_remove("CKRV_CONTAINER_RUNTIME")
+        .env_remove("CKRV_CONTAINER_SOCKET")
+        .env_remove("DOCKER_HOST")
+        .envs(envs.iter().copied())
+        .output()
+        .expect("Failed to execute ckrv")
+}
+
+fn setup_project(config: &str) -> TempDir {
+    let project = TempDir::new().expect("temp dir");
+    let dir = project.path().join(".chakravarti");
+    std::fs::create_dir_all(&dir).expect("create .chakravarti");
+    std::fs::write(dir.join("config.json"), config).expect("write config");
+    project
+}
+
+fn report(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("doctor should print JSON")
+}
+
+#[test]
+fn test_doctor_uses_configured_podman_socket() {
+    let socket = "/nonexistent/ckrv-test/podman.sock";
+    let project = setup_project(&format!(
+        r#"{{"version": "1.0", "container_runtime": {{"kind": "podman", "socket": "{socket}"}}}}"#
+    ));
+
+    let output = ckrv(project.path(), &[]);
+    assert!(!output.status.success(), "an unreachable daemon should fail");
+
+    let report = report(&output);
+    assert_eq!(report["success"], false);
+    assert_eq!(report["runtime"]["kind"], "podman");
+    assert_eq!(report["runtime"]["socket"], format!("unix://{socket}"));
+    assert_eq!(report["runtime"]["source"], "config");
+    assert_eq!(report["daemon"]["ok"], false);
+    assert!(report["image"].is_null());
+}
+
+#[test]
+fn test_doctor_env_overrides_config() {
+    let project = setup_project(
+        r#"{"version": "1.0", "container_runtime": {"kind": "podman", "socket": "/nonexistent/podman.sock"}}"#,
+    );
+
+    let output = ckrv(
+        project.path(),
+        &[
+            ("CKRV_CONTAINER_RUNTIME", "docker"),
+            ("CKRV_CONTAINER_SOCKET", "unix:///nonexistent/docker.sock"),
+        ],
+    );
+    let report = report(&output);
+    assert_eq!(report["runtime"]["kind"], "docker");
+    assert_eq!(report["runtime"]["socket"], "unix:///nonexistent/docker.sock");
+    assert_eq!(report["runtime"]["source"], "environment");
+    assert!(report["daemon"]["detail"]
+        .as_str()
+        .unwrap()
+        .contains("/nonexistent/docker.sock"));
+}
@@ -0,0 +1,115 @@
+//! Integration tests for the record/replay mock agent.
+//!
+//! - `CKRV_MOCK_AGENT=record` runs the real agent and saves its transcript
+//! - `CKRV_MOCK_AGENT=replay` plays the transcript back without any agent
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+const WORKFLOW: &str = r"
+version: '1.0'
+name: 'mocked'
+steps:
+  - id: implement
+    name: 'Implement'
+    prompt: 'Write a greeting for {{inputs.description}}'
+";
+
+/// A stand-in agent that edits a file and prints a summary.
+const FAKE_AGENT: &str = "#!/bin/sh\necho 'hello' > greeting.txt\necho 'Wrote greeting.txt'\n";
+
+fn ckrv_task(project: &Path, fixtures: &Path, mode: &str) -> std::process::Output {
+    let workspace = project.join("workspace");
+    std::fs::create_dir_all(&workspace).expect("create workspace");
+
+    Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args([
+            "task",
+            "the team",
+            "--workflow",
+            "workflow.yml",
+            "--agent",
+            "fake",
+        ])
+        .args(["--no-sandbox", "--use-worktree"])
+        .arg(&workspace)
+        .current_dir(project)
+        .env("XDG_CONFIG_HOME", project.join("config"))
+        .env("CKRV_MOCK_AGENT", mode)
+        .env("CKRV_MOCK_FIXTURES", fixtures)
+        .output()
+        .expect("Failed to execute ckrv")
+}
+
+fn setup_project(agent_binary: &Path) -> TempDir {
+    let project = TempDir::new().expect("temp dir");
+    std::fs::write(project.path().join("workflow.yml"), WORKFLOW).expect("write workflow");
+
+    let agents_dir = project.path().join(".chakravarti");
+    std::fs::create_dir_all(&agents_dir).expect("create agents dir");
+    std::fs::write(
+        agents_dir.join("agents.yaml"),
+        format!(
+            "agents:\n  - id: fake\n    name: Fake\n    agent_type: mock\n    binary_path: {}\n",
+            agent_binary.display()
+        ),
+    )
+    .expect("write agents.yaml");
+    project
+}
+
+#[test]
+fn test_mock_agent_records_then_replays() {
+    let tools = TempDir::new().expect("temp dir");
+    let fixtures = TempDir::new().expect("temp dir");
+    let agent = tools.path().join("fake-agent");
+    std::fs::write(&agent, FAKE_AGENT).expect("write agent");
+    std::fs::set_permissions(&agent, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    // Record against the real (fake) agent
+    let recording = setup_project(&agent);
+    let output = ckrv_task(recording.path(), fixtures.path(), "record");
+    assert!(
+        output.status.success(),
+        "record run failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_dir(fixtures.path())
+            .expect("fixtures")
+            .count(),
+        1
+    );
+
+    // Replay in a fresh project with the agent gone
+    std::fs::remove_file(&agent).expect("remove agent");
+    let replaying = setup_project(&agent);
+    let output = ckrv_task(replaying.path(), fixtures.path(), "replay");
+    assert!(
+        output.status.success(),
+        "replay run failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(replaying.path().join("workspace/greeting.txt"))
+            .expect("replayed file"),
+        "hello\n"
+    );
+}
+
+#[test]
+fn test_mock_agent_replay_without_fixture_fails() {
+    let fixtures = TempDir::new().expect("temp dir");
+    let project = setup_project(Path::new("/nonexistent/agent"));
+
+    let output = ckrv_task(project.path(), fixtures.path(), "replay");
+    assert!(
+        !output.status.success(),
+        "replay without a fixture should fail"
+    );
+}
@@ -0,0 +1,138 @@
+//! Integration tests for the encrypted secret store.
+//!
+//! - `ckrv secrets set/get/list/rm` manage a passphrase-protected store
+//! - `secret:` references in agents.yaml are resolved when the agent launches
+//! - Resolved values are redacted from agent output
+
+#![cfg(unix)]
+
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+const PASSPHRASE: &str = "correct horse battery staple";
+
+fn ckrv(project: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_ckrv"));
+    cmd.current_dir(project)
+        .env("XDG_CONFIG_HOME", project.join("config"))
+        .env("CKRV_SECRETS_PASSPHRASE", PASSPHRASE);
+    cmd
+}
+
+fn set_secret(project: &Path, name: &str, value: &str) -> Output {
+    let mut child = ckrv(project)
+        .args(["secrets", "set", name])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute ckrv");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(value.as_bytes())
+        .expect("write value");
+    child.wait_with_output().expect("wait for ckrv")
+}
+
+#[test]
+fn test_secrets_set_get_list_rm() {
+    let project = TempDir::new().expect("temp dir");
+
+    let output = set_secret(project.path(), "openrouter-main", "sk-or-v1-secret-value\n");
+    assert!(
+        output.status.success(),
+        "set failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let store = project.path().join(".chakravarti/secrets/secrets.enc");
+    let raw = std::fs::read_to_string(&store).expect("store written");
+    assert!(!raw.contains("sk-or-v1-secret-value"));
+
+    let output = ckrv(project.path())
+        .args(["secrets", "get", "openrouter-main"])
+        .output()
+        .expect("Failed to execute ckrv");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "sk-or-v1-secret-value");
+
+    let output = ckrv(project.path())
+        .args(["secrets", "list"])
+        .output()
+        .expect("Failed to execute ckrv");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("openrouter-main"));
+    assert!(!stdout.contains("sk-or-v1-secret-value"));
+
+    let output = ckrv(project.path())
+        .args(["secrets", "get", "openrouter-main"])
+        .env("CKRV_SECRETS_PASSPHRASE", "wrong")
+        .output()
+        .expect("Failed to execute ckrv");
+    assert!(!output.status.success(), "wrong passphrase should fail");
+
+    let output = ckrv(project.path())
+        .args(["secrets", "rm", "openrouter-main"])
+        .output()
+        .expect("Failed to execute ckrv");
+    assert!(output.status.success());
+    let output = ckrv(project.path())
+        .args(["secrets", "get", "openrouter-main"])
+        .output()
+        .expect("Failed to execute ckrv");
+    assert!(!output.status.success(), "removed secret should be gone");
+}
+
+#[test]
+fn test_secret_reference_resolved_at_launch_and_redacted() {
+    let project = TempDir::new().expect("temp dir");
+    let output = set_secret(project.path(), "agent-token", "tok-resolved-at-launch");
+    assert!(output.status.success());
+
+    // The agent records the token it received and also prints it
+    let agent = project.path().join("fake-agent");
+    std::fs::write(
+        &agent,
+        "#!/bin/sh\nprintf '%s' \"$AGENT_TOKEN\" > token.txt\necho \"token is $AGENT_TOKEN\"\n",
+    )
+    .expect("write agent");
+    std::fs::set_permissions(&agent, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    std::fs::write(
+        project.path().join("workflow.yml"),
+        "version: '1.0'\nname: 'secret'\nsteps:\n  - id: implement\n    name: 'Implement'\n    prompt: 'Do it'\n",
+    )
+    .expect("write workflow");
+    std::fs::write(
+        project.path().join(".chakravarti/agents.yaml"),
+        format!(
+            "agents:\n  - id: fake\n    name: Fake\n    agent_type: claude\n    binary_path: {}\n    env_vars:\n      AGENT_TOKEN: secret:agent-token\n",
+            agent.display()
+        ),
+    )
+    .expect("write agents.yaml");
+
+    let workspace = project.path().join("workspace");
+    std::fs::create_dir_all(&workspace).expect("create workspace");
+    let output = ckrv(project.path())
+        .args(["--verbose", "task", "anything", "--workflow", "workflow.yml"])
+        .args(["--agent", "fake", "--no-sandbox", "--use-worktree"])
+        .arg(&workspace)
+        .output()
+        .expect("Failed to execute ckrv");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "task failed: {stderr}");
+
+    assert_eq!(
+        std::fs::read_to_string(workspace.join("token.txt")).expect("token file"),
+        "tok-resolved-at-launch"
+    );
+    assert!(!stdout.contains("tok-resolved-at-launch"), "stdout leaked the secret");
+    assert!(!stderr.contains("tok-resolved-at-launch"), "stderr leaked the secret");
+}
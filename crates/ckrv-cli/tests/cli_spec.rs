@@ -249,3 +249,198 @@ fn test_spec_validate_nonexistent_file_fails() {
         "validate should fail for nonexistent file"
     );
 }
+
+// =============================================================================
+// Model API generation path
+// =============================================================================
+
+/// Stream one OpenAI-style chat completion, a line per chunk, and hand back
+/// the request headers (lowercased) and body.
+fn fake_completion_server(content: &str) -> (String, std::thread::JoinHandle<(String, String)>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let endpoint = format!("http://{}", listener.local_addr().expect("addr"));
+    let mut response = String::new();
+    for line in content.split_inclusive('\n') {
+        let chunk = serde_json::json!({
+            "model": "fake-model",
+            "choices": [{"index": 0, "delta": {"content": line}, "finish_reason": null}]
+        });
+        response.push_str(&format!("data: {chunk}\n\n"));
+    }
+    let usage = serde_json::json!({
+        "model": "fake-model",
+        "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150}
+    });
+    response.push_str(&format!("data: {usage}\n\ndata: [DONE]\n\n"));
+
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("accept");
+        let mut reader = BufReader::new(stream.try_clone().expect("clone"));
+        let mut headers = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).expect("header");
+            if line.trim().is_empty() {
+                break;
+            }
+            let line = line.to_ascii_lowercase();
+            if let Some(value) = line.strip_prefix("content-length:") {
+                content_length = value.trim().parse().expect("length");
+            }
+            headers.push_str(&line);
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).expect("body");
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .expect("respond");
+        (headers, String::from_utf8(body).expect("utf8"))
+    });
+
+    (endpoint, handle)
+}
+
+#[test]
+fn test_spec_design_uses_configured_provider() {
+    let repo = create_initialized_repo();
+    let (endpoint, server) = fake_completion_server("# Design\n\nUse a queue.");
+    let config = serde_json::json!({
+        "version": "1.0",
+        "generation": {"backend": "api", "provider": "local"},
+        "providers": {
+            "local": {
+                "kind": "local",
+                "base_url": endpoint,
+                "headers": {"X-Team": "air-gapped"},
+                "models": ["fake-model"]
+            }
+        }
+    });
+    std::fs::write(repo.path().join(".chakravarti/config.json"), config.to_string())
+        .expect("write config");
+    let spec_dir = repo.path().join(".specs/001-demo");
+    std::fs::create_dir_all(&spec_dir).expect("mkdir");
+    std::fs::write(spec_dir.join("spec.yaml"), "id: 001-demo\noverview: A demo feature\n")
+        .expect("write spec");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(["spec", "design", ".specs/001-demo/spec.yaml", "--json"])
+        .current_dir(repo.path())
+        .env_remove("CKRV_MOCK_AGENT")
+        .output()
+        .expect("Failed to execute ckrv");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "design should succeed: {stdout}");
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("Should be valid JSON");
+    assert_eq!(json["usage"]["prompt_tokens"], 120);
+    assert_eq!(json["usage"]["completion_tokens"], 30);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Use a queue."), "streamed lines echo to stderr: {stderr}");
+
+    let design = std::fs::read_to_string(spec_dir.join("design.md")).expect("design.md");
+    assert!(design.contains("Use a queue."));
+
+    let (headers, body) = server.join().expect("server");
+    assert!(headers.contains("x-team: air-gapped"));
+    assert!(!headers.contains("authorization"), "local providers send no key");
+    let request: serde_json::Value = serde_json::from_str(&body).expect("request JSON");
+    assert_eq!(request["model"], "fake-model");
+    assert_eq!(request["stream"], true);
+    assert!(request["messages"][0]["content"]
+        .as_str()
+        .is_some_and(|prompt| prompt.contains("001-demo")));
+}
+
+#[test]
+fn test_spec_design_auto_warns_when_provider_is_broken() {
+    let repo = create_initialized_repo();
+    let config = serde_json::json!({
+        "version": "1.0",
+        "generation": {"backend": "auto"},
+        "providers": {
+            "openai": {"kind": "openai", "api_key": "secret:missing-key"}
+        }
+    });
+    std::fs::write(repo.path().join(".chakravarti/config.json"), config.to_string())
+        .expect("write config");
+    let spec_dir = repo.path().join(".specs/001-demo");
+    std::fs::create_dir_all(&spec_dir).expect("mkdir");
+    std::fs::write(spec_dir.join("spec.yaml"), "id: 001-demo\noverview: A demo feature\n")
+        .expect("write spec");
+
+    // An unreachable container runtime keeps the agent fallback offline
+    let output = Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(["spec", "design", ".specs/001-demo/spec.yaml", "--json"])
+        .current_dir(repo.path())
+        .env_remove("CKRV_MOCK_AGENT")
+        .env("CKRV_CONTAINER_SOCKET", "unix:///nonexistent/ckrv-test/docker.sock")
+        .output()
+        .expect("Failed to execute ckrv");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Model API unavailable"), "{stderr}");
+    assert!(stderr.contains("missing-key"), "{stderr}");
+}
+
+#[test]
+fn test_spec_design_saves_metrics_with_cache_hits() {
+    let repo = create_initialized_repo();
+    let (endpoint, server) = fake_completion_server("# Design\n\nUse a queue.");
+    let config = serde_json::json!({
+        "version": "1.0",
+        "generation": {"backend": "api", "provider": "local"},
+        "response_cache": {"enabled": true},
+        "providers": {
+            "local": {"kind": "local", "base_url": endpoint, "models": ["fake-model"]}
+        }
+    });
+    std::fs::write(repo.path().join(".chakravarti/config.json"), config.to_string())
+        .expect("write config");
+    let spec_dir = repo.path().join(".specs/001-demo");
+    std::fs::create_dir_all(&spec_dir).expect("mkdir");
+    std::fs::write(spec_dir.join("spec.yaml"), "id: 001-demo\noverview: A demo feature\n")
+        .expect("write spec");
+
+    let design = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_ckrv"))
+            .args(["spec", "design", ".specs/001-demo/spec.yaml", "--force", "--json"])
+            .current_dir(repo.path())
+            .env_remove("CKRV_MOCK_AGENT")
+            .output()
+            .expect("Failed to execute ckrv");
+        assert!(output.status.success(), "design should succeed");
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("Should be valid JSON")
+    };
+    let report = |job_id: &str| {
+        let output = ckrv(&["report", job_id, "--json"], repo.path());
+        assert!(output.status.success(), "report should succeed");
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("Should be valid JSON")
+    };
+
+    let first = design();
+    server.join().expect("server");
+    assert_eq!(first["job_id"], "001-demo-design");
+    let metrics = report("001-demo-design");
+    assert_eq!(metrics["spec_id"], "001-demo");
+    assert_eq!(metrics["tokens"]["total"], 150);
+    assert_eq!(metrics["cost"]["cache_hits"].as_array().map(Vec::len), Some(0));
+
+    // The server is gone, so this one must come from the cache
+    let second = design();
+    assert_eq!(second["cached"], true);
+    let metrics = report("001-demo-design");
+    assert_eq!(metrics["tokens"]["total"], 0);
+    assert_eq!(metrics["cost"]["cache_hits"][0]["model"], "fake-model");
+    assert_eq!(metrics["cost"]["cache_hits"][0]["total"], 150);
+}
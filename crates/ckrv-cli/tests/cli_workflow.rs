@@ -0,0 +1,127 @@
+//! Integration tests for `ckrv workflow` commands.
+//!
+//! - `ckrv workflow validate <file>` lints a workflow without running it
+//! - `ckrv workflow render <file>` prints rendered prompts
+//! - `ckrv workflow graph <file>` prints step dependencies
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+const WORKFLOW: &str = r"
+version: '1.0'
+name: 'lint-me'
+steps:
+  - id: plan
+    name: 'Plan'
+    prompt: 'Plan: {{inputs.description}}'
+    outputs:
+      - name: plan_file
+        type: file
+        filename: plan.md
+  - id: implement
+    name: 'Implement'
+    prompt: 'Follow {{steps.plan.outputs.plan_file}}'
+";
+
+/// Helper to run the ckrv binary with arguments.
+fn ckrv(args: &[&str], cwd: &std::path::Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ckrv"))
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .expect("Failed to execute ckrv")
+}
+
+fn write_workflow(dir: &TempDir, content: &str) -> String {
+    let path = dir.path().join("workflow.yml");
+    std::fs::write(&path, content).expect("write workflow");
+    path.display().to_string()
+}
+
+#[test]
+fn test_workflow_validate_accepts_valid_workflow() {
+    let dir = TempDir::new().expect("temp dir");
+    let path = write_workflow(&dir, WORKFLOW);
+
+    let output = ckrv(&["workflow", "validate", &path, "--json"], dir.path());
+    assert!(output.status.success(), "validate should succeed");
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Should be valid JSON");
+    assert_eq!(json["valid"], true);
+}
+
+#[test]
+fn test_workflow_validate_rejects_unknown_variables() {
+    let dir = TempDir::new().expect("temp dir");
+    let path = write_workflow(
+        &dir,
+        &WORKFLOW.replace("{{inputs.description}}", "{{inputs.typo}}"),
+    );
+
+    let output = ckrv(&["workflow", "validate", &path, "--json"], dir.path());
+    assert!(!output.status.success(), "validate should fail");
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Should be valid JSON");
+    assert_eq!(json["valid"], false);
+    assert_eq!(json["errors"][0]["step"], "plan");
+}
+
+#[test]
+fn test_workflow_validate_rejects_unknown_agents() {
+    let dir = TempDir::new().expect("temp dir");
+    let workflow = WORKFLOW
+        .replace("steps:\n", "defaults:\n  tool: 'gemini'\nsteps:\n")
+        .replace("    name: 'Implement'\n", "    name: 'Implement'\n    agent: 'strnog'\n");
+    let path = write_workflow(&dir, &workflow);
+
+    let output = ckrv(&["workflow", "validate", &path, "--json"], dir.path());
+    assert!(!output.status.success(), "validate should fail");
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Should be valid JSON");
+    let errors = json["errors"].as_array().expect("errors");
+    assert_eq!(errors.len(), 1, "gemini is a known agent CLI: {errors:?}");
+    assert_eq!(errors[0]["step"], "implement");
+    assert!(errors[0]["message"]
+        .as_str()
+        .is_some_and(|m| m.contains("'strnog'")));
+}
+
+#[test]
+fn test_workflow_render_substitutes_inputs() {
+    let dir = TempDir::new().expect("temp dir");
+    let path = write_workflow(&dir, WORKFLOW);
+
+    let output = ckrv(
+        &[
+            "workflow",
+            "render",
+            &path,
+            "--input",
+            "description=Add login",
+        ],
+        dir.path(),
+    );
+    assert!(output.status.success(), "render should succeed");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Plan: Add login"));
+    assert!(stdout.contains("Follow <steps.plan.outputs.plan_file>"));
+}
+
+#[test]
+fn test_workflow_graph_lists_dependencies() {
+    let dir = TempDir::new().expect("temp dir");
+    let path = write_workflow(&dir, WORKFLOW);
+
+    let output = ckrv(&["workflow", "graph", &path, "--json"], dir.path());
+    assert!(output.status.success(), "graph should succeed");
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Should be valid JSON");
+    assert_eq!(json[1]["step"], "implement");
+    assert_eq!(json[1]["depends_on"][0], "plan");
+}
@@ -0,0 +1,550 @@
+//! The `agents.yaml` model and agent resolver.
+//!
+//! `agents.yaml` lists the coding agents available to a project. It is read
+//! from the global config directory (`~/.config/chakravarti/agents.yaml`)
+//! when that exists, and from `.chakravarti/agents.yaml` in the project
+//! otherwise. The CLI and the UI both load it through [`AgentsFile`] and
+//! turn entries into runnable [`AgentProfile`]s with
+//! [`AgentConfig::to_profile`].
+//!
+//! String values may use `${VAR}` and `${VAR:-default}`; [`AgentsFile::load`]
+//! expands them, while [`AgentsFile::load_raw`] keeps them for editing.
+//!
+//! An agent's `fallback` list, or the file's `fallback` list for the agent's
+//! level, names the agents a step moves on to when the agent hits a rate
+//! limit, outage or crash.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent_profile::AgentProfile;
+use crate::backend::AgentType;
+use crate::interpolate::{self, InterpolationError};
+
+/// File name of the agents configuration.
+pub const AGENTS_FILE: &str = "agents.yaml";
+
+/// Errors from loading or saving `agents.yaml`.
+#[derive(Debug, thiserror::Error)]
+pub enum AgentConfigError {
+    /// The file could not be parsed.
+    #[error("Failed to parse {path}: {message}")]
+    Parse {
+        /// Path of the file.
+        path: PathBuf,
+        /// Parse error.
+        message: String,
+    },
+
+    /// A referenced environment variable is not defined.
+    #[error(transparent)]
+    Interpolation(#[from] InterpolationError),
+
+    /// The file could not be serialized.
+    #[error("Failed to serialize agents: {0}")]
+    Serialize(String),
+
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// `OpenRouter` settings for an agent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenRouterConfig {
+    /// API key for `OpenRouter`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Model identifier (e.g., "moonshot/kimi-k2", "minimax/minimax-m1").
+    #[serde(default)]
+    pub model: String,
+    /// Custom base URL (default: <https://openrouter.ai/api>).
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Maximum output tokens.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// One agent entry in `agents.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Unique identifier.
+    pub id: String,
+    /// Display name.
+    #[serde(default)]
+    pub name: String,
+    /// Agent type, which selects the CLI that runs it.
+    #[serde(default)]
+    pub agent_type: AgentType,
+    /// Capability level (1-5, where 5 is strongest/most capable), used to
+    /// match tasks to agents by complexity.
+    #[serde(default = "default_level")]
+    pub level: u8,
+    /// Whether this is the default agent.
+    #[serde(default)]
+    pub is_default: bool,
+    /// Whether this agent is enabled.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `OpenRouter` configuration (model, key and endpoint).
+    #[serde(default)]
+    pub openrouter: Option<OpenRouterConfig>,
+    /// Custom CLI binary path (defaults to the agent type's binary).
+    #[serde(default)]
+    pub binary_path: Option<String>,
+    /// Additional CLI arguments.
+    #[serde(default)]
+    pub extra_args: Option<Vec<String>>,
+    /// Environment variables to set.
+    #[serde(default)]
+    pub env_vars: Option<BTreeMap<String, String>>,
+    /// IDs of the agents to try, in order, when this one hits a rate limit,
+    /// outage or crash. Overrides the file's per-level `fallback`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+}
+
+const fn default_level() -> u8 {
+    3 // Default to mid-tier
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+impl AgentConfig {
+    /// Create an enabled agent of the given type.
+    #[must_use]
+    pub fn new(id: impl Into<String>, name: impl Into<String>, agent_type: AgentType) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            agent_type,
+            level: default_level(),
+            is_default: false,
+            enabled: true,
+            description: None,
+            openrouter: None,
+            binary_path: None,
+            extra_args: None,
+            env_vars: None,
+            fallback: Vec::new(),
+        }
+    }
+
+    /// The model this agent asks for, if configured.
+    #[must_use]
+    pub fn model(&self) -> Option<&str> {
+        self.openrouter
+            .as_ref()
+            .map(|o| o.model.as_str())
+            .filter(|m| !m.is_empty())
+    }
+
+    /// Whether `model` names this agent (by ID, display name or model).
+    #[must_use]
+    pub fn matches_model(&self, model: &str) -> bool {
+        self.id == model || self.name == model || self.model() == Some(model)
+    }
+
+    /// A copy with `${VAR}` references expanded, for entries that didn't come
+    /// through [`AgentsFile::load`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a referenced variable is undefined.
+    pub fn expanded(&self) -> Result<Self, AgentConfigError> {
+        let mut value = serde_yaml::to_value(self)
+            .map_err(|e| AgentConfigError::Serialize(e.to_string()))?;
+        let source = format!("{AGENTS_FILE} (agent '{}')", self.id);
+        interpolate::interpolate_yaml(&mut value, &source, &[])?;
+        serde_yaml::from_value(value).map_err(|e| AgentConfigError::Serialize(e.to_string()))
+    }
+
+    /// The runnable profile for this agent.
+    ///
+    /// Plain Claude Code uses the configured model but not the `OpenRouter`
+    /// key; every other agent type talks to `OpenRouter` when a key is set.
+    #[must_use]
+    pub fn to_profile(&self) -> AgentProfile {
+        let binary = self
+            .binary_path
+            .as_deref()
+            .filter(|b| !b.is_empty())
+            .unwrap_or_else(|| self.agent_type.backend().default_binary());
+        let mut profile = AgentProfile::new(&self.id, binary).with_agent_type(self.agent_type);
+
+        if let Some(ref openrouter) = self.openrouter {
+            if let Some(model) = self.model() {
+                profile = profile.with_model(model);
+            }
+            if self.agent_type != AgentType::Claude {
+                profile = profile
+                    .with_openrouter(openrouter.api_key.clone(), openrouter.base_url.clone());
+            }
+            profile.max_tokens = openrouter.max_tokens;
+            profile.temperature = openrouter.temperature;
+        }
+        if let Some(ref args) = self.extra_args {
+            profile.extra_args.clone_from(args);
+        }
+        if let Some(ref env) = self.env_vars {
+            profile.env.clone_from(env);
+        }
+        profile.fallback.clone_from(&self.fallback);
+        profile
+    }
+}
+
+/// The contents of `agents.yaml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentsFile {
+    /// Configured agents.
+    #[serde(default)]
+    pub agents: Vec<AgentConfig>,
+    /// Fallback chains by capability level, for agents without their own
+    /// `fallback` list.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fallback: BTreeMap<u8, Vec<String>>,
+}
+
+impl AgentsFile {
+    /// Path of the global `agents.yaml`, if the platform has a config directory.
+    #[must_use]
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("chakravarti").join(AGENTS_FILE))
+    }
+
+    /// Path of `agents.yaml` for a project: the global file if it exists,
+    /// otherwise `.chakravarti/agents.yaml` in the project.
+    #[must_use]
+    pub fn path_for_project(project_root: &Path) -> PathBuf {
+        Self::global_path()
+            .filter(|p| p.exists())
+            .unwrap_or_else(|| project_root.join(".chakravarti").join(AGENTS_FILE))
+    }
+
+    /// Load agents from a file, expanding `${VAR}` references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or references
+    /// an undefined variable.
+    pub fn load(path: &Path) -> Result<Self, AgentConfigError> {
+        Self::read(path, true)
+    }
+
+    /// Load agents from a file as written, for editing and saving back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load_raw(path: &Path) -> Result<Self, AgentConfigError> {
+        Self::read(path, false)
+    }
+
+    fn read(path: &Path, expand: bool) -> Result<Self, AgentConfigError> {
+        let parse_error = |e: serde_yaml::Error| AgentConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        };
+        let content = std::fs::read_to_string(path)?;
+        let mut value: serde_yaml::Value = serde_yaml::from_str(&content).map_err(parse_error)?;
+        if expand {
+            interpolate::interpolate_yaml(&mut value, &path.display().to_string(), &[])?;
+        }
+        serde_yaml::from_value(value).map_err(parse_error)
+    }
+
+    /// Load the agents for a project, or an empty list if none are configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `agents.yaml` exists but cannot be read or parsed.
+    pub fn load_for_project(project_root: &Path) -> Result<Self, AgentConfigError> {
+        let path = Self::path_for_project(project_root);
+        if path.exists() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Like [`Self::load_for_project`], but without expanding variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `agents.yaml` exists but cannot be read or parsed.
+    pub fn load_for_project_raw(project_root: &Path) -> Result<Self, AgentConfigError> {
+        let path = Self::path_for_project(project_root);
+        if path.exists() {
+            Self::load_raw(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Save agents to a file, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), AgentConfigError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let yaml =
+            serde_yaml::to_string(self).map_err(|e| AgentConfigError::Serialize(e.to_string()))?;
+        std::fs::write(path, yaml)?;
+        Ok(())
+    }
+
+    /// Enabled agents, in file order.
+    pub fn enabled(&self) -> impl Iterator<Item = &AgentConfig> {
+        self.agents.iter().filter(|a| a.enabled)
+    }
+
+    /// Runnable profiles for all enabled agents, with their fallback chains.
+    #[must_use]
+    pub fn profiles(&self) -> Vec<AgentProfile> {
+        self.enabled().map(|a| self.profile(a)).collect()
+    }
+
+    /// The runnable profile for `agent`, with its fallback chain resolved.
+    #[must_use]
+    pub fn profile(&self, agent: &AgentConfig) -> AgentProfile {
+        AgentProfile {
+            fallback: self.fallback_for(agent),
+            ..agent.to_profile()
+        }
+    }
+
+    /// The agents to try after `agent` fails: its own `fallback` list, or
+    /// else the list for its level. Disabled and unknown agents, and `agent`
+    /// itself, are left out.
+    #[must_use]
+    pub fn fallback_for(&self, agent: &AgentConfig) -> Vec<String> {
+        let chain = if agent.fallback.is_empty() {
+            self.fallback.get(&agent.level).map_or(&[][..], Vec::as_slice)
+        } else {
+            agent.fallback.as_slice()
+        };
+
+        let mut ids: Vec<String> = Vec::new();
+        for id in chain {
+            if *id != agent.id && self.by_id(id).is_some() && !ids.contains(id) {
+                ids.push(id.clone());
+            }
+        }
+        ids
+    }
+
+    /// The enabled agent with this ID.
+    #[must_use]
+    pub fn by_id(&self, id: &str) -> Option<&AgentConfig> {
+        self.enabled().find(|a| a.id == id)
+    }
+
+    /// The enabled agent named by a model string: its ID, display name or
+    /// configured model.
+    #[must_use]
+    pub fn by_model(&self, model: &str) -> Option<&AgentConfig> {
+        self.by_id(model)
+            .or_else(|| self.enabled().find(|a| a.matches_model(model)))
+    }
+
+    /// The cheapest enabled agent whose level is at least `required`, or the
+    /// strongest one if none is capable enough.
+    #[must_use]
+    pub fn for_level(&self, required: u8) -> Option<&AgentConfig> {
+        self.enabled()
+            .filter(|a| a.level >= required)
+            .min_by_key(|a| a.level)
+            .or_else(|| self.enabled().max_by_key(|a| a.level))
+    }
+
+    /// The enabled default agent.
+    #[must_use]
+    pub fn default_agent(&self) -> Option<&AgentConfig> {
+        self.enabled().find(|a| a.is_default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const AGENTS: &str = r"
+agents:
+  - id: claude-default
+    name: Claude Code
+    agent_type: claude
+    level: 5
+    is_default: true
+  - id: cheap
+    name: MiniMax
+    agent_type: claude_open_router
+    level: 2
+    openrouter:
+      api_key: sk-or
+      model: minimax/minimax-m2.1
+      base_url: https://example.test/api
+      max_tokens: 8000
+    extra_args: ['--verbose']
+    env_vars:
+      FOO: bar
+  - id: mid
+    name: GLM
+    agent_type: claude_openrouter
+    level: 4
+    openrouter:
+      model: z-ai/glm-4.7
+  - id: off
+    name: Disabled
+    agent_type: codex
+    level: 3
+    enabled: false
+";
+
+    fn agents() -> AgentsFile {
+        serde_yaml::from_str(AGENTS).expect("parse")
+    }
+
+    #[test]
+    fn test_resolve_by_id_model_and_default() {
+        let agents = agents();
+
+        assert_eq!(agents.by_id("mid").expect("mid").name, "GLM");
+        assert!(agents.by_id("off").is_none());
+        assert_eq!(
+            agents.by_model("minimax/minimax-m2.1").expect("model").id,
+            "cheap"
+        );
+        assert_eq!(agents.by_model("GLM").expect("name").id, "mid");
+        assert_eq!(
+            agents.default_agent().expect("default").id,
+            "claude-default"
+        );
+    }
+
+    #[test]
+    fn test_resolve_by_level() {
+        let agents = agents();
+
+        assert_eq!(agents.for_level(1).expect("level 1").id, "cheap");
+        assert_eq!(agents.for_level(3).expect("level 3").id, "mid");
+        assert_eq!(agents.for_level(5).expect("level 5").id, "claude-default");
+
+        let weak = AgentsFile {
+            agents: vec![AgentConfig {
+                level: 2,
+                ..AgentConfig::new("weak", "Weak", AgentType::Claude)
+            }],
+            ..Default::default()
+        };
+        assert_eq!(weak.for_level(5).expect("fallback").id, "weak");
+    }
+
+    #[test]
+    fn test_profile_applies_every_field() {
+        let agents = agents();
+        let profile = agents.by_id("cheap").expect("cheap").to_profile();
+
+        assert_eq!(profile.agent_type, AgentType::ClaudeOpenRouter);
+        assert_eq!(profile.binary, "claude");
+        assert_eq!(profile.model.as_deref(), Some("minimax/minimax-m2.1"));
+        assert_eq!(profile.openrouter_api_key.as_deref(), Some("sk-or"));
+        assert_eq!(
+            profile.openrouter_base_url.as_deref(),
+            Some("https://example.test/api")
+        );
+        assert_eq!(profile.max_tokens, Some(8000));
+        assert_eq!(profile.extra_args, ["--verbose"]);
+        assert_eq!(profile.env.get("FOO").map(String::as_str), Some("bar"));
+
+        let invocation = profile.agent_type.backend().invocation("hi", &profile);
+        assert_eq!(
+            invocation.args.last().map(String::as_str),
+            Some("--verbose")
+        );
+        assert!(invocation
+            .env
+            .contains(&("FOO".to_string(), "bar".to_string())));
+        assert!(invocation.env.contains(&(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://example.test/api".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_fallback_chains() {
+        let mut agents = agents();
+        agents.fallback.insert(
+            2,
+            vec!["cheap".into(), "off".into(), "mid".into(), "claude-default".into()],
+        );
+        agents.agents[2].fallback = vec!["claude-default".into(), "nope".into()];
+
+        // Per-level list, without the agent itself or disabled/unknown agents
+        assert_eq!(
+            agents.fallback_for(agents.by_id("cheap").expect("cheap")),
+            ["mid", "claude-default"]
+        );
+        // The agent's own list wins over its level's
+        let profiles = agents.profiles();
+        let mid = profiles.iter().find(|p| p.id == "mid").expect("mid");
+        assert_eq!(mid.fallback, ["claude-default"]);
+        // No list for level 5
+        assert!(agents
+            .fallback_for(agents.by_id("claude-default").expect("default"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join(".chakravarti").join(AGENTS_FILE);
+
+        agents().save(&path).expect("save");
+        assert_eq!(AgentsFile::load(&path).expect("load"), agents());
+    }
+
+    #[test]
+    fn test_load_expands_variables_but_raw_keeps_them() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join(AGENTS_FILE);
+        std::fs::write(
+            &path,
+            "agents:\n  - id: a\n    name: A\n    agent_type: claude\n    binary_path: ${CKRV_TEST_BIN_7311:-/opt/claude}\n",
+        )
+        .expect("write");
+
+        let loaded = AgentsFile::load(&path).expect("load");
+        assert_eq!(loaded.agents[0].binary_path.as_deref(), Some("/opt/claude"));
+        let raw = AgentsFile::load_raw(&path).expect("load raw");
+        assert_eq!(
+            raw.agents[0].binary_path.as_deref(),
+            Some("${CKRV_TEST_BIN_7311:-/opt/claude}")
+        );
+
+        std::fs::write(
+            &path,
+            "agents:\n  - id: a\n    name: A\n    agent_type: claude\n    env_vars:\n      TOKEN: ${CKRV_TEST_TOKEN_7311}\n",
+        )
+        .expect("write");
+        let err = AgentsFile::load(&path).expect_err("undefined variable");
+        assert!(
+            matches!(err, AgentConfigError::Interpolation(ref e) if e.field == "agents[0].env_vars.TOKEN")
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! Per-agent run history and history-driven agent selection.
+//!
+//! Every batch `ckrv run` executes appends an [`AgentRunRecord`] to
+//! `.chakravarti/history/agent_runs.jsonl`: which agent produced it, the
+//! batch's complexity and language, whether it succeeded, how long it took
+//! and what it cost. [`AgentHistory::select`] turns those records into
+//! per-agent success rates, costs and durations and picks the agent for a
+//! new batch according to the [`OptimizeMode`].
+//!
+//! Until an agent has history the selection falls back to capability levels,
+//! so a fresh project behaves like [`AgentsFile::for_level`].
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::agent_config::{AgentConfig, AgentsFile};
+use crate::job::OptimizeMode;
+
+/// File the history is appended to, under `.chakravarti/history/`.
+pub const HISTORY_FILE: &str = "agent_runs.jsonl";
+
+/// Matching records needed before statistics for a narrower scope (same
+/// language, then same level) are preferred over broader ones.
+const MIN_SAMPLES: u32 = 3;
+
+/// Outcome of one batch run by one agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRunRecord {
+    /// ID of the agent that produced the batch, after any fallbacks.
+    pub agent: String,
+    /// Highest task complexity in the batch (1-5).
+    pub complexity: u8,
+    /// Main language of the files the batch touched, if known.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Whether the batch succeeded.
+    pub success: bool,
+    /// Wall-clock duration of the batch.
+    pub duration_ms: u64,
+    /// Cost reported by the agent, if any.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Spec the batch belongs to.
+    #[serde(default)]
+    pub spec: Option<String>,
+    /// Batch ID.
+    #[serde(default)]
+    pub batch: Option<String>,
+    /// Why the agent was chosen.
+    #[serde(default)]
+    pub selection: Option<String>,
+    /// When the run finished.
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Aggregated history for one agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentStats {
+    /// Runs counted.
+    pub runs: u32,
+    /// Runs that succeeded.
+    pub successes: u32,
+    /// Average reported cost, over runs that reported one.
+    pub avg_cost_usd: Option<f64>,
+    /// Average duration.
+    pub avg_duration_ms: Option<u64>,
+}
+
+impl AgentStats {
+    fn from_records<'a>(records: impl Iterator<Item = &'a AgentRunRecord>) -> Self {
+        let (mut runs, mut successes, mut duration_ms) = (0_u32, 0_u32, 0_u64);
+        let (mut costed, mut cost) = (0_u32, 0.0_f64);
+        for record in records {
+            runs += 1;
+            successes += u32::from(record.success);
+            duration_ms += record.duration_ms;
+            if let Some(c) = record.cost_usd {
+                costed += 1;
+                cost += c;
+            }
+        }
+        Self {
+            runs,
+            successes,
+            avg_cost_usd: (costed > 0).then(|| cost / f64::from(costed)),
+            avg_duration_ms: (runs > 0).then(|| duration_ms / u64::from(runs)),
+        }
+    }
+
+    /// Success rate with add-one smoothing, so a single run doesn't count as
+    /// 0% or 100% and an untried agent sits at 50%.
+    #[must_use]
+    pub fn success_rate(&self) -> f64 {
+        f64::from(self.successes + 1) / f64::from(self.runs + 2)
+    }
+}
+
+/// An agent chosen for a batch, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentSelection {
+    /// ID of the chosen agent.
+    pub agent_id: String,
+    /// Human-readable reasoning, for logs and the run record.
+    pub reason: String,
+}
+
+/// The recorded agent runs of a project.
+#[derive(Debug, Clone, Default)]
+pub struct AgentHistory {
+    records: Vec<AgentRunRecord>,
+}
+
+impl AgentHistory {
+    /// Path of the history file for a project.
+    #[must_use]
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root
+            .join(".chakravarti")
+            .join("history")
+            .join(HISTORY_FILE)
+    }
+
+    /// Create a history from records.
+    #[must_use]
+    pub const fn new(records: Vec<AgentRunRecord>) -> Self {
+        Self { records }
+    }
+
+    /// Load a project's history; a missing file is an empty history and
+    /// unreadable lines are skipped.
+    #[must_use]
+    pub fn load(project_root: &Path) -> Self {
+        let records = std::fs::read_to_string(Self::path(project_root))
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { records }
+    }
+
+    /// Append a record to a project's history.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn record(project_root: &Path, record: &AgentRunRecord) -> std::io::Result<()> {
+        let path = Self::path(project_root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(record).map_err(std::io::Error::other)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Recorded runs, oldest first.
+    #[must_use]
+    pub fn records(&self) -> &[AgentRunRecord] {
+        &self.records
+    }
+
+    /// Statistics for `agent` on batches like this one, from the narrowest
+    /// scope with enough runs: same level and language, same level, then
+    /// every run. Returns the statistics and a description of the scope.
+    #[must_use]
+    pub fn stats(
+        &self,
+        agent: &str,
+        complexity: u8,
+        language: Option<&str>,
+    ) -> (AgentStats, String) {
+        let runs = || self.records.iter().filter(|r| r.agent == agent);
+        let at_level = || runs().filter(|r| r.complexity == complexity);
+
+        if let Some(language) = language {
+            let stats = AgentStats::from_records(
+                at_level().filter(|r| r.language.as_deref() == Some(language)),
+            );
+            if stats.runs >= MIN_SAMPLES {
+                return (stats, format!("level-{complexity} {language} batches"));
+            }
+        }
+        let stats = AgentStats::from_records(at_level());
+        if stats.runs >= MIN_SAMPLES {
+            return (stats, format!("level-{complexity} batches"));
+        }
+        (AgentStats::from_records(runs()), "all batches".to_string())
+    }
+
+    /// Choose the agent for a batch of the given complexity and language.
+    ///
+    /// Candidates are the enabled agents rated for the complexity (or the
+    /// strongest agent if none is). Each is scored on its smoothed success
+    /// rate minus its relative cost and duration, weighted by `mode`; agents
+    /// without history are charged by capability level instead, so with no
+    /// history at all the cheapest capable agent wins.
+    #[must_use]
+    pub fn select(
+        &self,
+        agents: &AgentsFile,
+        complexity: u8,
+        language: Option<&str>,
+        mode: OptimizeMode,
+    ) -> Option<AgentSelection> {
+        let mut candidates: Vec<&AgentConfig> =
+            agents.enabled().filter(|a| a.level >= complexity).collect();
+        if candidates.is_empty() {
+            let strongest = agents.for_level(complexity)?;
+            return Some(AgentSelection {
+                agent_id: strongest.id.clone(),
+                reason: format!(
+                    "no agent is rated for level {complexity}; using the strongest (level {})",
+                    strongest.level
+                ),
+            });
+        }
+        candidates.sort_by_key(|a| a.level);
+
+        let stats: Vec<(AgentStats, String)> = candidates
+            .iter()
+            .map(|a| self.stats(&a.id, complexity, language))
+            .collect();
+        if stats.iter().all(|(s, _)| s.runs == 0) {
+            let cheapest = candidates[0];
+            return Some(AgentSelection {
+                agent_id: cheapest.id.clone(),
+                reason: format!(
+                    "no history yet; cheapest agent rated for level {complexity} (level {})",
+                    cheapest.level
+                ),
+            });
+        }
+
+        let max_cost = stats
+            .iter()
+            .filter_map(|(s, _)| s.avg_cost_usd)
+            .fold(0.0_f64, f64::max);
+        let max_duration = stats
+            .iter()
+            .filter_map(|(s, _)| s.avg_duration_ms)
+            .max()
+            .unwrap_or(0);
+        let (cost_weight, time_weight): (f64, f64) = match mode {
+            OptimizeMode::Cost => (0.6, 0.1),
+            OptimizeMode::Time => (0.1, 0.6),
+            OptimizeMode::Balanced => (0.3, 0.3),
+        };
+
+        let mut scored: Vec<(f64, &AgentConfig, &AgentStats, &str)> = candidates
+            .iter()
+            .zip(&stats)
+            .map(|(agent, (stats, scope))| {
+                let level_proxy = f64::from(agent.level) / 5.0;
+                let cost = match stats.avg_cost_usd {
+                    Some(c) if max_cost > 0.0 => c / max_cost,
+                    _ => level_proxy,
+                };
+                #[allow(clippy::cast_precision_loss)]
+                let duration = match stats.avg_duration_ms {
+                    Some(d) if stats.runs > 0 && max_duration > 0 => d as f64 / max_duration as f64,
+                    _ => level_proxy,
+                };
+                let score = time_weight
+                    .mul_add(-duration, cost_weight.mul_add(-cost, stats.success_rate()));
+                (score, *agent, stats, scope.as_str())
+            })
+            .collect();
+        // Highest score first; equal scores keep the cheaper level
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let (score, chosen, chosen_stats, chosen_scope) = scored[0];
+        let mut reason = format!(
+            "{} mode: {}",
+            mode_name(mode),
+            describe(chosen_stats, chosen_scope)
+        );
+        let _ = write!(reason, "; score {score:.2}");
+        if let Some((next_score, next, ..)) = scored.get(1) {
+            let _ = write!(reason, " vs '{}' {next_score:.2}", next.id);
+        }
+        Some(AgentSelection {
+            agent_id: chosen.id.clone(),
+            reason,
+        })
+    }
+}
+
+const fn mode_name(mode: OptimizeMode) -> &'static str {
+    match mode {
+        OptimizeMode::Cost => "cost",
+        OptimizeMode::Time => "time",
+        OptimizeMode::Balanced => "balanced",
+    }
+}
+
+fn describe(stats: &AgentStats, scope: &str) -> String {
+    if stats.runs == 0 {
+        return "no history".to_string();
+    }
+    let mut text = format!("{}/{} succeeded on {scope}", stats.successes, stats.runs);
+    if let Some(cost) = stats.avg_cost_usd {
+        let _ = write!(text, ", avg ${cost:.2}");
+    }
+    if let Some(ms) = stats.avg_duration_ms {
+        let _ = write!(text, ", avg {}s", ms / 1000);
+    }
+    text
+}
+
+/// The language of a source file, from its extension.
+#[must_use]
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "py" => "python",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "php" => "php",
+        "sql" => "sql",
+        "sh" | "bash" => "shell",
+        _ => return None,
+    })
+}
+
+/// The most common language among `paths`, if any is recognised.
+#[must_use]
+pub fn dominant_language<'a>(paths: impl IntoIterator<Item = &'a str>) -> Option<&'static str> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for language in paths.into_iter().filter_map(language_for_path) {
+        *counts.entry(language).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(language, _)| language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::AgentType;
+    use tempfile::TempDir;
+
+    fn agents() -> AgentsFile {
+        let agent = |id: &str, level: u8| AgentConfig {
+            level,
+            ..AgentConfig::new(id, id, AgentType::Claude)
+        };
+        AgentsFile {
+            agents: vec![agent("cheap", 2), agent("mid", 3), agent("strong", 5)],
+            ..Default::default()
+        }
+    }
+
+    fn record(agent: &str, success: bool, cost: f64, secs: u64) -> AgentRunRecord {
+        AgentRunRecord {
+            agent: agent.to_string(),
+            complexity: 3,
+            language: Some("rust".to_string()),
+            success,
+            duration_ms: secs * 1000,
+            cost_usd: Some(cost),
+            spec: None,
+            batch: None,
+            selection: None,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_without_history_picks_cheapest_capable_agent() {
+        let selection = AgentHistory::default()
+            .select(&agents(), 3, Some("rust"), OptimizeMode::Balanced)
+            .expect("selection");
+        assert_eq!(selection.agent_id, "mid");
+        assert!(selection.reason.starts_with("no history yet"));
+    }
+
+    #[test]
+    fn test_history_and_mode_drive_selection() {
+        // 'mid' is cheap but fails often and is slow; 'strong' is reliable,
+        // fast and expensive
+        let mut records = Vec::new();
+        for i in 0..4 {
+            records.push(record("mid", i == 0, 0.10, 600));
+            records.push(record("strong", true, 1.00, 120));
+        }
+        let history = AgentHistory::new(records);
+
+        let balanced = history
+            .select(&agents(), 3, Some("rust"), OptimizeMode::Balanced)
+            .expect("balanced");
+        assert_eq!(balanced.agent_id, "strong");
+        assert!(
+            balanced
+                .reason
+                .contains("4/4 succeeded on level-3 rust batches"),
+            "{}",
+            balanced.reason
+        );
+
+        // Cheap and reliable beats expensive when optimising for cost
+        let mut records = history.records().to_vec();
+        records.extend((0..8).map(|_| record("mid", true, 0.10, 600)));
+        let selection = AgentHistory::new(records)
+            .select(&agents(), 3, Some("rust"), OptimizeMode::Cost)
+            .expect("cost");
+        assert_eq!(selection.agent_id, "mid");
+        assert!(selection.reason.starts_with("cost mode:"));
+    }
+
+    #[test]
+    fn test_record_and_load_round_trip() {
+        let dir = TempDir::new().expect("temp dir");
+        AgentHistory::record(dir.path(), &record("mid", true, 0.5, 10)).expect("record");
+        AgentHistory::record(dir.path(), &record("strong", false, 1.5, 20)).expect("record");
+
+        let history = AgentHistory::load(dir.path());
+        assert_eq!(history.records().len(), 2);
+        let (stats, scope) = history.stats("strong", 3, Some("rust"));
+        assert_eq!((stats.runs, stats.successes), (1, 0));
+        assert_eq!(scope, "all batches");
+    }
+
+    #[test]
+    fn test_dominant_language() {
+        assert_eq!(
+            dominant_language(["src/a.rs", "web/b.tsx", "src/c.rs", "README.md"]),
+            Some("rust")
+        );
+        assert_eq!(dominant_language(["README.md"]), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Agent profiles for per-step agent and model selection.
+//!
+//! A profile is the resolved, runnable form of an agent from `agents.yaml`:
+//! which binary to invoke, which model to ask for, and how to reach it.
+//! Workflows pick profiles with `defaults.tool`, `defaults.model` and
+//! per-step `agent` overrides.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::backend::AgentType;
+use crate::workflow::{Workflow, WorkflowStep};
+
+/// A runnable agent configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentProfile {
+    /// Agent ID (from `agents.yaml`, or the binary name for ad-hoc agents).
+    pub id: String,
+    /// Which agent CLI this is; selects the backend that drives it.
+    #[serde(default)]
+    pub agent_type: AgentType,
+    /// Agent binary to invoke (e.g., "claude").
+    pub binary: String,
+    /// Model to request, if any.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// API key for routing Claude Code through `OpenRouter`.
+    #[serde(default)]
+    pub openrouter_api_key: Option<String>,
+    /// `OpenRouter` base URL (defaults to <https://openrouter.ai/api>).
+    #[serde(default)]
+    pub openrouter_base_url: Option<String>,
+    /// Maximum output tokens, where the agent CLI supports a limit.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature, where the agent CLI supports one.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Extra CLI arguments appended to the agent command.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Extra environment variables for the agent process.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// IDs of the agents to try, in order, when this one fails with a
+    /// retryable error.
+    #[serde(default)]
+    pub fallback: Vec<String>,
+}
+
+impl AgentProfile {
+    /// Create a profile that runs `binary` with its own default model.
+    ///
+    /// The agent type is inferred from the binary name.
+    #[must_use]
+    pub fn new(id: impl Into<String>, binary: impl Into<String>) -> Self {
+        let binary = binary.into();
+        Self {
+            id: id.into(),
+            agent_type: AgentType::from_binary(&binary),
+            binary,
+            model: None,
+            openrouter_api_key: None,
+            openrouter_base_url: None,
+            max_tokens: None,
+            temperature: None,
+            extra_args: Vec::new(),
+            env: BTreeMap::new(),
+            fallback: Vec::new(),
+        }
+    }
+
+    /// Set the agent type explicitly.
+    #[must_use]
+    pub const fn with_agent_type(mut self, agent_type: AgentType) -> Self {
+        self.agent_type = agent_type;
+        self
+    }
+
+    /// Set the model to request.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Route the agent through `OpenRouter`.
+    #[must_use]
+    pub fn with_openrouter(mut self, api_key: Option<String>, base_url: Option<String>) -> Self {
+        self.openrouter_api_key = api_key;
+        self.openrouter_base_url = base_url;
+        self
+    }
+
+    /// Set the agents to fall back to.
+    #[must_use]
+    pub fn with_fallback<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fallback = ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether this profile talks to `OpenRouter`.
+    #[must_use]
+    pub const fn uses_openrouter(&self) -> bool {
+        self.openrouter_api_key.is_some()
+    }
+}
+
+/// A workflow names an agent that is neither a profile nor a known agent CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "unknown agent '{agent}'{}: not an agents.yaml profile or a known agent CLI",
+    step.as_ref().map_or_else(|| " in defaults.tool".to_string(), |s| format!(" in step '{s}'"))
+)]
+pub struct UnknownAgentError {
+    /// The step naming the agent, or `None` for `defaults.tool`.
+    pub step: Option<String>,
+    /// The agent as written.
+    pub agent: String,
+}
+
+/// Resolve the agent for a workflow step.
+///
+/// Resolution order:
+/// 1. The step's `agent`, then the workflow's `defaults.tool`: a known
+///    profile ID selects that profile; the base agent's ID or binary, or
+///    another known agent CLI, runs that agent with its own settings.
+/// 2. If no profile was selected by ID, `defaults.model` selects the profile
+///    configured with that model, or else overrides the model of an agent
+///    on the base agent's backend. Other CLIs keep their own default model.
+/// 3. Otherwise the base profile is used unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the agent is neither a profile nor a known CLI.
+pub fn resolve_step_agent(
+    workflow: &Workflow,
+    step: &WorkflowStep,
+    base: &AgentProfile,
+    profiles: &[AgentProfile],
+) -> Result<AgentProfile, UnknownAgentError> {
+    let find_by_id = |id: &str| profiles.iter().find(|p| p.id == id);
+
+    let agent_ref = step.tool_or_default(workflow.default_tool());
+    if let Some(profile) = agent_ref.and_then(find_by_id) {
+        return Ok(profile.clone());
+    }
+
+    let resolved = match agent_ref {
+        Some(binary) if binary != base.id && binary != base.binary => {
+            let agent_type =
+                AgentType::known_binary(binary).ok_or_else(|| UnknownAgentError {
+                    step: step.agent.is_some().then(|| step.id.clone()),
+                    agent: binary.to_string(),
+                })?;
+            AgentProfile::new(binary, binary).with_agent_type(agent_type)
+        }
+        _ => base.clone(),
+    };
+
+    let default_model = workflow.defaults.as_ref().and_then(|d| d.model.as_deref());
+    let Some(model) = default_model.filter(|_| same_backend(&resolved, base)) else {
+        return Ok(resolved);
+    };
+    if let Some(profile) = profiles.iter().find(|p| {
+        p.model.as_deref() == Some(model) && (agent_ref.is_none() || same_backend(p, &resolved))
+    }) {
+        return Ok(profile.clone());
+    }
+    Ok(AgentProfile {
+        model: Some(model.to_string()),
+        ..resolved
+    })
+}
+
+/// The agents a workflow names in `defaults.tool` and step `agent` fields
+/// that [`resolve_step_agent`] would reject.
+#[must_use]
+pub fn unknown_agents(
+    workflow: &Workflow,
+    base: &AgentProfile,
+    profiles: &[AgentProfile],
+) -> Vec<UnknownAgentError> {
+    let named = workflow
+        .default_tool()
+        .map(|agent| (None, agent))
+        .into_iter()
+        .chain(workflow.steps.iter().filter_map(|step| {
+            step.agent.as_deref().map(|agent| (Some(step.id.clone()), agent))
+        }));
+    named
+        .filter(|(_, agent)| {
+            *agent != base.id
+                && *agent != base.binary
+                && !profiles.iter().any(|p| p.id == *agent)
+                && AgentType::known_binary(agent).is_none()
+        })
+        .map(|(step, agent)| UnknownAgentError {
+            step,
+            agent: agent.to_string(),
+        })
+        .collect()
+}
+
+/// Whether two agents are driven by the same backend.
+fn same_backend(a: &AgentProfile, b: &AgentProfile) -> bool {
+    a.agent_type.backend().name() == b.agent_type.backend().name()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = r#"
+version: '1.0'
+name: 'mixed'
+defaults:
+  model: 'minimax/minimax-m2.1'
+steps:
+  - id: plan
+    name: 'Plan'
+    agent: 'strong'
+    prompt: 'Plan it'
+  - id: implement
+    name: 'Implement'
+    prompt: 'Do it'
+  - id: docs
+    name: 'Docs'
+    agent: 'gemini'
+    prompt: 'Document it'
+"#;
+
+    fn profiles() -> Vec<AgentProfile> {
+        vec![
+            AgentProfile::new("strong", "claude").with_model("opus"),
+            AgentProfile::new("cheap", "claude")
+                .with_model("minimax/minimax-m2.1")
+                .with_openrouter(Some("sk-or".to_string()), None),
+        ]
+    }
+
+    #[test]
+    fn test_step_agent_selects_profile() {
+        let workflow = Workflow::parse(WORKFLOW).expect("parse");
+        let base = AgentProfile::new("claude", "claude");
+        let step = workflow.get_step("plan").expect("step");
+
+        let resolved = resolve_step_agent(&workflow, step, &base, &profiles()).expect("resolve");
+        assert_eq!(resolved.id, "strong");
+        assert_eq!(resolved.model.as_deref(), Some("opus"));
+    }
+
+    #[test]
+    fn test_default_model_selects_profile() {
+        let workflow = Workflow::parse(WORKFLOW).expect("parse");
+        let base = AgentProfile::new("claude", "claude");
+        let step = workflow.get_step("implement").expect("step");
+
+        let resolved = resolve_step_agent(&workflow, step, &base, &profiles()).expect("resolve");
+        assert_eq!(resolved.id, "cheap");
+        assert!(resolved.uses_openrouter());
+    }
+
+    #[test]
+    fn test_known_binary_keeps_its_own_model() {
+        let workflow = Workflow::parse(WORKFLOW).expect("parse");
+        let base = AgentProfile::new("claude", "claude");
+        let step = workflow.get_step("docs").expect("step");
+
+        // defaults.model names an OpenRouter model Gemini can't serve
+        let resolved = resolve_step_agent(&workflow, step, &base, &profiles()).expect("resolve");
+        assert_eq!(resolved.binary, "gemini");
+        assert_eq!(resolved.agent_type, AgentType::Gemini);
+        assert_eq!(resolved.model, None);
+    }
+
+    #[test]
+    fn test_unknown_agent_is_rejected() {
+        let workflow = Workflow::parse(&WORKFLOW.replace("'strong'", "'strnog'")).expect("parse");
+        let base = AgentProfile::new("claude", "claude");
+        let step = workflow.get_step("plan").expect("step");
+
+        let err = resolve_step_agent(&workflow, step, &base, &profiles()).expect_err("typo");
+        assert_eq!(err.step.as_deref(), Some("plan"));
+        assert_eq!(err.agent, "strnog");
+        assert_eq!(unknown_agents(&workflow, &base, &profiles()), vec![err]);
+    }
+
+    #[test]
+    fn test_no_overrides_uses_base() {
+        let workflow = Workflow::parse(
+            "version: '1.0'\nname: 'plain'\nsteps:\n  - id: a\n    name: 'A'\n    prompt: 'x'\n",
+        )
+        .expect("parse");
+        let base = AgentProfile::new("claude-default", "claude").with_model("sonnet");
+        let step = workflow.get_step("a").expect("step");
+
+        assert_eq!(
+            resolve_step_agent(&workflow, step, &base, &profiles()).expect("resolve"),
+            base
+        );
+    }
+}
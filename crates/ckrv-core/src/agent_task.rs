@@ -8,6 +8,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::backend::AgentUsage;
+
 /// A task instance representing a workflow execution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentTask {
@@ -29,6 +31,13 @@ pub struct AgentTask {
     /// Outputs collected from completed steps.
     #[serde(default)]
     pub step_outputs: HashMap<String, HashMap<String, String>>,
+    /// Agent that produced the most recent successful step, after any
+    /// fallbacks.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Token usage and cost reported across all steps.
+    #[serde(default)]
+    pub usage: Option<AgentUsage>,
 }
 
 /// Status of a task.
@@ -81,6 +90,8 @@ impl AgentTask {
             created_at: Utc::now(),
             updated_at: None,
             step_outputs: HashMap::new(),
+            agent: None,
+            usage: None,
         }
     }
 
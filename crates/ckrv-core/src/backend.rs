@@ -0,0 +1,1024 @@
+//! Agent backends: how to drive each supported coding-agent CLI.
+//!
+//! An [`AgentBackend`] knows one agent's command line, environment,
+//! output format and usage reporting. The runner picks the backend from
+//! the profile's [`AgentType`] (`agent_type` in `agents.yaml`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent_profile::AgentProfile;
+
+/// Default `OpenRouter` endpoint for Anthropic-compatible clients.
+const OPENROUTER_ANTHROPIC_URL: &str = "https://openrouter.ai/api";
+
+/// Default `OpenRouter` endpoint for OpenAI-compatible clients.
+const OPENROUTER_OPENAI_URL: &str = "https://openrouter.ai/api/v1";
+
+/// Kind of agent CLI, as configured by `agent_type` in `agents.yaml`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentType {
+    /// Claude Code.
+    #[default]
+    Claude,
+    /// Claude Code routed through `OpenRouter`.
+    #[serde(alias = "claude_openrouter")]
+    ClaudeOpenRouter,
+    /// Google Gemini CLI.
+    Gemini,
+    /// `OpenAI` Codex CLI.
+    Codex,
+    /// Cursor CLI.
+    Cursor,
+    /// Amp CLI.
+    Amp,
+    /// Qwen Code CLI.
+    QwenCode,
+    /// `OpenCode`.
+    Opencode,
+    /// Aider.
+    Aider,
+    /// Record/replay mock (see [`crate::mock_agent`]).
+    Mock,
+    /// Factory Droid.
+    FactoryDroid,
+    /// GitHub Copilot CLI.
+    Copilot,
+}
+
+impl AgentType {
+    /// Guess the agent type from a binary name (e.g. a workflow `tool: gemini`).
+    #[must_use]
+    pub fn from_binary(binary: &str) -> Self {
+        Self::known_binary(binary).unwrap_or(Self::Claude)
+    }
+
+    /// The agent type whose CLI is `binary`, if it is one we know.
+    #[must_use]
+    pub fn known_binary(binary: &str) -> Option<Self> {
+        let name = std::path::Path::new(binary)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(binary);
+        match name {
+            "claude" => Some(Self::Claude),
+            "codex" => Some(Self::Codex),
+            "gemini" => Some(Self::Gemini),
+            "cursor-agent" => Some(Self::Cursor),
+            "amp" => Some(Self::Amp),
+            "qwen" => Some(Self::QwenCode),
+            "opencode" => Some(Self::Opencode),
+            "aider" => Some(Self::Aider),
+            "droid" => Some(Self::FactoryDroid),
+            "copilot" => Some(Self::Copilot),
+            _ => None,
+        }
+    }
+
+    /// The backend that drives this agent type.
+    #[must_use]
+    pub fn backend(self) -> &'static dyn AgentBackend {
+        match self {
+            Self::Claude | Self::ClaudeOpenRouter => &ClaudeCodeBackend,
+            Self::Gemini => &GeminiBackend,
+            Self::Codex => &CodexBackend,
+            Self::Cursor => &CursorBackend,
+            Self::Amp => &AmpBackend,
+            Self::QwenCode => &QwenCodeBackend,
+            Self::Opencode => &OpenCodeBackend,
+            Self::Aider => &AiderBackend,
+            Self::Mock => &MockBackend,
+            Self::FactoryDroid => &FactoryDroidBackend,
+            Self::Copilot => &CopilotBackend,
+        }
+    }
+}
+
+/// A fully prepared agent command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentInvocation {
+    /// Program to run.
+    pub program: String,
+    /// Arguments, including the prompt.
+    pub args: Vec<String>,
+    /// Extra environment variables.
+    pub env: Vec<(String, String)>,
+}
+
+impl AgentInvocation {
+    /// Render as a single shell command line (for running inside a container).
+    #[must_use]
+    pub fn shell_command(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|part| shell_escape::escape(part.as_str().into()).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Token usage and cost reported by an agent CLI.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentUsage {
+    /// Input (prompt) tokens.
+    #[serde(default)]
+    pub input_tokens: Option<u64>,
+    /// Output (completion) tokens.
+    #[serde(default)]
+    pub output_tokens: Option<u64>,
+    /// Total tokens, when only a total is reported.
+    #[serde(default)]
+    pub total_tokens: Option<u64>,
+    /// Cost in USD, if reported.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Number of agent turns (model round trips), if reported.
+    #[serde(default)]
+    pub turns: Option<u64>,
+}
+
+impl AgentUsage {
+    /// Add `other` to these totals; a field stays `None` only if both are.
+    pub fn add(&mut self, other: &Self) {
+        fn sum<T: std::ops::Add<Output = T> + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            }
+        }
+        self.input_tokens = sum(self.input_tokens, other.input_tokens);
+        self.output_tokens = sum(self.output_tokens, other.output_tokens);
+        self.total_tokens = sum(self.total_tokens, other.total_tokens);
+        self.cost_usd = sum(self.cost_usd, other.cost_usd);
+        self.turns = sum(self.turns, other.turns);
+    }
+}
+
+/// A tool the agent called while running a step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Tool name, e.g. `Edit` or `Bash`.
+    pub name: String,
+    /// What the tool acted on (a file path, command or pattern), if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Whether the tool reported an error.
+    #[serde(default)]
+    pub failed: bool,
+}
+
+/// Drives one kind of agent CLI.
+pub trait AgentBackend: Send + Sync {
+    /// Display name of the agent.
+    fn name(&self) -> &'static str;
+
+    /// Binary used when the profile doesn't name one.
+    fn default_binary(&self) -> &'static str;
+
+    /// Arguments that run `prompt` non-interactively with file edits allowed.
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String>;
+
+    /// Environment variables for the profile (credentials, endpoints, model).
+    fn env(&self, _profile: &AgentProfile) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Environment variables, any one of which authenticates the agent.
+    fn credential_env(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Files under the home directory where the agent keeps a login.
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Where the profile's credentials come from, or a hint for setting them
+    /// up. Agents with no known credential locations always pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns a hint if no credentials were found.
+    fn credentials(&self, profile: &AgentProfile) -> Result<String, String> {
+        if let Some(key) = &profile.openrouter_api_key {
+            return Ok(crate::secrets::secret_reference(key).map_or_else(
+                || "OpenRouter API key".to_string(),
+                |name| format!("OpenRouter API key (secret:{name})"),
+            ));
+        }
+        let env_set = |var: &&&str| {
+            profile.env.contains_key(**var) || std::env::var(var).is_ok_and(|v| !v.is_empty())
+        };
+        if let Some(var) = self.credential_env().iter().find(env_set) {
+            return Ok(format!("${var}"));
+        }
+        let login = dirs::home_dir().and_then(|home| {
+            self.credential_files()
+                .iter()
+                .map(|file| home.join(file))
+                .find(|path| path.exists())
+        });
+        if let Some(path) = login {
+            return Ok(path.display().to_string());
+        }
+        if self.credential_env().is_empty() && self.credential_files().is_empty() {
+            return Ok("no credentials required".to_string());
+        }
+
+        let mut hint = Vec::new();
+        if !self.credential_env().is_empty() {
+            hint.push(format!("set {}", self.credential_env().join(" or ")));
+        }
+        if !self.credential_files().is_empty() {
+            hint.push(format!("log in with `{}`", self.default_binary()));
+        }
+        Err(format!("No credentials found: {}", hint.join(", or ")))
+    }
+
+    /// The agent's response text, from its raw stdout.
+    fn parse_output(&self, stdout: &str) -> String {
+        stdout.to_string()
+    }
+
+    /// Token usage reported in the agent's output, if any.
+    fn usage(&self, _stdout: &str, _stderr: &str) -> Option<AgentUsage> {
+        None
+    }
+
+    /// Tools the agent called, in order, from its raw stdout.
+    fn tool_calls(&self, _stdout: &str) -> Vec<ToolCall> {
+        Vec::new()
+    }
+
+    /// How a line of raw stdout is shown while the agent runs, or `None` to
+    /// hide it. Agents with machine-readable output render it here.
+    fn display_line(&self, line: &str) -> Option<String> {
+        Some(line.to_string())
+    }
+
+    /// ID of the session the agent ran in, from its raw stdout, for agents
+    /// that can resume one.
+    fn session_id(&self, _stdout: &str) -> Option<String> {
+        None
+    }
+
+    /// Extra arguments that continue `session_id` instead of starting a new
+    /// session. Empty if the agent can't resume sessions.
+    fn resume_args(&self, _session_id: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Errors the agent reported in machine-readable output, from its raw
+    /// stdout. Failures are classified from these and stderr only.
+    fn error_message(&self, _stdout: &str) -> Option<String> {
+        None
+    }
+
+    /// Build the full command for `prompt`, including the profile's extra
+    /// arguments and environment variables.
+    fn invocation(&self, prompt: &str, profile: &AgentProfile) -> AgentInvocation {
+        let mut args = self.args(prompt, profile);
+        args.extend(profile.extra_args.iter().cloned());
+        let mut env = self.env(profile);
+        env.extend(profile.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        AgentInvocation {
+            program: profile.binary.clone(),
+            args,
+            env,
+        }
+    }
+}
+
+/// Model name with an `openrouter/` prefix, as Aider and `OpenCode` expect.
+fn openrouter_model(profile: &AgentProfile) -> Option<String> {
+    profile.model.as_ref().map(|model| {
+        if profile.uses_openrouter() && !model.starts_with("openrouter/") {
+            format!("openrouter/{model}")
+        } else {
+            model.clone()
+        }
+    })
+}
+
+/// `OPENROUTER_API_KEY` for agents that talk to `OpenRouter` natively.
+fn openrouter_env(profile: &AgentProfile) -> Vec<(String, String)> {
+    profile
+        .openrouter_api_key
+        .as_ref()
+        .map(|key| vec![("OPENROUTER_API_KEY".to_string(), key.clone())])
+        .unwrap_or_default()
+}
+
+/// Parse a token count like `12,345`, `1.2k` or `3M`.
+fn parse_count(raw: &str) -> Option<u64> {
+    let raw = raw.trim().replace(',', "");
+    let (number, scale) = match raw.chars().last()?.to_ascii_lowercase() {
+        'k' => (&raw[..raw.len() - 1], 1_000.0),
+        'm' => (&raw[..raw.len() - 1], 1_000_000.0),
+        _ => (raw.as_str(), 1.0),
+    };
+    let value: f64 = number.parse().ok()?;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    Some((value * scale).round() as u64)
+}
+
+/// Claude Code (`claude -p`).
+pub struct ClaudeCodeBackend;
+
+impl AgentBackend for ClaudeCodeBackend {
+    fn name(&self) -> &'static str {
+        "Claude Code"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "claude"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["ANTHROPIC_API_KEY", "CLAUDE_CODE_OAUTH_TOKEN"]
+    }
+
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[".claude/.credentials.json", ".claude.json"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec![
+            "-p".to_string(),
+            prompt.to_string(),
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string(),
+            "--dangerously-skip-permissions".to_string(),
+        ];
+        // With OpenRouter the model is selected through the environment
+        if let (false, Some(model)) = (profile.uses_openrouter(), &profile.model) {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args
+    }
+
+    fn env(&self, profile: &AgentProfile) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = profile
+            .max_tokens
+            .map(|max| ("CLAUDE_CODE_MAX_OUTPUT_TOKENS".to_string(), max.to_string()))
+            .into_iter()
+            .collect();
+
+        // Per https://openrouter.ai/docs/guides/guides/claude-code-integration
+        let Some(ref api_key) = profile.openrouter_api_key else {
+            return env;
+        };
+        let base_url = profile
+            .openrouter_base_url
+            .as_deref()
+            .unwrap_or(OPENROUTER_ANTHROPIC_URL);
+
+        env.extend([
+            ("ANTHROPIC_BASE_URL".to_string(), base_url.to_string()),
+            ("ANTHROPIC_AUTH_TOKEN".to_string(), api_key.clone()),
+            // Must be explicitly empty!
+            ("ANTHROPIC_API_KEY".to_string(), String::new()),
+        ]);
+        if let Some(ref model) = profile.model {
+            // Set all tiers to the same model for consistency
+            for tier in ["SONNET", "OPUS", "HAIKU"] {
+                env.push((format!("ANTHROPIC_DEFAULT_{tier}_MODEL"), model.clone()));
+            }
+        }
+        env
+    }
+
+    /// `--output-format stream-json` ends with a result event holding the
+    /// response.
+    fn parse_output(&self, stdout: &str) -> String {
+        claude_result(stdout)
+            .and_then(|result| result.get("result")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| stdout.to_string())
+    }
+
+    /// The result event reports usage for the whole run, prompt caching
+    /// included.
+    fn usage(&self, stdout: &str, _stderr: &str) -> Option<AgentUsage> {
+        let result = claude_result(stdout)?;
+        let tokens = |key: &str| result.get("usage")?.get(key)?.as_u64();
+        let input = ["input_tokens", "cache_creation_input_tokens", "cache_read_input_tokens"]
+            .iter()
+            .filter_map(|key| tokens(key))
+            .reduce(|a, b| a + b);
+        Some(AgentUsage {
+            input_tokens: input,
+            output_tokens: tokens("output_tokens"),
+            total_tokens: None,
+            cost_usd: result.get("total_cost_usd").and_then(serde_json::Value::as_f64),
+            turns: result.get("num_turns").and_then(serde_json::Value::as_u64),
+        })
+    }
+
+    fn tool_calls(&self, stdout: &str) -> Vec<ToolCall> {
+        let blocks: Vec<serde_json::Value> = stream_events(stdout)
+            .filter_map(|event| event.pointer("/message/content")?.as_array().cloned())
+            .flatten()
+            .collect();
+        let failed: std::collections::HashSet<&str> = blocks
+            .iter()
+            .filter(|block| block["type"] == "tool_result" && block["is_error"] == true)
+            .filter_map(|block| block["tool_use_id"].as_str())
+            .collect();
+
+        blocks
+            .iter()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| ToolCall {
+                name: block["name"].as_str().unwrap_or("unknown").to_string(),
+                target: tool_target(&block["input"]),
+                failed: block["id"].as_str().is_some_and(|id| failed.contains(id)),
+            })
+            .collect()
+    }
+
+    fn display_line(&self, line: &str) -> Option<String> {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            return Some(line.to_string());
+        };
+        if event["type"] != "assistant" {
+            return None;
+        }
+        let parts: Vec<String> = event
+            .pointer("/message/content")?
+            .as_array()?
+            .iter()
+            .filter_map(|block| match block["type"].as_str()? {
+                "text" => block["text"].as_str().map(str::to_string),
+                "tool_use" => {
+                    let name = block["name"].as_str()?;
+                    Some(tool_target(&block["input"]).map_or_else(
+                        || format!("→ {name}"),
+                        |target| format!("→ {name} {target}"),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        (!parts.is_empty()).then(|| parts.join("\n"))
+    }
+
+    fn session_id(&self, stdout: &str) -> Option<String> {
+        claude_result(stdout)?
+            .get("session_id")?
+            .as_str()
+            .map(str::to_string)
+    }
+
+    fn resume_args(&self, session_id: &str) -> Vec<String> {
+        vec!["--resume".to_string(), session_id.to_string()]
+    }
+
+    /// A failed run ends with a result event flagged `is_error`; API errors
+    /// along the way carry an `error` field.
+    fn error_message(&self, stdout: &str) -> Option<String> {
+        let messages: Vec<String> = stream_events(stdout)
+            .filter_map(|event| {
+                if event["type"] == "result" && event["is_error"] == true {
+                    let text = event["result"].as_str().or_else(|| event["subtype"].as_str());
+                    return text.map(str::to_string);
+                }
+                match &event["error"] {
+                    serde_json::Value::String(error) => Some(error.clone()),
+                    error => error["message"].as_str().map(str::to_string),
+                }
+            })
+            .collect();
+        (!messages.is_empty()).then(|| messages.join("\n"))
+    }
+}
+
+/// The JSON events in line-delimited output, skipping anything else.
+fn stream_events(stdout: &str) -> impl Iterator<Item = serde_json::Value> + '_ {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+        .filter(serde_json::Value::is_object)
+}
+
+/// The final result event Claude Code prints with `--output-format json` or
+/// `stream-json`.
+fn claude_result(stdout: &str) -> Option<serde_json::Value> {
+    stream_events(stdout).filter(|event| event["type"] == "result").last()
+}
+
+/// What a tool call acted on, from the common input fields of agent tools.
+fn tool_target(input: &serde_json::Value) -> Option<String> {
+    const MAX_CHARS: usize = 120;
+
+    let target = ["file_path", "path", "command", "pattern", "url", "query"]
+        .iter()
+        .find_map(|key| input.get(key)?.as_str())?;
+    let target = target.lines().next().unwrap_or_default();
+    Some(if target.chars().count() > MAX_CHARS {
+        format!("{}…", target.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        target.to_string()
+    })
+}
+
+/// `OpenAI` Codex CLI (`codex exec`).
+pub struct CodexBackend;
+
+impl AgentBackend for CodexBackend {
+    fn name(&self) -> &'static str {
+        "Codex CLI"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "codex"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["OPENAI_API_KEY"]
+    }
+
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[".codex/auth.json"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec![
+            "exec".to_string(),
+            "--full-auto".to_string(),
+            "--skip-git-repo-check".to_string(),
+        ];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args.push(prompt.to_string());
+        args
+    }
+
+    /// Codex ends with `tokens used: N`, or `tokens used` with the count on the next line.
+    fn usage(&self, stdout: &str, stderr: &str) -> Option<AgentUsage> {
+        let lines: Vec<&str> = stdout
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .collect();
+        let index = lines
+            .iter()
+            .rposition(|line| line.to_ascii_lowercase().starts_with("tokens used"))?;
+        let rest = lines[index]["tokens used".len()..]
+            .trim_start_matches(':')
+            .trim();
+        let count = if rest.is_empty() {
+            lines.get(index + 1).copied().unwrap_or_default()
+        } else {
+            rest
+        };
+        Some(AgentUsage {
+            total_tokens: parse_count(count),
+            ..AgentUsage::default()
+        })
+    }
+}
+
+/// Gemini CLI (`gemini -p`).
+pub struct GeminiBackend;
+
+impl AgentBackend for GeminiBackend {
+    fn name(&self) -> &'static str {
+        "Gemini CLI"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["GEMINI_API_KEY", "GOOGLE_API_KEY"]
+    }
+
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[".gemini/oauth_creds.json"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec!["-p".to_string(), prompt.to_string(), "--yolo".to_string()];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args
+    }
+}
+
+/// Cursor CLI (`cursor-agent -p`).
+pub struct CursorBackend;
+
+impl AgentBackend for CursorBackend {
+    fn name(&self) -> &'static str {
+        "Cursor CLI"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "cursor-agent"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["CURSOR_API_KEY"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec![
+            "-p".to_string(),
+            prompt.to_string(),
+            "--force".to_string(),
+            "--output-format".to_string(),
+            "text".to_string(),
+        ];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args
+    }
+}
+
+/// Amp CLI (`amp -x`).
+pub struct AmpBackend;
+
+impl AgentBackend for AmpBackend {
+    fn name(&self) -> &'static str {
+        "Amp"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "amp"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["AMP_API_KEY"]
+    }
+
+    fn args(&self, prompt: &str, _profile: &AgentProfile) -> Vec<String> {
+        // Amp picks its own model
+        vec![
+            "-x".to_string(),
+            prompt.to_string(),
+            "--dangerously-allow-all".to_string(),
+        ]
+    }
+}
+
+/// Qwen Code CLI (`qwen -p`).
+pub struct QwenCodeBackend;
+
+impl AgentBackend for QwenCodeBackend {
+    fn name(&self) -> &'static str {
+        "Qwen Code"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "qwen"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["OPENAI_API_KEY", "DASHSCOPE_API_KEY"]
+    }
+
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[".qwen/oauth_creds.json"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec!["-p".to_string(), prompt.to_string(), "--yolo".to_string()];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args
+    }
+
+    /// Qwen Code speaks the `OpenAI` API, so `OpenRouter` is just another endpoint.
+    fn env(&self, profile: &AgentProfile) -> Vec<(String, String)> {
+        let Some(ref api_key) = profile.openrouter_api_key else {
+            return Vec::new();
+        };
+        let base_url = profile
+            .openrouter_base_url
+            .as_deref()
+            .unwrap_or(OPENROUTER_OPENAI_URL);
+        vec![
+            ("OPENAI_API_KEY".to_string(), api_key.clone()),
+            ("OPENAI_BASE_URL".to_string(), base_url.to_string()),
+        ]
+    }
+}
+
+/// Aider (`aider --message`).
+pub struct AiderBackend;
+
+impl AgentBackend for AiderBackend {
+    fn name(&self) -> &'static str {
+        "Aider"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "aider"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["OPENROUTER_API_KEY", "ANTHROPIC_API_KEY", "OPENAI_API_KEY"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec![
+            "--message".to_string(),
+            prompt.to_string(),
+            "--yes-always".to_string(),
+            "--no-auto-commits".to_string(),
+            "--no-pretty".to_string(),
+            "--no-check-update".to_string(),
+        ];
+        if let Some(model) = openrouter_model(profile) {
+            args.extend(["--model".to_string(), model]);
+        }
+        args
+    }
+
+    fn env(&self, profile: &AgentProfile) -> Vec<(String, String)> {
+        openrouter_env(profile)
+    }
+
+    /// Aider reports `Tokens: 1.2k sent, 345 received. Cost: $0.01 message, $0.05 session.`
+    fn usage(&self, stdout: &str, _stderr: &str) -> Option<AgentUsage> {
+        let line = stdout
+            .lines()
+            .rev()
+            .find(|l| l.trim_start().starts_with("Tokens:"))?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let mut usage = AgentUsage::default();
+        for pair in words.windows(2) {
+            match pair[1].trim_end_matches([',', '.']) {
+                "sent" => usage.input_tokens = parse_count(pair[0]),
+                "received" => usage.output_tokens = parse_count(pair[0]),
+                _ if pair[0] == "Cost:" && usage.cost_usd.is_none() => {
+                    usage.cost_usd = pair[1]
+                        .trim_start_matches('$')
+                        .trim_end_matches(',')
+                        .parse()
+                        .ok();
+                }
+                _ => {}
+            }
+        }
+        Some(usage)
+    }
+}
+
+/// `OpenCode` (`opencode run`).
+pub struct OpenCodeBackend;
+
+impl AgentBackend for OpenCodeBackend {
+    fn name(&self) -> &'static str {
+        "OpenCode"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "opencode"
+    }
+
+    fn credential_files(&self) -> &'static [&'static str] {
+        &[".local/share/opencode/auth.json"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec!["run".to_string()];
+        if let Some(model) = openrouter_model(profile) {
+            args.extend(["--model".to_string(), model]);
+        }
+        args.push(prompt.to_string());
+        args
+    }
+
+    fn env(&self, profile: &AgentProfile) -> Vec<(String, String)> {
+        openrouter_env(profile)
+    }
+}
+
+/// Factory Droid (`droid exec`).
+pub struct FactoryDroidBackend;
+
+impl AgentBackend for FactoryDroidBackend {
+    fn name(&self) -> &'static str {
+        "Factory Droid"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "droid"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["FACTORY_API_KEY"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec!["exec".to_string(), "--auto".to_string(), "high".to_string()];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args.push(prompt.to_string());
+        args
+    }
+}
+
+/// GitHub Copilot CLI (`copilot -p`).
+pub struct CopilotBackend;
+
+impl AgentBackend for CopilotBackend {
+    fn name(&self) -> &'static str {
+        "GitHub Copilot"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "copilot"
+    }
+
+    fn credential_env(&self) -> &'static [&'static str] {
+        &["COPILOT_GITHUB_TOKEN", "GH_TOKEN", "GITHUB_TOKEN"]
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        let mut args = vec![
+            "-p".to_string(),
+            prompt.to_string(),
+            "--allow-all-tools".to_string(),
+        ];
+        if let Some(ref model) = profile.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        args
+    }
+}
+
+/// Record/replay mock. Replays never run a command; recordings drive the
+/// real agent named by the profile's binary.
+pub struct MockBackend;
+
+impl AgentBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn default_binary(&self) -> &'static str {
+        "claude"
+    }
+
+    fn args(&self, prompt: &str, profile: &AgentProfile) -> Vec<String> {
+        AgentType::from_binary(&profile.binary)
+            .backend()
+            .args(prompt, profile)
+    }
+
+    fn env(&self, profile: &AgentProfile) -> Vec<(String, String)> {
+        AgentType::from_binary(&profile.binary).backend().env(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_openrouter_uses_env() {
+        let profile = AgentProfile::new("cheap", "claude")
+            .with_model("minimax/minimax-m2.1")
+            .with_openrouter(Some("sk-or".to_string()), None);
+        let invocation = AgentType::Claude.backend().invocation("hi", &profile);
+
+        assert_eq!(invocation.program, "claude");
+        assert!(!invocation.args.contains(&"--model".to_string()));
+        assert!(invocation
+            .env
+            .contains(&("ANTHROPIC_AUTH_TOKEN".to_string(), "sk-or".to_string())));
+        assert!(invocation.env.contains(&(
+            "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
+            "minimax/minimax-m2.1".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_backend_commands() {
+        let codex = AgentProfile::new("codex", "codex").with_model("o4-mini");
+        assert_eq!(
+            codex.agent_type.backend().args("fix it", &codex),
+            [
+                "exec",
+                "--full-auto",
+                "--skip-git-repo-check",
+                "--model",
+                "o4-mini",
+                "fix it"
+            ]
+        );
+
+        let aider = AgentProfile::new("aider", "aider")
+            .with_model("deepseek/deepseek-chat")
+            .with_openrouter(Some("sk-or".to_string()), None);
+        let invocation = aider.agent_type.backend().invocation("fix it", &aider);
+        assert!(invocation.args.ends_with(&[
+            "--model".to_string(),
+            "openrouter/deepseek/deepseek-chat".to_string()
+        ]));
+        assert_eq!(
+            invocation.env,
+            [("OPENROUTER_API_KEY".to_string(), "sk-or".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_shell_command_escapes_prompt() {
+        let profile = AgentProfile::new("gemini", "gemini");
+        let invocation = AgentType::Gemini
+            .backend()
+            .invocation("it's done", &profile);
+        assert_eq!(
+            invocation.shell_command(),
+            "gemini -p 'it'\\''s done' --yolo"
+        );
+    }
+
+    #[test]
+    fn test_usage_parsing() {
+        let stdout =
+            "Applied edit\nTokens: 1.2k sent, 345 received. Cost: $0.01 message, $0.05 session.\n";
+        let aider = AgentType::Aider.backend().usage(stdout, "").expect("usage");
+        assert_eq!(aider.input_tokens, Some(1200));
+        assert_eq!(aider.output_tokens, Some(345));
+        assert_eq!(aider.cost_usd, Some(0.01));
+
+        let codex = AgentType::Codex
+            .backend()
+            .usage("done\n", "tokens used\n12,345\n")
+            .expect("usage");
+        assert_eq!(codex.total_tokens, Some(12_345));
+
+        assert!(AgentType::Claude.backend().usage("done", "").is_none());
+    }
+
+    #[test]
+    fn test_claude_stream_json() {
+        let stdout = [
+            r#"{"type":"system","subtype":"init","session_id":"3f2a"}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Fixing."},{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/lib.rs"}}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","is_error":true}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"cargo test\necho done"}}]}}"#,
+            r#"{"type":"result","result":"Fixed.","session_id":"3f2a","num_turns":3,"total_cost_usd":0.042,"usage":{"input_tokens":10,"cache_read_input_tokens":900,"output_tokens":250}}"#,
+        ]
+        .join("\n");
+        let claude = AgentType::Claude.backend();
+
+        assert_eq!(claude.parse_output(&stdout), "Fixed.");
+        let usage = claude.usage(&stdout, "").expect("usage");
+        assert_eq!(usage.input_tokens, Some(910));
+        assert_eq!(usage.output_tokens, Some(250));
+        assert_eq!(usage.cost_usd, Some(0.042));
+        assert_eq!(usage.turns, Some(3));
+
+        let calls = claude.tool_calls(&stdout);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].target.as_deref(), Some("src/lib.rs"));
+        assert!(calls[0].failed);
+        assert_eq!(calls[1].target.as_deref(), Some("cargo test"));
+        assert!(!calls[1].failed);
+
+        let lines: Vec<Option<String>> = stdout.lines().map(|l| claude.display_line(l)).collect();
+        assert_eq!(lines[0], None);
+        assert_eq!(lines[1].as_deref(), Some("Fixing.\n→ Edit src/lib.rs"));
+        assert_eq!(lines[4], None);
+
+        // Transcript text is not an error, even when it quotes one
+        assert_eq!(claude.error_message(&stdout), None);
+        let failed = [
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Handle HTTP 401 in login()"}]}}"#,
+            r#"{"type":"result","subtype":"success","is_error":true,"result":"Invalid API key · Please run /login"}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            claude.error_message(&failed).as_deref(),
+            Some("Invalid API key · Please run /login")
+        );
+    }
+
+    #[test]
+    fn test_claude_sessions() {
+        let claude = AgentType::Claude.backend();
+        let stdout =
+            r#"{"type":"result","subtype":"success","result":"Done.","session_id":"3f2a"}"#;
+        assert_eq!(claude.parse_output(stdout), "Done.");
+        assert_eq!(claude.session_id(stdout).as_deref(), Some("3f2a"));
+        assert_eq!(claude.resume_args("3f2a"), ["--resume", "3f2a"]);
+
+        // Plain text (e.g. an older CLI) passes through without a session
+        assert_eq!(claude.parse_output("Done.\n"), "Done.\n");
+        assert!(claude.session_id("Done.\n").is_none());
+        assert!(AgentType::Codex.backend().resume_args("3f2a").is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//! Workspace checkpoints, for undoing a failed agent's edits.
+//!
+//! A checkpoint is a git tree holding every file in the work tree that git
+//! doesn't ignore, tracked or not. It is written through a throwaway index,
+//! so the repository's own index, stash and history are left alone.
+//! Restoring it rewrites changed files and removes files created since;
+//! ignored files (build output, `.ckrv/`) are never touched.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Errors from capturing or restoring a checkpoint.
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    /// Git could not be run.
+    #[error("failed to run git: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A git command failed (e.g. the directory is not a git work tree).
+    #[error("git {command} failed: {stderr}")]
+    Git {
+        /// The git subcommand.
+        command: String,
+        /// What git printed on stderr.
+        stderr: String,
+    },
+}
+
+/// The state of a git work tree at one point in time.
+#[derive(Debug, Clone)]
+pub struct WorkspaceCheckpoint {
+    root: PathBuf,
+    tree: String,
+}
+
+impl WorkspaceCheckpoint {
+    /// Snapshot the work tree containing `workdir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `workdir` is not inside a git work tree or git
+    /// fails.
+    pub async fn capture(workdir: &Path) -> Result<Self, CheckpointError> {
+        let root = PathBuf::from(git(workdir, None, &["rev-parse", "--show-toplevel"]).await?.trim());
+
+        // Start from the real index so unchanged files aren't re-hashed
+        let index = TempIndex::new();
+        let real = git(&root, None, &["rev-parse", "--git-path", "index"]).await?;
+        if tokio::fs::copy(root.join(real.trim()), &index.0).await.is_err() {
+            tracing::debug!("No index to seed the checkpoint from, hashing every file");
+        }
+
+        git(&root, Some(&index.0), &["add", "-A"]).await?;
+        let tree = git(&root, Some(&index.0), &["write-tree"]).await?.trim().to_string();
+        Ok(Self { root, tree })
+    }
+
+    /// Put the work tree back the way it was when captured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails.
+    pub async fn restore(&self) -> Result<(), CheckpointError> {
+        let index = TempIndex::new();
+        git(&self.root, Some(&index.0), &["read-tree", &self.tree]).await?;
+        git(&self.root, Some(&index.0), &["clean", "-fdq"]).await?;
+        git(&self.root, Some(&index.0), &["checkout-index", "-a", "-f"]).await?;
+        Ok(())
+    }
+}
+
+/// An index file in the temp directory, removed on drop.
+struct TempIndex(PathBuf);
+
+impl TempIndex {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("ckrv-index-{}", uuid::Uuid::new_v4())))
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Run git in `dir`, optionally against another index file.
+async fn git(dir: &Path, index: Option<&Path>, args: &[&str]) -> Result<String, CheckpointError> {
+    let mut command = tokio::process::Command::new("git");
+    command.args(args).current_dir(dir);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let output = command.output().await?;
+    if !output.status.success() {
+        return Err(CheckpointError::Git {
+            command: args.first().copied().unwrap_or_default().to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git_sync(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("run git");
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_restore_undoes_edits() {
+        let dir = TempDir::new().expect("temp dir");
+        let root = dir.path();
+        git_sync(root, &["init", "-q"]);
+        std::fs::write(root.join(".gitignore"), "target/\n").expect("write");
+        std::fs::write(root.join("a.txt"), "one\n").expect("write");
+        git_sync(root, &["add", "."]);
+        git_sync(root, &["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]);
+
+        // Uncommitted work from earlier steps is part of the checkpoint
+        std::fs::write(root.join("a.txt"), "two\n").expect("write");
+        std::fs::write(root.join("b.txt"), "new\n").expect("write");
+        let status = git_sync(root, &["status", "--porcelain"]);
+
+        let checkpoint = WorkspaceCheckpoint::capture(root).await.expect("capture");
+
+        std::fs::write(root.join("a.txt"), "half-edited\n").expect("write");
+        std::fs::remove_file(root.join("b.txt")).expect("remove");
+        std::fs::create_dir_all(root.join("src/nested")).expect("mkdir");
+        std::fs::write(root.join("src/nested/c.txt"), "stray\n").expect("write");
+        std::fs::create_dir_all(root.join("target")).expect("mkdir");
+        std::fs::write(root.join("target/out"), "build\n").expect("write");
+
+        checkpoint.restore().await.expect("restore");
+
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).expect("read"), "two\n");
+        assert_eq!(std::fs::read_to_string(root.join("b.txt")).expect("read"), "new\n");
+        assert!(!root.join("src").exists());
+        assert!(root.join("target/out").exists(), "ignored files are kept");
+        assert_eq!(git_sync(root, &["status", "--porcelain"]), status);
+    }
+
+    #[tokio::test]
+    async fn test_capture_outside_git_fails() {
+        let dir = TempDir::new().expect("temp dir");
+        assert!(WorkspaceCheckpoint::capture(dir.path()).await.is_err());
+    }
+}
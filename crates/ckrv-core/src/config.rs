@@ -1,10 +1,13 @@
 //! Configuration types for Chakravarti CLI.
 
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use ckrv_sandbox::RuntimeConfig;
 use serde::{Deserialize, Serialize};
 
-use crate::CoreError;
+use crate::interpolate;
+use crate::{CoreError, OptimizeMode, TaskType};
 
 /// Default configuration for a Chakravarti project.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +30,202 @@ pub struct Config {
     /// Executor model override.
     #[serde(default)]
     pub executor_model: Option<String>,
+
+    /// How text-only generation (`ckrv spec new/design/tasks`, `ckrv plan`) is run.
+    #[serde(default)]
+    pub generation: GenerationConfig,
+
+    /// Named model API providers, in addition to those detected from
+    /// `OPENAI_API_KEY`, `ANTHROPIC_API_KEY` and `CKRV_MODEL_ENDPOINT`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<String, ProviderConfig>,
+
+    /// Equivalent models across providers, keyed by alias, then provider
+    /// name (e.g. `{"kimi-k2": {"openrouter": "moonshotai/kimi-k2"}}`).
+    /// Failover only goes to providers with an entry for the requested model.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_aliases: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// On-disk cache of model API responses.
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+
+    /// Rules choosing a model by task type, complexity and optimization
+    /// mode, tried in order; the first with a usable model wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing_rules: Vec<RoutingRule>,
+
+    /// Container runtime for sandboxed agents; `CKRV_CONTAINER_RUNTIME` and
+    /// `CKRV_CONTAINER_SOCKET` take precedence.
+    #[serde(default)]
+    pub container_runtime: RuntimeConfig,
+}
+
+/// API flavour of a configured provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Anthropic Messages API.
+    Anthropic,
+    /// `OpenAI` chat completions API.
+    OpenAi,
+    /// `OpenRouter`, an `OpenAI`-compatible gateway with a model metadata endpoint.
+    OpenRouter,
+    /// A local `OpenAI`-compatible server (Ollama, llama.cpp, vLLM); no key needed.
+    Local,
+}
+
+impl ProviderKind {
+    /// Base URL used when the provider config doesn't set one.
+    #[must_use]
+    pub const fn default_base_url(self) -> &'static str {
+        match self {
+            Self::Anthropic => "https://api.anthropic.com/v1",
+            Self::OpenAi => "https://api.openai.com/v1",
+            Self::OpenRouter => "https://openrouter.ai/api/v1",
+            Self::Local => "http://localhost:11434/v1",
+        }
+    }
+
+    /// Whether requests need an API key.
+    #[must_use]
+    pub const fn requires_key(self) -> bool {
+        !matches!(self, Self::Local)
+    }
+}
+
+/// A named model API provider in `config.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// API flavour.
+    pub kind: ProviderKind,
+
+    /// Endpoint; defaults to the kind's public API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+
+    /// API key, either inline, `${VAR}` or a `secret:<name>` reference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Extra headers sent with every request (values may be `secret:` references).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Models served by this provider; the first is its default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+}
+
+/// Which path text-only generation takes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerationBackend {
+    /// Call the model API when a provider is configured, else run the agent.
+    #[default]
+    Auto,
+    /// Always call the model API.
+    Api,
+    /// Always run the coding agent in the sandbox.
+    Agent,
+}
+
+/// Settings for text-only generation through the model API.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    /// Model API or agent.
+    #[serde(default)]
+    pub backend: GenerationBackend,
+
+    /// Name of the provider to call (a `providers` key, `anthropic` or
+    /// `openai`); any available when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// Model to request; falls back to `planner_model`, then the router default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Maximum tokens to generate per request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// A model routing rule in `config.json`. Conditions left unset match any
+/// request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Name reported with the selection; defaults to the rule's position.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Task type the rule applies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<TaskType>,
+
+    /// Optimization mode the rule applies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize: Option<OptimizeMode>,
+
+    /// Lowest task complexity (1-5) the rule applies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_complexity: Option<u8>,
+
+    /// Highest task complexity (1-5) the rule applies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_complexity: Option<u8>,
+
+    /// Models to choose from, most preferred first.
+    pub models: Vec<String>,
+}
+
+impl RoutingRule {
+    /// Whether the rule applies to a task. Complexity bounds only match
+    /// tasks with a known complexity.
+    #[must_use]
+    pub fn matches(&self, task: TaskType, optimize: OptimizeMode, complexity: Option<u8>) -> bool {
+        let in_range = |bound: Option<u8>, within: fn(u8, u8) -> bool| {
+            bound.map_or(true, |bound| complexity.is_some_and(|c| within(c, bound)))
+        };
+        self.task.map_or(true, |t| t == task)
+            && self.optimize.map_or(true, |o| o == optimize)
+            && in_range(self.min_complexity, |c, min| c >= min)
+            && in_range(self.max_complexity, |c, max| c <= max)
+    }
+}
+
+/// Settings for the model response cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseCacheConfig {
+    /// Reuse responses to identical requests. Off unless enabled.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Hours a cached response stays valid.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub ttl_hours: u64,
+
+    /// Size limit for the cache in megabytes; oldest entries go first.
+    #[serde(default = "default_cache_max_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_hours: default_cache_ttl_hours(),
+            max_size_mb: default_cache_max_mb(),
+        }
+    }
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24 * 7
+}
+
+fn default_cache_max_mb() -> u64 {
+    100
 }
 
 fn default_max_attempts() -> u32 {
@@ -41,23 +240,50 @@ impl Default for Config {
             max_attempts: 3,
             planner_model: None,
             executor_model: None,
+            generation: GenerationConfig::default(),
+            providers: BTreeMap::new(),
+            model_aliases: BTreeMap::new(),
+            response_cache: ResponseCacheConfig::default(),
+            routing_rules: Vec::new(),
+            container_runtime: RuntimeConfig::default(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from a file.
+    /// Load configuration from a file, expanding `${VAR}` and
+    /// `${VAR:-default}` in string values.
     ///
     /// # Errors
     ///
-    /// Returns an error if the file cannot be read or parsed.
+    /// Returns an error if the file cannot be read or parsed, or references
+    /// an undefined variable.
     pub fn load(path: &Path) -> Result<Self, CoreError> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| CoreError::InvalidSpec(format!("Failed to read config: {e}")))?;
-        serde_json::from_str(&content)
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| CoreError::InvalidSpec(format!("Failed to parse config: {e}")))?;
+        interpolate::interpolate_json(&mut value, &path.display().to_string())
+            .map_err(|e| CoreError::InvalidSpec(e.to_string()))?;
+        serde_json::from_value(value)
             .map_err(|e| CoreError::InvalidSpec(format!("Failed to parse config: {e}")))
     }
 
+    /// Load `.chakravarti/config.json` from a project root, or the defaults
+    /// when the project has no config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be loaded.
+    pub fn load_project(root: &Path) -> Result<Self, CoreError> {
+        let path = root.join(".chakravarti").join("config.json");
+        if path.exists() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
     /// Save configuration to a file.
     ///
     /// # Errors
@@ -69,6 +295,36 @@ impl Config {
         std::fs::write(path, content)
             .map_err(|e| CoreError::InvalidSpec(format!("Failed to write config: {e}")))
     }
+
+    /// `default_optimize` as a mode. Empty or unknown values are balanced.
+    #[must_use]
+    pub fn optimize_mode(&self) -> OptimizeMode {
+        match self.default_optimize.to_lowercase().as_str() {
+            "cost" => OptimizeMode::Cost,
+            "time" => OptimizeMode::Time,
+            _ => OptimizeMode::Balanced,
+        }
+    }
+
+    /// The first model of the first routing rule matching a task that
+    /// `available` accepts, with the rule's name.
+    pub fn route(
+        &self,
+        task: TaskType,
+        optimize: OptimizeMode,
+        complexity: Option<u8>,
+        mut available: impl FnMut(&str) -> bool,
+    ) -> Option<(&str, String)> {
+        self.routing_rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.matches(task, optimize, complexity))
+            .find_map(|(i, rule)| {
+                let model = rule.models.iter().find(|model| available(model))?;
+                let name = rule.name.clone().unwrap_or_else(|| format!("#{}", i + 1));
+                Some((model.as_str(), name))
+            })
+    }
 }
 
 #[cfg(test)]
@@ -97,6 +353,145 @@ mod tests {
         assert_eq!(config.max_attempts, loaded.max_attempts);
     }
 
+    #[test]
+    fn test_config_load_expands_variables() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        std::fs::write(
+            &path,
+            r#"{"version": "1.0", "planner_model": "${CKRV_TEST_PLANNER_5121:-gpt-4o}", "executor_model": "${CKRV_TEST_EXECUTOR_5121}"}"#,
+        )
+        .expect("write");
+
+        let err = Config::load(&path).expect_err("undefined variable");
+        assert!(err.to_string().contains("executor_model"));
+        assert!(err.to_string().contains("CKRV_TEST_EXECUTOR_5121"));
+
+        std::fs::write(
+            &path,
+            r#"{"version": "1.0", "planner_model": "${CKRV_TEST_PLANNER_5121:-gpt-4o}"}"#,
+        )
+        .expect("write");
+        let config = Config::load(&path).expect("load");
+        assert_eq!(config.planner_model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn test_config_generation_section() {
+        let dir = TempDir::new().expect("temp dir");
+        let config = Config::load_project(dir.path()).expect("defaults");
+        assert_eq!(config.generation.backend, GenerationBackend::Auto);
+
+        std::fs::create_dir_all(dir.path().join(".chakravarti")).expect("mkdir");
+        std::fs::write(
+            dir.path().join(".chakravarti/config.json"),
+            r#"{"version": "1.0", "generation": {"backend": "api", "provider": "anthropic", "model": "claude-sonnet-4-5"}}"#,
+        )
+        .expect("write");
+        let config = Config::load_project(dir.path()).expect("load");
+        assert_eq!(config.generation.backend, GenerationBackend::Api);
+        assert_eq!(config.generation.provider.as_deref(), Some("anthropic"));
+        assert_eq!(config.generation.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(config.generation.max_tokens, None);
+    }
+
+    #[test]
+    fn test_config_providers() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "providers": {
+                    "openrouter": {
+                        "kind": "openrouter",
+                        "api_key": "secret:openrouter",
+                        "headers": {"X-Title": "ckrv"},
+                        "models": ["moonshotai/kimi-k2"]
+                    },
+                    "ollama": {"kind": "local", "models": ["qwen2.5-coder:14b"]}
+                }
+            }"#,
+        )
+        .expect("parse");
+
+        let openrouter = &config.providers["openrouter"];
+        assert_eq!(openrouter.kind, ProviderKind::OpenRouter);
+        assert_eq!(openrouter.api_key.as_deref(), Some("secret:openrouter"));
+        assert_eq!(openrouter.headers["X-Title"], "ckrv");
+
+        let ollama = &config.providers["ollama"];
+        assert_eq!(ollama.kind, ProviderKind::Local);
+        assert!(!ollama.kind.requires_key());
+        assert_eq!(ollama.kind.default_base_url(), "http://localhost:11434/v1");
+
+        let json = serde_json::to_string(&Config::default()).expect("serialize");
+        assert!(!json.contains("providers"));
+    }
+
+    #[test]
+    fn test_config_routing_rules() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "routing_rules": [
+                    {"name": "hard", "task": "execution", "min_complexity": 4, "models": ["claude-sonnet-4-5"]},
+                    {"optimize": "cost", "models": ["gpt-4o-mini", "claude-haiku-4-5"]}
+                ]
+            }"#,
+        )
+        .expect("parse");
+
+        let [hard, cheap] = &config.routing_rules[..] else {
+            panic!("expected two rules");
+        };
+        assert_eq!(hard.task, Some(TaskType::Execution));
+        assert!(hard.matches(TaskType::Execution, OptimizeMode::Balanced, Some(5)));
+        assert!(!hard.matches(TaskType::Execution, OptimizeMode::Balanced, Some(3)));
+        assert!(!hard.matches(TaskType::Execution, OptimizeMode::Balanced, None));
+        assert!(!hard.matches(TaskType::Planning, OptimizeMode::Balanced, Some(5)));
+        assert!(cheap.matches(TaskType::Verification, OptimizeMode::Cost, None));
+        assert!(!cheap.matches(TaskType::Verification, OptimizeMode::Time, None));
+
+        // Rules whose models aren't available are skipped
+        assert_eq!(
+            config.route(TaskType::Execution, OptimizeMode::Cost, Some(5), |m| m == "claude-haiku-4-5"),
+            Some(("claude-haiku-4-5", "#2".to_string()))
+        );
+        assert_eq!(
+            config.route(TaskType::Execution, OptimizeMode::Balanced, Some(5), |_| true),
+            Some(("claude-sonnet-4-5", "hard".to_string()))
+        );
+        assert_eq!(config.route(TaskType::Execution, OptimizeMode::Balanced, Some(2), |_| true), None);
+    }
+
+    #[test]
+    fn test_config_optimize_mode() {
+        let mut config = Config::default();
+        assert_eq!(config.optimize_mode(), OptimizeMode::Balanced);
+        config.default_optimize = "Cost".to_string();
+        assert_eq!(config.optimize_mode(), OptimizeMode::Cost);
+        config.default_optimize = "fastest".to_string();
+        assert_eq!(config.optimize_mode(), OptimizeMode::Balanced);
+    }
+
+    #[test]
+    fn test_config_container_runtime() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "container_runtime": {"kind": "podman", "socket": "/run/user/1000/podman/podman.sock"}
+            }"#,
+        )
+        .expect("parse");
+        assert_eq!(config.container_runtime.kind, ckrv_sandbox::RuntimeKind::Podman);
+        assert_eq!(
+            config.container_runtime.socket.as_deref(),
+            Some("/run/user/1000/podman/podman.sock")
+        );
+
+        let config: Config = serde_json::from_str(r#"{"version": "1.0"}"#).expect("parse");
+        assert_eq!(config.container_runtime, RuntimeConfig::default());
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = Config::default();
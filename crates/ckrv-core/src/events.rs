@@ -1,7 +1,9 @@
 //! Job events for progress tracking.
 
+use ckrv_sandbox::OutputStream;
 use serde::{Deserialize, Serialize};
 
+use crate::failure::FailureKind;
 use crate::{AttemptResult, RunState};
 
 /// Events emitted during job execution.
@@ -20,6 +22,25 @@ pub enum JobEvent {
     /// A step failed.
     StepFailed { step_id: String, error: String },
 
+    /// A step exceeded its timeout and its agent was killed.
+    StepTimedOut { step_id: String, timeout_secs: u64 },
+
+    /// A line of output from the agent running a step.
+    AgentOutput {
+        step_id: String,
+        stream: OutputStream,
+        line: String,
+    },
+
+    /// A step's agent failed and the step is moving on to the next agent in
+    /// its fallback chain.
+    AgentFallback {
+        step_id: String,
+        from: String,
+        to: String,
+        failure: FailureKind,
+    },
+
     /// An attempt started.
     AttemptStarted { number: u32 },
 
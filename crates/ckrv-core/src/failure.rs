@@ -0,0 +1,186 @@
+//! Classification of failed agent runs.
+//!
+//! Few agent CLIs report why they failed in a structured way, so the runner
+//! looks at their stderr and at the error the backend found in their output
+//! (such as Claude Code's `is_error` result event). Regular stdout is never
+//! inspected: it holds the agent's transcript, which can mention anything.
+//! The classification decides whether a failed step moves on to the next
+//! agent in its fallback chain.
+
+use serde::{Deserialize, Serialize};
+
+/// Why an agent run failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The provider refused the request for capacity: rate limits, exhausted
+    /// quota, or an overloaded or unavailable service.
+    RateLimit,
+    /// The agent could not authenticate with its provider.
+    Auth,
+    /// The agent was killed after exceeding the step timeout.
+    Timeout,
+    /// Anything else that made the agent exit unsuccessfully.
+    Crash,
+}
+
+/// Phrases that mark an authentication failure.
+const AUTH_PATTERNS: &[&str] = &[
+    "unauthorized",
+    "unauthenticated",
+    "authentication failed",
+    "authentication_error",
+    "invalid api key",
+    "invalid x-api-key",
+    "incorrect api key",
+    "api key not valid",
+    "invalid_api_key",
+    "not logged in",
+    "please log in",
+    "please run /login",
+    "login required",
+];
+
+/// Phrases that mark a rate limit or provider outage.
+const RATE_LIMIT_PATTERNS: &[&str] = &[
+    "rate limit",
+    "rate_limit",
+    "ratelimit",
+    "too many requests",
+    "quota exceeded",
+    "exceeded your current quota",
+    "resource_exhausted",
+    "insufficient credits",
+    "overloaded",
+    "service unavailable",
+    "temporarily unavailable",
+    "usage limit",
+];
+
+/// Text that introduces an HTTP status code in error messages, e.g.
+/// `HTTP 401`, `status: 429` or Claude Code's `API Error: 529`.
+const STATUS_PREFIXES: &[&str] = &[
+    "http ",
+    "http/1.1 ",
+    "http/2 ",
+    "status ",
+    "status: ",
+    "status code ",
+    "status code: ",
+    "status_code: ",
+    "\"status\":",
+    "\"status\": ",
+    "api error: ",
+    "error code: ",
+];
+
+impl FailureKind {
+    /// Classify a failed run from its stderr and the structured error its
+    /// backend reported, if any.
+    #[must_use]
+    pub fn classify(stderr: &str, error: Option<&str>, timed_out: bool) -> Self {
+        if timed_out {
+            return Self::Timeout;
+        }
+
+        let text = format!("{stderr}\n{}", error.unwrap_or_default()).to_lowercase();
+        let mentions = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+        let has_status = |codes: &[&str]| codes.iter().any(|code| contains_status(&text, code));
+
+        if mentions(AUTH_PATTERNS) || has_status(&["401", "403"]) {
+            Self::Auth
+        } else if mentions(RATE_LIMIT_PATTERNS) || has_status(&["402", "429", "503", "529"]) {
+            Self::RateLimit
+        } else {
+            Self::Crash
+        }
+    }
+
+    /// Whether another agent might succeed where this one failed.
+    ///
+    /// Authentication failures are not retried: they need the user to fix
+    /// credentials, and falling back would hide that.
+    #[must_use]
+    pub const fn is_retryable(self) -> bool {
+        !matches!(self, Self::Auth)
+    }
+
+    /// Short label for logs.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::RateLimit => "rate limit",
+            Self::Auth => "auth",
+            Self::Timeout => "timeout",
+            Self::Crash => "crash",
+        }
+    }
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Whether `text` reports the HTTP status `code` after one of
+/// [`STATUS_PREFIXES`], and not as the start of a longer number.
+fn contains_status(text: &str, code: &str) -> bool {
+    STATUS_PREFIXES.iter().any(|prefix| {
+        let needle = format!("{prefix}{code}");
+        text.match_indices(&needle).any(|(i, _)| {
+            let after = text[i + needle.len()..].chars().next();
+            !after.is_some_and(|c| c.is_ascii_digit())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_from_output() {
+        assert_eq!(
+            FailureKind::classify("API Error: 429 Too Many Requests", None, false),
+            FailureKind::RateLimit
+        );
+        assert_eq!(
+            FailureKind::classify("Error: Overloaded (529)", None, false),
+            FailureKind::RateLimit
+        );
+        assert_eq!(
+            FailureKind::classify("", Some("Invalid API key · Please run /login"), false),
+            FailureKind::Auth
+        );
+        assert_eq!(
+            FailureKind::classify("request failed: HTTP 403", None, false),
+            FailureKind::Auth
+        );
+        assert_eq!(FailureKind::classify("", None, true), FailureKind::Timeout);
+        assert_eq!(
+            FailureKind::classify("panicked at src/main.rs", None, false),
+            FailureKind::Crash
+        );
+    }
+
+    #[test]
+    fn test_bare_status_numbers_are_not_failures() {
+        assert_eq!(
+            FailureKind::classify("error: expected 401 items, found 403 at line 429", None, false),
+            FailureKind::Crash
+        );
+        assert_eq!(
+            FailureKind::classify("", Some("Test failed: status 4010 unexpected"), false),
+            FailureKind::Crash
+        );
+    }
+
+    #[test]
+    fn test_only_auth_is_not_retryable() {
+        assert!(FailureKind::RateLimit.is_retryable());
+        assert!(FailureKind::Timeout.is_retryable());
+        assert!(FailureKind::Crash.is_retryable());
+        assert!(!FailureKind::Auth.is_retryable());
+    }
+}
@@ -0,0 +1,262 @@
+//! `${VAR}` and `${VAR:-default}` interpolation for configuration files.
+//!
+//! Interpolation runs on the parsed document, before it is deserialized, so
+//! only string values are touched and errors can name the field they came
+//! from. `${VAR:-default}` falls back to `default` when `VAR` is unset or
+//! empty; `$${` produces a literal `${` for text meant for a shell. Anything
+//! else that merely looks like `${...}` is left alone.
+
+/// An undefined variable with no default.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{file}: `{field}` uses undefined variable ${{{var}}} (set it, or give a default with ${{{var}:-value}})")]
+pub struct InterpolationError {
+    /// File (or source) being loaded.
+    pub file: String,
+    /// Dotted path of the field, e.g. `agents[0].openrouter.api_key`.
+    pub field: String,
+    /// Variable name.
+    pub var: String,
+}
+
+/// Interpolate `${VAR}` references in a single string using `lookup`.
+///
+/// Returns the name of the first undefined variable on failure.
+///
+/// # Errors
+///
+/// Returns the variable name if a required variable is not defined.
+pub fn interpolate_with(
+    text: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if let Some(after) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some((name, default, consumed)) = parse_reference(tail) else {
+            out.push('$');
+            rest = &tail[1..];
+            continue;
+        };
+
+        match (
+            lookup(name).filter(|v| !v.is_empty() || default.is_none()),
+            default,
+        ) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => return Err(name.to_string()),
+        }
+        rest = &tail[consumed..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parse `${NAME}` or `${NAME:-default}` at the start of `text`, returning
+/// the name, the default and the number of bytes consumed.
+fn parse_reference(text: &str) -> Option<(&str, Option<&str>, usize)> {
+    let body = text.strip_prefix("${")?;
+    let close = body.find('}')?;
+    let inner = &body[..close];
+    let (name, default) = match inner.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (inner, None),
+    };
+
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some((name, default, close + 3))
+}
+
+fn env_lookup(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Interpolate a single string from the process environment.
+///
+/// # Errors
+///
+/// Returns an error naming `file` and `field` if a required variable is
+/// undefined.
+pub fn interpolate_str(text: &str, file: &str, field: &str) -> Result<String, InterpolationError> {
+    interpolate_with(text, &env_lookup).map_err(|var| InterpolationError {
+        file: file.to_string(),
+        field: field.to_string(),
+        var,
+    })
+}
+
+/// Interpolate every string value in a YAML document from the environment.
+///
+/// Mapping keys named in `skip` are left untouched along with everything
+/// below them.
+///
+/// # Errors
+///
+/// Returns an error naming the file and field of the first undefined variable.
+pub fn interpolate_yaml(
+    value: &mut serde_yaml::Value,
+    file: &str,
+    skip: &[&str],
+) -> Result<(), InterpolationError> {
+    walk_yaml(value, file, "", skip)
+}
+
+/// Like [`interpolate_yaml`] for a value nested at `field` in its file, so
+/// errors report the full path.
+///
+/// # Errors
+///
+/// Returns an error naming the file and field of the first undefined variable.
+pub fn interpolate_yaml_field(
+    value: &mut serde_yaml::Value,
+    file: &str,
+    field: &str,
+    skip: &[&str],
+) -> Result<(), InterpolationError> {
+    walk_yaml(value, file, field, skip)
+}
+
+fn walk_yaml(
+    value: &mut serde_yaml::Value,
+    file: &str,
+    field: &str,
+    skip: &[&str],
+) -> Result<(), InterpolationError> {
+    match value {
+        serde_yaml::Value::String(s) => *s = interpolate_str(s, file, field)?,
+        serde_yaml::Value::Sequence(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                walk_yaml(item, file, &format!("{field}[{i}]"), skip)?;
+            }
+        }
+        serde_yaml::Value::Mapping(map) => {
+            for (key, item) in map.iter_mut() {
+                let key = key
+                    .as_str()
+                    .map_or_else(|| format!("{key:?}"), str::to_string);
+                if skip.contains(&key.as_str()) {
+                    continue;
+                }
+                walk_yaml(item, file, &join_field(field, &key), skip)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => walk_yaml(&mut tagged.value, file, field, skip)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Interpolate every string value in a JSON document from the environment.
+///
+/// # Errors
+///
+/// Returns an error naming the file and field of the first undefined variable.
+pub fn interpolate_json(
+    value: &mut serde_json::Value,
+    file: &str,
+) -> Result<(), InterpolationError> {
+    walk_json(value, file, "")
+}
+
+fn walk_json(
+    value: &mut serde_json::Value,
+    file: &str,
+    field: &str,
+) -> Result<(), InterpolationError> {
+    match value {
+        serde_json::Value::String(s) => *s = interpolate_str(s, file, field)?,
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                walk_json(item, file, &format!("{field}[{i}]"))?;
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                walk_json(item, file, &join_field(field, key))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn join_field(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME_DIR" => Some("/home/dev".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_variables_and_defaults() {
+        assert_eq!(
+            interpolate_with("${HOME_DIR}/bin", &lookup).expect("interpolate"),
+            "/home/dev/bin"
+        );
+        assert_eq!(
+            interpolate_with("${MODEL:-sonnet}", &lookup).expect("interpolate"),
+            "sonnet"
+        );
+        assert_eq!(interpolate_with("${EMPTY:-x}", &lookup).expect("interpolate"), "x");
+        assert_eq!(interpolate_with("${EMPTY}", &lookup).expect("interpolate"), "");
+        assert_eq!(
+            interpolate_with("${MISSING}", &lookup).expect_err("missing variable"),
+            "MISSING"
+        );
+    }
+
+    #[test]
+    fn test_escapes_and_non_references_left_alone() {
+        assert_eq!(
+            interpolate_with("echo $${HOME} $PATH ${{ x }} $", &lookup).expect("interpolate"),
+            "echo ${HOME} $PATH ${{ x }} $"
+        );
+    }
+
+    #[test]
+    fn test_yaml_error_names_file_and_field() {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(
+            "agents:\n  - id: a\n    openrouter:\n      api_key: ${CKRV_TEST_UNSET_KEY_4471}\n",
+        )
+        .expect("parse yaml");
+        let err = interpolate_yaml(&mut value, "agents.yaml", &[]).expect_err("unset variable");
+        assert_eq!(err.file, "agents.yaml");
+        assert_eq!(err.field, "agents[0].openrouter.api_key");
+        assert_eq!(err.var, "CKRV_TEST_UNSET_KEY_4471");
+    }
+
+    #[test]
+    fn test_yaml_skips_named_fields() {
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str("steps:\n  - prompt: 'echo ${CKRV_TEST_UNSET_4472}'\n")
+                .expect("parse yaml");
+        assert!(interpolate_yaml(&mut value, "wf.yml", &["prompt"]).is_ok());
+    }
+}
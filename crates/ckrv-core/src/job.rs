@@ -46,6 +46,18 @@ pub enum OptimizeMode {
     Balanced,
 }
 
+/// Type of task for model routing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskType {
+    /// Planning phase - needs strong reasoning.
+    Planning,
+    /// Execution phase - needs code generation.
+    Execution,
+    /// Verification phase - needs analysis.
+    Verification,
+}
+
 /// A job represents a single execution of a specification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
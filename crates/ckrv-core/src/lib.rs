@@ -3,37 +3,60 @@
 //! This crate contains the fundamental types and traits that define
 //! the Chakravarti domain model: Spec, Plan, Job, Attempt, and RunState.
 
+pub mod agent_config;
+pub mod agent_history;
+pub mod agent_profile;
 pub mod agent_task;
+pub mod backend;
+pub mod checkpoint;
 pub mod config;
 pub mod error;
 pub mod events;
+pub mod failure;
+pub mod interpolate;
 pub mod job;
+pub mod mock_agent;
 pub mod orchestrator;
 pub mod plan;
 pub mod planner;
 pub mod prompt;
 pub mod runner;
+pub mod secrets;
 pub mod spec;
 pub mod state;
 pub mod step;
 pub mod step_result;
+pub mod structured_output;
 pub mod workflow;
 
+pub use agent_config::{AgentConfig, AgentsFile};
+pub use agent_history::{AgentHistory, AgentRunRecord, AgentSelection};
+pub use agent_profile::AgentProfile;
 pub use agent_task::{AgentTask, AgentTaskStatus, TaskError};
-pub use config::Config;
+pub use backend::{AgentBackend, AgentInvocation, AgentType, AgentUsage, ToolCall};
+pub use checkpoint::{CheckpointError, WorkspaceCheckpoint};
+pub use config::{
+    Config, GenerationBackend, GenerationConfig, ProviderConfig, ProviderKind, ResponseCacheConfig,
+    RoutingRule,
+};
 pub use error::CoreError;
 pub use events::JobEvent;
-pub use job::{Attempt, AttemptResult, Job, JobConfig, OptimizeMode};
+pub use failure::FailureKind;
+pub use interpolate::InterpolationError;
+pub use job::{Attempt, AttemptResult, Job, JobConfig, OptimizeMode, TaskType};
+pub use mock_agent::{MockAgent, MockMode};
 pub use orchestrator::{
     DefaultOrchestrator, EventHandler, Orchestrator, OrchestratorError, OrchestratorResult,
 };
 pub use plan::Plan;
 pub use planner::{DefaultPlanner, PlanContext, PlanError, Planner};
 pub use prompt::{PromptRenderer, RenderContext, RenderError, StepOutputs};
+pub use secrets::{SecretError, SecretStore};
 pub use spec::{Spec, VerifyConfig};
 pub use state::RunState;
 pub use step::{Step, StepStatus, StepType};
 pub use step_result::{StepExecutionResult, StepExecutionStatus};
+pub use structured_output::OutputError;
 pub use workflow::{
     OutputType, StepOutput, Workflow, WorkflowDefaults, WorkflowError, WorkflowStep,
 };
@@ -0,0 +1,436 @@
+//! Record/replay mock agent for deterministic, offline tests.
+//!
+//! In record mode a real agent runs as usual and its transcript (stdout and
+//! the files it wrote) is saved under a fixtures directory, keyed by a hash
+//! of the prompt. In replay mode the transcript is played back without
+//! invoking any agent: the files are written into the workspace and the
+//! recorded stdout is returned.
+//!
+//! The mock is selected with `agent_type: mock` in `agents.yaml`, or for
+//! every agent call with `CKRV_MOCK_AGENT=record|replay`. Fixtures live in
+//! `.ckrv/fixtures/agents/` unless `CKRV_MOCK_FIXTURES` points elsewhere.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Environment variable that enables the mock (`record` or `replay`).
+pub const MOCK_MODE_ENV: &str = "CKRV_MOCK_AGENT";
+
+/// Environment variable overriding the fixtures directory.
+pub const MOCK_FIXTURES_ENV: &str = "CKRV_MOCK_FIXTURES";
+
+/// Default fixtures directory, relative to the project root.
+pub const DEFAULT_FIXTURES_DIR: &str = ".ckrv/fixtures/agents";
+
+/// Directories never captured in a transcript.
+const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules", ".ckrv"];
+
+/// Errors from recording or replaying transcripts.
+#[derive(Debug, thiserror::Error)]
+pub enum MockError {
+    /// No transcript was recorded for the prompt.
+    #[error("No recorded transcript for prompt {hash} in {dir} (record one with {MOCK_MODE_ENV}=record)")]
+    MissingFixture {
+        /// Prompt hash.
+        hash: String,
+        /// Fixtures directory searched.
+        dir: PathBuf,
+    },
+
+    /// Transcript file is not valid.
+    #[error("Invalid transcript {path}: {message}")]
+    InvalidFixture {
+        /// Transcript path.
+        path: PathBuf,
+        /// Parse error.
+        message: String,
+    },
+
+    /// Unknown mock mode.
+    #[error("Unknown mock agent mode '{0}' (expected 'record' or 'replay')")]
+    UnknownMode(String),
+
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Whether the mock records real runs or replays recorded ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MockMode {
+    /// Run the real agent and save its transcript.
+    Record,
+    /// Play back saved transcripts without running an agent.
+    #[default]
+    Replay,
+}
+
+impl FromStr for MockMode {
+    type Err = MockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => Err(MockError::UnknownMode(other.to_string())),
+        }
+    }
+}
+
+/// A recorded agent run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentTranscript {
+    /// Hash of the prompt this transcript answers.
+    pub prompt_hash: String,
+    /// The prompt, kept for readability when reviewing fixtures.
+    pub prompt: String,
+    /// Agent stdout.
+    pub stdout: String,
+    /// Agent stderr.
+    #[serde(default)]
+    pub stderr: String,
+    /// Whether the agent succeeded.
+    pub success: bool,
+    /// Files written by the agent (relative path -> content).
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Files deleted by the agent (relative paths).
+    #[serde(default)]
+    pub deleted: Vec<String>,
+}
+
+/// Content hashes of every file in a workspace, taken before a recorded run.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceSnapshot {
+    files: BTreeMap<String, String>,
+}
+
+impl WorkspaceSnapshot {
+    /// Hash every file under `root`, skipping VCS and build directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn capture(root: &Path) -> Result<Self, MockError> {
+        let mut files = BTreeMap::new();
+        walk(root, root, &mut |relative, content| {
+            files.insert(relative, content_hash(content));
+        })?;
+        Ok(Self { files })
+    }
+
+    /// Files added or changed since the snapshot, and files removed.
+    fn changes(&self, root: &Path) -> Result<(BTreeMap<String, String>, Vec<String>), MockError> {
+        let mut written = BTreeMap::new();
+        let mut seen = HashSet::new();
+        walk(root, root, &mut |relative, content| {
+            if self.files.get(&relative) != Some(&content_hash(content)) {
+                match std::str::from_utf8(content) {
+                    Ok(text) => {
+                        written.insert(relative.clone(), text.to_string());
+                    }
+                    Err(_) => tracing::warn!(path = %relative, "Not recording binary file"),
+                }
+            }
+            seen.insert(relative);
+        })?;
+
+        let deleted = self
+            .files
+            .keys()
+            .filter(|path| !seen.contains(*path))
+            .cloned()
+            .collect();
+        Ok((written, deleted))
+    }
+}
+
+/// Whether `path` is a plain relative path (no `..`, `.` or root) that
+/// stays inside the directory it is joined to.
+fn is_workspace_relative(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Record/replay agent backed by a fixtures directory.
+#[derive(Debug, Clone)]
+pub struct MockAgent {
+    fixtures_dir: PathBuf,
+    mode: MockMode,
+}
+
+impl MockAgent {
+    /// Create a mock agent over `fixtures_dir`.
+    #[must_use]
+    pub fn new(fixtures_dir: impl Into<PathBuf>, mode: MockMode) -> Self {
+        Self {
+            fixtures_dir: fixtures_dir.into(),
+            mode,
+        }
+    }
+
+    /// The mock configured by `CKRV_MOCK_AGENT`, if set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mode is not `record` or `replay`.
+    pub fn from_env(project_root: &Path) -> Result<Option<Self>, MockError> {
+        match std::env::var(MOCK_MODE_ENV) {
+            Ok(mode) if !mode.trim().is_empty() => {
+                Ok(Some(Self::new(fixtures_dir(project_root), mode.parse()?)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The mock for an agent configured with `agent_type: mock`.
+    ///
+    /// Replays unless `CKRV_MOCK_AGENT` asks for recording.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mode is not `record` or `replay`.
+    pub fn for_profile(project_root: &Path) -> Result<Self, MockError> {
+        Ok(Self::from_env(project_root)?
+            .unwrap_or_else(|| Self::new(fixtures_dir(project_root), MockMode::Replay)))
+    }
+
+    /// Whether this mock records or replays.
+    #[must_use]
+    pub const fn mode(&self) -> MockMode {
+        self.mode
+    }
+
+    /// Path of the transcript for `prompt`.
+    #[must_use]
+    pub fn fixture_path(&self, prompt: &str) -> PathBuf {
+        self.fixtures_dir
+            .join(format!("{}.json", prompt_hash(prompt)))
+    }
+
+    /// Play back the transcript for `prompt`, applying its file changes to `workdir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no transcript was recorded or it cannot be applied.
+    pub fn replay(&self, prompt: &str, workdir: &Path) -> Result<AgentTranscript, MockError> {
+        let path = self.fixture_path(prompt);
+        if !path.exists() {
+            return Err(MockError::MissingFixture {
+                hash: prompt_hash(prompt),
+                dir: self.fixtures_dir.clone(),
+            });
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let transcript: AgentTranscript =
+            serde_json::from_str(&content).map_err(|e| MockError::InvalidFixture {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+
+        // Check every path before touching the workspace
+        let paths = transcript.files.keys().chain(&transcript.deleted);
+        if let Some(bad) = paths.into_iter().find(|p| !is_workspace_relative(p)) {
+            return Err(MockError::InvalidFixture {
+                path,
+                message: format!("path '{bad}' is not a relative path inside the workspace"),
+            });
+        }
+
+        for (relative, content) in &transcript.files {
+            let target = workdir.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(target, content)?;
+        }
+        for relative in &transcript.deleted {
+            let target = workdir.join(relative);
+            if target.exists() {
+                std::fs::remove_file(target)?;
+            }
+        }
+
+        tracing::debug!(fixture = %path.display(), files = transcript.files.len(), "Replayed agent transcript");
+        Ok(transcript)
+    }
+
+    /// Save the transcript of a real run of `prompt` in `workdir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the workspace cannot be read or the transcript written.
+    pub fn record(
+        &self,
+        prompt: &str,
+        workdir: &Path,
+        before: &WorkspaceSnapshot,
+        stdout: &str,
+        stderr: &str,
+        success: bool,
+    ) -> Result<PathBuf, MockError> {
+        let (files, deleted) = before.changes(workdir)?;
+        let transcript = AgentTranscript {
+            prompt_hash: prompt_hash(prompt),
+            prompt: prompt.to_string(),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            success,
+            files,
+            deleted,
+        };
+
+        std::fs::create_dir_all(&self.fixtures_dir)?;
+        let path = self.fixture_path(prompt);
+        let json =
+            serde_json::to_string_pretty(&transcript).map_err(|e| MockError::InvalidFixture {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+        std::fs::write(&path, json)?;
+
+        tracing::info!(fixture = %path.display(), "Recorded agent transcript");
+        Ok(path)
+    }
+}
+
+/// Hex SHA-256 of a prompt, used as its fixture key.
+#[must_use]
+pub fn prompt_hash(prompt: &str) -> String {
+    hex::encode(Sha256::digest(prompt.as_bytes()))
+}
+
+fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+fn fixtures_dir(project_root: &Path) -> PathBuf {
+    std::env::var_os(MOCK_FIXTURES_ENV)
+        .map_or_else(|| project_root.join(DEFAULT_FIXTURES_DIR), PathBuf::from)
+}
+
+/// Visit every file under `dir` with its path relative to `root`.
+fn walk(root: &Path, dir: &Path, visit: &mut dyn FnMut(String, &[u8])) -> Result<(), MockError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if !IGNORED_DIRS.iter().any(|ignored| name == *ignored) {
+                walk(root, &path, visit)?;
+            }
+        } else if file_type.is_file() {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            visit(relative, &std::fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_then_replay() {
+        let fixtures = TempDir::new().expect("tempdir");
+        let recorded = TempDir::new().expect("tempdir");
+        std::fs::write(recorded.path().join("keep.txt"), "unchanged").expect("write");
+        std::fs::write(recorded.path().join("old.txt"), "bye").expect("write");
+
+        let recorder = MockAgent::new(fixtures.path(), MockMode::Record);
+        let before = WorkspaceSnapshot::capture(recorded.path()).expect("snapshot");
+
+        // Simulate the agent's edits
+        std::fs::create_dir_all(recorded.path().join("src")).expect("mkdir");
+        std::fs::write(recorded.path().join("src/lib.rs"), "pub fn hi() {}").expect("write");
+        std::fs::remove_file(recorded.path().join("old.txt")).expect("remove");
+
+        recorder
+            .record("add hi", recorded.path(), &before, "Added hi\n", "", true)
+            .expect("record");
+
+        let replay_dir = TempDir::new().expect("tempdir");
+        std::fs::write(replay_dir.path().join("old.txt"), "bye").expect("write");
+        let player = MockAgent::new(fixtures.path(), MockMode::Replay);
+        let transcript = player.replay("add hi", replay_dir.path()).expect("replay");
+
+        assert_eq!(transcript.stdout, "Added hi\n");
+        assert!(transcript.success);
+        assert_eq!(transcript.files.keys().collect::<Vec<_>>(), ["src/lib.rs"]);
+        assert_eq!(
+            std::fs::read_to_string(replay_dir.path().join("src/lib.rs")).expect("read"),
+            "pub fn hi() {}"
+        );
+        assert!(!replay_dir.path().join("old.txt").exists());
+    }
+
+    #[test]
+    fn test_replay_missing_fixture() {
+        let fixtures = TempDir::new().expect("tempdir");
+        let player = MockAgent::new(fixtures.path(), MockMode::Replay);
+
+        let err = player
+            .replay("never recorded", fixtures.path())
+            .expect_err("missing");
+        assert!(matches!(err, MockError::MissingFixture { .. }));
+        assert!(err.to_string().contains(&prompt_hash("never recorded")));
+    }
+
+    #[test]
+    fn test_replay_rejects_paths_outside_workspace() {
+        let fixtures = TempDir::new().expect("tempdir");
+        let workdir = TempDir::new().expect("tempdir");
+        let player = MockAgent::new(fixtures.path(), MockMode::Replay);
+
+        for bad in ["../escape.txt", "/tmp/escape.txt", "src/../../escape.txt", ""] {
+            let transcript = AgentTranscript {
+                prompt_hash: prompt_hash("escape"),
+                prompt: "escape".to_string(),
+                success: true,
+                files: BTreeMap::from([
+                    ("ok.txt".to_string(), "fine".to_string()),
+                    (bad.to_string(), "pwned".to_string()),
+                ]),
+                ..AgentTranscript::default()
+            };
+            std::fs::write(
+                player.fixture_path("escape"),
+                serde_json::to_string(&transcript).expect("serialize"),
+            )
+            .expect("write fixture");
+
+            let err = player.replay("escape", workdir.path()).expect_err(bad);
+            assert!(matches!(err, MockError::InvalidFixture { .. }), "{bad}: {err}");
+            assert!(!workdir.path().join("ok.txt").exists());
+        }
+        assert!(!workdir.path().parent().expect("parent").join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_mode_parsing() {
+        assert_eq!(
+            "Record".parse::<MockMode>().expect("mode"),
+            MockMode::Record
+        );
+        assert_eq!(
+            "replay".parse::<MockMode>().expect("mode"),
+            MockMode::Replay
+        );
+        assert!("live".parse::<MockMode>().is_err());
+    }
+}
@@ -3,11 +3,30 @@
 //! This module provides template rendering for workflow step prompts,
 //! supporting variable substitution like `{{inputs.description}}` and
 //! `{{steps.plan.outputs.plan_file}}`.
+//!
+//! Renderers bound to a project also provide helpers that pull in live
+//! context:
+//!
+//! - `{{file "src/lib.rs"}}` - contents of a file inside the project
+//! - `{{git_diff "main"}}` - `git diff` against a base (default `HEAD`)
+//! - `{{spec_section "requirements"}}` - a (dotted) section of `spec.yaml`
+//! - `{{tasks_for_batch "foundation"}}` - the tasks of a batch in `plan.yaml`
+//!
+//! Reusable partials are loaded from `.chakravarti/prompts/partials/` and
+//! included with `{{> name}}`.
 
-use handlebars::Handlebars;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderErrorReason, ScopedJson,
+};
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
+
+/// Directory (relative to the project root) holding prompt partials.
+pub const PARTIALS_DIR: &str = ".chakravarti/prompts/partials";
 
 /// Prompt renderer using Handlebars templates.
 pub struct PromptRenderer<'a> {
@@ -37,6 +56,15 @@ pub enum RenderError {
     #[error("Template rendering failed: {0}")]
     TemplateError(String),
 
+    /// A partial template could not be loaded.
+    #[error("Failed to load partial '{name}': {message}")]
+    PartialError {
+        /// Partial name or path.
+        name: String,
+        /// The error message.
+        message: String,
+    },
+
     /// Missing required variable.
     #[error("Missing required variable: {0}")]
     MissingVariable(String),
@@ -49,9 +77,121 @@ impl<'a> PromptRenderer<'a> {
         let mut handlebars = Handlebars::new();
         // Strict mode: fail on missing variables
         handlebars.set_strict_mode(true);
+        // Prompts are plain text, so never HTML-escape substituted values
+        handlebars.register_escape_fn(handlebars::no_escape);
         Self { handlebars }
     }
 
+    /// Create a renderer with project helpers and partials registered.
+    ///
+    /// `workspace` is the directory `file` and `git_diff` operate on (usually
+    /// the task worktree), `project_root` is where partials are loaded from
+    /// and `spec_dir` is the directory holding `spec.yaml`, `tasks.yaml` and
+    /// `plan.yaml`, if known.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a partial fails to load or parse.
+    pub fn for_project(
+        workspace: &Path,
+        project_root: &Path,
+        spec_dir: Option<&Path>,
+    ) -> Result<Self, RenderError> {
+        let mut renderer = Self::new();
+        renderer.register_project_helpers(workspace, spec_dir);
+        renderer.load_partials(&project_root.join(PARTIALS_DIR))?;
+        Ok(renderer)
+    }
+
+    /// Register the `file`, `git_diff`, `spec_section` and `tasks_for_batch`
+    /// helpers.
+    pub fn register_project_helpers(&mut self, workspace: &Path, spec_dir: Option<&Path>) {
+        let spec_dir = spec_dir.map(Path::to_path_buf);
+        self.handlebars.register_helper(
+            "file",
+            Box::new(FileHelper {
+                root: workspace.to_path_buf(),
+            }),
+        );
+        self.handlebars.register_helper(
+            "git_diff",
+            Box::new(GitDiffHelper {
+                root: workspace.to_path_buf(),
+            }),
+        );
+        self.handlebars.register_helper(
+            "spec_section",
+            Box::new(SpecSectionHelper {
+                spec_dir: spec_dir.clone(),
+            }),
+        );
+        self.handlebars
+            .register_helper("tasks_for_batch", Box::new(TasksForBatchHelper { spec_dir }));
+    }
+
+    /// Register stand-ins for the project helpers that render a placeholder
+    /// such as `<file src/main.rs>` instead of touching the filesystem.
+    ///
+    /// Used to lint templates without a workspace or spec.
+    pub fn register_placeholder_helpers(&mut self) {
+        for name in ["file", "git_diff", "spec_section", "tasks_for_batch"] {
+            self.handlebars
+                .register_helper(name, Box::new(PlaceholderHelper { name }));
+        }
+    }
+
+    /// Register every `*.hbs`, `*.md` and `*.txt` file in `dir` as a partial
+    /// named after its file stem. A missing directory is not an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a partial cannot be read or fails to parse.
+    pub fn load_partials(&mut self, dir: &Path) -> Result<usize, RenderError> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_partial = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| matches!(e, "hbs" | "md" | "txt"));
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !is_partial {
+                continue;
+            }
+
+            let content =
+                std::fs::read_to_string(&path).map_err(|e| RenderError::PartialError {
+                    name: path.display().to_string(),
+                    message: e.to_string(),
+                })?;
+            self.register_partial(name, &content)?;
+            count += 1;
+        }
+
+        tracing::debug!(dir = %dir.display(), count, "Loaded prompt partials");
+        Ok(count)
+    }
+
+    /// Register a partial template, usable as `{{> name}}`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the partial fails to parse.
+    pub fn register_partial(&mut self, name: &str, template: &str) -> Result<(), RenderError> {
+        self.handlebars
+            .register_partial(name, template)
+            .map_err(|e| RenderError::PartialError {
+                name: name.to_string(),
+                message: e.to_string(),
+            })
+    }
+
     /// Render a prompt template with the given context.
     ///
     /// # Errors
@@ -75,6 +215,257 @@ impl<'a> PromptRenderer<'a> {
     }
 }
 
+/// Get a string parameter of a helper, or a render error naming it.
+fn string_param(
+    h: &Helper<'_>,
+    index: usize,
+    helper: &'static str,
+) -> Result<Option<String>, handlebars::RenderError> {
+    h.param(index).map_or(Ok(None), |param| match param.value() {
+        Value::String(s) => Ok(Some(s.clone())),
+        Value::Number(n) => Ok(Some(n.to_string())),
+        _ => Err(RenderErrorReason::InvalidParamType(helper).into()),
+    })
+}
+
+fn helper_error(message: impl Into<String>) -> handlebars::RenderError {
+    RenderErrorReason::Other(message.into()).into()
+}
+
+const fn derived(value: String) -> ScopedJson<'static> {
+    ScopedJson::Derived(Value::String(value))
+}
+
+fn require_spec_dir<'p>(
+    spec_dir: Option<&'p PathBuf>,
+    helper: &str,
+) -> Result<&'p PathBuf, handlebars::RenderError> {
+    spec_dir.ok_or_else(|| helper_error(format!("{helper}: no spec directory for this task")))
+}
+
+fn read_yaml(path: &Path) -> Result<serde_yaml::Value, handlebars::RenderError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| helper_error(format!("failed to read {}: {e}", path.display())))?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| helper_error(format!("failed to parse {}: {e}", path.display())))
+}
+
+/// Renders `<helper args...>` in place of a project helper.
+struct PlaceholderHelper {
+    name: &'static str,
+}
+
+impl HelperDef for PlaceholderHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let mut placeholder = format!("<{}", self.name);
+        for index in 0..h.params().len() {
+            if let Some(arg) = string_param(h, index, self.name)? {
+                placeholder.push(' ');
+                placeholder.push_str(&arg);
+            }
+        }
+        placeholder.push('>');
+        Ok(derived(placeholder))
+    }
+}
+
+/// `{{file "path"}}`: contents of a file inside the workspace.
+struct FileHelper {
+    root: PathBuf,
+}
+
+impl HelperDef for FileHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let relative = string_param(h, 0, "file")?
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("file", 0))?;
+
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        let path = root
+            .join(&relative)
+            .canonicalize()
+            .map_err(|e| helper_error(format!("file: cannot read '{relative}': {e}")))?;
+        if !path.starts_with(&root) {
+            return Err(helper_error(format!(
+                "file: '{relative}' is outside the workspace"
+            )));
+        }
+
+        std::fs::read_to_string(&path)
+            .map(derived)
+            .map_err(|e| helper_error(format!("file: cannot read '{relative}': {e}")))
+    }
+}
+
+/// `{{git_diff base}}`: `git diff` of the workspace against `base`.
+struct GitDiffHelper {
+    root: PathBuf,
+}
+
+impl HelperDef for GitDiffHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let base = string_param(h, 0, "git_diff")?.unwrap_or_else(|| "HEAD".to_string());
+        if base.is_empty() || base.starts_with('-') {
+            return Err(helper_error(format!("git_diff: invalid base '{base}'")));
+        }
+
+        // Diff against the resolved commit so `base` is never parsed as an option
+        let commit = run_git(
+            &self.root,
+            &["rev-parse", "--verify", "--quiet", &format!("{base}^{{commit}}")],
+        )
+        .map_err(|_| helper_error(format!("git_diff: unknown revision '{base}'")))?;
+        let diff = run_git(&self.root, &["diff", commit.trim(), "--"])?;
+
+        Ok(derived(diff))
+    }
+}
+
+/// Run `git` in `root` and return its stdout.
+///
+/// Helpers are synchronous, but renderers are called from the workflow
+/// runner's async tasks, so on a multi-threaded runtime the call is moved
+/// off the worker thread.
+fn run_git(root: &Path, args: &[&str]) -> Result<String, handlebars::RenderError> {
+    let run = || {
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+    };
+    let output = match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(run)
+        }
+        _ => run(),
+    }
+    .map_err(|e| helper_error(format!("git_diff: failed to run git: {e}")))?;
+
+    if !output.status.success() {
+        return Err(helper_error(format!(
+            "git_diff: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `{{spec_section "requirements.functional"}}`: a section of `spec.yaml`.
+struct SpecSectionHelper {
+    spec_dir: Option<PathBuf>,
+}
+
+impl HelperDef for SpecSectionHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let section = string_param(h, 0, "spec_section")?
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("spec_section", 0))?;
+        let spec_dir = require_spec_dir(self.spec_dir.as_ref(), "spec_section")?;
+        let spec = read_yaml(&spec_dir.join("spec.yaml"))?;
+
+        let mut value = &spec;
+        for key in section.split('.') {
+            value = value.get(key).ok_or_else(|| {
+                helper_error(format!("spec_section: spec.yaml has no section '{section}'"))
+            })?;
+        }
+
+        let text = match value {
+            serde_yaml::Value::String(s) => s.clone(),
+            other => serde_yaml::to_string(other)
+                .map_err(|e| helper_error(format!("spec_section: {e}")))?,
+        };
+        Ok(derived(text))
+    }
+}
+
+/// `{{tasks_for_batch "batch-id"}}`: tasks assigned to a batch in `plan.yaml`.
+struct TasksForBatchHelper {
+    spec_dir: Option<PathBuf>,
+}
+
+impl HelperDef for TasksForBatchHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let batch_id = string_param(h, 0, "tasks_for_batch")?
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("tasks_for_batch", 0))?;
+        let spec_dir = require_spec_dir(self.spec_dir.as_ref(), "tasks_for_batch")?;
+
+        let plan = read_yaml(&spec_dir.join("plan.yaml"))?;
+        let task_ids: Vec<&str> = plan
+            .get("batches")
+            .and_then(serde_yaml::Value::as_sequence)
+            .and_then(|batches| {
+                batches
+                    .iter()
+                    .find(|b| b.get("id").and_then(serde_yaml::Value::as_str) == Some(&batch_id))
+            })
+            .and_then(|b| b.get("task_ids"))
+            .and_then(serde_yaml::Value::as_sequence)
+            .ok_or_else(|| {
+                helper_error(format!("tasks_for_batch: no batch '{batch_id}' in plan.yaml"))
+            })?
+            .iter()
+            .filter_map(serde_yaml::Value::as_str)
+            .collect();
+
+        let tasks = read_yaml(&spec_dir.join("tasks.yaml"))?;
+        let field = |task: &serde_yaml::Value, key: &str| {
+            task.get(key)
+                .and_then(serde_yaml::Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let mut text = String::new();
+        for task in tasks
+            .get("tasks")
+            .and_then(serde_yaml::Value::as_sequence)
+            .into_iter()
+            .flatten()
+        {
+            let id = field(task, "id");
+            if !task_ids.contains(&id.as_str()) {
+                continue;
+            }
+            let _ = write!(text, "- [{id}] {}: {}", field(task, "title"), field(task, "description"));
+            let file = field(task, "file");
+            if !file.is_empty() {
+                let _ = write!(text, " ({file})");
+            }
+            text.push('\n');
+        }
+        Ok(derived(text))
+    }
+}
+
 impl Default for PromptRenderer<'_> {
     fn default() -> Self {
         Self::new()
@@ -154,6 +545,7 @@ impl Default for StepOutputs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_render_simple_template() {
@@ -209,6 +601,131 @@ Please proceed.
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_values_are_not_html_escaped() {
+        let renderer = PromptRenderer::new();
+        let context = RenderContext::new().with_input("code", "if a < b && c > \"d\" {}");
+
+        let result = renderer.render("{{inputs.code}}", &context).expect("render");
+        assert_eq!(result, "if a < b && c > \"d\" {}");
+    }
+
+    #[test]
+    fn test_file_helper() {
+        let dir = TempDir::new().expect("temp dir");
+        std::fs::write(dir.path().join("notes.md"), "remember <this>").expect("write");
+
+        let renderer = PromptRenderer::for_project(dir.path(), dir.path(), None).expect("renderer");
+        let result = renderer
+            .render("Notes: {{file \"notes.md\"}}", &RenderContext::new())
+            .expect("render");
+        assert_eq!(result, "Notes: remember <this>");
+
+        let outside = renderer.render("{{file \"../../etc/passwd\"}}", &RenderContext::new());
+        assert!(outside.is_err());
+    }
+
+    #[test]
+    fn test_git_diff_helper() {
+        let dir = TempDir::new().expect("temp dir");
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .expect("run git")
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.path().join("a.txt"), "one\n").expect("write");
+        git(&["add", "."]);
+        git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]);
+        std::fs::write(dir.path().join("a.txt"), "two\n").expect("write");
+
+        let renderer = PromptRenderer::for_project(dir.path(), dir.path(), None).expect("renderer");
+        let diff = renderer
+            .render("{{git_diff}}", &RenderContext::new())
+            .expect("render");
+        assert!(diff.contains("+two"));
+
+        let out = dir.path().join("out.txt");
+        let injected = format!("{{{{git_diff \"--output={}\"}}}}", out.display());
+        assert!(renderer.render(&injected, &RenderContext::new()).is_err());
+        assert!(!out.exists());
+        assert!(renderer
+            .render("{{git_diff \"no-such-branch\"}}", &RenderContext::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_spec_section_and_tasks_for_batch() {
+        let dir = TempDir::new().expect("temp dir");
+        std::fs::write(
+            dir.path().join("spec.yaml"),
+            "id: demo\nrequirements:\n  functional:\n    - Users can log in\n",
+        )
+        .expect("write spec");
+        std::fs::write(
+            dir.path().join("plan.yaml"),
+            "batches:\n  - id: auth\n    task_ids: [\"T002\"]\n",
+        )
+        .expect("write plan");
+        std::fs::write(
+            dir.path().join("tasks.yaml"),
+            "tasks:\n  - id: T001\n    title: Setup\n    description: Scaffold\n  - id: T002\n    title: Login\n    description: Add login form\n    file: src/login.rs\n",
+        )
+        .expect("write tasks");
+
+        let renderer =
+            PromptRenderer::for_project(dir.path(), dir.path(), Some(dir.path())).expect("renderer");
+
+        let section = renderer
+            .render("{{spec_section \"requirements.functional\"}}", &RenderContext::new())
+            .expect("render section");
+        assert!(section.contains("Users can log in"));
+
+        let tasks = renderer
+            .render("{{tasks_for_batch \"auth\"}}", &RenderContext::new())
+            .expect("render tasks");
+        assert_eq!(tasks, "- [T002] Login: Add login form (src/login.rs)\n");
+    }
+
+    #[test]
+    fn test_spec_helpers_require_spec_dir() {
+        let dir = TempDir::new().expect("temp dir");
+        let renderer = PromptRenderer::for_project(dir.path(), dir.path(), None).expect("renderer");
+
+        let result = renderer.render("{{spec_section \"overview\"}}", &RenderContext::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_placeholder_helpers() {
+        let mut renderer = PromptRenderer::new();
+        renderer.register_placeholder_helpers();
+
+        let result = renderer
+            .render("{{file \"src/main.rs\"}} {{tasks_for_batch 2}}", &RenderContext::new())
+            .expect("render");
+        assert_eq!(result, "<file src/main.rs> <tasks_for_batch 2>");
+    }
+
+    #[test]
+    fn test_partials_loaded_from_project() {
+        let dir = TempDir::new().expect("temp dir");
+        let partials = dir.path().join(PARTIALS_DIR);
+        std::fs::create_dir_all(&partials).expect("mkdir");
+        std::fs::write(partials.join("rules.md"), "Follow {{inputs.style}} style.").expect("write");
+        std::fs::write(partials.join("ignored.json"), "{}").expect("write");
+
+        let renderer = PromptRenderer::for_project(dir.path(), dir.path(), None).expect("renderer");
+        let context = RenderContext::new().with_input("style", "rustfmt");
+
+        let result = renderer.render("{{> rules}}", &context).expect("render");
+        assert_eq!(result, "Follow rustfmt style.");
+    }
+
     #[test]
     fn test_record_output() {
         let mut context = RenderContext::new();
@@ -1,16 +1,34 @@
 //! Workflow runner for executing multi-step agent workflows.
 //!
 //! The Runner iterates through workflow steps, renders prompts,
-//! invokes the agent, and collects outputs.
+//! invokes the agent, and collects outputs. Agent output is streamed
+//! line by line as [`JobEvent::AgentOutput`] events while a step runs.
 
-use std::path::Path;
-use std::time::Instant;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use ckrv_sandbox::{OutputSink, OutputStream};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+use crate::agent_profile::{self, AgentProfile};
 use crate::agent_task::{AgentTask, AgentTaskStatus};
+use crate::backend::{AgentBackend, AgentInvocation, AgentType, AgentUsage, ToolCall};
+use crate::checkpoint::WorkspaceCheckpoint;
+use crate::events::JobEvent;
+use crate::failure::FailureKind;
+use crate::mock_agent::{MockAgent, MockMode, WorkspaceSnapshot};
+use crate::orchestrator::EventHandler;
 use crate::prompt::{PromptRenderer, RenderContext};
 use crate::step_result::StepExecutionResult;
+use crate::secrets::{self, SecretResolver};
+use crate::structured_output;
 use crate::workflow::{OutputType, Workflow, WorkflowStep};
 
+/// Name of the step output under which a step's agent session ID is saved.
+pub const SESSION_OUTPUT: &str = "session_id";
+
 /// Configuration for the workflow runner.
 #[derive(Debug, Clone)]
 pub struct RunnerConfig {
@@ -32,6 +50,19 @@ pub struct RunnerConfig {
     pub openrouter_model: Option<String>,
     /// OpenRouter base URL (defaults to https://openrouter.ai/api).
     pub openrouter_base_url: Option<String>,
+    /// Spec directory used by the `spec_section` and `tasks_for_batch`
+    /// prompt helpers.
+    pub spec_dir: Option<PathBuf>,
+    /// ID of the agent the settings above came from (defaults to the binary name).
+    pub agent_id: Option<String>,
+    /// Agents available to workflow `defaults.tool`/`defaults.model` and
+    /// per-step `agent` overrides.
+    pub agents: Vec<AgentProfile>,
+    /// Project root; mock agent fixtures are resolved against it
+    /// (defaults to the step's workspace).
+    pub project_root: Option<PathBuf>,
+    /// Container runtime for the sandbox.
+    pub container_runtime: ckrv_sandbox::RuntimeConfig,
 }
 
 impl Default for RunnerConfig {
@@ -46,7 +77,46 @@ impl Default for RunnerConfig {
             openrouter_api_key: None,
             openrouter_model: None,
             openrouter_base_url: None,
+            spec_dir: None,
+            agent_id: None,
+            agents: Vec::new(),
+            project_root: None,
+            container_runtime: ckrv_sandbox::RuntimeConfig::default(),
+        }
+    }
+}
+
+impl RunnerConfig {
+    /// The agent used when a workflow does not override it.
+    #[must_use]
+    pub fn base_agent(&self) -> AgentProfile {
+        let id = self.agent_id.as_ref().unwrap_or(&self.agent_binary);
+        if let Some(profile) = self.agents.iter().find(|p| &p.id == id) {
+            return profile.clone();
         }
+        let mut profile = AgentProfile::new(id, &self.agent_binary).with_openrouter(
+            self.openrouter_api_key.clone(),
+            self.openrouter_base_url.clone(),
+        );
+        profile.model.clone_from(&self.openrouter_model);
+        profile
+    }
+
+    /// The agent for each of the workflow's steps, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a step names an unknown agent.
+    pub fn step_agents(
+        &self,
+        workflow: &Workflow,
+    ) -> Result<Vec<AgentProfile>, agent_profile::UnknownAgentError> {
+        let base = self.base_agent();
+        workflow
+            .steps
+            .iter()
+            .map(|step| agent_profile::resolve_step_agent(workflow, step, &base, &self.agents))
+            .collect()
     }
 }
 
@@ -82,19 +152,94 @@ pub enum RunnerError {
         message: String,
     },
 
+    /// Prompt helpers or partials could not be set up.
+    #[error("Failed to prepare prompt templates: {0}")]
+    TemplateSetup(String),
+
     /// Agent invocation failed.
     #[error("Failed to invoke agent: {0}")]
     AgentError(String),
 
+    /// Structured outputs were still invalid after re-prompting.
+    #[error("Invalid outputs from step '{step_id}': {message}")]
+    OutputValidation {
+        /// The step ID.
+        step_id: String,
+        /// The validation error message.
+        message: String,
+    },
+
     /// Task persistence failed.
     #[error("Failed to save task: {0}")]
     PersistenceError(String),
+
+    /// A step names an agent that can't be resolved.
+    #[error(transparent)]
+    UnknownAgent(#[from] agent_profile::UnknownAgentError),
+}
+
+/// Output of a single agent invocation.
+struct AgentRun {
+    stdout: String,
+    stderr: String,
+    success: bool,
+    timed_out: bool,
+    usage: Option<AgentUsage>,
+    tool_calls: Vec<ToolCall>,
+    session_id: Option<String>,
+    /// Errors the agent reported in its structured output.
+    error: Option<String>,
+}
+
+impl AgentRun {
+    /// Why the run failed, judged from stderr and the reported errors.
+    fn failure(&self) -> FailureKind {
+        FailureKind::classify(&self.stderr, self.error.as_deref(), self.timed_out)
+    }
+}
+
+/// Outcome of sending a single prompt to an agent with
+/// [`WorkflowRunner::probe`].
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    /// Whether the agent exited successfully.
+    pub success: bool,
+    /// Whether the agent was killed for exceeding the step timeout.
+    pub timed_out: bool,
+    /// The agent's response.
+    pub output: String,
+    /// The agent's stderr.
+    pub stderr: String,
+    /// Wall-clock time from launch to exit.
+    pub duration_ms: u64,
+    /// Token usage the agent reported, if any.
+    pub usage: Option<AgentUsage>,
+}
+
+/// Outcome of sending a prompt to an agent and its fallbacks with
+/// [`WorkflowRunner::invoke`].
+#[derive(Debug, Clone)]
+pub struct InvokeResult {
+    /// The agent whose run this is: the first to succeed, or the last tried.
+    pub agent: AgentProfile,
+    /// Whether the agent exited successfully.
+    pub success: bool,
+    /// Whether the agent was killed for exceeding the step timeout.
+    pub timed_out: bool,
+    /// Why the agent failed, if it did.
+    pub failure: Option<FailureKind>,
+    /// The agent's response.
+    pub output: String,
+    /// The agent's stderr.
+    pub stderr: String,
+    /// IDs of the agents that failed before `agent`, in order.
+    pub fell_back_from: Vec<String>,
 }
 
 /// The workflow runner executes workflow steps sequentially.
 pub struct WorkflowRunner {
     config: RunnerConfig,
-    renderer: PromptRenderer<'static>,
+    event_handler: Option<Arc<dyn EventHandler>>,
 }
 
 impl WorkflowRunner {
@@ -103,10 +248,46 @@ impl WorkflowRunner {
     pub fn new(config: RunnerConfig) -> Self {
         Self {
             config,
-            renderer: PromptRenderer::new(),
+            event_handler: None,
         }
     }
 
+    /// Set the handler that receives step progress and agent output events.
+    #[must_use]
+    pub fn with_event_handler(mut self, handler: Arc<dyn EventHandler>) -> Self {
+        self.event_handler = Some(handler);
+        self
+    }
+
+    fn emit(&self, event: JobEvent) {
+        if let Some(ref handler) = self.event_handler {
+            handler.handle(event);
+        }
+    }
+
+    /// Sink that forwards agent output lines for `step_id` as events, with
+    /// stdout rendered for display by `backend`.
+    fn output_sink(
+        &self,
+        step_id: &str,
+        backend: &'static dyn AgentBackend,
+    ) -> Option<OutputSink> {
+        let handler = self.event_handler.clone()?;
+        let step_id = step_id.to_string();
+        Some(OutputSink::new(move |stream, line| {
+            let line = match stream {
+                OutputStream::Stdout => backend.display_line(line),
+                OutputStream::Stderr => Some(line.to_string()),
+            };
+            let Some(line) = line else { return };
+            handler.handle(JobEvent::AgentOutput {
+                step_id: step_id.clone(),
+                stream,
+                line: secrets::redact(&line).into_owned(),
+            });
+        }))
+    }
+
     /// Run a workflow for the given task.
     ///
     /// # Errors
@@ -122,6 +303,9 @@ impl WorkflowRunner {
         let mut step_results = Vec::new();
         let mut all_success = true;
 
+        // Resolve every step's agent first, so a typo fails before any work
+        let agents = self.config.step_agents(workflow)?;
+
         // Ensure workspace directory exists
         let workspace_dir = if task.worktree_path.exists() {
             task.worktree_path.clone()
@@ -138,6 +322,11 @@ impl WorkflowRunner {
 
         tracing::info!(workspace = %workspace_dir.display(), "Running workflow in workspace");
 
+        // Prompt helpers read from the workspace; partials come from the project
+        let renderer =
+            PromptRenderer::for_project(&workspace_dir, base_dir, self.config.spec_dir.as_deref())
+                .map_err(|e| RunnerError::TemplateSetup(e.to_string()))?;
+
         // Build initial render context from task
         let mut context = RenderContext::new()
             .with_input("description", &task.original_prompt)
@@ -148,10 +337,17 @@ impl WorkflowRunner {
         task.save(base_dir)
             .map_err(|e| RunnerError::PersistenceError(e.to_string()))?;
 
+        // The latest agent session as (agent ID, session ID)
+        let mut session: Option<(String, String)> = None;
+
         // Execute each step
-        for step in &workflow.steps {
+        for (step, agent) in workflow.steps.iter().zip(agents) {
+            let resume = session
+                .as_ref()
+                .filter(|(agent_id, _)| step.resume_session && *agent_id == agent.id)
+                .map(|(_, session_id)| session_id.as_str());
             let step_result = self
-                .execute_step(step, &context, task, &workspace_dir)
+                .execute_step(&renderer, &agent, step, &context, resume, &workspace_dir)
                 .await;
 
             match &step_result {
@@ -161,11 +357,35 @@ impl WorkflowRunner {
                         context.record_output(&step.id, name, value.clone());
                         task.record_step_output(&step.id, name, value.clone());
                     }
+                    if result.is_success() {
+                        task.agent.clone_from(&result.agent);
+                    }
+                    if let (Some(agent_id), Some(session_id)) = (&result.agent, &result.session_id)
+                    {
+                        task.record_step_output(&step.id, SESSION_OUTPUT, session_id.clone());
+                        session = Some((agent_id.clone(), session_id.clone()));
+                    }
+                    if let Some(ref usage) = result.usage {
+                        task.usage.get_or_insert_with(AgentUsage::default).add(usage);
+                    }
                     step_results.push(result.clone());
+
+                    // A failed or timed-out agent stops the workflow, keeping its output
+                    if !result.is_success() {
+                        all_success = false;
+                        if !self.config.continue_on_failure {
+                            break;
+                        }
+                    }
                 }
                 Err(e) => {
                     all_success = false;
-                    let failed_result = StepExecutionResult::failed(&step.id, e.to_string());
+                    self.emit(JobEvent::StepFailed {
+                        step_id: step.id.clone(),
+                        error: e.to_string(),
+                    });
+                    let failed_result = StepExecutionResult::failed(&step.id, e.to_string())
+                        .with_agent(&agent.id, agent.model.clone());
                     step_results.push(failed_result);
 
                     if !self.config.continue_on_failure {
@@ -197,37 +417,89 @@ impl WorkflowRunner {
         })
     }
 
-    /// Execute a single workflow step.
+    /// Execute a single workflow step, continuing the agent session `resume`
+    /// if given.
     async fn execute_step(
         &self,
+        renderer: &PromptRenderer<'_>,
+        agent: &AgentProfile,
         step: &WorkflowStep,
         context: &RenderContext,
-        _task: &AgentTask,
+        resume: Option<&str>,
         workspace_dir: &Path,
     ) -> Result<StepExecutionResult, RunnerError> {
         let start = Instant::now();
 
         // Render the prompt
-        let prompt = self.renderer.render(&step.prompt, context).map_err(|e| {
+        let mut prompt = renderer.render(&step.prompt, context).map_err(|e| {
             RunnerError::PromptRenderError {
                 step_id: step.id.clone(),
                 message: e.to_string(),
             }
         })?;
 
-        tracing::info!(step_id = %step.id, "Executing step with prompt length: {}", prompt.len());
+        // Ask for a fenced JSON block if the step declares value outputs
+        if let Some(instructions) = structured_output::output_instructions(&step.outputs) {
+            prompt.push_str(&instructions);
+        }
+
+        tracing::info!(
+            step_id = %step.id,
+            agent = %agent.id,
+            model = ?agent.model,
+            resume = ?resume,
+            "Executing step with prompt length: {}",
+            prompt.len()
+        );
+
+        self.emit(JobEvent::StepStarted {
+            step_id: step.id.clone(),
+        });
+
+        // Invoke the agent CLI, moving down its fallback chain on retryable failures
+        let (agent, run, fell_back_from) = self
+            .invoke_with_fallback(agent, &step.id, &prompt, resume, workspace_dir)
+            .await?;
+        let agent = &agent;
+
+        // Validate structured outputs, re-prompting once on failure
+        let (run, mut values) = self
+            .validate_outputs(agent, step, &prompt, run, workspace_dir)
+            .await?;
 
-        // Invoke the agent CLI
-        let (stdout, stderr, success) = self.invoke_agent(&prompt, workspace_dir).await?;
+        let duration_ms = start.elapsed().as_millis() as u64;
 
-        // Build result
-        let mut result = if success {
-            StepExecutionResult::success(&step.id, start.elapsed().as_millis() as u64)
+        // Build result, keeping whatever output the agent produced
+        let mut result = if run.timed_out {
+            self.emit(JobEvent::StepTimedOut {
+                step_id: step.id.clone(),
+                timeout_secs: self.config.step_timeout_secs,
+            });
+            StepExecutionResult::timed_out(&step.id, duration_ms)
+        } else if run.success {
+            self.emit(JobEvent::StepCompleted {
+                step_id: step.id.clone(),
+                duration_ms,
+            });
+            StepExecutionResult::success(&step.id, duration_ms)
         } else {
-            StepExecutionResult::failed(&step.id, &stderr)
+            self.emit(JobEvent::StepFailed {
+                step_id: step.id.clone(),
+                error: run.stderr.clone(),
+            });
+            StepExecutionResult::failed(&step.id, &run.stderr)
         };
 
-        result = result.with_stdout(&stdout).with_stderr(&stderr);
+        let failure = (!run.success).then(|| run.failure());
+        result = result
+            .with_stdout(&run.stdout)
+            .with_stderr(&run.stderr)
+            .with_agent(&agent.id, agent.model.clone())
+            .with_usage(run.usage)
+            .with_tool_calls(run.tool_calls)
+            .with_failure(failure)
+            .with_fell_back_from(fell_back_from)
+            .with_session_id(run.session_id);
 
         // Parse outputs based on step output definitions
         for output_def in &step.outputs {
@@ -248,11 +520,11 @@ impl WorkflowRunner {
                         }
                     }
                 }
-                OutputType::String => {
-                    // For string outputs, try to extract from stdout
-                    // Look for JSON output or use the full stdout
-                    let output_value = self.extract_string_output(&stdout, &output_def.name);
-                    result = result.with_output(&output_def.name, output_value);
+                _ => {
+                    // Value outputs were validated from the JSON block above
+                    if let Some(value) = values.remove(&output_def.name) {
+                        result = result.with_output(&output_def.name, value);
+                    }
                 }
             }
         }
@@ -260,142 +532,465 @@ impl WorkflowRunner {
         Ok(result)
     }
 
+    /// Parse the value outputs `step` declares from a successful `run`. If
+    /// they are invalid the agent is asked once to correct them, in the same
+    /// session so it sees its first answer.
+    async fn validate_outputs(
+        &self,
+        agent: &AgentProfile,
+        step: &WorkflowStep,
+        prompt: &str,
+        mut run: AgentRun,
+        workspace_dir: &Path,
+    ) -> Result<(AgentRun, HashMap<String, String>), RunnerError> {
+        if !run.success {
+            return Ok((run, HashMap::new()));
+        }
+        let e = match structured_output::parse_outputs(&run.stdout, &step.outputs) {
+            Ok(values) => return Ok((run, values)),
+            Err(e) => e,
+        };
+
+        tracing::warn!(step_id = %step.id, error = %e, "Invalid step outputs, re-prompting");
+        let retry_prompt = structured_output::correction_prompt(prompt, &e);
+        let first = run;
+        run = self
+            .invoke_agent(agent, &step.id, &retry_prompt, first.session_id.as_deref(), workspace_dir)
+            .await?;
+        // Usage and tool calls cover both attempts
+        run.session_id = run.session_id.or(first.session_id);
+        run.usage = match (first.usage, run.usage) {
+            (Some(mut total), Some(retry)) => {
+                total.add(&retry);
+                Some(total)
+            }
+            (first, retry) => first.or(retry),
+        };
+        run.tool_calls.splice(0..0, first.tool_calls);
+
+        if !run.success {
+            return Ok((run, HashMap::new()));
+        }
+        let values = structured_output::parse_outputs(&run.stdout, &step.outputs).map_err(|e| {
+            RunnerError::OutputValidation {
+                step_id: step.id.clone(),
+                message: e.to_string(),
+            }
+        })?;
+        Ok((run, values))
+    }
+
+    /// Run `agent`, and on a retryable failure each agent in its fallback
+    /// chain in turn until one succeeds.
+    ///
+    /// Only `agent` itself resumes the session `resume`. If resuming crashes
+    /// (e.g. the session has expired) it is retried once in a fresh session.
+    ///
+    /// Before each retry the workspace is reset to how it was before the
+    /// first attempt, so no agent starts from another's half-finished edits.
+    ///
+    /// Returns the agent whose run is returned, that run, and the IDs of the
+    /// agents that failed before it.
+    async fn invoke_with_fallback(
+        &self,
+        agent: &AgentProfile,
+        step_id: &str,
+        prompt: &str,
+        mut resume: Option<&str>,
+        workdir: &Path,
+    ) -> Result<(AgentProfile, AgentRun, Vec<String>), RunnerError> {
+        let mut chain = self.fallback_chain(agent).into_iter().peekable();
+        let mut current = agent.clone();
+        let mut failed = Vec::new();
+
+        let checkpoint = if resume.is_some() || chain.peek().is_some() {
+            WorkspaceCheckpoint::capture(workdir)
+                .await
+                .map_err(|e| {
+                    tracing::warn!(
+                        error = %e,
+                        "Cannot checkpoint the workspace, retries will not reset it"
+                    );
+                })
+                .ok()
+        } else {
+            None
+        };
+
+        loop {
+            let run = self
+                .invoke_agent(&current, step_id, prompt, resume, workdir)
+                .await?;
+            if run.success {
+                return Ok((current, run, failed));
+            }
+
+            let failure = run.failure();
+            if let (Some(session_id), FailureKind::Crash) = (resume.take(), failure) {
+                tracing::warn!(
+                    step_id = %step_id,
+                    agent = %current.id,
+                    session_id = %session_id,
+                    "Resuming the agent session failed, starting a fresh one"
+                );
+                restore_checkpoint(checkpoint.as_ref()).await?;
+                continue;
+            }
+            let next = if failure.is_retryable() {
+                chain.next()
+            } else {
+                None
+            };
+            let Some(next) = next else {
+                return Ok((current, run, failed));
+            };
+
+            tracing::warn!(
+                step_id = %step_id,
+                from = %current.id,
+                to = %next.id,
+                failure = %failure,
+                "Agent failed, falling back"
+            );
+            self.emit(JobEvent::AgentFallback {
+                step_id: step_id.to_string(),
+                from: current.id.clone(),
+                to: next.id.clone(),
+                failure,
+            });
+            restore_checkpoint(checkpoint.as_ref()).await?;
+            failed.push(std::mem::replace(&mut current, next).id);
+        }
+    }
+
+    /// The configured agents named in `agent`'s fallback list, in order.
+    fn fallback_chain(&self, agent: &AgentProfile) -> Vec<AgentProfile> {
+        agent
+            .fallback
+            .iter()
+            .filter(|id| **id != agent.id)
+            .filter_map(|id| {
+                let profile = self.config.agents.iter().find(|p| &p.id == id);
+                if profile.is_none() {
+                    tracing::warn!(agent = %agent.id, fallback = %id, "Unknown fallback agent, skipping");
+                }
+                profile.cloned()
+            })
+            .collect()
+    }
+
     async fn invoke_agent(
         &self,
+        agent: &AgentProfile,
+        step_id: &str,
+        prompt: &str,
+        resume: Option<&str>,
+        workdir: &std::path::Path,
+    ) -> Result<AgentRun, RunnerError> {
+        let Some(mock) = self.mock_agent(agent, workdir)? else {
+            return self
+                .run_agent(agent, step_id, prompt, resume, workdir)
+                .await;
+        };
+
+        match mock.mode() {
+            MockMode::Replay => {
+                let transcript = mock
+                    .replay(prompt, workdir)
+                    .map_err(|e| RunnerError::AgentError(e.to_string()))?;
+                if let Some(sink) = self.output_sink(step_id, AgentType::Mock.backend()) {
+                    for line in transcript.stdout.lines() {
+                        sink.emit(OutputStream::Stdout, line);
+                    }
+                }
+                Ok(AgentRun {
+                    stdout: transcript.stdout,
+                    stderr: transcript.stderr,
+                    success: transcript.success,
+                    timed_out: false,
+                    usage: None,
+                    tool_calls: Vec::new(),
+                    session_id: None,
+                    error: None,
+                })
+            }
+            MockMode::Record => {
+                let before = WorkspaceSnapshot::capture(workdir)
+                    .map_err(|e| RunnerError::AgentError(e.to_string()))?;
+                let real = agent
+                    .clone()
+                    .with_agent_type(AgentType::from_binary(&agent.binary));
+                let run = self
+                    .run_agent(&real, step_id, prompt, resume, workdir)
+                    .await?;
+                mock.record(prompt, workdir, &before, &run.stdout, &run.stderr, run.success)
+                    .map_err(|e| RunnerError::AgentError(e.to_string()))?;
+                Ok(run)
+            }
+        }
+    }
+
+    /// The record/replay mock for this agent, if one applies.
+    fn mock_agent(
+        &self,
+        agent: &AgentProfile,
+        workdir: &Path,
+    ) -> Result<Option<MockAgent>, RunnerError> {
+        let root = self.config.project_root.as_deref().unwrap_or(workdir);
+        let mock = if agent.agent_type == AgentType::Mock {
+            MockAgent::for_profile(root).map(Some)
+        } else {
+            MockAgent::from_env(root)
+        };
+        mock.map_err(|e| RunnerError::AgentError(e.to_string()))
+    }
+
+    /// Build the command for `agent`, resolving `secret:` references in its
+    /// environment only now, right before launch.
+    fn launch_invocation(
+        &self,
+        agent: &AgentProfile,
+        prompt: &str,
+        resume: Option<&str>,
+    ) -> Result<AgentInvocation, RunnerError> {
+        let backend = agent.agent_type.backend();
+        let mut invocation = backend.invocation(prompt, agent);
+        if let Some(session_id) = resume {
+            invocation.args.extend(backend.resume_args(session_id));
+        }
+        SecretResolver::new(self.config.project_root.as_deref())
+            .resolve_env(&mut invocation.env)
+            .map_err(|e| RunnerError::AgentError(e.to_string()))?;
+        Ok(invocation)
+    }
+
+    /// Send one prompt to `agent` outside any workflow, in the sandbox if
+    /// configured. Mock agents run their real backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the agent can't be launched.
+    pub async fn probe(
+        &self,
+        agent: &AgentProfile,
+        prompt: &str,
+        workdir: &Path,
+    ) -> Result<ProbeResult, RunnerError> {
+        let agent = if agent.agent_type == AgentType::Mock {
+            agent.clone().with_agent_type(AgentType::from_binary(&agent.binary))
+        } else {
+            agent.clone()
+        };
+        let start = Instant::now();
+        let run = self.run_agent(&agent, "probe", prompt, None, workdir).await?;
+        Ok(ProbeResult {
+            success: run.success,
+            timed_out: run.timed_out,
+            output: run.stdout,
+            stderr: run.stderr,
+            duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+            usage: run.usage,
+        })
+    }
+
+    /// Send one prompt to `agent` outside any workflow, falling back through
+    /// its chain and resetting the workspace between attempts just as a
+    /// workflow step does. Output and fallbacks are reported as events under
+    /// `step_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an agent can't be launched or the workspace
+    /// can't be reset.
+    pub async fn invoke(
+        &self,
+        agent: &AgentProfile,
+        step_id: &str,
+        prompt: &str,
+        workdir: &Path,
+    ) -> Result<InvokeResult, RunnerError> {
+        let (agent, run, fell_back_from) = self
+            .invoke_with_fallback(agent, step_id, prompt, None, workdir)
+            .await?;
+        Ok(InvokeResult {
+            failure: (!run.success).then(|| run.failure()),
+            agent,
+            success: run.success,
+            timed_out: run.timed_out,
+            output: run.stdout,
+            stderr: run.stderr,
+            fell_back_from,
+        })
+    }
+
+    async fn run_agent(
+        &self,
+        agent: &AgentProfile,
+        step_id: &str,
         prompt: &str,
+        resume: Option<&str>,
         workdir: &std::path::Path,
-    ) -> Result<(String, String, bool), RunnerError> {
+    ) -> Result<AgentRun, RunnerError> {
         if self.config.use_sandbox {
-            self.invoke_agent_sandboxed(prompt, workdir).await
+            self.invoke_agent_sandboxed(agent, step_id, prompt, resume, workdir)
+                .await
         } else {
-            self.invoke_agent_local(prompt, workdir).await
+            self.invoke_agent_local(agent, step_id, prompt, resume, workdir)
+                .await
         }
     }
 
     /// Invoke agent locally (no Docker).
+    ///
+    /// The agent runs in its own process group so that a timeout kills it
+    /// together with any tools it spawned.
     async fn invoke_agent_local(
         &self,
+        agent: &AgentProfile,
+        step_id: &str,
         prompt: &str,
+        resume: Option<&str>,
         workdir: &std::path::Path,
-    ) -> Result<(String, String, bool), RunnerError> {
-        use std::process::Command;
+    ) -> Result<AgentRun, RunnerError> {
+        use std::process::Stdio;
+        use tokio::process::Command;
+
+        let backend = agent.agent_type.backend();
+        let invocation = self.launch_invocation(agent, prompt, resume)?;
 
         // Resolve the agent binary path
-        let agent_path = self.resolve_agent_path();
+        let agent_path = resolve_agent_path(&invocation.program);
 
-        tracing::debug!(agent_path = %agent_path, "Invoking agent locally");
+        tracing::debug!(
+            agent_path = %agent_path,
+            backend = backend.name(),
+            openrouter = agent.uses_openrouter(),
+            model = ?agent.model,
+            "Invoking agent locally"
+        );
 
-        // Build the claude command with tools enabled for actual file operations
-        // Using --dangerously-skip-permissions because we're running in a controlled task workspace
+        // The backend's arguments run the agent non-interactively with edits
+        // allowed, which is fine because we're in a controlled task workspace
         let mut cmd = Command::new(&agent_path);
-        cmd.args([
-            "-p",
-            prompt,
-            "--output-format",
-            "text",
-            "--dangerously-skip-permissions",
-        ]);
-        cmd.current_dir(workdir);
-
-        // Set OpenRouter environment variables if configured
-        // Per https://openrouter.ai/docs/guides/guides/claude-code-integration
-        if let Some(ref api_key) = self.config.openrouter_api_key {
-            let base_url = self.config.openrouter_base_url
-                .as_deref()
-                .unwrap_or("https://openrouter.ai/api");
-            
-            tracing::info!(
-                base_url = %base_url,
-                model = ?self.config.openrouter_model,
-                "Using OpenRouter for Claude Code"
-            );
+        cmd.args(&invocation.args)
+            .envs(invocation.env.iter().map(|(k, v)| (k, v)))
+            .current_dir(workdir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn().map_err(|e| {
+            RunnerError::AgentError(format!("Failed to spawn {agent_path}: {e}"))
+        })?;
+
+        // Stream output line by line while keeping everything read so far
+        let sink = self.output_sink(step_id, backend);
+        let stdout = Arc::new(Mutex::new(String::new()));
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let readers = [
+            child.stdout.take().map(|out| {
+                tokio::spawn(stream_lines(out, OutputStream::Stdout, sink.clone(), Arc::clone(&stdout)))
+            }),
+            child.stderr.take().map(|err| {
+                tokio::spawn(stream_lines(err, OutputStream::Stderr, sink.clone(), Arc::clone(&stderr)))
+            }),
+        ];
 
-            // Required env vars for OpenRouter
-            cmd.env("ANTHROPIC_BASE_URL", base_url);
-            cmd.env("ANTHROPIC_AUTH_TOKEN", api_key);
-            cmd.env("ANTHROPIC_API_KEY", ""); // Must be explicitly empty!
-
-            // Optional: override default model
-            if let Some(ref model) = self.config.openrouter_model {
-                // Set all tiers to the same model for consistency
-                cmd.env("ANTHROPIC_DEFAULT_SONNET_MODEL", model);
-                cmd.env("ANTHROPIC_DEFAULT_OPUS_MODEL", model);
-                cmd.env("ANTHROPIC_DEFAULT_HAIKU_MODEL", model);
+        let timeout = Duration::from_secs(self.config.step_timeout_secs);
+        let (success, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(Ok(status)) => (status.success(), false),
+            Ok(Err(e)) => {
+                return Err(RunnerError::AgentError(format!(
+                    "Failed to wait for {agent_path}: {e}"
+                )))
             }
-        }
+            Err(_) => {
+                tracing::warn!(
+                    agent = %agent_path,
+                    timeout_secs = self.config.step_timeout_secs,
+                    "Agent timed out, killing its process group"
+                );
+                kill_process_group(&mut child);
+                let _ = child.wait().await;
+                (false, true)
+            }
+        };
 
-        let output = cmd.output().map_err(|e| {
-            RunnerError::AgentError(format!("Failed to spawn {}: {}", agent_path, e))
-        })?;
+        // Drain remaining output; don't hang on pipes held open by escaped children
+        for reader in readers.into_iter().flatten() {
+            let abort = reader.abort_handle();
+            if tokio::time::timeout(Duration::from_secs(5), reader).await.is_err() {
+                abort.abort();
+            }
+        }
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let success = output.status.success();
+        let stdout = secrets::redact(&take_buffer(&stdout)).into_owned();
+        let stderr = secrets::redact(&take_buffer(&stderr)).into_owned();
 
         tracing::debug!(
             agent = %agent_path,
             success = success,
+            timed_out = timed_out,
             stdout_len = stdout.len(),
             stderr_len = stderr.len(),
             "Agent invocation complete"
         );
 
-        Ok((stdout, stderr, success))
+        Ok(AgentRun {
+            usage: backend.usage(&stdout, &stderr),
+            tool_calls: backend.tool_calls(&stdout),
+            session_id: backend.session_id(&stdout),
+            error: backend.error_message(&stdout),
+            stdout: backend.parse_output(&stdout),
+            stderr,
+            success,
+            timed_out,
+        })
     }
 
     /// Invoke agent inside Docker sandbox.
     async fn invoke_agent_sandboxed(
         &self,
+        agent: &AgentProfile,
+        step_id: &str,
         prompt: &str,
+        resume: Option<&str>,
         workdir: &std::path::Path,
-    ) -> Result<(String, String, bool), RunnerError> {
-        use ckrv_sandbox::{DefaultAllowList, DockerSandbox, ExecuteConfig, Sandbox};
-        use std::time::Duration;
+    ) -> Result<AgentRun, RunnerError> {
+        use ckrv_sandbox::{ContainerRuntime, DefaultAllowList, DockerSandbox, ExecuteConfig, Sandbox};
 
         tracing::info!("Invoking agent in Docker sandbox");
 
         // Create sandbox
-        let sandbox = DockerSandbox::new(DefaultAllowList::default()).map_err(|e| {
-            RunnerError::AgentError(format!("Failed to create Docker sandbox: {}", e))
-        })?;
+        let runtime = ContainerRuntime::resolve(&self.config.container_runtime);
+        let sandbox = DockerSandbox::with_runtime(DefaultAllowList::default(), &runtime)
+            .map_err(|e| {
+                RunnerError::AgentError(format!("Failed to create Docker sandbox: {}", e))
+            })?;
 
-        // Build command: claude -p "prompt" --dangerously-skip-permissions --output-format text
-        // We use --dangerously-skip-permissions because we're in a controlled sandbox
-        let command = format!(
-            "{} -p {} --dangerously-skip-permissions --output-format text",
-            self.config.agent_binary,
-            shell_escape::escape(prompt.into())
+        let backend = agent.agent_type.backend();
+        let invocation = self.launch_invocation(agent, prompt, resume)?;
+
+        tracing::debug!(
+            backend = backend.name(),
+            openrouter = agent.uses_openrouter(),
+            model = ?agent.model,
+            "Invoking agent in sandbox"
         );
 
         // Configure execution
         let mut config = ExecuteConfig::new("", workdir.to_path_buf())
-            .shell(&command)
+            .shell(invocation.shell_command())
             .with_timeout(Duration::from_secs(self.config.step_timeout_secs))
             .with_keep_container(self.config.keep_container);
-
-        // Add OpenRouter environment variables if configured
-        if let Some(ref api_key) = self.config.openrouter_api_key {
-            let base_url = self.config.openrouter_base_url
-                .as_deref()
-                .unwrap_or("https://openrouter.ai/api");
-
-            tracing::info!(
-                base_url = %base_url,
-                model = ?self.config.openrouter_model,
-                "Using OpenRouter for Claude Code in sandbox"
-            );
-
-            config = config
-                .env("ANTHROPIC_BASE_URL", base_url)
-                .env("ANTHROPIC_AUTH_TOKEN", api_key)
-                .env("ANTHROPIC_API_KEY", ""); // Must be explicitly empty!
-
-            if let Some(ref model) = self.config.openrouter_model {
-                config = config
-                    .env("ANTHROPIC_DEFAULT_SONNET_MODEL", model)
-                    .env("ANTHROPIC_DEFAULT_OPUS_MODEL", model)
-                    .env("ANTHROPIC_DEFAULT_HAIKU_MODEL", model);
-            }
+        if let Some(sink) = self.output_sink(step_id, backend) {
+            config = config.with_output_sink(sink);
+        }
+        for (key, value) in &invocation.env {
+            config = config.env(key, value);
         }
 
         // Execute in sandbox
@@ -404,65 +999,133 @@ impl WorkflowRunner {
             .await
             .map_err(|e| RunnerError::AgentError(format!("Sandbox execution failed: {}", e)))?;
 
-        let success = result.success();
+        let success = result.success() && !result.timed_out;
 
         tracing::debug!(
             exit_code = result.exit_code,
+            timed_out = result.timed_out,
             stdout_len = result.stdout.len(),
             stderr_len = result.stderr.len(),
             duration_ms = result.duration_ms,
             "Sandbox execution complete"
         );
 
-        Ok((result.stdout, result.stderr, success))
+        let stdout = secrets::redact(&result.stdout);
+        let stderr = secrets::redact(&result.stderr);
+        Ok(AgentRun {
+            usage: backend.usage(&stdout, &stderr),
+            tool_calls: backend.tool_calls(&stdout),
+            session_id: backend.session_id(&stdout),
+            error: backend.error_message(&stdout),
+            stdout: backend.parse_output(&stdout),
+            stderr: stderr.into_owned(),
+            success,
+            timed_out: result.timed_out,
+        })
     }
+}
 
-    /// Resolve the agent binary path, checking common installation locations.
-    fn resolve_agent_path(&self) -> String {
-        use std::path::PathBuf;
-
-        // If already an absolute path, use it directly
-        if self.config.agent_binary.starts_with('/') {
-            return self.config.agent_binary.clone();
-        }
-
-        // Common locations to check
-        let home = std::env::var("HOME").unwrap_or_default();
-        let candidates = [
-            format!("{}/.local/bin/{}", home, self.config.agent_binary),
-            format!("/usr/local/bin/{}", self.config.agent_binary),
-            format!("/usr/bin/{}", self.config.agent_binary),
-            format!("{}/.npm/bin/{}", home, self.config.agent_binary),
-            format!("{}/.cargo/bin/{}", home, self.config.agent_binary),
-        ];
+/// Reset the workspace to `checkpoint`, if one was taken.
+async fn restore_checkpoint(checkpoint: Option<&WorkspaceCheckpoint>) -> Result<(), RunnerError> {
+    let Some(checkpoint) = checkpoint else {
+        return Ok(());
+    };
+    checkpoint
+        .restore()
+        .await
+        .map_err(|e| RunnerError::AgentError(format!("Failed to reset the workspace: {e}")))
+}
 
-        for candidate in &candidates {
-            if PathBuf::from(candidate).exists() {
-                tracing::debug!(path = %candidate, "Found agent binary");
-                return candidate.clone();
+/// Read `reader` line by line, forwarding each line to `sink` and appending
+/// it to `buffer`.
+async fn stream_lines(
+    reader: impl AsyncRead + Unpin,
+    stream: OutputStream,
+    sink: Option<OutputSink>,
+    buffer: Arc<Mutex<String>>,
+) {
+    let mut reader = BufReader::new(reader);
+    let mut raw = Vec::new();
+    loop {
+        raw.clear();
+        match reader.read_until(b'\n', &mut raw).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let chunk = String::from_utf8_lossy(&raw);
+                if let Some(ref sink) = sink {
+                    sink.emit(stream, chunk.trim_end_matches(['\n', '\r']));
+                }
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.push_str(&chunk);
+                }
             }
         }
+    }
+}
+
+fn take_buffer(buffer: &Mutex<String>) -> String {
+    buffer.lock().map(|mut b| std::mem::take(&mut *b)).unwrap_or_default()
+}
 
-        // Fallback to bare name (rely on PATH)
-        self.config.agent_binary.clone()
+/// Kill a child and everything in its process group.
+#[cfg(unix)]
+fn kill_process_group(child: &mut tokio::process::Child) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let killed = child
+        .id()
+        .and_then(|pid| i32::try_from(pid).ok())
+        .is_some_and(|pid| killpg(Pid::from_raw(pid), Signal::SIGKILL).is_ok());
+    if !killed {
+        let _ = child.start_kill();
     }
+}
 
-    /// Extract a string output from agent response.
-    fn extract_string_output(&self, stdout: &str, output_name: &str) -> String {
-        // Try to parse as JSON and extract the named field
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(stdout) {
-            if let Some(value) = json.get(output_name) {
-                return value.as_str().unwrap_or(&value.to_string()).to_string();
-            }
-        }
+/// Kill a child process.
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut tokio::process::Child) {
+    let _ = child.start_kill();
+}
 
-        // Otherwise, return a summary of the output
-        if stdout.len() > 200 {
-            format!("{}...", &stdout[..200])
-        } else {
-            stdout.to_string()
+/// Resolve an agent binary path, checking common installation locations.
+fn resolve_agent_path(binary: &str) -> String {
+    // Fallback to bare name (rely on PATH)
+    locate_agent_binary(binary).map_or_else(|| binary.to_string(), |p| p.display().to_string())
+}
+
+/// Find an agent binary on the host: an absolute path as given, then common
+/// installation locations, then `PATH`.
+#[must_use]
+pub fn locate_agent_binary(binary: &str) -> Option<PathBuf> {
+    // If already an absolute path, use it directly
+    if binary.starts_with('/') {
+        let path = PathBuf::from(binary);
+        return path.exists().then_some(path);
+    }
+
+    // Common locations to check
+    let home = std::env::var("HOME").unwrap_or_default();
+    let candidates = [
+        format!("{home}/.local/bin/{binary}"),
+        format!("/usr/local/bin/{binary}"),
+        format!("/usr/bin/{binary}"),
+        format!("{home}/.npm/bin/{binary}"),
+        format!("{home}/.cargo/bin/{binary}"),
+    ];
+
+    for candidate in &candidates {
+        if PathBuf::from(candidate).exists() {
+            tracing::debug!(path = %candidate, "Found agent binary");
+            return Some(PathBuf::from(candidate));
         }
     }
+
+    std::env::var_os("PATH").and_then(|path| {
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(binary))
+            .find(|candidate| candidate.is_file())
+    })
 }
 
 impl Default for WorkflowRunner {
@@ -524,4 +1187,241 @@ steps:
         // Task should have recorded outputs
         assert!(task.get_step_output("step1", "result").is_some());
     }
+
+    /// Collects agent output lines.
+    #[derive(Default)]
+    struct CollectingHandler {
+        lines: Mutex<Vec<String>>,
+    }
+
+    impl EventHandler for CollectingHandler {
+        fn handle(&self, event: JobEvent) {
+            if let JobEvent::AgentOutput { line, .. } = event {
+                if let Ok(mut lines) = self.lines.lock() {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_streams_output_and_enforces_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let agent = dir.path().join("slow-agent");
+        std::fs::write(&agent, "#!/bin/sh\necho partial\nsleep 30 &\nwait\n").expect("write agent");
+        std::fs::set_permissions(&agent, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let workflow = Workflow::parse(TEST_WORKFLOW).expect("parse");
+        let mut task = AgentTask::new("test-timeout", "Test", "test", dir.path().to_path_buf());
+        let handler = Arc::new(CollectingHandler::default());
+        let runner = WorkflowRunner::new(RunnerConfig {
+            agent_binary: agent.display().to_string(),
+            step_timeout_secs: 1,
+            ..Default::default()
+        })
+        .with_event_handler(handler.clone());
+
+        let started = Instant::now();
+        let result = runner.run(&workflow, &mut task, dir.path()).await.expect("run");
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!result.success);
+        let step = &result.step_results[0];
+        assert_eq!(step.status, crate::StepExecutionStatus::Timeout);
+        assert_eq!(step.stdout, "partial\n");
+        assert_eq!(*handler.lines.lock().expect("lock"), vec!["partial".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_falls_back_on_retryable_failures_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let script = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).expect("write agent");
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .expect("chmod");
+            AgentProfile::new(name, path.display().to_string())
+        };
+        let limited = script("limited", "echo 'API Error: 429 rate limit exceeded' >&2\nexit 1")
+            .with_fallback(["backup"]);
+        let locked =
+            script("locked", "echo 'Invalid API key' >&2\nexit 1").with_fallback(["backup"]);
+        let backup = script(
+            "backup",
+            "echo '```json'\necho '{\"result\": \"done\"}'\necho '```'",
+        );
+
+        let workflow = &Workflow::parse(TEST_WORKFLOW).expect("parse");
+        let run = |base: &str| {
+            let runner = WorkflowRunner::new(RunnerConfig {
+                agent_id: Some(base.to_string()),
+                agents: vec![limited.clone(), locked.clone(), backup.clone()],
+                ..Default::default()
+            });
+            let dir = dir.path().to_path_buf();
+            async move {
+                let mut task = AgentTask::new("test-fallback", "Test", "test", dir.clone());
+                let result = runner.run(workflow, &mut task, &dir).await.expect("run");
+                (result, task)
+            }
+        };
+
+        // A rate limit moves on to the next agent
+        let (result, task) = run("limited").await;
+        let step = &result.step_results[0];
+        assert!(result.success, "{}", step.stderr);
+        assert_eq!(step.agent.as_deref(), Some("backup"));
+        assert_eq!(step.fell_back_from, ["limited"]);
+        assert_eq!(task.agent.as_deref(), Some("backup"));
+
+        // An auth failure is not retried
+        let (result, task) = run("locked").await;
+        let step = &result.step_results[0];
+        assert!(!result.success);
+        assert_eq!(step.agent.as_deref(), Some("locked"));
+        assert_eq!(step.failure, Some(FailureKind::Auth));
+        assert!(task.agent.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_invoke_resets_workspace_between_fallbacks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let agents = TempDir::new().expect("temp dir");
+        let script = |name: &str, body: &str| {
+            let path = agents.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).expect("write agent");
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .expect("chmod");
+            AgentProfile::new(name, path.display().to_string())
+        };
+        // Mentions a status code in its output, which alone is not a failure
+        let messy = script(
+            "messy",
+            "echo 'lib.rs now returns 401 for bad tokens'\n\
+             echo broken > lib.rs\necho stray > stray.rs\n\
+             echo 'rate limit exceeded' >&2\nexit 1",
+        )
+        .with_fallback(["clean"]);
+        let clean = script(
+            "clean",
+            "test ! -e stray.rs && grep -q original lib.rs || { echo dirty >&2; exit 1; }\necho ok",
+        );
+
+        let workspace = TempDir::new().expect("temp dir");
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(workspace.path())
+                .status()
+                .expect("run git");
+            assert!(status.success(), "git {args:?} failed");
+        };
+        git(&["init", "-q"]);
+        std::fs::write(workspace.path().join("lib.rs"), "original\n").expect("write");
+        git(&["add", "."]);
+        git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]);
+
+        let runner = WorkflowRunner::new(RunnerConfig {
+            agents: vec![messy.clone(), clean],
+            ..Default::default()
+        });
+        let result = runner
+            .invoke(&messy, "step", "Do it", workspace.path())
+            .await
+            .expect("invoke");
+
+        assert!(result.success, "{}", result.stderr);
+        assert_eq!(result.agent.id, "clean");
+        assert_eq!(result.fell_back_from, ["messy"]);
+        assert!(result.failure.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_runner_resumes_agent_sessions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let log = dir.path().join("args.log");
+        let path = dir.path().join("claude");
+        let body = format!(
+            r#"#!/bin/sh
+echo "$*" >> {log}
+case "$*" in
+  *"--resume gone"*) echo 'No conversation found with session ID: gone' >&2; exit 1 ;;
+esac
+echo '{{"type":"result","result":"ok","session_id":"sess-'$(wc -l < {log} | tr -d ' ')'"}}'
+"#,
+            log = log.display()
+        );
+        std::fs::write(&path, body).expect("write agent");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let workflow = Workflow::parse(
+            r"
+version: '1.0'
+name: 'sessions'
+steps:
+  - id: plan
+    name: 'Plan'
+    prompt: 'Plan it'
+  - id: implement
+    name: 'Implement'
+    prompt: 'Do it'
+  - id: review
+    name: 'Review'
+    prompt: 'Review it'
+    resume_session: false
+",
+        )
+        .expect("parse");
+        let runner = WorkflowRunner::new(RunnerConfig {
+            agents: vec![AgentProfile::new("claude", path.display().to_string())],
+            ..Default::default()
+        });
+        let mut task = AgentTask::new("test-sessions", "Test", "test", dir.path().to_path_buf());
+        let result = runner
+            .run(&workflow, &mut task, dir.path())
+            .await
+            .expect("run");
+        assert!(result.success);
+
+        let calls = std::fs::read_to_string(&log).expect("log");
+        let calls: Vec<&str> = calls.lines().collect();
+        assert!(!calls[0].contains("--resume"));
+        assert!(calls[1].contains("--resume sess-1"));
+        assert!(!calls[2].contains("--resume"));
+        assert_eq!(result.step_results[1].stdout, "ok");
+        assert_eq!(
+            task.get_step_output("implement", SESSION_OUTPUT)
+                .map(String::as_str),
+            Some("sess-2")
+        );
+
+        // A session that can't be resumed is retried fresh
+        std::fs::write(&log, "").expect("reset log");
+        let run = runner
+            .invoke_with_fallback(
+                &runner.config.agents[0],
+                "step",
+                "Do it",
+                Some("gone"),
+                dir.path(),
+            )
+            .await
+            .expect("invoke");
+        assert!(run.1.success);
+        assert!(std::fs::read_to_string(&log)
+            .expect("log")
+            .lines()
+            .nth(1)
+            .is_some_and(|call| !call.contains("--resume")));
+    }
 }
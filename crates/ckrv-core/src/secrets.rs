@@ -0,0 +1,603 @@
+//! Encrypted secret store for agent and provider API keys.
+//!
+//! Secrets are kept as one AES-256-GCM encrypted map in
+//! `.chakravarti/secrets/secrets.enc` (or `secrets.enc` in the global config
+//! directory). The encryption key is derived from `CKRV_SECRETS_PASSPHRASE`
+//! with Argon2id when that variable is set, and is otherwise a random key kept
+//! in the OS keyring.
+//!
+//! Config files refer to a secret as `secret:<name>`. References are resolved
+//! only when an agent process or container is launched, and every resolved
+//! value is registered with [`redact`] so it is masked in logs and traces.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+/// Prefix marking a config value as a reference into the secret store.
+pub const SECRET_PREFIX: &str = "secret:";
+
+/// Environment variable holding the store passphrase.
+pub const PASSPHRASE_ENV: &str = "CKRV_SECRETS_PASSPHRASE";
+
+/// File name of the encrypted store.
+pub const SECRETS_FILE: &str = "secrets.enc";
+
+/// Keyring service and account holding the random store key.
+const KEYRING_SERVICE: &str = "chakravarti-secrets";
+const KEYRING_ACCOUNT: &str = "default";
+
+/// Replacement text for redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Values shorter than this are never redacted; masking them would mangle
+/// ordinary output.
+const MIN_REDACT_LEN: usize = 4;
+
+/// Errors from the secret store.
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    /// No secret with this name.
+    #[error("Secret '{0}' not found (add it with `ckrv secrets set {0}`)")]
+    NotFound(String),
+
+    /// Secret name contains unsupported characters.
+    #[error("Invalid secret name '{0}': use letters, digits, '-', '_' and '.'")]
+    InvalidName(String),
+
+    /// Neither a passphrase nor the keyring could supply a key.
+    #[error(
+        "No secrets key available: set {PASSPHRASE_ENV} or make the OS keyring available ({0})"
+    )]
+    NoKey(String),
+
+    /// The store was encrypted with a passphrase that isn't set.
+    #[error("{0} is encrypted with a passphrase; set {PASSPHRASE_ENV} to unlock it")]
+    PassphraseRequired(PathBuf),
+
+    /// Decryption failed.
+    #[error("Could not decrypt {0}: wrong key or corrupted file")]
+    Decrypt(PathBuf),
+
+    /// Store file is not valid.
+    #[error("Invalid secrets file {path}: {message}")]
+    InvalidFile {
+        /// Store path.
+        path: PathBuf,
+        /// Parse error.
+        message: String,
+    },
+
+    /// Key derivation or encryption failed.
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Where the store's encryption key comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// Argon2id over `CKRV_SECRETS_PASSPHRASE` and the file's salt.
+    Passphrase,
+    /// Random key stored in the OS keyring.
+    Keyring,
+}
+
+/// On-disk form of the store.
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    key_source: KeySource,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// A decrypted secret store.
+pub struct SecretStore {
+    path: PathBuf,
+    key_source: KeySource,
+    salt: Vec<u8>,
+    /// `None` for a new keyring-backed store until a secret is set, so
+    /// reading an empty store never creates a keyring entry.
+    key: Option<[u8; 32]>,
+    secrets: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretStore")
+            .field("path", &self.path)
+            .field("key_source", &self.key_source)
+            .field("names", &self.secrets.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecretStore {
+    /// Path of the global store.
+    #[must_use]
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("chakravarti").join(SECRETS_FILE))
+    }
+
+    /// Path of a project's store.
+    #[must_use]
+    pub fn project_path(project_root: &Path) -> PathBuf {
+        project_root
+            .join(".chakravarti")
+            .join("secrets")
+            .join(SECRETS_FILE)
+    }
+
+    /// Open the store at `path`, creating an empty one if it doesn't exist.
+    ///
+    /// A new store uses the passphrase when `CKRV_SECRETS_PASSPHRASE` is set
+    /// and the OS keyring otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no key is available or the file can't be decrypted.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SecretError> {
+        Self::open_with(path, passphrase_from_env().as_deref())
+    }
+
+    /// Open the store at `path` with an explicit passphrase.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no key is available or the file can't be decrypted.
+    pub fn open_with(
+        path: impl Into<PathBuf>,
+        passphrase: Option<&str>,
+    ) -> Result<Self, SecretError> {
+        let path = path.into();
+        if !path.exists() {
+            return Self::create(path, passphrase);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let file: EncryptedFile =
+            serde_json::from_str(&content).map_err(|e| SecretError::InvalidFile {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+        let invalid = |message: &str| SecretError::InvalidFile {
+            path: path.clone(),
+            message: message.to_string(),
+        };
+        let salt = hex::decode(&file.salt).map_err(|_| invalid("bad salt"))?;
+        let nonce = hex::decode(&file.nonce).map_err(|_| invalid("bad nonce"))?;
+        let ciphertext = hex::decode(&file.ciphertext).map_err(|_| invalid("bad ciphertext"))?;
+        if nonce.len() != 12 {
+            return Err(invalid("bad nonce"));
+        }
+
+        let key = match file.key_source {
+            KeySource::Passphrase => {
+                let passphrase =
+                    passphrase.ok_or_else(|| SecretError::PassphraseRequired(path.clone()))?;
+                derive_key(passphrase, &salt)?
+            }
+            KeySource::Keyring => keyring_key(false)?,
+        };
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| SecretError::Decrypt(path.clone()))?;
+        let secrets = serde_json::from_slice(&plaintext).map_err(|e| SecretError::InvalidFile {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+
+        Ok(Self {
+            path,
+            key_source: file.key_source,
+            salt,
+            key: Some(key),
+            secrets,
+        })
+    }
+
+    fn create(path: PathBuf, passphrase: Option<&str>) -> Result<Self, SecretError> {
+        let (key_source, salt, key) = if let Some(passphrase) = passphrase {
+            let mut salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = derive_key(passphrase, &salt)?;
+            (KeySource::Passphrase, salt, Some(key))
+        } else {
+            (KeySource::Keyring, Vec::new(), None)
+        };
+        Ok(Self {
+            path,
+            key_source,
+            salt,
+            key,
+            secrets: BTreeMap::new(),
+        })
+    }
+
+    /// Store file path.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the encryption key comes from.
+    #[must_use]
+    pub const fn key_source(&self) -> KeySource {
+        self.key_source
+    }
+
+    /// Look up a secret.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(String::as_str)
+    }
+
+    /// Add or replace a secret. Call [`Self::save`] to persist it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is invalid, or if this is a new
+    /// keyring-backed store and no keyring key can be created.
+    pub fn set(&mut self, name: &str, value: impl Into<String>) -> Result<(), SecretError> {
+        validate_name(name)?;
+        if self.key.is_none() {
+            self.key = Some(keyring_key(true)?);
+        }
+        self.secrets.insert(name.to_string(), value.into());
+        Ok(())
+    }
+
+    /// Remove a secret, returning whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.secrets.remove(name).is_some()
+    }
+
+    /// Names of all secrets, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.secrets.keys().map(String::as_str)
+    }
+
+    /// Encrypt and write the store with a fresh nonce.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encryption or writing fails.
+    pub fn save(&self) -> Result<(), SecretError> {
+        let plaintext =
+            serde_json::to_vec(&self.secrets).map_err(|e| SecretError::Crypto(e.to_string()))?;
+        let key = match self.key {
+            Some(key) => key,
+            None => keyring_key(true)?,
+        };
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|e| SecretError::Crypto(e.to_string()))?;
+
+        let file = EncryptedFile {
+            version: 1,
+            key_source: self.key_source,
+            salt: hex::encode(&self.salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        let json =
+            serde_json::to_string_pretty(&file).map_err(|e| SecretError::Crypto(e.to_string()))?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("enc.tmp");
+        write_private(&tmp, json.as_bytes())?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Resolves `secret:` references for a project at launch time.
+///
+/// Stores are opened lazily, so launching an agent whose config holds no
+/// references never touches the keyring or asks for a passphrase. The
+/// project store is consulted before the global one.
+#[derive(Debug)]
+pub struct SecretResolver {
+    project_root: Option<PathBuf>,
+    stores: Option<Vec<SecretStore>>,
+}
+
+impl SecretResolver {
+    /// Create a resolver for `project_root` (global store only if `None`).
+    #[must_use]
+    pub fn new(project_root: Option<&Path>) -> Self {
+        Self {
+            project_root: project_root.map(Path::to_path_buf),
+            stores: None,
+        }
+    }
+
+    /// Resolve `value` if it is a `secret:` reference; other values pass
+    /// through unchanged. Resolved values are registered for redaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secret is missing or a store can't be opened.
+    pub fn resolve(&mut self, value: &str) -> Result<String, SecretError> {
+        let Some(name) = secret_reference(value) else {
+            return Ok(value.to_string());
+        };
+        let resolved = self
+            .stores()?
+            .iter()
+            .find_map(|store| store.get(name))
+            .ok_or_else(|| SecretError::NotFound(name.to_string()))?
+            .to_string();
+        register_redaction(&resolved);
+        Ok(resolved)
+    }
+
+    /// Resolve every value in an environment list in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any referenced secret can't be resolved.
+    pub fn resolve_env(&mut self, env: &mut [(String, String)]) -> Result<(), SecretError> {
+        for (_, value) in env.iter_mut() {
+            if secret_reference(value).is_some() {
+                *value = self.resolve(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn stores(&mut self) -> Result<&[SecretStore], SecretError> {
+        if self.stores.is_none() {
+            let paths = self
+                .project_root
+                .as_deref()
+                .map(SecretStore::project_path)
+                .into_iter()
+                .chain(SecretStore::global_path());
+            let mut stores = Vec::new();
+            for path in paths.filter(|p| p.exists()) {
+                stores.push(SecretStore::open(path)?);
+            }
+            self.stores = Some(stores);
+        }
+        Ok(self.stores.as_deref().unwrap_or_default())
+    }
+}
+
+/// The secret name if `value` is a `secret:<name>` reference.
+#[must_use]
+pub fn secret_reference(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix(SECRET_PREFIX)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+fn redactions() -> &'static RwLock<Vec<String>> {
+    static REDACTIONS: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+    REDACTIONS.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Mask `value` wherever [`redact`] is applied from now on.
+pub fn register_redaction(value: &str) {
+    if value.len() < MIN_REDACT_LEN {
+        return;
+    }
+    if let Ok(mut values) = redactions().write() {
+        if !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+            // Longest first so a value containing another is masked whole
+            values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        }
+    }
+}
+
+/// Replace every registered secret value in `text`.
+#[must_use]
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let Ok(values) = redactions().read() else {
+        return Cow::Borrowed(text);
+    };
+    let mut result = Cow::Borrowed(text);
+    for value in values.iter() {
+        if result.contains(value.as_str()) {
+            result = Cow::Owned(result.replace(value.as_str(), REDACTED));
+        }
+    }
+    result
+}
+
+/// Writer that redacts secret values before passing output on.
+///
+/// Wrap the tracing subscriber's writer in this so resolved secrets never
+/// reach log output.
+#[derive(Debug)]
+pub struct RedactingWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    /// Wrap `inner`.
+    pub const fn new(inner: W) -> Self {
+        Self { inner }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.inner.write_all(redact(text).as_bytes())?,
+            Err(_) => self.inner.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), SecretError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SecretError::InvalidName(name.to_string()))
+    }
+}
+
+fn passphrase_from_env() -> Option<String> {
+    std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], SecretError> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| SecretError::Crypto(e.to_string()))?;
+    Ok(key)
+}
+
+/// Fetch the store key from the OS keyring, generating it if `create` is set
+/// and none exists yet.
+fn keyring_key(create: bool) -> Result<[u8; 32], SecretError> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT)
+        .map_err(|e| SecretError::NoKey(e.to_string()))?;
+    match entry.get_password() {
+        Ok(encoded) => {
+            let bytes =
+                hex::decode(encoded.trim()).map_err(|e| SecretError::NoKey(e.to_string()))?;
+            bytes
+                .try_into()
+                .map_err(|_| SecretError::NoKey("keyring entry is not a 256-bit key".to_string()))
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let key: [u8; 32] = Aes256Gcm::generate_key(OsRng).into();
+            entry
+                .set_password(&hex::encode(key))
+                .map_err(|e| SecretError::NoKey(e.to_string()))?;
+            Ok(key)
+        }
+        Err(e) => Err(SecretError::NoKey(e.to_string())),
+    }
+}
+
+/// Write `data` to `path`, readable only by the owner on Unix.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_roundtrip_with_passphrase() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join(SECRETS_FILE);
+
+        let mut store = SecretStore::open_with(&path, Some("hunter2")).expect("open store");
+        assert_eq!(store.key_source(), KeySource::Passphrase);
+        store.set("openrouter-main", "sk-or-v1-abcdef").expect("set");
+        store.save().expect("save");
+
+        let raw = std::fs::read_to_string(&path).expect("read store");
+        assert!(!raw.contains("sk-or-v1-abcdef"));
+
+        let reopened = SecretStore::open_with(&path, Some("hunter2")).expect("open store");
+        assert_eq!(reopened.get("openrouter-main"), Some("sk-or-v1-abcdef"));
+        assert_eq!(
+            reopened.names().collect::<Vec<_>>(),
+            vec!["openrouter-main"]
+        );
+
+        assert!(matches!(
+            SecretStore::open_with(&path, Some("wrong")),
+            Err(SecretError::Decrypt(_))
+        ));
+        assert!(matches!(
+            SecretStore::open_with(&path, None),
+            Err(SecretError::PassphraseRequired(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_names_rejected() {
+        let dir = TempDir::new().expect("temp dir");
+        let mut store = SecretStore::open_with(dir.path().join(SECRETS_FILE), Some("pw")).expect("open store");
+        assert!(store.set("has space", "x").is_err());
+        assert!(store.set("", "x").is_err());
+        assert!(store.set("ok.name_1-2", "x").is_ok());
+    }
+
+    #[test]
+    fn test_secret_reference() {
+        assert_eq!(
+            secret_reference("secret:openrouter-main"),
+            Some("openrouter-main")
+        );
+        assert_eq!(secret_reference("sk-or-plain"), None);
+        assert_eq!(secret_reference("secret:"), None);
+    }
+
+    #[test]
+    fn test_open_missing_store_without_key() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join(SECRETS_FILE);
+
+        // Listing or reading a store that doesn't exist needs no keyring key
+        let store = SecretStore::open_with(&path, None).expect("open store");
+        assert_eq!(store.key_source(), KeySource::Keyring);
+        assert_eq!(store.names().count(), 0);
+        assert_eq!(store.get("anything"), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_redact_registered_values() {
+        register_redaction("sk-test-redact-me");
+        register_redaction("abc");
+        assert_eq!(
+            redact("key=sk-test-redact-me ok abc"),
+            "key=[REDACTED] ok abc"
+        );
+
+        let mut out = Vec::new();
+        RedactingWriter::new(&mut out)
+            .write_all(b"token sk-test-redact-me\n")
+            .expect("write");
+        assert_eq!(String::from_utf8(out).expect("utf-8"), "token [REDACTED]\n");
+    }
+}
@@ -3,6 +3,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::backend::{AgentUsage, ToolCall};
+use crate::failure::FailureKind;
+
 /// Result of executing a single workflow step.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepExecutionResult {
@@ -18,6 +21,28 @@ pub struct StepExecutionResult {
     pub stderr: String,
     /// Execution duration in milliseconds.
     pub duration_ms: u64,
+    /// ID of the agent that ran this step.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Model the agent was asked to use.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Token usage reported by the agent, if it reports any.
+    #[serde(default)]
+    pub usage: Option<AgentUsage>,
+    /// Tools the agent called, if it reports them.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Why the agent failed, for failed and timed-out steps.
+    #[serde(default)]
+    pub failure: Option<FailureKind>,
+    /// Agents that failed this step before `agent` ran it, in order.
+    #[serde(default)]
+    pub fell_back_from: Vec<String>,
+    /// Agent session this step ran in, if the agent reports one. Later steps
+    /// with the same agent resume it.
+    #[serde(default)]
+    pub session_id: Option<String>,
 }
 
 /// Status of a step execution.
@@ -45,6 +70,13 @@ impl StepExecutionResult {
             stdout: String::new(),
             stderr: String::new(),
             duration_ms,
+            agent: None,
+            model: None,
+            usage: None,
+            tool_calls: Vec::new(),
+            failure: None,
+            fell_back_from: Vec::new(),
+            session_id: None,
         }
     }
 
@@ -58,6 +90,23 @@ impl StepExecutionResult {
             stdout: String::new(),
             stderr: error.into(),
             duration_ms: 0,
+            agent: None,
+            model: None,
+            usage: None,
+            tool_calls: Vec::new(),
+            failure: None,
+            fell_back_from: Vec::new(),
+            session_id: None,
+        }
+    }
+
+    /// Create a result for a step whose agent was killed after timing out.
+    #[must_use]
+    pub fn timed_out(step_id: impl Into<String>, duration_ms: u64) -> Self {
+        Self {
+            status: StepExecutionStatus::Timeout,
+            duration_ms,
+            ..Self::failed(step_id, String::new())
         }
     }
 
@@ -82,6 +131,49 @@ impl StepExecutionResult {
         self
     }
 
+    /// Record the agent (and model) that ran this step.
+    #[must_use]
+    pub fn with_agent(mut self, agent: impl Into<String>, model: Option<String>) -> Self {
+        self.agent = Some(agent.into());
+        self.model = model;
+        self
+    }
+
+    /// Record the agent's reported token usage.
+    #[must_use]
+    pub const fn with_usage(mut self, usage: Option<AgentUsage>) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Record the tools the agent called.
+    #[must_use]
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    /// Record why the agent failed.
+    #[must_use]
+    pub const fn with_failure(mut self, failure: Option<FailureKind>) -> Self {
+        self.failure = failure;
+        self
+    }
+
+    /// Record the agents that failed this step before the one that ran it.
+    #[must_use]
+    pub fn with_fell_back_from(mut self, agents: Vec<String>) -> Self {
+        self.fell_back_from = agents;
+        self
+    }
+
+    /// Record the agent session the step ran in.
+    #[must_use]
+    pub fn with_session_id(mut self, session_id: Option<String>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
     /// Check if the step succeeded.
     #[must_use]
     pub fn is_success(&self) -> bool {
@@ -115,4 +207,22 @@ mod tests {
         assert_eq!(result.status, StepExecutionStatus::Failed);
         assert_eq!(result.stderr, "Agent crashed");
     }
+
+    #[test]
+    fn test_timed_out_result() {
+        let result = StepExecutionResult::timed_out("implement", 300_000).with_stdout("partial");
+
+        assert!(!result.is_success());
+        assert_eq!(result.status, StepExecutionStatus::Timeout);
+        assert_eq!(result.stdout, "partial");
+    }
+
+    #[test]
+    fn test_result_records_agent() {
+        let result = StepExecutionResult::success("plan", 10)
+            .with_agent("claude-opus", Some("opus".to_string()));
+
+        assert_eq!(result.agent.as_deref(), Some("claude-opus"));
+        assert_eq!(result.model.as_deref(), Some("opus"));
+    }
 }
@@ -0,0 +1,474 @@
+//! Structured step output extraction and validation.
+//!
+//! Steps that declare value outputs (anything other than `file`) ask the agent
+//! to finish its response with a fenced JSON block. This module builds those
+//! instructions, extracts the block from the agent's stdout and validates each
+//! value against its declared type or JSON Schema.
+//!
+//! The validator supports the commonly used subset of JSON Schema: `type`,
+//! `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`,
+//! `minItems`/`maxItems`, `minLength`/`maxLength` and `minimum`/`maximum`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde_json::{json, Map, Value};
+
+use crate::workflow::{OutputType, StepOutput};
+
+/// Errors from structured output extraction.
+#[derive(Debug, thiserror::Error)]
+pub enum OutputError {
+    /// The agent response contained no fenced JSON block.
+    #[error("No fenced ```json block found in agent output")]
+    MissingBlock,
+
+    /// The fenced block was not valid JSON.
+    #[error("Invalid JSON in output block: {0}")]
+    InvalidJson(String),
+
+    /// The fenced block was valid JSON but not an object.
+    #[error("Output block must be a JSON object keyed by output name")]
+    NotAnObject,
+
+    /// One or more outputs failed validation.
+    #[error("Output validation failed: {}", .0.join("; "))]
+    Validation(Vec<String>),
+}
+
+impl OutputType {
+    /// Get the JSON Schema equivalent of a simple output type.
+    ///
+    /// Returns `None` for file outputs, which are not part of the JSON block.
+    #[must_use]
+    pub fn json_schema(&self) -> Option<Value> {
+        let type_name = match self {
+            Self::File => return None,
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "boolean",
+            Self::List => "array",
+            Self::Object => "object",
+        };
+        Some(json!({ "type": type_name }))
+    }
+}
+
+impl StepOutput {
+    /// Whether this output is read from the agent's JSON block.
+    #[must_use]
+    pub fn is_structured(&self) -> bool {
+        self.output_type != OutputType::File
+    }
+
+    /// Get the effective schema for this output.
+    ///
+    /// An explicit `schema` takes precedence over the simple `type`.
+    #[must_use]
+    pub fn effective_schema(&self) -> Option<Value> {
+        self.schema
+            .clone()
+            .or_else(|| self.output_type.json_schema())
+    }
+}
+
+/// Build the prompt suffix asking the agent for a fenced JSON block.
+///
+/// Returns `None` if none of the outputs are structured.
+#[must_use]
+pub fn output_instructions(outputs: &[StepOutput]) -> Option<String> {
+    let structured: Vec<&StepOutput> = outputs.iter().filter(|o| o.is_structured()).collect();
+    if structured.is_empty() {
+        return None;
+    }
+
+    let mut text = String::from(
+        "\n\n## Required Outputs\n\
+         When you are done, end your response with a single fenced ```json block \
+         containing a JSON object with exactly these keys:\n",
+    );
+    for output in &structured {
+        let schema = output
+            .effective_schema()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        let _ = write!(text, "- `{}`: {schema}", output.name);
+        if let Some(ref description) = output.description {
+            let _ = write!(text, " - {description}");
+        }
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// Build a follow-up prompt asking the agent to correct invalid outputs.
+#[must_use]
+pub fn correction_prompt(original_prompt: &str, error: &OutputError) -> String {
+    format!(
+        "{original_prompt}\n\n## Output Correction\n\
+         Your previous response did not provide valid outputs: {error}\n\
+         Respond again and end with the corrected fenced ```json block."
+    )
+}
+
+/// Extract the last fenced JSON block from agent output.
+///
+/// Accepts blocks tagged ```` ```json ```` as well as untagged fences whose
+/// body parses as JSON. If no fence is present, the whole output is tried.
+///
+/// # Errors
+///
+/// Returns an error if no JSON block can be found or parsed.
+pub fn extract_json_block(stdout: &str) -> Result<Value, OutputError> {
+    let mut blocks: Vec<(bool, String)> = Vec::new();
+    let mut current: Option<(bool, String)> = None;
+
+    for line in stdout.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            if let Some(block) = current.take() {
+                blocks.push(block);
+            } else {
+                let tag = rest.trim().to_lowercase();
+                current = Some((tag == "json", String::new()));
+            }
+        } else if let Some((_, ref mut body)) = current {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    let candidate = blocks
+        .iter()
+        .rev()
+        .find(|(tagged, _)| *tagged)
+        .or_else(|| {
+            blocks
+                .iter()
+                .rev()
+                .find(|(_, body)| serde_json::from_str::<Value>(body).is_ok())
+        })
+        .map(|(_, body)| body.as_str());
+
+    candidate.map_or_else(
+        || serde_json::from_str(stdout.trim()).map_err(|_| OutputError::MissingBlock),
+        |body| serde_json::from_str(body).map_err(|e| OutputError::InvalidJson(e.to_string())),
+    )
+}
+
+/// Extract and validate all structured outputs from agent stdout.
+///
+/// Returns the outputs rendered as strings: JSON strings are stored as-is,
+/// every other value is stored as compact JSON.
+///
+/// # Errors
+///
+/// Returns an error if the block is missing, malformed, or any output fails
+/// validation.
+pub fn parse_outputs(
+    stdout: &str,
+    outputs: &[StepOutput],
+) -> Result<HashMap<String, String>, OutputError> {
+    let structured: Vec<&StepOutput> = outputs.iter().filter(|o| o.is_structured()).collect();
+    if structured.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let block = extract_json_block(stdout)?;
+    let object = block.as_object().ok_or(OutputError::NotAnObject)?;
+
+    let mut errors = Vec::new();
+    let mut values = HashMap::new();
+
+    for output in structured {
+        let Some(value) = object.get(&output.name) else {
+            errors.push(format!("missing output '{}'", output.name));
+            continue;
+        };
+
+        if let Some(schema) = output.effective_schema() {
+            validate(value, &schema, &output.name, &mut errors);
+        }
+
+        values.insert(output.name.clone(), value_to_string(value));
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(OutputError::Validation(errors))
+    }
+}
+
+/// Render a JSON value as a step output string.
+#[must_use]
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Validate a value against a JSON Schema, appending errors for `path`.
+pub fn validate(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`{}` schemas accept everything; `false` rejects everything.
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{path}: no value is allowed"));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(value, t)) {
+            errors.push(format!(
+                "{path}: expected {}, got {}",
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{path}: must be one of {}",
+                Value::Array(options.clone())
+            ));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            errors.push(format!("{path}: must equal {expected}"));
+        }
+    }
+
+    match value {
+        Value::Object(map) => validate_object(map, schema, path, errors),
+        Value::Array(items) => validate_array(items, schema, path, errors),
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{path}: shorter than {min} characters"));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{path}: longer than {max} characters"));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{path}: must be >= {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{path}: must be <= {max}"));
+                }
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn validate_object(
+    map: &Map<String, Value>,
+    schema: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(key) {
+                errors.push(format!("{path}: missing required property '{key}'"));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, child) in map {
+        let child_path = format!("{path}.{key}");
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => validate(child, child_schema, &child_path, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("{path}: unexpected property '{key}'"));
+                }
+                Some(additional @ Value::Object(_)) => {
+                    validate(child, additional, &child_path, errors);
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+fn validate_array(
+    items: &[Value],
+    schema: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let len = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if len < min {
+            errors.push(format!("{path}: must have at least {min} items"));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if len > max {
+            errors.push(format!("{path}: must have at most {max} items"));
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+            validate(item, item_schema, &format!("{path}[{i}]"), errors);
+        }
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, output_type: OutputType, schema: Option<Value>) -> StepOutput {
+        StepOutput {
+            name: name.to_string(),
+            output_type,
+            description: None,
+            filename: None,
+            schema,
+        }
+    }
+
+    #[test]
+    fn test_extract_tagged_block() {
+        let stdout = "Done.\n```json\n{\"summary\": \"ok\"}\n```\n";
+        let value = extract_json_block(stdout).expect("block");
+        assert_eq!(value["summary"], "ok");
+    }
+
+    #[test]
+    fn test_extract_prefers_last_tagged_block() {
+        let stdout =
+            "```json\n{\"a\": 1}\n```\ntext\n```rust\nfn main() {}\n```\n```json\n{\"a\": 2}\n```";
+        let value = extract_json_block(stdout).expect("block");
+        assert_eq!(value["a"], 2);
+    }
+
+    #[test]
+    fn test_extract_missing_block() {
+        let result = extract_json_block("I finished the task.");
+        assert!(matches!(result, Err(OutputError::MissingBlock)));
+    }
+
+    #[test]
+    fn test_parse_simple_types() {
+        let outputs = vec![
+            output("summary", OutputType::String, None),
+            output("count", OutputType::Number, None),
+            output("ok", OutputType::Bool, None),
+            output("files", OutputType::List, None),
+        ];
+        let stdout = "```json\n{\"summary\": \"done\", \"count\": 3, \"ok\": true, \"files\": [\"a.rs\"]}\n```";
+
+        let values = parse_outputs(stdout, &outputs).expect("valid");
+        assert_eq!(values["summary"], "done");
+        assert_eq!(values["count"], "3");
+        assert_eq!(values["ok"], "true");
+        assert_eq!(values["files"], "[\"a.rs\"]");
+    }
+
+    #[test]
+    fn test_parse_type_mismatch() {
+        let outputs = vec![output("count", OutputType::Number, None)];
+        let stdout = "```json\n{\"count\": \"three\"}\n```";
+
+        let err = parse_outputs(stdout, &outputs).expect_err("invalid");
+        assert!(err.to_string().contains("expected number"));
+    }
+
+    #[test]
+    fn test_parse_missing_output() {
+        let outputs = vec![output("summary", OutputType::String, None)];
+        let err = parse_outputs("```json\n{}\n```", &outputs).expect_err("invalid");
+        assert!(err.to_string().contains("missing output 'summary'"));
+    }
+
+    #[test]
+    fn test_schema_validation() {
+        let schema = json!({
+            "type": "object",
+            "required": ["risk", "files"],
+            "additionalProperties": false,
+            "properties": {
+                "risk": { "enum": ["low", "high"] },
+                "files": { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+            }
+        });
+        let outputs = vec![output("review", OutputType::Object, Some(schema))];
+
+        let good = "```json\n{\"review\": {\"risk\": \"low\", \"files\": [\"a.rs\"]}}\n```";
+        assert!(parse_outputs(good, &outputs).is_ok());
+
+        let bad = "```json\n{\"review\": {\"risk\": \"medium\", \"files\": [], \"extra\": 1}}\n```";
+        let OutputError::Validation(errors) = parse_outputs(bad, &outputs).expect_err("invalid")
+        else {
+            panic!("expected validation error");
+        };
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_file_outputs_are_not_structured() {
+        let outputs = vec![output("plan", OutputType::File, None)];
+        assert!(output_instructions(&outputs).is_none());
+        assert!(parse_outputs("no json here", &outputs)
+            .expect("nothing to parse")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_output_instructions_list_keys() {
+        let outputs = vec![output("summary", OutputType::String, None)];
+        let text = output_instructions(&outputs).expect("instructions");
+        assert!(text.contains("`summary`"));
+        assert!(text.contains("```json"));
+    }
+}
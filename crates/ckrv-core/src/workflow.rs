@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::interpolate::{self, InterpolationError};
+
 /// A workflow defines a sequence of steps to be executed by an AI agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workflow {
@@ -54,18 +56,27 @@ pub struct WorkflowStep {
     /// Expected outputs from this step.
     #[serde(default)]
     pub outputs: Vec<StepOutput>,
+    /// Continue the previous step's agent session when both run on the
+    /// same agent (default: true). Set to false to start fresh, e.g. for a
+    /// review that shouldn't see the implementer's reasoning.
+    #[serde(default = "default_resume_session")]
+    pub resume_session: bool,
 }
 
 fn default_step_type() -> String {
     "agent".to_string()
 }
 
+const fn default_resume_session() -> bool {
+    true
+}
+
 /// An expected output from a step.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepOutput {
     /// Output variable name.
     pub name: String,
-    /// Output type: "file" or "string".
+    /// Output type: "file", "string", "number", "bool", "list" or "object".
     #[serde(rename = "type")]
     pub output_type: OutputType,
     /// Description of the output.
@@ -74,6 +85,9 @@ pub struct StepOutput {
     /// For file type: the filename to look for.
     #[serde(default)]
     pub filename: Option<String>,
+    /// JSON Schema the value must satisfy (overrides the simple `type` check).
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
 }
 
 /// Type of step output.
@@ -82,8 +96,18 @@ pub struct StepOutput {
 pub enum OutputType {
     /// Output is a file created by the agent.
     File,
-    /// Output is a string value (JSON key or parsed from response).
+    /// Output is a string value from the agent's JSON output block.
     String,
+    /// Output is a number from the agent's JSON output block.
+    Number,
+    /// Output is a boolean from the agent's JSON output block.
+    #[serde(alias = "boolean")]
+    Bool,
+    /// Output is a list from the agent's JSON output block.
+    #[serde(alias = "array")]
+    List,
+    /// Output is an object from the agent's JSON output block.
+    Object,
 }
 
 /// Errors from workflow operations.
@@ -101,6 +125,10 @@ pub enum WorkflowError {
     #[error("Workflow validation failed: {0}")]
     ValidationError(String),
 
+    /// A referenced environment variable is not defined.
+    #[error(transparent)]
+    Interpolation(#[from] InterpolationError),
+
     /// IO error.
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -120,7 +148,7 @@ impl Workflow {
         }
 
         let content = fs::read_to_string(path)?;
-        Self::parse(&content)
+        Self::parse_from(&content, &path.display().to_string())
     }
 
     /// Parse a workflow from YAML string.
@@ -129,8 +157,18 @@ impl Workflow {
     ///
     /// Returns an error if parsing fails.
     pub fn parse(yaml: &str) -> Result<Self, WorkflowError> {
-        let workflow: Workflow =
+        Self::parse_from(yaml, "workflow")
+    }
+
+    /// Parse a workflow, expanding `${VAR}` references in every field except
+    /// step prompts, which are Handlebars templates that often contain shell
+    /// snippets.
+    fn parse_from(yaml: &str, source: &str) -> Result<Self, WorkflowError> {
+        let mut value: serde_yaml::Value =
             serde_yaml::from_str(yaml).map_err(|e| WorkflowError::ParseError(e.to_string()))?;
+        interpolate::interpolate_yaml(&mut value, source, &["prompt"])?;
+        let workflow: Self =
+            serde_yaml::from_value(value).map_err(|e| WorkflowError::ParseError(e.to_string()))?;
 
         workflow.validate()?;
         Ok(workflow)
@@ -159,6 +197,15 @@ impl Workflow {
                     step.id
                 )));
             }
+
+            for output in &step.outputs {
+                if output.schema.is_some() && output.output_type == OutputType::File {
+                    return Err(WorkflowError::ValidationError(format!(
+                        "Output '{}' of step '{}' is a file and cannot declare a schema",
+                        output.name, step.id
+                    )));
+                }
+            }
         }
 
         Ok(())
@@ -175,6 +222,18 @@ impl Workflow {
     pub fn get_step(&self, id: &str) -> Option<&WorkflowStep> {
         self.steps.iter().find(|s| s.id == id)
     }
+
+    /// Get the steps each step depends on, in workflow order.
+    ///
+    /// A step depends on every step whose outputs its prompt references
+    /// (`{{steps.<id>.outputs.<name>}}`).
+    #[must_use]
+    pub fn step_dependencies(&self) -> Vec<(&str, Vec<String>)> {
+        self.steps
+            .iter()
+            .map(|step| (step.id.as_str(), step.referenced_steps()))
+            .collect()
+    }
 }
 
 impl WorkflowStep {
@@ -183,6 +242,29 @@ impl WorkflowStep {
     pub fn tool_or_default<'a>(&'a self, workflow_default: Option<&'a str>) -> Option<&'a str> {
         self.agent.as_deref().or(workflow_default)
     }
+
+    /// IDs of the steps referenced by this step's prompt, without duplicates.
+    #[must_use]
+    pub fn referenced_steps(&self) -> Vec<String> {
+        let is_id_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        let mut ids: Vec<String> = Vec::new();
+
+        for (index, _) in self.prompt.match_indices("steps.") {
+            let preceded_by_id = self.prompt[..index].chars().next_back().is_some_and(is_id_char);
+            if preceded_by_id {
+                continue;
+            }
+            let id: String = self.prompt[index + "steps.".len()..]
+                .chars()
+                .take_while(|&c| is_id_char(c))
+                .collect();
+            if !id.is_empty() && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        ids
+    }
 }
 
 #[cfg(test)]
@@ -222,6 +304,25 @@ steps:
         assert_eq!(workflow.steps.len(), 2);
     }
 
+    #[test]
+    fn test_parse_expands_variables_outside_prompts() {
+        let workflow = Workflow::parse(
+            "version: '1.0'\nname: 'env'\ndefaults:\n  model: ${CKRV_TEST_MODEL_6231:-sonnet}\nsteps:\n  - id: a\n    name: A\n    prompt: 'echo ${HOME_UNSET_6231}'\n",
+        )
+        .expect("parse");
+        assert_eq!(
+            workflow.defaults.and_then(|d| d.model).as_deref(),
+            Some("sonnet")
+        );
+        assert_eq!(workflow.steps[0].prompt, "echo ${HOME_UNSET_6231}");
+
+        let err = Workflow::parse(
+            "version: '1.0'\nname: 'env'\nsteps:\n  - id: a\n    name: A\n    agent: ${CKRV_TEST_AGENT_6231}\n    prompt: p\n",
+        )
+        .expect_err("undefined variable");
+        assert!(matches!(err, WorkflowError::Interpolation(ref e) if e.field == "steps[0].agent"));
+    }
+
     #[test]
     fn test_workflow_defaults() {
         let workflow = Workflow::parse(SAMPLE_WORKFLOW).expect("parse");
@@ -239,6 +340,15 @@ steps:
         assert!(workflow.get_step("nonexistent").is_none());
     }
 
+    #[test]
+    fn test_step_dependencies() {
+        let workflow = Workflow::parse(SAMPLE_WORKFLOW).expect("parse");
+
+        let deps = workflow.step_dependencies();
+        assert_eq!(deps[0], ("plan", vec![]));
+        assert_eq!(deps[1], ("implement", vec!["plan".to_string()]));
+    }
+
     #[test]
     fn test_step_outputs() {
         let workflow = Workflow::parse(SAMPLE_WORKFLOW).expect("parse");
@@ -249,6 +359,57 @@ steps:
         assert_eq!(plan_step.outputs[0].filename, Some("plan.md".to_string()));
     }
 
+    #[test]
+    fn test_structured_output_types() {
+        let yaml = r#"
+version: '1.0'
+name: 'typed'
+steps:
+  - id: review
+    name: Review
+    prompt: Review the change
+    outputs:
+      - name: approved
+        type: bool
+      - name: files
+        type: array
+      - name: findings
+        type: object
+        schema:
+          type: object
+          required: [severity]
+          properties:
+            severity:
+              enum: [low, high]
+"#;
+        let workflow = Workflow::parse(yaml).expect("parse");
+        let outputs = &workflow.steps[0].outputs;
+
+        assert_eq!(outputs[0].output_type, OutputType::Bool);
+        assert_eq!(outputs[1].output_type, OutputType::List);
+        assert!(outputs[2].schema.is_some());
+    }
+
+    #[test]
+    fn test_validation_file_output_with_schema() {
+        let yaml = r#"
+version: '1.0'
+name: 'files'
+steps:
+  - id: plan
+    name: Plan
+    prompt: Plan it
+    outputs:
+      - name: plan_file
+        type: file
+        filename: plan.md
+        schema:
+          type: string
+"#;
+        let result = Workflow::parse(yaml);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_validation_empty_name() {
         let yaml = r#"
@@ -4,6 +4,7 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use crate::cost::CostEstimate;
+use crate::pricing::PricingCatalog;
 use crate::report::{Metrics, StepMetrics, TokenUsageEntry};
 
 /// Trait for collecting metrics.
@@ -11,9 +12,20 @@ pub trait MetricsCollector: Send + Sync {
     /// Record timing for a step.
     fn record_timing(&self, step_id: &str, duration: Duration);
 
-    /// Record token usage.
+    /// Record token usage, estimating its cost from model pricing.
     fn record_tokens(&self, model: &str, input: u64, output: u64);
 
+    /// Record token usage as reported by an agent, with the cost it reported
+    /// if any. Falls back to an estimate like [`Self::record_tokens`].
+    fn record_reported_usage(&self, model: &str, input: u64, output: u64, cost_usd: Option<f64>);
+
+    /// Record usage served from the response cache. It is reported
+    /// separately and adds nothing to the job's cost.
+    fn record_cache_hit(&self, model: &str, input: u64, output: u64);
+
+    /// Record a step's full metrics (tokens, cost, turns, tool calls).
+    fn record_step(&self, step: StepMetrics);
+
     /// Start a new job.
     fn start_job(&self, job_id: &str, spec_id: &str);
 
@@ -28,6 +40,7 @@ pub trait MetricsCollector: Send + Sync {
 #[derive(Debug)]
 pub struct DefaultMetricsCollector {
     inner: Arc<Mutex<CollectorState>>,
+    pricing: PricingCatalog,
 }
 
 #[derive(Debug, Default)]
@@ -38,6 +51,8 @@ struct CollectorState {
     step_metrics: Vec<StepMetrics>,
     token_usage: Vec<TokenUsageEntry>,
     cost: CostEstimate,
+    cache_hits: Vec<TokenUsageEntry>,
+    cache_savings_usd: f64,
 }
 
 impl Default for DefaultMetricsCollector {
@@ -52,23 +67,32 @@ impl DefaultMetricsCollector {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(Mutex::new(CollectorState::default())),
+            pricing: PricingCatalog::new(),
         }
     }
+
+    /// Estimate costs with `pricing` instead of the bundled prices.
+    #[must_use]
+    pub fn with_pricing(mut self, pricing: PricingCatalog) -> Self {
+        self.pricing = pricing;
+        self
+    }
 }
 
 impl MetricsCollector for DefaultMetricsCollector {
     fn record_timing(&self, step_id: &str, duration: Duration) {
         if let Ok(mut state) = self.inner.lock() {
-            state.step_metrics.push(StepMetrics {
-                step_id: step_id.to_string(),
-                duration_ms: duration.as_millis() as u64,
-                model: None,
-                tokens: None,
-            });
+            state
+                .step_metrics
+                .push(StepMetrics::new(step_id, duration.as_millis() as u64));
         }
     }
 
     fn record_tokens(&self, model: &str, input: u64, output: u64) {
+        self.record_reported_usage(model, input, output, None);
+    }
+
+    fn record_reported_usage(&self, model: &str, input: u64, output: u64, cost_usd: Option<f64>) {
         if let Ok(mut state) = self.inner.lock() {
             // Add token usage
             state.token_usage.push(TokenUsageEntry {
@@ -77,12 +101,29 @@ impl MetricsCollector for DefaultMetricsCollector {
                 output_tokens: output,
             });
 
-            // Calculate and add cost
-            let cost = CostEstimate::from_tokens(model, input, output);
+            // Prefer the reported cost over an estimate
+            let cost = cost_usd.unwrap_or_else(|| self.pricing.cost(model, input, output));
             state.cost.add(model, cost);
         }
     }
 
+    fn record_cache_hit(&self, model: &str, input: u64, output: u64) {
+        if let Ok(mut state) = self.inner.lock() {
+            state.cache_hits.push(TokenUsageEntry {
+                model: model.to_string(),
+                input_tokens: input,
+                output_tokens: output,
+            });
+            state.cache_savings_usd += self.pricing.cost(model, input, output);
+        }
+    }
+
+    fn record_step(&self, step: StepMetrics) {
+        if let Ok(mut state) = self.inner.lock() {
+            state.step_metrics.push(step);
+        }
+    }
+
     fn start_job(&self, job_id: &str, spec_id: &str) {
         if let Ok(mut state) = self.inner.lock() {
             state.job_id = job_id.to_string();
@@ -91,6 +132,8 @@ impl MetricsCollector for DefaultMetricsCollector {
             state.step_metrics.clear();
             state.token_usage.clear();
             state.cost = CostEstimate::default();
+            state.cache_hits.clear();
+            state.cache_savings_usd = 0.0;
         }
     }
 
@@ -107,6 +150,8 @@ impl MetricsCollector for DefaultMetricsCollector {
                 total_time_ms,
                 token_usage: state.token_usage.clone(),
                 cost: state.cost.clone(),
+                cache_hits: state.cache_hits.clone(),
+                cache_savings_usd: state.cache_savings_usd,
                 step_metrics: state.step_metrics.clone(),
                 retry_count: 0,
                 success,
@@ -183,6 +228,39 @@ mod tests {
         assert!(snapshot.cost.total_usd > 0.0);
     }
 
+    #[test]
+    fn test_collector_prefers_reported_cost() {
+        let collector = DefaultMetricsCollector::new();
+        collector.start_job("job-123", "spec-abc");
+        collector.record_reported_usage("claude-sonnet-4", 1000, 500, Some(0.25));
+
+        let mut step = StepMetrics::new("implement", 1200);
+        step.tool_calls.push(crate::report::ToolCallEntry {
+            name: "Edit".to_string(),
+            target: Some("src/lib.rs".to_string()),
+            failed: false,
+        });
+        collector.record_step(step);
+
+        let snapshot = collector.snapshot();
+        assert!((snapshot.cost.total_usd - 0.25).abs() < f64::EPSILON);
+        assert_eq!(snapshot.step_metrics[0].tool_calls[0].name, "Edit");
+    }
+
+    #[test]
+    fn test_collector_reports_cache_hits_separately() {
+        let collector = DefaultMetricsCollector::new();
+        collector.start_job("job-123", "spec-abc");
+        collector.record_tokens("gpt-4o", 1000, 500);
+        collector.record_cache_hit("gpt-4o", 1000, 500);
+
+        let snapshot = collector.snapshot();
+        assert_eq!(snapshot.total_tokens(), 1500);
+        assert_eq!(snapshot.cache_hits.len(), 1);
+        assert!((snapshot.cache_savings_usd - snapshot.cost.total_usd).abs() < f64::EPSILON);
+        assert_eq!(snapshot.summary().cache_hits, 1);
+    }
+
     #[test]
     fn test_collector_finish_job() {
         let collector = DefaultMetricsCollector::new();
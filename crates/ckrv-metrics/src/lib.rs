@@ -5,13 +5,16 @@
 pub mod collector;
 pub mod cost;
 pub mod error;
+pub mod pricing;
 pub mod report;
 pub mod time;
 
 pub use collector::{DefaultMetricsCollector, MetricsCollector, StepTimer};
-pub use cost::{CostEstimate, ModelPricing};
+pub use cost::CostEstimate;
 pub use error::MetricsError;
+pub use pricing::{ModelPricing, PricingCatalog};
 pub use report::{
     FileMetricsStorage, Metrics, MetricsStorage, MetricsSummary, StepMetrics, TokenUsageEntry,
+    ToolCallEntry,
 };
 pub use time::{format_duration, format_ms, Stopwatch};
@@ -0,0 +1,430 @@
+//! Model pricing catalog.
+//!
+//! Every cost figure in the tool comes from a [`PricingCatalog`]. A catalog
+//! is built in layers, later ones overriding earlier ones:
+//!
+//! 1. the table bundled with the binary (`pricing.json` beside this file);
+//! 2. a cached `OpenRouter` `/models` response at [`OPENROUTER_CACHE`];
+//! 3. the project's own prices at [`PRICING_OVERRIDE`].
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::MetricsError;
+
+/// Project file with price overrides, relative to the project root.
+pub const PRICING_OVERRIDE: &str = ".chakravarti/pricing.json";
+
+/// Cached `OpenRouter` `/models` response, relative to the project root.
+pub const OPENROUTER_CACHE: &str = ".chakravarti/cache/openrouter-models.json";
+
+/// Prices per 1M input and output tokens for models missing from the catalog.
+pub const FALLBACK_PRICES: (f64, f64) = (1.0, 2.0);
+
+const BUNDLED: &str = include_str!("pricing.json");
+
+/// Pricing information for a model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Model identifier.
+    pub model: String,
+    /// Provider name.
+    #[serde(default)]
+    pub provider: String,
+    /// Cost per 1M input tokens in USD.
+    pub input_cost_per_million: f64,
+    /// Cost per 1M output tokens in USD.
+    pub output_cost_per_million: f64,
+    /// Context window size in tokens.
+    #[serde(default)]
+    pub context_window: u32,
+    /// Max output tokens.
+    #[serde(default)]
+    pub max_output: u32,
+    /// Whether the model accepts tool definitions. Assumed unless a pricing
+    /// file says otherwise.
+    #[serde(default = "default_supports_tools")]
+    pub supports_tools: bool,
+}
+
+const fn default_supports_tools() -> bool {
+    true
+}
+
+impl ModelPricing {
+    /// Calculate cost for given token counts.
+    #[must_use]
+    pub fn calculate_cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        let input_cost = (input_tokens as f64 / 1_000_000.0) * self.input_cost_per_million;
+        let output_cost = (output_tokens as f64 / 1_000_000.0) * self.output_cost_per_million;
+        input_cost + output_cost
+    }
+
+    /// Get average cost per 1K tokens (for estimation).
+    #[must_use]
+    pub fn avg_cost_per_1k(&self) -> f64 {
+        (self.input_cost_per_million + self.output_cost_per_million) / 2.0 / 1000.0
+    }
+}
+
+/// Pricing catalog with known model prices.
+#[derive(Debug, Clone)]
+pub struct PricingCatalog {
+    models: HashMap<String, ModelPricing>,
+}
+
+impl Default for PricingCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PricingCatalog {
+    /// Create a catalog with the bundled prices.
+    #[must_use]
+    pub fn new() -> Self {
+        // The bundled table is checked by the tests below
+        let bundled: Vec<ModelPricing> = serde_json::from_str(BUNDLED).unwrap_or_default();
+        let mut catalog = Self {
+            models: HashMap::new(),
+        };
+        catalog.extend(bundled);
+        catalog
+    }
+
+    /// Load the catalog for a project: the bundled prices, then the cached
+    /// `OpenRouter` model list, then the project's overrides.
+    ///
+    /// Missing files are skipped. Unreadable ones are skipped with a
+    /// warning, so a bad file never stops cost reporting.
+    #[must_use]
+    pub fn load(root: &Path) -> Self {
+        let mut catalog = Self::new();
+        catalog.extend(read_layer(&root.join(OPENROUTER_CACHE), parse_openrouter_models));
+        catalog.extend(read_layer(&root.join(PRICING_OVERRIDE), |body| {
+            serde_json::from_str(body).map_err(|e| MetricsError::LoadFailed(e.to_string()))
+        }));
+        catalog
+    }
+
+    /// Add or replace prices.
+    pub fn extend(&mut self, models: impl IntoIterator<Item = ModelPricing>) {
+        self.models
+            .extend(models.into_iter().map(|p| (p.model.clone(), p)));
+    }
+
+    /// Cost in USD of a request, using [`FALLBACK_PRICES`] for unknown
+    /// models.
+    #[must_use]
+    pub fn cost(&self, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+        self.get(model).map_or_else(
+            || fallback(model).calculate_cost(input_tokens, output_tokens),
+            |p| p.calculate_cost(input_tokens, output_tokens),
+        )
+    }
+
+    /// Average cost per 1K tokens, for estimates before a request is made.
+    #[must_use]
+    pub fn cost_per_1k(&self, model: &str) -> f64 {
+        self.get(model)
+            .map_or_else(|| fallback(model).avg_cost_per_1k(), ModelPricing::avg_cost_per_1k)
+    }
+
+    /// Get pricing for a model. A dated snapshot such as
+    /// `gpt-4o-mini-2024-07-18` is priced as its undated name; other
+    /// unknown variants have no price.
+    #[must_use]
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        self.models
+            .get(model)
+            .or_else(|| self.models.get(undated(model)?))
+    }
+
+    /// Get all available models.
+    #[must_use]
+    pub fn models(&self) -> Vec<&str> {
+        self.models.keys().map(String::as_str).collect()
+    }
+
+    /// Get models by provider.
+    #[must_use]
+    pub fn by_provider(&self, provider: &str) -> Vec<&ModelPricing> {
+        self.models
+            .values()
+            .filter(|p| p.provider == provider)
+            .collect()
+    }
+
+    /// Get the cheapest model for a provider.
+    #[must_use]
+    pub fn cheapest(&self, provider: Option<&str>) -> Option<&ModelPricing> {
+        self.models
+            .values()
+            .filter(|p| provider.map_or(true, |prov| p.provider == prov))
+            .min_by(|a, b| {
+                a.avg_cost_per_1k()
+                    .partial_cmp(&b.avg_cost_per_1k())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Get the most capable model for a provider (by context window).
+    #[must_use]
+    pub fn most_capable(&self, provider: Option<&str>) -> Option<&ModelPricing> {
+        self.models
+            .values()
+            .filter(|p| provider.map_or(true, |prov| p.provider == prov))
+            .max_by_key(|p| p.context_window)
+    }
+}
+
+/// Prices from one layer's file, or none if it is missing or malformed.
+fn read_layer(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<Vec<ModelPricing>, MetricsError>,
+) -> Vec<ModelPricing> {
+    let Ok(body) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    parse(&body).unwrap_or_else(|e| {
+        tracing::warn!(path = %path.display(), error = %e, "Ignoring pricing file");
+        Vec::new()
+    })
+}
+
+/// `model` without a trailing `-YYYYMMDD` or `-YYYY-MM-DD` date, if it has one.
+fn undated(model: &str) -> Option<&str> {
+    let digits = |s: &str, n: usize| s.len() == n && s.bytes().all(|b| b.is_ascii_digit());
+    let (base, date) = model.rsplit_once('-')?;
+    if digits(date, 8) {
+        return Some(base);
+    }
+    let mut parts = model.rsplitn(4, '-');
+    let (day, month, year, base) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    (digits(year, 4) && digits(month, 2) && digits(day, 2)).then_some(base)
+}
+
+fn fallback(model: &str) -> ModelPricing {
+    ModelPricing {
+        model: model.to_string(),
+        provider: String::new(),
+        input_cost_per_million: FALLBACK_PRICES.0,
+        output_cost_per_million: FALLBACK_PRICES.1,
+        context_window: 0,
+        max_output: 0,
+        supports_tools: true,
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenRouterList {
+    data: Vec<OpenRouterEntry>,
+}
+
+#[derive(Deserialize)]
+struct OpenRouterEntry {
+    id: String,
+    #[serde(default)]
+    context_length: Option<u32>,
+    #[serde(default)]
+    pricing: Option<OpenRouterPricing>,
+    #[serde(default)]
+    top_provider: Option<OpenRouterTopProvider>,
+    #[serde(default)]
+    supported_parameters: Option<Vec<String>>,
+}
+
+/// Prices are decimal strings in USD per token.
+#[derive(Deserialize)]
+struct OpenRouterPricing {
+    #[serde(default)]
+    prompt: Option<String>,
+    #[serde(default)]
+    completion: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenRouterTopProvider {
+    #[serde(default)]
+    max_completion_tokens: Option<u32>,
+}
+
+/// Parse an `OpenRouter` `/models` response into pricing entries, skipping
+/// models without a published price.
+///
+/// # Errors
+///
+/// Returns an error if the body is not a model list.
+pub fn parse_openrouter_models(body: &str) -> Result<Vec<ModelPricing>, MetricsError> {
+    let list: OpenRouterList =
+        serde_json::from_str(body).map_err(|e| MetricsError::LoadFailed(e.to_string()))?;
+    let per_million = |price: Option<&String>| {
+        price
+            .and_then(|p| p.parse::<f64>().ok())
+            .filter(|p| *p >= 0.0)
+            .map(|p| p * 1_000_000.0)
+    };
+
+    Ok(list
+        .data
+        .into_iter()
+        .filter_map(|entry| {
+            let pricing = entry.pricing?;
+            let context_window = entry.context_length.unwrap_or(0);
+            Some(ModelPricing {
+                input_cost_per_million: per_million(pricing.prompt.as_ref())?,
+                output_cost_per_million: per_million(pricing.completion.as_ref())?,
+                max_output: entry
+                    .top_provider
+                    .and_then(|p| p.max_completion_tokens)
+                    .unwrap_or(context_window),
+                context_window,
+                supports_tools: entry
+                    .supported_parameters
+                    .map_or(true, |params| params.iter().any(|p| p == "tools")),
+                provider: "openrouter".to_string(),
+                model: entry.id,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_pricing_calculate_cost() {
+        let pricing = ModelPricing {
+            model: "test".to_string(),
+            provider: "test".to_string(),
+            input_cost_per_million: 1.0,
+            output_cost_per_million: 2.0,
+            context_window: 8000,
+            max_output: 4000,
+            supports_tools: true,
+        };
+
+        // 1M input + 1M output = $1 + $2 = $3
+        let cost = pricing.calculate_cost(1_000_000, 1_000_000);
+        assert!((cost - 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_catalog_get() {
+        let catalog = PricingCatalog::new();
+
+        let gpt4o = catalog.get("gpt-4o").expect("should find gpt-4o");
+        assert_eq!(gpt4o.provider, "openai");
+        assert!(gpt4o.input_cost_per_million > 0.0);
+    }
+
+    #[test]
+    fn test_catalog_dated_match() {
+        let catalog = PricingCatalog::new();
+
+        // Dated snapshots are priced as the undated model
+        let matched = catalog.get("gpt-4o-mini-2024-07-18").expect("dated gpt-4o-mini");
+        assert_eq!(matched.model, "gpt-4o-mini");
+        let matched = catalog.get("claude-haiku-4-5-20251001").expect("dated haiku");
+        assert_eq!(matched.model, "claude-haiku-4-5");
+
+        // Other variants are not priced as a different model
+        assert!(catalog.get("gpt-4o-mini-audio-preview").is_none());
+        assert!(catalog.get("gpt-4o-2024").is_none());
+    }
+
+    #[test]
+    fn test_catalog_cheapest() {
+        let catalog = PricingCatalog::new();
+
+        let cheapest = catalog
+            .cheapest(Some("openai"))
+            .expect("should find cheapest");
+        assert_eq!(cheapest.model, "gpt-4o-mini");
+    }
+
+    #[test]
+    fn test_catalog_by_provider() {
+        let catalog = PricingCatalog::new();
+
+        let anthropic_models = catalog.by_provider("anthropic");
+        assert!(!anthropic_models.is_empty());
+        assert!(anthropic_models.iter().all(|p| p.provider == "anthropic"));
+    }
+
+    #[test]
+    fn test_avg_cost_per_1k() {
+        let pricing = ModelPricing {
+            model: "test".to_string(),
+            provider: "test".to_string(),
+            input_cost_per_million: 2.0,
+            output_cost_per_million: 4.0,
+            context_window: 8000,
+            max_output: 4000,
+            supports_tools: true,
+        };
+
+        // (2 + 4) / 2 / 1000 = 0.003
+        assert!((pricing.avg_cost_per_1k() - 0.003).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_bundled_table_covers_default_and_planner_models() {
+        let catalog = PricingCatalog::new();
+        for model in [
+            "claude-sonnet-4-5",
+            "claude-haiku-4-5",
+            "gpt-4o",
+            "minimax/minimax-m2.1",
+            "z-ai/glm-4.7",
+        ] {
+            assert!(catalog.get(model).is_some(), "{model} missing");
+        }
+        assert_eq!(catalog.get("gpt-4o-2024-08-06").expect("gpt-4o").model, "gpt-4o");
+        assert!((catalog.cost("unknown-model", 1_000_000, 1_000_000) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_load_layers_cache_and_overrides() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = dir.path().join(OPENROUTER_CACHE);
+        std::fs::create_dir_all(cache.parent().expect("parent")).expect("mkdir");
+        std::fs::write(
+            &cache,
+            r#"{"data": [
+                {"id": "moonshotai/kimi-k2", "context_length": 131072,
+                 "pricing": {"prompt": "0.0000006", "completion": "0.0000025"},
+                 "top_provider": {"max_completion_tokens": 16384},
+                 "supported_parameters": ["temperature", "tools"]},
+                {"id": "openrouter/auto", "pricing": {"prompt": "-1", "completion": "-1"}},
+                {"id": "z-ai/glm-4.7",
+                 "pricing": {"prompt": "0.0000005", "completion": "0.000002"},
+                 "supported_parameters": ["temperature"]}
+            ]}"#,
+        )
+        .expect("write cache");
+        std::fs::write(
+            dir.path().join(PRICING_OVERRIDE),
+            r#"[{"model": "gpt-4o", "input_cost_per_million": 1.0, "output_cost_per_million": 1.0}]"#,
+        )
+        .expect("write overrides");
+
+        let catalog = PricingCatalog::load(dir.path());
+        let kimi = catalog.get("moonshotai/kimi-k2").expect("kimi");
+        assert!((kimi.input_cost_per_million - 0.6).abs() < 1e-9);
+        assert!((kimi.output_cost_per_million - 2.5).abs() < 1e-9);
+        assert_eq!((kimi.context_window, kimi.max_output), (131_072, 16384));
+        assert!(kimi.supports_tools);
+        assert!(!catalog.get("z-ai/glm-4.7").expect("glm").supports_tools);
+        assert!(catalog.get("openrouter/auto").is_none());
+        assert!((catalog.get("z-ai/glm-4.7").expect("glm").input_cost_per_million - 0.5).abs() < 1e-9);
+        assert!((catalog.cost("gpt-4o", 1_000_000, 1_000_000) - 2.0).abs() < 1e-9);
+
+        std::fs::write(dir.path().join(PRICING_OVERRIDE), "not json").expect("write");
+        let catalog = PricingCatalog::load(dir.path());
+        assert!((catalog.cost("gpt-4o", 1_000_000, 0) - 2.5).abs() < 1e-9);
+    }
+}
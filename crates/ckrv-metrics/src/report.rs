@@ -19,6 +19,12 @@ pub struct Metrics {
     pub token_usage: Vec<TokenUsageEntry>,
     /// Cost estimate.
     pub cost: CostEstimate,
+    /// Token usage served from the response cache, not billed.
+    #[serde(default)]
+    pub cache_hits: Vec<TokenUsageEntry>,
+    /// Estimated cost avoided by cache hits, in USD.
+    #[serde(default)]
+    pub cache_savings_usd: f64,
     /// Per-step metrics.
     pub step_metrics: Vec<StepMetrics>,
     /// Number of retry attempts.
@@ -49,12 +55,7 @@ impl Metrics {
 
     /// Add step metrics.
     pub fn add_step(&mut self, step_id: impl Into<String>, duration_ms: u64) {
-        self.step_metrics.push(StepMetrics {
-            step_id: step_id.into(),
-            duration_ms,
-            model: None,
-            tokens: None,
-        });
+        self.step_metrics.push(StepMetrics::new(step_id, duration_ms));
     }
 
     /// Get total token count.
@@ -75,6 +76,8 @@ impl Metrics {
             duration_secs: self.total_time_ms as f64 / 1000.0,
             total_tokens: self.total_tokens(),
             estimated_cost_usd: self.cost.total_usd,
+            cache_hits: self.cache_hits.len(),
+            cache_savings_usd: self.cache_savings_usd,
             steps: self.step_metrics.len(),
             retries: self.retry_count,
             success: self.success,
@@ -95,6 +98,12 @@ pub struct MetricsSummary {
     pub total_tokens: u64,
     /// Estimated cost in USD.
     pub estimated_cost_usd: f64,
+    /// Number of responses served from the cache.
+    #[serde(default)]
+    pub cache_hits: usize,
+    /// Estimated cost avoided by cache hits, in USD.
+    #[serde(default)]
+    pub cache_savings_usd: f64,
     /// Number of steps executed.
     pub steps: usize,
     /// Number of retries.
@@ -133,6 +142,44 @@ pub struct StepMetrics {
     pub model: Option<String>,
     /// Tokens used (if applicable).
     pub tokens: Option<TokenUsageEntry>,
+    /// Cost the agent reported for the step, in USD.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Number of agent turns, if reported.
+    #[serde(default)]
+    pub turns: Option<u64>,
+    /// Tools the agent called, in order.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallEntry>,
+}
+
+impl StepMetrics {
+    /// Create metrics for a step with only its duration.
+    #[must_use]
+    pub fn new(step_id: impl Into<String>, duration_ms: u64) -> Self {
+        Self {
+            step_id: step_id.into(),
+            duration_ms,
+            model: None,
+            tokens: None,
+            cost_usd: None,
+            turns: None,
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+/// A tool call made by an agent during a step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallEntry {
+    /// Tool name.
+    pub name: String,
+    /// What the tool acted on (file path, command, ...), if known.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Whether the tool reported an error.
+    #[serde(default)]
+    pub failed: bool,
 }
 
 /// Trait for metrics storage.
@@ -1,5 +1,7 @@
 //! Token usage accounting.
 
+use ckrv_core::AgentUsage;
+use ckrv_metrics::PricingCatalog;
 use serde::{Deserialize, Serialize};
 
 /// Token usage for a model request.
@@ -32,6 +34,24 @@ impl TokenUsage {
     }
 }
 
+impl From<&AgentUsage> for TokenUsage {
+    /// Usage reported by an agent CLI. A total without a breakdown counts as
+    /// prompt tokens.
+    fn from(usage: &AgentUsage) -> Self {
+        let count = |tokens: Option<u64>| u32::try_from(tokens.unwrap_or(0)).unwrap_or(u32::MAX);
+        let prompt = match (usage.input_tokens, usage.output_tokens) {
+            (None, None) => count(usage.total_tokens),
+            (input, _) => count(input),
+        };
+        let completion = count(usage.output_tokens);
+        Self {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt.saturating_add(completion),
+        }
+    }
+}
+
 /// Accumulated usage across multiple requests.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageAccumulator {
@@ -61,20 +81,19 @@ impl UsageAccumulator {
             .add(usage);
     }
 
-    /// Estimate cost in USD (rough estimates).
+    /// Estimate cost in USD from each model's prices in `pricing`.
     #[must_use]
-    pub fn estimate_cost(&self) -> f64 {
-        // Very rough cost estimates per 1M tokens
-        // GPT-4o: ~$5/1M input, ~$15/1M output
-        // GPT-4o-mini: ~$0.15/1M input, ~$0.60/1M output
-        let input_cost_per_m = 2.5; // Average
-        let output_cost_per_m = 7.5; // Average
-
-        let input_cost = (f64::from(self.total.prompt_tokens) / 1_000_000.0) * input_cost_per_m;
-        let output_cost =
-            (f64::from(self.total.completion_tokens) / 1_000_000.0) * output_cost_per_m;
-
-        input_cost + output_cost
+    pub fn estimate_cost(&self, pricing: &PricingCatalog) -> f64 {
+        self.by_model
+            .iter()
+            .map(|(model, usage)| {
+                pricing.cost(
+                    model,
+                    usage.prompt_tokens.into(),
+                    usage.completion_tokens.into(),
+                )
+            })
+            .sum()
     }
 }
 
@@ -101,6 +120,24 @@ mod tests {
         assert_eq!(usage1.total_tokens, 450);
     }
 
+    #[test]
+    fn test_token_usage_from_agent_usage() {
+        let reported = AgentUsage {
+            input_tokens: Some(910),
+            output_tokens: Some(250),
+            ..AgentUsage::default()
+        };
+        let usage = TokenUsage::from(&reported);
+        assert_eq!(usage.prompt_tokens, 910);
+        assert_eq!(usage.total_tokens, 1160);
+
+        let total_only = AgentUsage {
+            total_tokens: Some(12_345),
+            ..AgentUsage::default()
+        };
+        assert_eq!(TokenUsage::from(&total_only).total_tokens, 12_345);
+    }
+
     #[test]
     fn test_accumulator_record() {
         let mut acc = UsageAccumulator::new();
@@ -116,8 +153,10 @@ mod tests {
     fn test_estimate_cost() {
         let mut acc = UsageAccumulator::new();
         acc.record("gpt-4o", &TokenUsage::new(1_000_000, 500_000));
+        acc.record("gpt-4o-mini", &TokenUsage::new(1_000_000, 0));
 
-        let cost = acc.estimate_cost();
-        assert!(cost > 0.0);
+        // $2.50 + $5.00 for gpt-4o, $0.15 for gpt-4o-mini
+        let cost = acc.estimate_cost(&PricingCatalog::new());
+        assert!((cost - 7.65).abs() < 1e-9);
     }
 }
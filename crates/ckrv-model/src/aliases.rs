@@ -0,0 +1,177 @@
+//! Equivalent model names across providers.
+//!
+//! The same model is published under different names: Anthropic's
+//! `claude-sonnet-4-5` is `anthropic/claude-sonnet-4.5` on `OpenRouter`. The
+//! router uses this map to fail over between providers without sending a
+//! model name a provider doesn't know.
+//!
+//! Built-in groups name models per provider kind, so they apply to a
+//! provider whatever it is called in `config.json`. Groups from
+//! `model_aliases` name models per configured provider.
+
+use std::collections::BTreeMap;
+
+use ckrv_core::ProviderKind;
+
+/// Built-in groups: alias, then (provider kind, model) pairs.
+const BUILTIN: &[(&str, &[(ProviderKind, &str)])] = &[
+    (
+        "claude-sonnet-4-5",
+        &[
+            (ProviderKind::Anthropic, "claude-sonnet-4-5"),
+            (ProviderKind::OpenRouter, "anthropic/claude-sonnet-4.5"),
+        ],
+    ),
+    (
+        "claude-haiku-4-5",
+        &[
+            (ProviderKind::Anthropic, "claude-haiku-4-5"),
+            (ProviderKind::OpenRouter, "anthropic/claude-haiku-4.5"),
+        ],
+    ),
+    (
+        "gpt-4o",
+        &[(ProviderKind::OpenAi, "gpt-4o"), (ProviderKind::OpenRouter, "openai/gpt-4o")],
+    ),
+    (
+        "gpt-4o-mini",
+        &[
+            (ProviderKind::OpenAi, "gpt-4o-mini"),
+            (ProviderKind::OpenRouter, "openai/gpt-4o-mini"),
+        ],
+    ),
+];
+
+/// Groups of equivalent models, keyed by alias.
+#[derive(Debug, Clone)]
+pub struct ModelAliases {
+    /// Built-in groups, by provider kind.
+    builtin: BTreeMap<String, Vec<(ProviderKind, String)>>,
+    /// Configured groups, by provider name.
+    groups: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for ModelAliases {
+    fn default() -> Self {
+        let builtin = BUILTIN
+            .iter()
+            .map(|(alias, models)| {
+                let models = models
+                    .iter()
+                    .map(|(kind, model)| (*kind, (*model).to_string()))
+                    .collect();
+                ((*alias).to_string(), models)
+            })
+            .collect();
+        Self {
+            builtin,
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+impl ModelAliases {
+    /// Add groups, e.g. from `model_aliases` in `config.json`. Entries for
+    /// an existing alias are merged into it; a configured provider's entry
+    /// takes precedence over the built-in name for its kind.
+    #[must_use]
+    pub fn with_groups(mut self, groups: &BTreeMap<String, BTreeMap<String, String>>) -> Self {
+        for (alias, models) in groups {
+            self.groups
+                .entry(alias.clone())
+                .or_default()
+                .extend(models.iter().map(|(p, m)| (p.clone(), m.clone())));
+        }
+        self
+    }
+
+    /// The name the provider called `provider`, of kind `kind`, uses for
+    /// `model`, if `model` is an alias or any provider's name in a group
+    /// that has an entry for that provider or its kind.
+    #[must_use]
+    pub fn resolve(&self, model: &str, provider: &str, kind: Option<ProviderKind>) -> Option<&str> {
+        let configured = self.groups.keys().filter(|a| !self.builtin.contains_key(*a));
+        self.builtin
+            .keys()
+            .chain(configured)
+            .filter(|alias| *alias == model || self.names(alias).any(|m| m == model))
+            .find_map(|alias| {
+                let by_name = self.groups.get(alias).and_then(|models| models.get(provider));
+                by_name.or_else(|| {
+                    let kind = kind?;
+                    self.builtin
+                        .get(alias)?
+                        .iter()
+                        .find_map(|(k, m)| (*k == kind).then_some(m))
+                })
+            })
+            .map(String::as_str)
+    }
+
+    /// Every model name in the group `alias`.
+    fn names<'a>(&'a self, alias: &str) -> impl Iterator<Item = &'a String> {
+        let builtin = self.builtin.get(alias).into_iter().flatten().map(|(_, m)| m);
+        let configured = self.groups.get(alias).into_iter().flat_map(BTreeMap::values);
+        builtin.chain(configured)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_across_providers() {
+        let aliases = ModelAliases::default();
+        assert_eq!(
+            aliases.resolve("claude-sonnet-4-5", "openrouter", Some(ProviderKind::OpenRouter)),
+            Some("anthropic/claude-sonnet-4.5")
+        );
+        assert_eq!(
+            aliases.resolve("anthropic/claude-sonnet-4.5", "anthropic", Some(ProviderKind::Anthropic)),
+            Some("claude-sonnet-4-5")
+        );
+        assert_eq!(aliases.resolve("gpt-4o", "anthropic", Some(ProviderKind::Anthropic)), None);
+        assert_eq!(aliases.resolve("unknown", "openai", Some(ProviderKind::OpenAi)), None);
+
+        let mut groups = BTreeMap::new();
+        groups.insert(
+            "coder".to_string(),
+            BTreeMap::from([
+                ("ollama".to_string(), "qwen2.5-coder:14b".to_string()),
+                ("openrouter".to_string(), "qwen/qwen-2.5-coder-32b-instruct".to_string()),
+            ]),
+        );
+        let aliases = aliases.with_groups(&groups);
+        assert_eq!(
+            aliases.resolve("qwen2.5-coder:14b", "openrouter", Some(ProviderKind::OpenRouter)),
+            Some("qwen/qwen-2.5-coder-32b-instruct")
+        );
+        assert_eq!(aliases.resolve("coder", "ollama", None), Some("qwen2.5-coder:14b"));
+    }
+
+    #[test]
+    fn test_builtin_groups_follow_provider_kind() {
+        let aliases = ModelAliases::default();
+        assert_eq!(
+            aliases.resolve("claude-sonnet-4-5", "gateway", Some(ProviderKind::OpenRouter)),
+            Some("anthropic/claude-sonnet-4.5")
+        );
+        assert_eq!(aliases.resolve("claude-sonnet-4-5", "openrouter", None), None);
+
+        // A configured name for the provider wins over its kind's
+        let groups = BTreeMap::from([(
+            "claude-sonnet-4-5".to_string(),
+            BTreeMap::from([("gateway".to_string(), "sonnet-latest".to_string())]),
+        )]);
+        let aliases = aliases.with_groups(&groups);
+        assert_eq!(
+            aliases.resolve("anthropic/claude-sonnet-4.5", "gateway", Some(ProviderKind::OpenRouter)),
+            Some("sonnet-latest")
+        );
+        assert_eq!(
+            aliases.resolve("sonnet-latest", "anthropic", Some(ProviderKind::Anthropic)),
+            Some("claude-sonnet-4-5")
+        );
+    }
+}
@@ -1,18 +1,26 @@
 //! Anthropic Claude model provider implementation.
 
 use async_trait::async_trait;
+use ckrv_core::ProviderKind;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    provider::{CompletionRequest, CompletionResponse, ModelProvider},
+    provider::{
+        CompletionRequest, CompletionResponse, CompletionStream, Message, ModelProvider, ToolCall,
+        ToolChoice, ToolDefinition,
+    },
+    sse::{self, SseEvent, StreamState},
     ModelError, TokenUsage,
 };
 
 /// Anthropic API provider.
 pub struct AnthropicProvider {
     client: reqwest::Client,
+    name: String,
     api_key: String,
     base_url: String,
+    headers: Vec<(String, String)>,
+    models: Vec<String>,
 }
 
 impl AnthropicProvider {
@@ -27,8 +35,11 @@ impl AnthropicProvider {
 
         Ok(Self {
             client: reqwest::Client::new(),
+            name: "anthropic".to_string(),
             api_key,
             base_url: "https://api.anthropic.com/v1".to_string(),
+            headers: Vec::new(),
+            models: Vec::new(),
         })
     }
 
@@ -37,10 +48,34 @@ impl AnthropicProvider {
     pub fn with_endpoint(api_key: String, base_url: String) -> Self {
         Self {
             client: reqwest::Client::new(),
+            name: "anthropic".to_string(),
             api_key,
             base_url,
+            headers: Vec::new(),
+            models: Vec::new(),
         }
     }
+
+    /// Set the name the router knows this provider by.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Add headers sent with every request.
+    #[must_use]
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Set the models this provider serves.
+    #[must_use]
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = models;
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -52,12 +87,51 @@ struct AnthropicRequest {
     system: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: AnthropicMessageContent,
+}
+
+/// Plain text, or content blocks when the turn carries tool calls or
+/// results.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum AnthropicMessageContent {
+    Text(String),
+    Blocks(Vec<AnthropicBlock>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
 }
 
 #[derive(Deserialize)]
@@ -72,7 +146,14 @@ struct AnthropicResponse {
 struct AnthropicContent {
     #[serde(rename = "type")]
     content_type: String,
+    #[serde(default)]
     text: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    input: serde_json::Value,
 }
 
 #[derive(Deserialize)]
@@ -94,27 +175,75 @@ struct AnthropicErrorDetail {
 #[async_trait]
 impl ModelProvider for AnthropicProvider {
     fn name(&self) -> &str {
-        "anthropic"
+        &self.name
+    }
+
+    fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    fn kind(&self) -> Option<ProviderKind> {
+        Some(ProviderKind::Anthropic)
     }
 
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ModelError> {
-        // Extract system message if present
-        let mut system_message: Option<String> = None;
-        let messages: Vec<AnthropicMessage> = request
-            .messages
-            .into_iter()
-            .filter_map(|m| {
-                if m.role == "system" {
-                    system_message = Some(m.content);
-                    None
-                } else {
-                    Some(AnthropicMessage {
-                        role: m.role,
-                        content: m.content,
-                    })
-                }
-            })
-            .collect();
+        let body = self
+            .send(request, false)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+
+        let api_response: AnthropicResponse =
+            serde_json::from_str(&body).map_err(|e| ModelError::ParseError(e.to_string()))?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in api_response.content {
+            match block.content_type.as_str() {
+                "text" => content.push_str(&block.text),
+                "tool_use" => tool_calls.push(ToolCall {
+                    id: block.id,
+                    name: block.name,
+                    arguments: block.input,
+                }),
+                _ => {}
+            }
+        }
+
+        let total_tokens = api_response.usage.input_tokens + api_response.usage.output_tokens;
+
+        Ok(CompletionResponse {
+            content,
+            usage: TokenUsage {
+                prompt_tokens: api_response.usage.input_tokens,
+                completion_tokens: api_response.usage.output_tokens,
+                total_tokens,
+            },
+            model: api_response.model,
+            finish_reason: api_response.stop_reason,
+            tool_calls,
+            cached: false,
+        })
+    }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ModelError> {
+        let response = self.send(request, true).await?;
+        Ok(sse::completion_stream(response, decode_event))
+    }
+}
+
+impl AnthropicProvider {
+    /// Send a Messages API request, turning error statuses into errors.
+    async fn send(
+        &self,
+        request: CompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, ModelError> {
+        let (system_message, messages) = to_anthropic_messages(request.messages);
 
         let api_request = AnthropicRequest {
             model: request.model,
@@ -122,62 +251,160 @@ impl ModelProvider for AnthropicProvider {
             max_tokens: request.max_tokens.unwrap_or(4096),
             system: system_message,
             temperature: request.temperature,
+            stream,
+            tools: request.tools.into_iter().map(to_anthropic_tool).collect(),
+            tool_choice: request.tool_choice.map(|choice| to_anthropic_tool_choice(&choice)),
         };
 
-        let response = self
+        let mut builder = self
             .client
             .post(format!("{}/messages", self.base_url))
             .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2024-01-01")
-            .header("Content-Type", "application/json")
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
             .json(&api_request)
             .send()
             .await
             .map_err(|e| ModelError::NetworkError(e.to_string()))?;
 
-        let status = response.status();
-        let body = response
-            .text()
-            .await
-            .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(ModelError::from_response(response, |body| {
+            serde_json::from_str::<AnthropicError>(body)
+                .ok()
+                .map(|e| e.error.message)
+        })
+        .await)
+    }
+}
 
-        if !status.is_success() {
-            if let Ok(error) = serde_json::from_str::<AnthropicError>(&body) {
-                return Err(ModelError::ApiError {
-                    status: status.as_u16(),
-                    message: error.error.message,
+/// Split out the system prompt and convert the rest to Messages API turns.
+///
+/// Tool calls become `tool_use` blocks on the assistant turn. Tool results
+/// become `tool_result` blocks on a user turn, with consecutive results
+/// sharing one turn as the API requires.
+fn to_anthropic_messages(messages: Vec<Message>) -> (Option<String>, Vec<AnthropicMessage>) {
+    let mut system_message: Option<String> = None;
+    let mut turns: Vec<AnthropicMessage> = Vec::new();
+    for m in messages {
+        match m.role.as_str() {
+            "system" => system_message = Some(m.content),
+            "tool" => {
+                let block = AnthropicBlock::ToolResult {
+                    tool_use_id: m.tool_call_id.unwrap_or_default(),
+                    content: m.content,
+                };
+                match turns.last_mut() {
+                    Some(AnthropicMessage {
+                        role,
+                        content: AnthropicMessageContent::Blocks(blocks),
+                    }) if role == "user" => blocks.push(block),
+                    _ => turns.push(AnthropicMessage {
+                        role: "user".to_string(),
+                        content: AnthropicMessageContent::Blocks(vec![block]),
+                    }),
+                }
+            }
+            _ if !m.tool_calls.is_empty() => {
+                let text = (!m.content.is_empty()).then_some(AnthropicBlock::Text { text: m.content });
+                let calls = m.tool_calls.into_iter().map(|call| AnthropicBlock::ToolUse {
+                    id: call.id,
+                    name: call.name,
+                    input: call.arguments,
+                });
+                turns.push(AnthropicMessage {
+                    role: m.role,
+                    content: AnthropicMessageContent::Blocks(text.into_iter().chain(calls).collect()),
                 });
             }
-            return Err(ModelError::ApiError {
-                status: status.as_u16(),
-                message: body,
-            });
+            _ => turns.push(AnthropicMessage {
+                role: m.role,
+                content: AnthropicMessageContent::Text(m.content),
+            }),
         }
+    }
+    (system_message, turns)
+}
 
-        let api_response: AnthropicResponse =
-            serde_json::from_str(&body).map_err(|e| ModelError::ParseError(e.to_string()))?;
+fn to_anthropic_tool(tool: ToolDefinition) -> AnthropicTool {
+    AnthropicTool {
+        name: tool.name,
+        description: tool.description,
+        input_schema: tool.parameters,
+    }
+}
 
-        let content = api_response
-            .content
-            .into_iter()
-            .filter(|c| c.content_type == "text")
-            .map(|c| c.text)
-            .collect::<Vec<_>>()
-            .join("");
+fn to_anthropic_tool_choice(choice: &ToolChoice) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => serde_json::json!({"type": "auto"}),
+        ToolChoice::None => serde_json::json!({"type": "none"}),
+        ToolChoice::Required => serde_json::json!({"type": "any"}),
+        ToolChoice::Tool(name) => serde_json::json!({"type": "tool", "name": name}),
+    }
+}
 
-        let total_tokens = api_response.usage.input_tokens + api_response.usage.output_tokens;
+/// Decode one Messages API stream event.
+fn decode_event(state: &mut StreamState, event: &SseEvent) -> Result<Option<String>, ModelError> {
+    let data: serde_json::Value =
+        serde_json::from_str(&event.data).map_err(|e| ModelError::ParseError(e.to_string()))?;
+    let tokens = |value: &serde_json::Value| value.as_u64().and_then(|n| u32::try_from(n).ok());
 
-        Ok(CompletionResponse {
-            content,
-            usage: TokenUsage {
-                prompt_tokens: api_response.usage.input_tokens,
-                completion_tokens: api_response.usage.output_tokens,
-                total_tokens,
-            },
-            model: api_response.model,
-            finish_reason: api_response.stop_reason,
-        })
+    match data["type"].as_str().unwrap_or_default() {
+        "message_start" => {
+            let message = &data["message"];
+            state.model = message["model"].as_str().unwrap_or_default().to_string();
+            if let Some(input) = tokens(&message["usage"]["input_tokens"]) {
+                state.prompt_tokens = input;
+            }
+        }
+        "content_block_start" if data["content_block"]["type"] == "tool_use" => {
+            let block = &data["content_block"];
+            let call = state.tool_call(data["index"].as_u64().unwrap_or_default());
+            call.id = block["id"].as_str().unwrap_or_default().to_string();
+            call.name = block["name"].as_str().unwrap_or_default().to_string();
+        }
+        "content_block_delta" if data["delta"]["type"] == "text_delta" => {
+            return Ok(data["delta"]["text"].as_str().map(str::to_string));
+        }
+        "content_block_delta" if data["delta"]["type"] == "input_json_delta" => {
+            let partial = data["delta"]["partial_json"].as_str().unwrap_or_default();
+            state
+                .tool_call(data["index"].as_u64().unwrap_or_default())
+                .arguments
+                .push_str(partial);
+        }
+        "message_delta" => {
+            if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                state.finish_reason = reason.to_string();
+            }
+            if let Some(output) = tokens(&data["usage"]["output_tokens"]) {
+                state.completion_tokens = output;
+            }
+        }
+        "message_stop" => state.done = true,
+        "error" => {
+            let error = &data["error"];
+            let message = error["message"].as_str().unwrap_or_default().to_string();
+            return Err(match error["type"].as_str() {
+                Some("rate_limit_error") => ModelError::RateLimited { retry_after: None },
+                Some("overloaded_error") => ModelError::ApiError {
+                    status: 529,
+                    message,
+                },
+                _ => ModelError::ApiError {
+                    status: 500,
+                    message,
+                },
+            });
+        }
+        _ => {}
     }
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -201,4 +428,115 @@ mod tests {
 
         assert_eq!(provider.name(), "anthropic");
     }
+
+    #[test]
+    fn test_tool_messages_map_to_blocks() {
+        let messages = vec![
+            Message::new("system", "Inspect the repo."),
+            Message::user("What does main do?"),
+            Message::assistant_tool_calls(
+                "Reading it.",
+                vec![
+                    ToolCall {
+                        id: "toolu_1".to_string(),
+                        name: "read_file".to_string(),
+                        arguments: serde_json::json!({"path": "src/main.rs"}),
+                    },
+                    ToolCall {
+                        id: "toolu_2".to_string(),
+                        name: "list_dir".to_string(),
+                        arguments: serde_json::json!({"path": "src"}),
+                    },
+                ],
+            ),
+            Message::tool_result("toolu_1", "fn main() {}"),
+            Message::tool_result("toolu_2", "main.rs"),
+        ];
+
+        let (system, turns) = to_anthropic_messages(messages);
+        assert_eq!(system.as_deref(), Some("Inspect the repo."));
+        assert_eq!(
+            serde_json::to_value(&turns).expect("serialize"),
+            serde_json::json!([
+                {"role": "user", "content": "What does main do?"},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Reading it."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "read_file",
+                     "input": {"path": "src/main.rs"}},
+                    {"type": "tool_use", "id": "toolu_2", "name": "list_dir",
+                     "input": {"path": "src"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "fn main() {}"},
+                    {"type": "tool_result", "tool_use_id": "toolu_2", "content": "main.rs"}
+                ]}
+            ])
+        );
+        assert_eq!(
+            to_anthropic_tool_choice(&ToolChoice::Tool("read_file".to_string())),
+            serde_json::json!({"type": "tool", "name": "read_file"})
+        );
+    }
+
+    #[test]
+    fn test_decode_streamed_tool_use() {
+        let events = [
+            r#"{"type":"message_start","message":{"model":"claude-sonnet-4-5","usage":{"input_tokens":40}}}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"read_file","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"path\": "}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"README.md\"}"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":12}}"#,
+        ];
+        let mut state = StreamState::default();
+        for data in events {
+            let event = SseEvent {
+                event: None,
+                data: data.to_string(),
+            };
+            decode_event(&mut state, &event).expect("decode");
+        }
+
+        let call = &state.tool_calls[&1];
+        assert_eq!((call.id.as_str(), call.name.as_str()), ("toolu_1", "read_file"));
+        assert_eq!(call.arguments, r#"{"path": "README.md"}"#);
+    }
+
+    #[test]
+    fn test_decode_stream_events() {
+        let events = [
+            r#"{"type":"message_start","message":{"model":"claude-sonnet-4-5","usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" world"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":15}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut state = StreamState::default();
+        let mut deltas = Vec::new();
+        for data in events {
+            let event = SseEvent {
+                event: None,
+                data: data.to_string(),
+            };
+            if let Some(delta) = decode_event(&mut state, &event).expect("decode") {
+                deltas.push(delta);
+            }
+        }
+
+        assert_eq!(deltas, vec!["Hello", " world"]);
+        assert_eq!(state.model, "claude-sonnet-4-5");
+        assert_eq!(state.finish_reason, "end_turn");
+        assert_eq!((state.prompt_tokens, state.completion_tokens), (25, 15));
+        assert!(state.done);
+
+        let overloaded = SseEvent {
+            event: Some("error".to_string()),
+            data: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+                .to_string(),
+        };
+        let err = decode_event(&mut state, &overloaded).expect_err("error event");
+        assert!(err.to_string().contains("529"));
+    }
 }
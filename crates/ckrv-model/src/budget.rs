@@ -0,0 +1,317 @@
+//! Prompt budgeting against model context windows.
+//!
+//! Prompt builders split their context into [`PromptSection`]s and let a
+//! [`PromptBudget`] decide which ones go in whole, which are shortened and
+//! which are left out. Token counts are estimated at about four characters
+//! per token, which is close enough for code and English text to keep a
+//! prompt inside the window without calling a tokenizer.
+
+use ckrv_metrics::PricingCatalog;
+
+/// Context window assumed for models the pricing catalog doesn't know.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+/// Average characters per token used by [`estimate_tokens`].
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Sections that would be cut below this many tokens are dropped instead.
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+/// Appended to a truncated section.
+const TRUNCATED_MARKER: &str = "\n[... truncated to fit the context window]";
+
+/// Estimate the number of tokens in `text`.
+#[must_use]
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// A piece of prompt context that can be shortened or left out.
+#[derive(Debug, Clone)]
+pub struct PromptSection {
+    /// Name shown when the section is trimmed, such as a file path or task ID.
+    pub label: String,
+    /// Full text.
+    pub text: String,
+    /// Shorter stand-in used when the full text doesn't fit.
+    pub summary: Option<String>,
+    /// Sections with higher priority claim the budget first.
+    pub priority: i64,
+}
+
+impl PromptSection {
+    /// Create a section with default priority and no summary.
+    pub fn new(label: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            text: text.into(),
+            summary: None,
+            priority: 0,
+        }
+    }
+
+    /// Use `summary` instead of cutting the text when it doesn't fit.
+    #[must_use]
+    pub fn with_summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Set the priority.
+    #[must_use]
+    pub const fn with_priority(mut self, priority: i64) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// How much of a section made it into the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// The full text.
+    Full,
+    /// The summary.
+    Summarized,
+    /// The start of the text.
+    Truncated,
+    /// Nothing.
+    Dropped,
+}
+
+/// A section after budgeting.
+#[derive(Debug, Clone)]
+pub struct FittedSection {
+    /// The section's label.
+    pub label: String,
+    /// Text to include; empty when dropped.
+    pub text: String,
+    /// How the section was fitted.
+    pub fit: Fit,
+}
+
+/// Sections after budgeting, in the order they were given.
+#[derive(Debug, Clone)]
+pub struct Fitted {
+    /// Model the sections were fitted for.
+    pub model: String,
+    /// The fitted sections.
+    pub sections: Vec<FittedSection>,
+}
+
+impl Fitted {
+    /// The text of every section that was kept, joined with `separator`.
+    #[must_use]
+    pub fn join(&self, separator: &str) -> String {
+        self.sections
+            .iter()
+            .filter(|s| s.fit != Fit::Dropped)
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Labels of the sections fitted as `fit`.
+    #[must_use]
+    pub fn labels(&self, fit: Fit) -> Vec<&str> {
+        self.sections
+            .iter()
+            .filter(|s| s.fit == fit)
+            .map(|s| s.label.as_str())
+            .collect()
+    }
+
+    /// A warning describing what was shortened or left out, or `None` when
+    /// everything fit.
+    #[must_use]
+    pub fn warning(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            (Fit::Summarized, "summarized"),
+            (Fit::Truncated, "truncated"),
+            (Fit::Dropped, "dropped"),
+        ]
+        .into_iter()
+        .filter_map(|(fit, verb)| {
+            let labels = self.labels(fit);
+            (!labels.is_empty()).then(|| format!("{verb} {}", labels.join(", ")))
+        })
+        .collect();
+        (!parts.is_empty()).then(|| {
+            format!(
+                "Prompt too large for {}'s context window; {}",
+                self.model,
+                parts.join("; ")
+            )
+        })
+    }
+}
+
+/// Tokens left for prompt context on a model.
+#[derive(Debug, Clone)]
+pub struct PromptBudget {
+    model: String,
+    available: usize,
+}
+
+impl PromptBudget {
+    /// A budget of `tokens` for `model`.
+    pub fn new(model: impl Into<String>, tokens: usize) -> Self {
+        Self {
+            model: model.into(),
+            available: tokens,
+        }
+    }
+
+    /// The budget for a prompt to `model`: its context window from
+    /// `pricing`, less room for the reply. The reply gets the model's output
+    /// limit, but never more than a quarter of the window.
+    #[must_use]
+    pub fn for_model(pricing: &PricingCatalog, model: &str) -> Self {
+        let (window, max_output) = pricing.get(model).map_or((0, 0), |p| {
+            (p.context_window as usize, p.max_output as usize)
+        });
+        let window = if window == 0 { DEFAULT_CONTEXT_WINDOW } else { window };
+        let reply = match max_output {
+            0 => window / 4,
+            limit => limit.min(window / 4),
+        };
+        Self::new(model, window - reply)
+    }
+
+    /// Tokens still available.
+    #[must_use]
+    pub const fn available(&self) -> usize {
+        self.available
+    }
+
+    /// Set aside room for text that is always sent, such as instructions.
+    pub fn reserve(&mut self, text: &str) {
+        self.available = self.available.saturating_sub(estimate_tokens(text));
+    }
+
+    /// Fit `sections` into the remaining budget.
+    ///
+    /// Sections claim the budget in priority order, highest first, with ties
+    /// going to the earlier section. Each is kept whole if it fits, else
+    /// replaced by its summary, else cut short, else dropped.
+    pub fn fit(&mut self, sections: &[PromptSection]) -> Fitted {
+        let mut order: Vec<usize> = (0..sections.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(sections[i].priority));
+
+        let mut fitted: Vec<Option<FittedSection>> = vec![None; sections.len()];
+        for i in order {
+            fitted[i] = Some(self.fit_one(&sections[i]));
+        }
+        Fitted {
+            model: self.model.clone(),
+            sections: fitted.into_iter().flatten().collect(),
+        }
+    }
+
+    fn fit_one(&mut self, section: &PromptSection) -> FittedSection {
+        let (text, fit) = self.take(section);
+        FittedSection {
+            label: section.label.clone(),
+            text,
+            fit,
+        }
+    }
+
+    fn take(&mut self, section: &PromptSection) -> (String, Fit) {
+        let tokens = estimate_tokens(&section.text);
+        if tokens <= self.available {
+            self.available -= tokens;
+            return (section.text.clone(), Fit::Full);
+        }
+        if let Some(ref summary) = section.summary {
+            let tokens = estimate_tokens(summary);
+            if tokens <= self.available {
+                self.available -= tokens;
+                return (summary.clone(), Fit::Summarized);
+            }
+        } else if self.available >= MIN_TRUNCATED_TOKENS {
+            let keep = (self.available - estimate_tokens(TRUNCATED_MARKER)) * CHARS_PER_TOKEN;
+            let mut text: String = section.text.chars().take(keep).collect();
+            if let Some(end) = text.rfind('\n') {
+                text.truncate(end);
+            }
+            text.push_str(TRUNCATED_MARKER);
+            self.available = self.available.saturating_sub(estimate_tokens(&text));
+            return (text, Fit::Truncated);
+        }
+        (String::new(), Fit::Dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_budget_for_model_leaves_room_for_the_reply() {
+        let pricing = PricingCatalog::new();
+        assert_eq!(PromptBudget::for_model(&pricing, "gpt-4o").available(), 128_000 - 16_384);
+        assert_eq!(
+            PromptBudget::for_model(&pricing, "claude-sonnet-4-5").available(),
+            200_000 - 50_000
+        );
+        assert_eq!(
+            PromptBudget::for_model(&pricing, "unknown-model").available(),
+            DEFAULT_CONTEXT_WINDOW - DEFAULT_CONTEXT_WINDOW / 4
+        );
+    }
+
+    #[test]
+    fn test_fit_keeps_everything_within_budget() {
+        let mut budget = PromptBudget::new("gpt-4o", 100);
+        let fitted = budget.fit(&[PromptSection::new("a", "x".repeat(40)), PromptSection::new("b", "y".repeat(40))]);
+        assert_eq!(fitted.join("|"), format!("{}|{}", "x".repeat(40), "y".repeat(40)));
+        assert!(fitted.warning().is_none());
+        assert_eq!(budget.available(), 80);
+    }
+
+    #[test]
+    fn test_fit_summarizes_lowest_priority_first() {
+        // Three 50-token tasks with 10-token summaries in a 120-token budget
+        let task = |id: &str, priority| {
+            PromptSection::new(id, "t".repeat(200))
+                .with_summary(id.repeat(20))
+                .with_priority(priority)
+        };
+        let mut budget = PromptBudget::new("gpt-4o", 120);
+        let fitted = budget.fit(&[task("T1", 0), task("T2", 1), task("T3", 2)]);
+
+        assert_eq!(fitted.labels(Fit::Full), vec!["T2", "T3"]);
+        assert_eq!(fitted.labels(Fit::Summarized), vec!["T1"]);
+        assert_eq!(fitted.sections[0].text, "T1".repeat(20));
+        let warning = fitted.warning().expect("summarizing warns");
+        assert!(warning.contains("gpt-4o"));
+        assert!(warning.contains("summarized T1"));
+    }
+
+    #[test]
+    fn test_fit_truncates_then_drops() {
+        let long = (0..200).map(|i| format!("line {i}\n")).collect::<String>();
+        let mut budget = PromptBudget::new("gpt-4o", 150);
+        let fitted = budget.fit(&[
+            PromptSection::new("a.rs", long.clone()),
+            PromptSection::new("b.rs", long),
+        ]);
+
+        assert_eq!(fitted.sections[0].fit, Fit::Truncated);
+        assert!(fitted.sections[0].text.starts_with("line 0\n"));
+        assert!(fitted.sections[0].text.ends_with(TRUNCATED_MARKER));
+        assert!(estimate_tokens(&fitted.sections[0].text) <= 150);
+        assert_eq!(fitted.labels(Fit::Dropped), vec!["b.rs"]);
+        assert_eq!(
+            fitted.warning().expect("trimming warns"),
+            "Prompt too large for gpt-4o's context window; truncated a.rs; dropped b.rs"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! On-disk cache of model responses.
+//!
+//! Entries are keyed by a SHA-256 digest of the provider, model, messages
+//! and request parameters, so an identical request reuses the stored
+//! response instead of paying for it again. Entries expire after a TTL, and
+//! the oldest are evicted once the cache grows past its size limit.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ckrv_core::ResponseCacheConfig;
+use sha2::{Digest, Sha256};
+
+use crate::{CompletionRequest, CompletionResponse, ModelError};
+
+/// Cache directory, relative to the project root.
+pub const CACHE_DIR: &str = ".chakravarti/cache/responses";
+
+/// Content-addressed store of completion responses.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl ResponseCache {
+    /// Create a cache in `dir` with the default TTL and size limit.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let defaults = ResponseCacheConfig::default();
+        Self {
+            dir: dir.into(),
+            ttl: Duration::from_secs(defaults.ttl_hours * 3600),
+            max_bytes: defaults.max_size_mb * 1024 * 1024,
+        }
+    }
+
+    /// The project's cache, or `None` when `config` leaves it disabled.
+    #[must_use]
+    pub fn from_config(root: &Path, config: &ResponseCacheConfig) -> Option<Self> {
+        config.enabled.then(|| {
+            Self::new(root.join(CACHE_DIR))
+                .with_ttl(Duration::from_secs(config.ttl_hours.saturating_mul(3600)))
+                .with_max_bytes(config.max_size_mb.saturating_mul(1024 * 1024))
+        })
+    }
+
+    /// Set how long entries stay valid.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the total size the cache may grow to.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Key for a request sent to `provider`.
+    #[must_use]
+    pub fn key(provider: &str, request: &CompletionRequest) -> String {
+        let material = serde_json::json!({
+            "provider": provider,
+            "model": request.model,
+            "messages": request.messages,
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature,
+            "tools": request.tools,
+            "tool_choice": request.tool_choice,
+        });
+        hex::encode(Sha256::digest(material.to_string().as_bytes()))
+    }
+
+    /// The stored response for `key`, if present and not expired.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<CompletionResponse> {
+        let path = self.entry_path(key);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if self.expired(modified) {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        let body = std::fs::read_to_string(&path).ok()?;
+        let mut response: CompletionResponse = serde_json::from_str(&body).ok()?;
+        response.cached = true;
+        Some(response)
+    }
+
+    /// Store a response, then evict expired and excess entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry can't be written.
+    pub fn put(&self, key: &str, response: &CompletionResponse) -> Result<(), ModelError> {
+        let write_error = |e: std::io::Error| {
+            ModelError::ConfigError(format!("Cannot write cache {}: {e}", self.dir.display()))
+        };
+        std::fs::create_dir_all(&self.dir).map_err(write_error)?;
+        let body =
+            serde_json::to_vec(response).map_err(|e| ModelError::ParseError(e.to_string()))?;
+        // Write then rename, so readers never see a partial entry
+        let partial = self.dir.join(format!("{key}.tmp"));
+        std::fs::write(&partial, body).map_err(write_error)?;
+        std::fs::rename(&partial, self.entry_path(key)).map_err(write_error)?;
+        self.prune();
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    fn expired(&self, modified: SystemTime) -> bool {
+        modified.elapsed().map_or(false, |age| age > self.ttl)
+    }
+
+    /// Remove expired entries, then the oldest until under the size limit.
+    fn prune(&self) {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = dir
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect();
+        entries.sort();
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (modified, len, path) in entries {
+            if total <= self.max_bytes && !self.expired(modified) {
+                continue;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, TokenUsage};
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![Message::user(prompt)],
+            ..CompletionRequest::default()
+        }
+    }
+
+    fn response(content: &str) -> CompletionResponse {
+        CompletionResponse {
+            content: content.to_string(),
+            usage: TokenUsage::new(100, 50),
+            model: "claude-sonnet-4-5".to_string(),
+            finish_reason: "end_turn".to_string(),
+            tool_calls: Vec::new(),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_key_covers_provider_and_request() {
+        let key = ResponseCache::key("anthropic", &request("a"));
+        assert_eq!(key, ResponseCache::key("anthropic", &request("a")));
+        assert_ne!(key, ResponseCache::key("openrouter", &request("a")));
+        assert_ne!(key, ResponseCache::key("anthropic", &request("b")));
+
+        let mut hotter = request("a");
+        hotter.temperature = Some(0.9);
+        assert_ne!(key, ResponseCache::key("anthropic", &hotter));
+    }
+
+    #[test]
+    fn test_get_put_and_ttl() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ResponseCache::new(dir.path());
+        let key = ResponseCache::key("anthropic", &request("a"));
+        assert!(cache.get(&key).is_none());
+
+        cache.put(&key, &response("cached text")).expect("put");
+        let hit = cache.get(&key).expect("hit");
+        assert_eq!(hit.content, "cached text");
+        assert!(hit.cached);
+
+        let expired = cache.with_ttl(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(expired.get(&key).is_none());
+        assert!(!dir.path().join(format!("{key}.json")).exists());
+    }
+
+    #[test]
+    fn test_put_evicts_oldest_over_size_limit() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let entry_size = serde_json::to_vec(&response("x")).expect("json").len() as u64;
+        let cache = ResponseCache::new(dir.path()).with_max_bytes(entry_size * 2);
+
+        let keys: Vec<String> = ["a", "b", "c"]
+            .iter()
+            .map(|prompt| ResponseCache::key("anthropic", &request(prompt)))
+            .collect();
+        for key in &keys {
+            cache.put(key, &response("x")).expect("put");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(cache.get(&keys[0]).is_none());
+        assert!(cache.get(&keys[1]).is_some());
+        assert!(cache.get(&keys[2]).is_some());
+    }
+}
@@ -43,13 +43,40 @@ pub enum ModelError {
 }
 
 impl ModelError {
-    /// Check if error is retryable.
+    /// Check if error is retryable: network failures, rate limits, timeouts
+    /// and server-side (5xx, including overloaded 529) API errors.
     #[must_use]
     pub fn is_retryable(&self) -> bool {
-        matches!(
-            self,
-            Self::NetworkError(_) | Self::RateLimited { .. } | Self::Timeout(_)
-        )
+        match self {
+            Self::NetworkError(_) | Self::RateLimited { .. } | Self::Timeout(_) => true,
+            Self::ApiError { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Build the error for a failed HTTP response. A 429 becomes
+    /// [`ModelError::RateLimited`] carrying the `Retry-After` seconds;
+    /// `message` extracts the provider's error message from the body.
+    pub(crate) async fn from_response(
+        response: reqwest::Response,
+        message: fn(&str) -> Option<String>,
+    ) -> Self {
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok());
+            return Self::RateLimited { retry_after };
+        }
+        match response.text().await {
+            Ok(body) => Self::ApiError {
+                status: status.as_u16(),
+                message: message(&body).unwrap_or(body),
+            },
+            Err(e) => Self::NetworkError(e.to_string()),
+        }
     }
 }
 
@@ -80,5 +107,15 @@ mod tests {
         }
         .is_retryable());
         assert!(!ModelError::ConfigError("bad".to_string()).is_retryable());
+        assert!(ModelError::ApiError {
+            status: 529,
+            message: "Overloaded".to_string()
+        }
+        .is_retryable());
+        assert!(!ModelError::ApiError {
+            status: 401,
+            message: "Unauthorized".to_string()
+        }
+        .is_retryable());
     }
 }
@@ -1,20 +1,32 @@
 //! Model gateway and routing for Chakravarti CLI.
 //!
 //! This crate provides model provider abstraction, routing logic,
-//! and token/cost accounting.
+//! token/cost accounting and prompt budgeting.
 
 pub mod accounting;
+pub mod aliases;
 pub mod anthropic;
+pub mod budget;
+pub mod cache;
 pub mod error;
 pub mod openai;
-pub mod pricing;
+pub mod openrouter;
 pub mod provider;
 pub mod router;
+mod sse;
 
 pub use accounting::{TokenUsage, UsageAccumulator};
+pub use aliases::ModelAliases;
 pub use anthropic::AnthropicProvider;
+pub use budget::{estimate_tokens, Fit, Fitted, PromptBudget, PromptSection};
+pub use cache::ResponseCache;
 pub use error::ModelError;
 pub use openai::OpenAIProvider;
-pub use pricing::{ModelPricing, PricingCatalog};
-pub use provider::{CompletionRequest, CompletionResponse, Message, ModelProvider};
-pub use router::{BudgetTracker, ModelRouter, ModelSelection, RoutingContext, TaskType};
+pub use ckrv_metrics::{ModelPricing, PricingCatalog};
+pub use provider::{
+    CompletionRequest, CompletionResponse, CompletionStream, Message, ModelProvider, StreamEvent,
+    ToolCall, ToolChoice, ToolDefinition,
+};
+pub use router::{
+    BudgetTracker, ModelRouter, ModelSelection, RetryPolicy, RoutingContext, TaskType,
+};
@@ -1,18 +1,27 @@
 //! OpenAI model provider implementation.
 
 use async_trait::async_trait;
+use ckrv_core::ProviderKind;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    provider::{CompletionRequest, CompletionResponse, Message, ModelProvider},
+    provider::{
+        CompletionRequest, CompletionResponse, CompletionStream, Message, ModelProvider, ToolCall,
+        ToolChoice, ToolDefinition,
+    },
+    sse::{self, SseEvent, StreamState},
     ModelError, TokenUsage,
 };
 
 /// OpenAI API provider.
 pub struct OpenAIProvider {
     client: reqwest::Client,
+    name: String,
     api_key: String,
     base_url: String,
+    headers: Vec<(String, String)>,
+    models: Vec<String>,
+    kind: ProviderKind,
 }
 
 impl OpenAIProvider {
@@ -27,8 +36,12 @@ impl OpenAIProvider {
 
         Ok(Self {
             client: reqwest::Client::new(),
+            name: "openai".to_string(),
             api_key,
             base_url: "https://api.openai.com/v1".to_string(),
+            headers: Vec::new(),
+            models: Vec::new(),
+            kind: ProviderKind::OpenAi,
         })
     }
 
@@ -37,10 +50,42 @@ impl OpenAIProvider {
     pub fn with_endpoint(api_key: String, base_url: String) -> Self {
         Self {
             client: reqwest::Client::new(),
+            name: "openai".to_string(),
             api_key,
             base_url,
+            headers: Vec::new(),
+            models: Vec::new(),
+            kind: ProviderKind::OpenAi,
         }
     }
+
+    /// Set the name the router knows this provider by.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Add headers sent with every request.
+    #[must_use]
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Set the models this provider serves.
+    #[must_use]
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Set the API flavour behind the endpoint (`OpenAI` by default).
+    #[must_use]
+    pub const fn with_kind(mut self, kind: ProviderKind) -> Self {
+        self.kind = kind;
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -51,12 +96,64 @@ struct OpenAIRequest {
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<OpenAIStreamOptions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAITool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct OpenAIStreamOptions {
+    include_usage: bool,
 }
 
 #[derive(Serialize)]
 struct OpenAIMessage {
     role: String,
-    content: String,
+    /// Null on assistant turns that only call tools.
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAIToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OpenAITool {
+    #[serde(rename = "type")]
+    tool_type: &'static str,
+    function: OpenAIFunction,
+}
+
+#[derive(Serialize)]
+struct OpenAIFunction {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+/// A tool call; `arguments` is a JSON document encoded as a string.
+#[derive(Serialize, Deserialize)]
+struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type", default = "function_type")]
+    call_type: String,
+    function: OpenAIFunctionCall,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+fn function_type() -> String {
+    "function".to_string()
 }
 
 #[derive(Deserialize)]
@@ -74,7 +171,10 @@ struct OpenAIChoice {
 
 #[derive(Deserialize)]
 struct OpenAIMessageResponse {
-    content: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCall>,
 }
 
 #[derive(Deserialize)]
@@ -97,55 +197,25 @@ struct OpenAIErrorDetail {
 #[async_trait]
 impl ModelProvider for OpenAIProvider {
     fn name(&self) -> &str {
-        "openai"
+        &self.name
     }
 
-    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ModelError> {
-        let messages: Vec<OpenAIMessage> = request
-            .messages
-            .into_iter()
-            .map(|m| OpenAIMessage {
-                role: m.role,
-                content: m.content,
-            })
-            .collect();
-
-        let api_request = OpenAIRequest {
-            model: request.model,
-            messages,
-            max_tokens: request.max_tokens,
-            temperature: request.temperature,
-        };
+    fn models(&self) -> &[String] {
+        &self.models
+    }
 
-        let response = self
-            .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&api_request)
-            .send()
-            .await
-            .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+    fn kind(&self) -> Option<ProviderKind> {
+        Some(self.kind)
+    }
 
-        let status = response.status();
-        let body = response
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ModelError> {
+        let body = self
+            .send(request, false)
+            .await?
             .text()
             .await
             .map_err(|e| ModelError::NetworkError(e.to_string()))?;
 
-        if !status.is_success() {
-            if let Ok(error) = serde_json::from_str::<OpenAIError>(&body) {
-                return Err(ModelError::ApiError {
-                    status: status.as_u16(),
-                    message: error.error.message,
-                });
-            }
-            return Err(ModelError::ApiError {
-                status: status.as_u16(),
-                message: body,
-            });
-        }
-
         let api_response: OpenAIResponse =
             serde_json::from_str(&body).map_err(|e| ModelError::ParseError(e.to_string()))?;
 
@@ -155,8 +225,21 @@ impl ModelProvider for OpenAIProvider {
             .next()
             .ok_or_else(|| ModelError::ParseError("No choices in response".to_string()))?;
 
+        let tool_calls = choice
+            .message
+            .tool_calls
+            .into_iter()
+            .map(|call| {
+                Ok(ToolCall {
+                    id: call.id,
+                    name: call.function.name,
+                    arguments: parse_arguments(&call.function.arguments)?,
+                })
+            })
+            .collect::<Result<_, ModelError>>()?;
+
         Ok(CompletionResponse {
-            content: choice.message.content,
+            content: choice.message.content.unwrap_or_default(),
             usage: TokenUsage {
                 prompt_tokens: api_response.usage.prompt_tokens,
                 completion_tokens: api_response.usage.completion_tokens,
@@ -164,8 +247,174 @@ impl ModelProvider for OpenAIProvider {
             },
             model: api_response.model,
             finish_reason: choice.finish_reason,
+            tool_calls,
+            cached: false,
         })
     }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ModelError> {
+        let response = self.send(request, true).await?;
+        Ok(sse::completion_stream(response, decode_chunk))
+    }
+}
+
+impl OpenAIProvider {
+    /// Send a chat completions request, turning error statuses into errors.
+    async fn send(
+        &self,
+        request: CompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, ModelError> {
+        let messages: Vec<OpenAIMessage> =
+            request.messages.into_iter().map(to_openai_message).collect();
+
+        let api_request = OpenAIRequest {
+            model: request.model,
+            messages,
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            stream,
+            stream_options: stream.then_some(OpenAIStreamOptions {
+                include_usage: true,
+            }),
+            tools: request.tools.into_iter().map(to_openai_tool).collect(),
+            tool_choice: request.tool_choice.map(|choice| to_openai_tool_choice(&choice)),
+        };
+
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Content-Type", "application/json");
+        // Local servers take no key
+        if !self.api_key.is_empty() {
+            builder = builder.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .json(&api_request)
+            .send()
+            .await
+            .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(ModelError::from_response(response, |body| {
+            serde_json::from_str::<OpenAIError>(body)
+                .ok()
+                .map(|e| e.error.message)
+        })
+        .await)
+    }
+}
+
+fn to_openai_message(message: Message) -> OpenAIMessage {
+    let tool_calls: Vec<OpenAIToolCall> = message
+        .tool_calls
+        .into_iter()
+        .map(|call| OpenAIToolCall {
+            id: call.id,
+            call_type: function_type(),
+            function: OpenAIFunctionCall {
+                name: call.name,
+                arguments: call.arguments.to_string(),
+            },
+        })
+        .collect();
+    let content = if message.content.is_empty() && !tool_calls.is_empty() {
+        None
+    } else {
+        Some(message.content)
+    };
+    OpenAIMessage {
+        role: message.role,
+        content,
+        tool_calls,
+        tool_call_id: message.tool_call_id,
+    }
+}
+
+fn to_openai_tool(tool: ToolDefinition) -> OpenAITool {
+    OpenAITool {
+        tool_type: "function",
+        function: OpenAIFunction {
+            name: tool.name,
+            description: tool.description,
+            parameters: tool.parameters,
+        },
+    }
+}
+
+fn to_openai_tool_choice(choice: &ToolChoice) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => "auto".into(),
+        ToolChoice::None => "none".into(),
+        ToolChoice::Required => "required".into(),
+        ToolChoice::Tool(name) => {
+            serde_json::json!({"type": "function", "function": {"name": name}})
+        }
+    }
+}
+
+/// Parse a tool call's JSON-encoded arguments; empty means no arguments.
+fn parse_arguments(arguments: &str) -> Result<serde_json::Value, ModelError> {
+    if arguments.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(arguments).map_err(|e| ModelError::ParseError(format!("tool arguments: {e}")))
+}
+
+/// Decode one chat completion chunk. The stream ends with `data: [DONE]`;
+/// usage arrives in a final chunk with no choices.
+fn decode_chunk(state: &mut StreamState, event: &SseEvent) -> Result<Option<String>, ModelError> {
+    if event.data == "[DONE]" {
+        state.done = true;
+        return Ok(None);
+    }
+    let data: serde_json::Value =
+        serde_json::from_str(&event.data).map_err(|e| ModelError::ParseError(e.to_string()))?;
+    if let Some(message) = data["error"]["message"].as_str() {
+        return Err(ModelError::ApiError {
+            status: 500,
+            message: message.to_string(),
+        });
+    }
+
+    let tokens = |value: &serde_json::Value| value.as_u64().and_then(|n| u32::try_from(n).ok());
+    if let Some(model) = data["model"].as_str() {
+        state.model = model.to_string();
+    }
+    if let Some(prompt) = tokens(&data["usage"]["prompt_tokens"]) {
+        state.prompt_tokens = prompt;
+    }
+    if let Some(completion) = tokens(&data["usage"]["completion_tokens"]) {
+        state.completion_tokens = completion;
+    }
+
+    let choice = &data["choices"][0];
+    if let Some(reason) = choice["finish_reason"].as_str() {
+        state.finish_reason = reason.to_string();
+    }
+    // Tool calls arrive in pieces: the first carries the id and name, the
+    // rest append to the arguments
+    for delta in choice["delta"]["tool_calls"].as_array().into_iter().flatten() {
+        let call = state.tool_call(delta["index"].as_u64().unwrap_or_default());
+        if let Some(id) = delta["id"].as_str() {
+            call.id = id.to_string();
+        }
+        if let Some(name) = delta["function"]["name"].as_str() {
+            call.name.push_str(name);
+        }
+        if let Some(arguments) = delta["function"]["arguments"].as_str() {
+            call.arguments.push_str(arguments);
+        }
+    }
+    Ok(choice["delta"]["content"].as_str().map(str::to_string))
 }
 
 #[cfg(test)]
@@ -191,4 +440,83 @@ mod tests {
         assert_eq!(provider.name(), "openai");
         assert_eq!(provider.base_url, "https://custom.api.com");
     }
+
+    #[test]
+    fn test_tool_messages_map_to_wire_format() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({"path": "README.md"}),
+        };
+        let messages: Vec<OpenAIMessage> = vec![
+            Message::assistant_tool_calls("", vec![call]),
+            Message::tool_result("call_1", "# Readme"),
+        ]
+        .into_iter()
+        .map(to_openai_message)
+        .collect();
+
+        assert_eq!(
+            serde_json::to_value(&messages).expect("serialize"),
+            serde_json::json!([
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "read_file", "arguments": "{\"path\":\"README.md\"}"}}
+                ]},
+                {"role": "tool", "content": "# Readme", "tool_call_id": "call_1"}
+            ])
+        );
+        assert_eq!(to_openai_tool_choice(&ToolChoice::Required), "required");
+    }
+
+    #[test]
+    fn test_decode_streamed_tool_calls() {
+        let chunks = [
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"read_file","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"path\":"}}]},"finish_reason":null}]}"#,
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"src\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+        ];
+        let mut state = StreamState::default();
+        for data in chunks {
+            let event = SseEvent {
+                event: None,
+                data: data.to_string(),
+            };
+            assert!(decode_chunk(&mut state, &event).expect("decode").is_none());
+        }
+
+        let call = &state.tool_calls[&0];
+        assert_eq!((call.id.as_str(), call.name.as_str()), ("call_1", "read_file"));
+        assert_eq!(call.arguments, r#"{"path":"src"}"#);
+        assert_eq!(state.finish_reason, "tool_calls");
+    }
+
+    #[test]
+    fn test_decode_stream_chunks() {
+        let chunks = [
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":""},"finish_reason":null}]}"#,
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":null}]}"#,
+            r#"{"model":"gpt-4o","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+            r#"{"model":"gpt-4o","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2,"total_tokens":11}}"#,
+            "[DONE]",
+        ];
+
+        let mut state = StreamState::default();
+        let mut text = String::new();
+        for data in chunks {
+            let event = SseEvent {
+                event: None,
+                data: data.to_string(),
+            };
+            if let Some(delta) = decode_chunk(&mut state, &event).expect("decode") {
+                text.push_str(&delta);
+            }
+        }
+
+        assert_eq!(text, "Hi");
+        assert_eq!(state.model, "gpt-4o");
+        assert_eq!(state.finish_reason, "stop");
+        assert_eq!((state.prompt_tokens, state.completion_tokens), (9, 2));
+        assert!(state.done);
+    }
 }
@@ -0,0 +1,173 @@
+//! `OpenRouter` model metadata.
+//!
+//! Completions go through [`crate::OpenAIProvider`], since `OpenRouter` speaks
+//! the `OpenAI` chat completions API. This module reads its `/models`
+//! endpoint, which lists every routable model with its context window and
+//! per-token prices, and keeps a copy for [`crate::PricingCatalog::load`].
+
+use std::path::Path;
+use std::time::Duration;
+
+use ckrv_metrics::pricing::{parse_openrouter_models, OPENROUTER_CACHE};
+
+use crate::{ModelError, ModelPricing};
+
+/// Public `OpenRouter` API endpoint.
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// How long to wait for a connection to the endpoint.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the whole `/models` response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How long to wait after a failed refresh before trying again.
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(60 * 60);
+
+/// Fetch the model list from an `OpenRouter` endpoint.
+///
+/// # Errors
+///
+/// Returns an error if the request fails or the response can't be parsed.
+pub async fn fetch_models(
+    base_url: &str,
+    api_key: Option<&str>,
+) -> Result<Vec<ModelPricing>, ModelError> {
+    parse_models(&fetch_body(base_url, api_key).await?)
+}
+
+/// Parse a `/models` response into pricing entries, skipping models
+/// without a published price.
+///
+/// # Errors
+///
+/// Returns an error if the body is not a model list.
+pub fn parse_models(body: &str) -> Result<Vec<ModelPricing>, ModelError> {
+    parse_openrouter_models(body).map_err(|e| ModelError::ParseError(e.to_string()))
+}
+
+/// Refresh the project's cached model list if it is missing or older than
+/// `max_age`. Returns whether the cache was rewritten.
+///
+/// A failed refresh is not retried for an hour, so an unreachable endpoint
+/// doesn't slow down every run; the failure is recorded in a marker file
+/// next to the cache.
+///
+/// # Errors
+///
+/// Returns an error if the model list can't be fetched or written.
+pub async fn refresh_cache(
+    root: &Path,
+    base_url: &str,
+    api_key: Option<&str>,
+    max_age: Duration,
+) -> Result<bool, ModelError> {
+    let path = root.join(OPENROUTER_CACHE);
+    let failed = path.with_extension("failed");
+    if younger_than(&path, max_age) || younger_than(&failed, RETRY_AFTER_FAILURE) {
+        return Ok(false);
+    }
+
+    let body = match fetch_body(base_url, api_key).await {
+        Ok(body) => body,
+        Err(e) => {
+            write_file(&failed, &e.to_string())?;
+            return Err(e);
+        }
+    };
+    parse_models(&body)?;
+    write_file(&path, &body)?;
+    let _ = std::fs::remove_file(&failed);
+    Ok(true)
+}
+
+fn write_file(path: &Path, body: &str) -> Result<(), ModelError> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, body)
+    };
+    write().map_err(|e| ModelError::ConfigError(format!("Cannot write {}: {e}", path.display())))
+}
+
+/// Whether the file at `path` exists and was modified less than `age` ago.
+fn younger_than(path: &Path, age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified.elapsed().is_ok_and(|elapsed| elapsed < age))
+}
+
+async fn fetch_body(base_url: &str, api_key: Option<&str>) -> Result<String, ModelError> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+    let mut request = client.get(format!("{base_url}/models"));
+    if let Some(key) = api_key {
+        request = request.header("Authorization", format!("Bearer {key}"));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| ModelError::NetworkError(e.to_string()))?;
+    if !status.is_success() {
+        return Err(ModelError::ApiError {
+            status: status.as_u16(),
+            message: body,
+        });
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_models() {
+        let body = r#"{"data": [
+            {"id": "moonshotai/kimi-k2", "name": "Kimi K2", "context_length": 131072,
+             "pricing": {"prompt": "0.0000006", "completion": "0.0000025"}},
+            {"id": "no/pricing"}
+        ]}"#;
+
+        let models = parse_models(body).expect("parse");
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].provider, "openrouter");
+        assert!(matches!(parse_models("not json"), Err(ModelError::ParseError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_cache_skips_fresh_cache() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join(OPENROUTER_CACHE);
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        std::fs::write(&path, r#"{"data": []}"#).expect("write");
+
+        // Unreachable endpoint: only a stale cache would try it
+        let refreshed = refresh_cache(
+            dir.path(),
+            "http://127.0.0.1:9",
+            None,
+            Duration::from_secs(3600),
+        )
+        .await;
+        assert!(!refreshed.expect("fresh cache"));
+        assert!(refresh_cache(dir.path(), "http://127.0.0.1:9", None, Duration::ZERO)
+            .await
+            .is_err());
+
+        // A failure is remembered, so the next run doesn't try again
+        assert!(path.with_extension("failed").exists());
+        let retried = refresh_cache(dir.path(), "http://127.0.0.1:9", None, Duration::ZERO).await;
+        assert!(!retried.expect("backed off"));
+    }
+}
@@ -1,21 +1,103 @@
 //! Model provider abstraction.
 
+use std::pin::Pin;
+
 use async_trait::async_trait;
+use ckrv_core::ProviderKind;
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
 
 use crate::{ModelError, TokenUsage};
 
 /// A message in a completion request.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Message {
-    /// Role: system, user, or assistant.
+    /// Role: system, user, assistant, or tool.
     pub role: String,
     /// Message content.
     pub content: String,
+    /// Tools an assistant message called.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// For tool messages, the call this is the result of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    /// A message with the given role and text.
+    #[must_use]
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    /// A user message.
+    #[must_use]
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    /// An assistant turn that called tools, to send back with their results.
+    #[must_use]
+    pub fn assistant_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::new("assistant", content)
+        }
+    }
+
+    /// The result of a tool call.
+    #[must_use]
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new("tool", content)
+        }
+    }
+}
+
+/// A tool the model may call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    /// Tool name.
+    pub name: String,
+    /// What the tool does, for the model.
+    pub description: String,
+    /// JSON Schema for the tool's arguments.
+    pub parameters: serde_json::Value,
+}
+
+/// Whether and which tools the model must call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoice {
+    /// The model decides.
+    Auto,
+    /// No tool calls.
+    None,
+    /// At least one tool call.
+    Required,
+    /// A call to the named tool.
+    Tool(String),
+}
+
+/// A tool call made by the model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Call identifier, echoed back in the result message.
+    pub id: String,
+    /// Name of the tool called.
+    pub name: String,
+    /// Arguments, as a JSON value matching the tool's schema.
+    pub arguments: serde_json::Value,
 }
 
 /// Request for a model completion.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CompletionRequest {
     /// Model to use.
     pub model: String,
@@ -25,10 +107,14 @@ pub struct CompletionRequest {
     pub max_tokens: Option<u32>,
     /// Temperature for sampling.
     pub temperature: Option<f32>,
+    /// Tools the model may call.
+    pub tools: Vec<ToolDefinition>,
+    /// Tool choice; the API's default when `None`.
+    pub tool_choice: Option<ToolChoice>,
 }
 
 /// Response from a model completion.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionResponse {
     /// Generated content.
     pub content: String,
@@ -38,18 +124,66 @@ pub struct CompletionResponse {
     pub model: String,
     /// Reason for finishing.
     pub finish_reason: String,
+    /// Tools the model called, in order.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Whether this was served from the response cache rather than the API.
+    #[serde(skip)]
+    pub cached: bool,
 }
 
+/// An incremental event from a streaming completion.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// A chunk of generated text.
+    Delta(String),
+    /// The finished completion, with the full content and final usage.
+    Done(CompletionResponse),
+}
+
+/// A stream of completion events, ending with [`StreamEvent::Done`].
+pub type CompletionStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, ModelError>> + Send>>;
+
 /// Trait for model providers.
 #[async_trait]
 pub trait ModelProvider: Send + Sync {
     /// Get provider name.
     fn name(&self) -> &str;
 
+    /// Models this provider is configured to serve; empty when unrestricted.
+    fn models(&self) -> &[String] {
+        &[]
+    }
+
+    /// The API this provider speaks, if it is one of the known kinds.
+    fn kind(&self) -> Option<ProviderKind> {
+        None
+    }
+
     /// Generate a completion.
     ///
     /// # Errors
     ///
     /// Returns an error if the API call fails.
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ModelError>;
+
+    /// Generate a completion as a stream of text deltas.
+    ///
+    /// The default implementation waits for [`ModelProvider::complete`] and
+    /// yields its content as a single delta.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be started; failures after
+    /// that arrive as stream items.
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ModelError> {
+        let response = self.complete(request).await?;
+        Ok(Box::pin(futures_util::stream::iter([
+            Ok(StreamEvent::Delta(response.content.clone())),
+            Ok(StreamEvent::Done(response)),
+        ])))
+    }
 }
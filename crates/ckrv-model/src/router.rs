@@ -1,14 +1,19 @@
 //! Model routing logic with optimization modes.
 
+use std::future::Future;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use ckrv_core::OptimizeMode;
+use ckrv_core::secrets::SecretResolver;
+use ckrv_core::{Config, OptimizeMode, ProviderConfig, ProviderKind, RoutingRule};
+use futures_util::StreamExt;
 
 use crate::{
+    aliases::ModelAliases,
     anthropic::AnthropicProvider,
     openai::OpenAIProvider,
-    provider::{CompletionRequest, CompletionResponse, ModelProvider},
-    ModelError,
+    provider::{CompletionRequest, CompletionResponse, CompletionStream, ModelProvider, StreamEvent},
+    ModelError, PricingCatalog, ResponseCache,
 };
 
 /// Context for routing decisions.
@@ -22,6 +27,10 @@ pub struct RoutingContext {
     pub estimated_tokens: Option<u32>,
     /// Model override (if specified by user).
     pub model_override: Option<String>,
+    /// Task complexity (1-5), if known.
+    pub complexity: Option<u8>,
+    /// Whether requests will carry tool definitions.
+    pub requires_tools: bool,
 }
 
 impl Default for RoutingContext {
@@ -31,20 +40,13 @@ impl Default for RoutingContext {
             task_type: TaskType::Execution,
             estimated_tokens: None,
             model_override: None,
+            complexity: None,
+            requires_tools: false,
         }
     }
 }
 
-/// Type of task for routing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TaskType {
-    /// Planning phase - needs strong reasoning.
-    Planning,
-    /// Execution phase - needs code generation.
-    Execution,
-    /// Verification phase - needs analysis.
-    Verification,
-}
+pub use ckrv_core::TaskType;
 
 /// Budget tracker for cost-optimized routing.
 #[derive(Debug, Clone)]
@@ -55,6 +57,10 @@ pub struct BudgetTracker {
     pub spent_usd: f64,
     /// Token count by model.
     pub tokens_by_model: std::collections::HashMap<String, (u64, u64)>,
+    /// Responses served from the cache, which cost nothing.
+    pub cache_hits: u32,
+    /// Tokens of cached responses by model, kept apart from spend.
+    pub cached_tokens_by_model: std::collections::HashMap<String, (u64, u64)>,
 }
 
 impl Default for BudgetTracker {
@@ -71,6 +77,8 @@ impl BudgetTracker {
             max_budget_usd,
             spent_usd: 0.0,
             tokens_by_model: std::collections::HashMap::new(),
+            cache_hits: 0,
+            cached_tokens_by_model: std::collections::HashMap::new(),
         }
     }
 
@@ -85,6 +93,17 @@ impl BudgetTracker {
         entry.1 += output_tokens;
     }
 
+    /// Record a response served from the cache.
+    pub fn record_cache_hit(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        self.cache_hits += 1;
+        let entry = self
+            .cached_tokens_by_model
+            .entry(model.to_string())
+            .or_insert((0, 0));
+        entry.0 += input_tokens;
+        entry.1 += output_tokens;
+    }
+
     /// Check if budget is available.
     #[must_use]
     pub fn has_budget(&self, estimated_cost: f64) -> bool {
@@ -117,12 +136,70 @@ pub struct ModelSelection {
     pub reason: String,
 }
 
+/// Retry settings for model requests.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per provider, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on each further retry.
+    pub base_delay: Duration,
+    /// Longest backoff. A `retry_after` longer than this fails over to the
+    /// next provider instead of waiting.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (from 1) after `error`, or `None`
+    /// to give up on the provider.
+    fn delay(&self, error: &ModelError, retry: u32) -> Option<Duration> {
+        if let ModelError::RateLimited {
+            retry_after: Some(seconds),
+        } = error
+        {
+            let wait = Duration::from_secs(u64::from(*seconds));
+            return (wait <= self.max_delay).then_some(wait);
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << (retry - 1).min(16))
+            .min(self.max_delay);
+        // Equal jitter: half the backoff fixed, half random
+        let half = backoff / 2;
+        Some(half + half.mul_f64(jitter()))
+    }
+}
+
+/// A random fraction in `[0, 1]`.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    f64::from(u32::try_from(bits >> 32).unwrap_or_default()) / f64::from(u32::MAX)
+}
+
 /// Model router for selecting and calling providers.
 pub struct ModelRouter {
     providers: Vec<Arc<dyn ModelProvider>>,
     default_planner_model: String,
     default_executor_model: String,
     budget: Arc<Mutex<BudgetTracker>>,
+    aliases: ModelAliases,
+    retry: RetryPolicy,
+    pricing: Arc<PricingCatalog>,
+    cache: Option<Arc<ResponseCache>>,
+    rules: Vec<RoutingRule>,
 }
 
 impl ModelRouter {
@@ -132,38 +209,127 @@ impl ModelRouter {
     ///
     /// Returns an error if no providers are available.
     pub fn new() -> Result<Self, ModelError> {
-        let mut providers: Vec<Arc<dyn ModelProvider>> = Vec::new();
-
-        // Try to create OpenAI provider
-        if let Ok(provider) = OpenAIProvider::new() {
-            providers.push(Arc::new(provider));
-        }
-
-        // Try to create Anthropic provider
-        if let Ok(provider) = AnthropicProvider::new() {
-            providers.push(Arc::new(provider));
-        }
+        Self::with_providers(env_providers())
+    }
 
-        // Check for custom endpoint
-        if let (Ok(key), Ok(url)) = (
-            std::env::var("CKRV_MODEL_API_KEY"),
-            std::env::var("CKRV_MODEL_ENDPOINT"),
-        ) {
-            let provider = OpenAIProvider::with_endpoint(key, url);
-            providers.push(Arc::new(provider));
+    /// Create a router from the providers named in `config.json`, followed
+    /// by those detected from the environment whose names aren't configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured provider is missing its key or a
+    /// `secret:` reference can't be resolved, or if no providers are available.
+    pub fn from_config(config: &Config, secrets: &mut SecretResolver) -> Result<Self, ModelError> {
+        let mut providers = Vec::new();
+        for (name, provider) in &config.providers {
+            providers.push(configured_provider(name, provider, secrets)?);
         }
+        providers.extend(
+            env_providers()
+                .into_iter()
+                .filter(|p| !config.providers.contains_key(p.name())),
+        );
+        Ok(Self::with_providers(providers)?
+            .with_aliases(ModelAliases::default().with_groups(&config.model_aliases))
+            .with_rules(config.routing_rules.clone()))
+    }
 
-        if providers.is_empty() {
+    /// Create a router over an explicit list of providers.
+    ///
+    /// Default models follow the first provider: its first configured model,
+    /// else Claude models for Anthropic and GPT models otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list is empty.
+    pub fn with_providers(providers: Vec<Arc<dyn ModelProvider>>) -> Result<Self, ModelError> {
+        let Some(first) = providers.first() else {
             return Err(ModelError::ConfigError(
                 "No model providers configured".to_string(),
             ));
-        }
+        };
+        let (planner, executor) = first.models().first().map_or_else(
+            || {
+                let (planner, executor) = default_models(first.name());
+                (planner.to_string(), executor.to_string())
+            },
+            |model| (model.clone(), model.clone()),
+        );
 
         Ok(Self {
             providers,
-            default_planner_model: "gpt-4o".to_string(),
-            default_executor_model: "gpt-4o-mini".to_string(),
+            default_planner_model: planner,
+            default_executor_model: executor,
             budget: Arc::new(Mutex::new(BudgetTracker::default())),
+            aliases: ModelAliases::default(),
+            retry: RetryPolicy::default(),
+            pricing: Arc::new(PricingCatalog::new()),
+            cache: None,
+            rules: Vec::new(),
+        })
+    }
+
+    /// Serve repeated requests from `cache`, or always call the API when
+    /// `None`.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache.map(Arc::new);
+        self
+    }
+
+    /// Price requests with `pricing` instead of the bundled prices.
+    #[must_use]
+    pub fn with_pricing(mut self, pricing: PricingCatalog) -> Self {
+        self.pricing = Arc::new(pricing);
+        self
+    }
+
+    /// Use `aliases` to find equivalent models when failing over.
+    #[must_use]
+    pub fn with_aliases(mut self, aliases: ModelAliases) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Choose models with `rules` before the built-in selection.
+    #[must_use]
+    pub fn with_rules(mut self, rules: Vec<RoutingRule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Set the retry policy.
+    #[must_use]
+    pub const fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Restrict the router to the provider with the given name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the available providers if none matches.
+    pub fn with_provider(self, name: &str) -> Result<Self, ModelError> {
+        let available = self.provider_names().join(", ");
+        let providers: Vec<_> = self
+            .providers
+            .into_iter()
+            .filter(|p| p.name() == name)
+            .collect();
+        if providers.is_empty() {
+            return Err(ModelError::ConfigError(format!(
+                "Provider '{name}' is not configured (available: {available})"
+            )));
+        }
+        Ok(Self {
+            budget: self.budget,
+            aliases: self.aliases,
+            retry: self.retry,
+            pricing: self.pricing,
+            cache: self.cache,
+            rules: self.rules,
+            ..Self::with_providers(providers)?
         })
     }
 
@@ -190,93 +356,181 @@ impl ModelRouter {
     }
 
     /// Select the best model for a task with full details.
+    ///
+    /// An explicit override wins, then the first matching routing rule
+    /// with a usable model, then the built-in choice for the optimization
+    /// mode. Only models a configured provider serves are considered, and
+    /// models whose context window is too small for the estimated input, or
+    /// that lack tool support the request needs, are skipped.
     #[must_use]
     pub fn select(&self, context: &RoutingContext) -> ModelSelection {
         // Honor explicit override
         if let Some(ref override_model) = context.model_override {
-            return ModelSelection {
-                model: override_model.clone(),
-                provider: self.provider_for_model(override_model),
-                estimated_cost_per_1k: self.cost_per_1k(override_model),
-                reason: "User override".to_string(),
-            };
+            return self.selection(override_model.clone(), "User override".to_string());
         }
 
-        // Select based on optimization mode
-        let (model, reason) = match context.optimize {
-            OptimizeMode::Cost => self.select_cost_optimized(context),
-            OptimizeMode::Time => self.select_time_optimized(context),
-            OptimizeMode::Balanced => self.select_balanced(context),
-        };
+        let (model, reason) = self.select_by_rule(context).unwrap_or_else(|| {
+            match context.optimize {
+                OptimizeMode::Cost => self.select_cost_optimized(context),
+                OptimizeMode::Time => self.select_time_optimized(context),
+                OptimizeMode::Balanced => self.select_balanced(context),
+            }
+        });
+        self.selection(model, reason)
+    }
 
+    fn selection(&self, model: String, reason: String) -> ModelSelection {
+        let provider = self
+            .candidates(&model)
+            .first()
+            .map_or_else(|| self.provider_for_model(&model), |(p, _)| p.name().to_string());
         ModelSelection {
-            provider: self.provider_for_model(&model),
-            estimated_cost_per_1k: self.cost_per_1k(&model),
+            provider,
+            estimated_cost_per_1k: self.pricing.cost_per_1k(&model),
             model,
             reason,
         }
     }
 
-    fn select_cost_optimized(&self, context: &RoutingContext) -> (String, String) {
-        // Always use cheapest models for cost optimization
-        let model = match context.task_type {
-            TaskType::Planning => "gpt-4o-mini",
-            TaskType::Execution => "gpt-4o-mini",
-            TaskType::Verification => "gpt-4o-mini",
+    /// The model chosen by the first rule that matches `context` and lists a
+    /// usable model.
+    fn select_by_rule(&self, context: &RoutingContext) -> Option<(String, String)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| {
+                rule.matches(context.task_type, context.optimize, context.complexity)
+            })
+            .find_map(|(i, rule)| {
+                let usable = self.usable(rule.models.iter().map(String::as_str), context);
+                let (model, why) = self.break_tie(&usable, context.optimize)?;
+                let name = rule.name.clone().unwrap_or_else(|| format!("#{}", i + 1));
+                Some((model, format!("Routing rule '{name}': {why}")))
+            })
+    }
+
+    /// The `models` a configured provider serves and that suit `context`.
+    fn usable<'a>(
+        &self,
+        models: impl IntoIterator<Item = &'a str>,
+        context: &RoutingContext,
+    ) -> Vec<&'a str> {
+        models
+            .into_iter()
+            .filter(|model| self.serves(model))
+            .filter(|model| {
+                // Models missing from the catalog have nothing to rule them out
+                self.pricing.get(model).map_or(true, |meta| {
+                    let fits = match context.estimated_tokens {
+                        Some(tokens) if meta.context_window > 0 => tokens <= meta.context_window,
+                        _ => true,
+                    };
+                    fits && (meta.supports_tools || !context.requires_tools)
+                })
+            })
+            .collect()
+    }
+
+    /// Pick among usable models by catalog metadata: the cheapest when
+    /// optimizing for cost, the largest context window (then the cheapest)
+    /// when optimizing for time, else the first listed.
+    fn break_tie(&self, models: &[&str], optimize: OptimizeMode) -> Option<(String, String)> {
+        let by_price = |a: &str, b: &str| {
+            self.pricing
+                .cost_per_1k(a)
+                .total_cmp(&self.pricing.cost_per_1k(b))
+                .then_with(|| a.cmp(b))
         };
-        (
-            model.to_string(),
-            "Cost optimized: using cheapest model".to_string(),
+        let window = |model: &str| self.pricing.get(model).map_or(0, |p| p.context_window);
+        let (model, why) = match optimize {
+            OptimizeMode::Cost => (models.iter().min_by(|a, b| by_price(a, b))?, "cheapest"),
+            OptimizeMode::Time => (
+                models
+                    .iter()
+                    .min_by(|a, b| window(b).cmp(&window(a)).then_with(|| by_price(a, b)))?,
+                "largest context window",
+            ),
+            OptimizeMode::Balanced => (models.first()?, "first listed"),
+        };
+        let why = match models.len() {
+            1 => format!("{model}, the only usable model"),
+            n => format!("{model}, {why} of {n} usable models"),
+        };
+        Some(((*model).to_string(), why))
+    }
+
+    /// The best catalog model for `optimize` among those configured
+    /// providers serve, or the task's default model if none is usable.
+    fn select_from_catalog(
+        &self,
+        context: &RoutingContext,
+        optimize: OptimizeMode,
+        label: &str,
+    ) -> (String, String) {
+        let usable = self.usable(self.pricing.models(), context);
+        self.break_tie(&usable, optimize).map_or_else(
+            || {
+                let model = self.default_model(context.task_type);
+                let reason = format!("{label}: default {model}, no catalog model is usable");
+                (model, reason)
+            },
+            |(model, why)| (model, format!("{label}: {why}")),
         )
     }
 
+    fn default_model(&self, task_type: TaskType) -> String {
+        match task_type {
+            TaskType::Planning => self.default_planner_model.clone(),
+            TaskType::Execution | TaskType::Verification => self.default_executor_model.clone(),
+        }
+    }
+
+    fn select_cost_optimized(&self, context: &RoutingContext) -> (String, String) {
+        // Always use the cheapest usable model
+        self.select_from_catalog(context, OptimizeMode::Cost, "Cost optimized")
+    }
+
     fn select_time_optimized(&self, context: &RoutingContext) -> (String, String) {
-        // Use faster/more capable models for time optimization
-        let model = match context.task_type {
-            TaskType::Planning => "gpt-4o",  // Better reasoning = fewer retries
-            TaskType::Execution => "gpt-4o", // Better code gen = fewer retries
-            TaskType::Verification => "gpt-4o-mini", // Quick verification
-        };
-        (
-            model.to_string(),
-            "Time optimized: using fastest capable model".to_string(),
-        )
+        match context.task_type {
+            // Quick verification
+            TaskType::Verification => {
+                self.select_from_catalog(context, OptimizeMode::Cost, "Time optimized")
+            }
+            // Most capable model = fewer retries
+            TaskType::Planning | TaskType::Execution => {
+                self.select_from_catalog(context, OptimizeMode::Time, "Time optimized")
+            }
+        }
     }
 
     fn select_balanced(&self, context: &RoutingContext) -> (String, String) {
         // Use task-appropriate models
-        let model = match context.task_type {
-            TaskType::Planning => self.default_planner_model.clone(),
-            TaskType::Execution => self.default_executor_model.clone(),
-            TaskType::Verification => "gpt-4o-mini".to_string(),
-        };
+        if context.task_type == TaskType::Verification {
+            return self.select_from_catalog(context, OptimizeMode::Cost, "Balanced");
+        }
+        let model = self.default_model(context.task_type);
         (
             model.clone(),
             format!("Balanced: {} for {:?}", model, context.task_type),
         )
     }
 
+    /// The provider a model belongs to: the catalog's, else a guess from
+    /// its name.
     fn provider_for_model(&self, model: &str) -> String {
-        if model.starts_with("claude") {
-            "anthropic".to_string()
-        } else if model.starts_with("gpt") || model.starts_with("o1") {
-            "openai".to_string()
-        } else {
-            "custom".to_string()
-        }
-    }
-
-    fn cost_per_1k(&self, model: &str) -> f64 {
-        // Approximate cost per 1K tokens (input + output average)
-        match model {
-            "gpt-4o" => 0.00625,       // ($2.5 + $10) / 2 / 1000
-            "gpt-4o-mini" => 0.000375, // ($0.15 + $0.60) / 2 / 1000
-            "gpt-4-turbo" => 0.02,
-            "claude-3-5-sonnet" => 0.009,
-            "claude-3-5-haiku" => 0.0024,
-            "claude-3-opus" => 0.045,
-            _ => 0.01, // Default estimate
-        }
+        let guess = || {
+            if model.starts_with("claude") {
+                "anthropic".to_string()
+            } else if model.starts_with("gpt") || model.starts_with("o1") {
+                "openai".to_string()
+            } else {
+                "custom".to_string()
+            }
+        };
+        self.pricing
+            .get(model)
+            .filter(|p| !p.provider.is_empty())
+            .map_or_else(guess, |p| p.provider.clone())
     }
 
     /// Select the best model for a task (simple string return).
@@ -285,38 +539,166 @@ impl ModelRouter {
         self.select(context).model
     }
 
-    /// Complete a request using the first available provider.
+    /// Complete a request.
+    ///
+    /// Providers that serve the model are tried in turn (see
+    /// [`ModelRouter::candidates`]). Retryable errors are retried on the same
+    /// provider with exponential backoff and jitter, or after the
+    /// `retry_after` of a rate limit; other errors fail over at once. With a
+    /// response cache, a stored response for any candidate is returned
+    /// without calling the API.
     ///
     /// # Errors
     ///
-    /// Returns an error if all providers fail.
+    /// Returns [`ModelError::ModelNotFound`] if no provider serves the
+    /// model, otherwise the last provider's error if all of them fail.
     pub async fn complete(
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse, ModelError> {
-        let mut last_error = None;
+        if let Some(response) = self.cache_lookup(&request) {
+            return Ok(response);
+        }
+        let (response, served) = self
+            .with_failover(&request, |provider, request| async move {
+                provider.complete(request).await
+            })
+            .await?;
+        record_usage(&self.budget, &self.pricing, &served.model, &response);
+        if let Some(ref cache) = self.cache {
+            store(cache, &served.key(&request), &response);
+        }
+        Ok(response)
+    }
 
-        for provider in &self.providers {
-            match provider.complete(request.clone()).await {
-                Ok(response) => {
-                    // Record budget usage
-                    if let Ok(mut budget) = self.budget.lock() {
-                        let cost = self.cost_per_1k(&request.model)
-                            * (response.usage.prompt_tokens + response.usage.completion_tokens)
-                                as f64
-                            / 1000.0;
-                        budget.record(
-                            &request.model,
-                            response.usage.prompt_tokens.into(),
-                            response.usage.completion_tokens.into(),
-                            cost,
+    /// Stream a completion, with the same retries, failover and caching as
+    /// [`ModelRouter::complete`] for starting the stream; errors after it
+    /// has started end the stream. Usage is recorded against the budget
+    /// when the stream finishes. A cached response arrives as one delta.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no provider can start the stream.
+    pub async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ModelError> {
+        if let Some(response) = self.cache_lookup(&request) {
+            return Ok(Box::pin(futures_util::stream::iter([
+                Ok(StreamEvent::Delta(response.content.clone())),
+                Ok(StreamEvent::Done(response)),
+            ])));
+        }
+        let (stream, served) = self
+            .with_failover(&request, |provider, request| async move {
+                provider.complete_stream(request).await
+            })
+            .await?;
+        let budget = Arc::clone(&self.budget);
+        let pricing = Arc::clone(&self.pricing);
+        let cache = self
+            .cache
+            .as_ref()
+            .map(|cache| (Arc::clone(cache), served.key(&request)));
+        Ok(Box::pin(stream.inspect(move |event| {
+            if let Ok(StreamEvent::Done(response)) = event {
+                record_usage(&budget, &pricing, &served.model, response);
+                if let Some((ref cache, ref key)) = cache {
+                    store(cache, key, response);
+                }
+            }
+        })))
+    }
+
+    /// A cached response for the request from any candidate provider,
+    /// recorded as a cache hit.
+    fn cache_lookup(&self, request: &CompletionRequest) -> Option<CompletionResponse> {
+        let cache = self.cache.as_ref()?;
+        let (served, response) =
+            self.candidates(&request.model)
+                .into_iter()
+                .find_map(|(provider, model)| {
+                    let served = Served {
+                        provider: provider.name().to_string(),
+                        model,
+                    };
+                    let response = cache.get(&served.key(request))?;
+                    Some((served, response))
+                })?;
+        tracing::debug!(
+            provider = %served.provider,
+            model = %served.model,
+            "Model response served from cache"
+        );
+        if let Ok(mut budget) = self.budget.lock() {
+            let usage = &response.usage;
+            budget.record_cache_hit(
+                &served.model,
+                usage.prompt_tokens.into(),
+                usage.completion_tokens.into(),
+            );
+        }
+        Some(response)
+    }
+
+    /// Run `call` against each candidate provider until one succeeds,
+    /// returning its result and where it was served.
+    async fn with_failover<T, F, Fut>(
+        &self,
+        request: &CompletionRequest,
+        call: F,
+    ) -> Result<(T, Served), ModelError>
+    where
+        F: Fn(Arc<dyn ModelProvider>, CompletionRequest) -> Fut,
+        Fut: Future<Output = Result<T, ModelError>>,
+    {
+        let candidates = self.candidates(&request.model);
+        if candidates.is_empty() {
+            return Err(ModelError::ModelNotFound(format!(
+                "{} (no configured provider serves it)",
+                request.model
+            )));
+        }
+
+        let max_attempts = self.retry.max_attempts.max(1);
+        let mut last_error = None;
+        for (provider, model) in candidates {
+            let request = CompletionRequest {
+                model: model.clone(),
+                ..request.clone()
+            };
+            for attempt in 1..=max_attempts {
+                match call(Arc::clone(provider), request.clone()).await {
+                    Ok(value) => {
+                        tracing::debug!(provider = provider.name(), model = %model, attempt, "Model request succeeded");
+                        return Ok((
+                            value,
+                            Served {
+                                provider: provider.name().to_string(),
+                                model,
+                            },
+                        ));
+                    }
+                    Err(e) => {
+                        let delay = if e.is_retryable() && attempt < max_attempts {
+                            self.retry.delay(&e, attempt)
+                        } else {
+                            None
+                        };
+                        tracing::warn!(
+                            provider = provider.name(),
+                            model = %model,
+                            attempt,
+                            error = %e,
+                            retry_in = ?delay,
+                            "Model request failed"
                         );
+                        last_error = Some(e);
+                        match delay {
+                            Some(delay) => tokio::time::sleep(delay).await,
+                            None => break,
+                        }
                     }
-                    return Ok(response);
-                }
-                Err(e) => {
-                    last_error = Some(e);
-                    continue;
                 }
             }
         }
@@ -325,6 +707,54 @@ impl ModelRouter {
             .unwrap_or_else(|| ModelError::ConfigError("No providers available".to_string())))
     }
 
+    /// Providers that serve `model`, in the order requests try them, each
+    /// with the name it knows the model by: providers listing the model,
+    /// then the one its name implies, then those with an alias for it. A
+    /// model no first-party API claims also goes to providers without a
+    /// model list.
+    fn candidates(&self, model: &str) -> Vec<(&Arc<dyn ModelProvider>, String)> {
+        self.ranked_candidates(model)
+            .into_iter()
+            .map(|(_, p, m)| (p, m))
+            .collect()
+    }
+
+    /// Whether a configured provider serves `model` for routing. Catalog
+    /// models must be served directly, since their metadata describes the
+    /// provider they are listed under; aliases and providers without a model
+    /// list only count for models outside the catalog.
+    fn serves(&self, model: &str) -> bool {
+        let known = self.pricing.get(model).is_some();
+        self.ranked_candidates(model)
+            .iter()
+            .any(|(rank, ..)| *rank < 2 || !known)
+    }
+
+    /// [`ModelRouter::candidates`] with their rank.
+    fn ranked_candidates(&self, model: &str) -> Vec<(u8, &Arc<dyn ModelProvider>, String)> {
+        let implied = self.provider_for_model(model);
+        let mut ranked: Vec<_> = self
+            .providers
+            .iter()
+            .filter_map(|p| {
+                if p.models().iter().any(|m| m == model) {
+                    Some((0, p, model.to_string()))
+                } else if p.name() == implied {
+                    Some((1, p, model.to_string()))
+                } else if let Some(alias) = self.aliases.resolve(model, p.name(), p.kind()) {
+                    Some((2, p, alias.to_string()))
+                } else {
+                    // Unrestricted providers may serve models that no
+                    // first-party API claims
+                    let first_party = matches!(implied.as_str(), "openai" | "anthropic");
+                    (!first_party && p.models().is_empty()).then(|| (3, p, model.to_string()))
+                }
+            })
+            .collect();
+        ranked.sort_by_key(|(rank, ..)| *rank);
+        ranked
+    }
+
     /// Get the list of available provider names.
     #[must_use]
     pub fn provider_names(&self) -> Vec<&str> {
@@ -332,10 +762,576 @@ impl ModelRouter {
     }
 }
 
+/// The provider and model name a request was served by.
+struct Served {
+    provider: String,
+    model: String,
+}
+
+impl Served {
+    /// Cache key for `request` as sent to this provider and model.
+    fn key(&self, request: &CompletionRequest) -> String {
+        ResponseCache::key(
+            &self.provider,
+            &CompletionRequest {
+                model: self.model.clone(),
+                ..request.clone()
+            },
+        )
+    }
+}
+
+/// Store a response in the cache; failures only warn.
+fn store(cache: &ResponseCache, key: &str, response: &CompletionResponse) {
+    if let Err(e) = cache.put(key, response) {
+        tracing::warn!(error = %e, "Could not cache model response");
+    }
+}
+
+/// Record a completion's usage against the budget.
+fn record_usage(
+    budget: &Mutex<BudgetTracker>,
+    pricing: &PricingCatalog,
+    model: &str,
+    response: &CompletionResponse,
+) {
+    if let Ok(mut budget) = budget.lock() {
+        let usage = &response.usage;
+        let (input, output) = (usage.prompt_tokens.into(), usage.completion_tokens.into());
+        budget.record(model, input, output, pricing.cost(model, input, output));
+    }
+}
+
+/// Providers detected from `OPENAI_API_KEY`, `ANTHROPIC_API_KEY` and
+/// `CKRV_MODEL_API_KEY` with `CKRV_MODEL_ENDPOINT`.
+fn env_providers() -> Vec<Arc<dyn ModelProvider>> {
+    let mut providers: Vec<Arc<dyn ModelProvider>> = Vec::new();
+
+    // Try to create OpenAI provider
+    if let Ok(provider) = OpenAIProvider::new() {
+        providers.push(Arc::new(provider));
+    }
+
+    // Try to create Anthropic provider
+    if let Ok(provider) = AnthropicProvider::new() {
+        providers.push(Arc::new(provider));
+    }
+
+    // Check for custom endpoint
+    if let (Ok(key), Ok(url)) = (
+        std::env::var("CKRV_MODEL_API_KEY"),
+        std::env::var("CKRV_MODEL_ENDPOINT"),
+    ) {
+        let provider = OpenAIProvider::with_endpoint(key, url);
+        providers.push(Arc::new(provider));
+    }
+
+    providers
+}
+
+/// Build a provider from its `config.json` entry, resolving `secret:`
+/// references in the key and headers.
+fn configured_provider(
+    name: &str,
+    config: &ProviderConfig,
+    secrets: &mut SecretResolver,
+) -> Result<Arc<dyn ModelProvider>, ModelError> {
+    let mut resolve = |value: &str| {
+        secrets
+            .resolve(value)
+            .map_err(|e| ModelError::ConfigError(format!("Provider '{name}': {e}")))
+    };
+
+    let api_key = match config.api_key.as_deref() {
+        Some(key) => resolve(key)?,
+        None if config.kind.requires_key() => {
+            return Err(ModelError::ConfigError(format!(
+                "Provider '{name}' needs an api_key"
+            )));
+        }
+        None => String::new(),
+    };
+    let mut headers = Vec::new();
+    for (header, value) in &config.headers {
+        headers.push((header.clone(), resolve(value)?));
+    }
+    let base_url = config
+        .base_url
+        .clone()
+        .unwrap_or_else(|| config.kind.default_base_url().to_string());
+    let base_url = base_url.trim_end_matches('/').to_string();
+
+    Ok(match config.kind {
+        ProviderKind::Anthropic => Arc::new(
+            AnthropicProvider::with_endpoint(api_key, base_url)
+                .with_name(name)
+                .with_headers(headers)
+                .with_models(config.models.clone()),
+        ),
+        ProviderKind::OpenAi | ProviderKind::OpenRouter | ProviderKind::Local => Arc::new(
+            OpenAIProvider::with_endpoint(api_key, base_url)
+                .with_name(name)
+                .with_kind(config.kind)
+                .with_headers(headers)
+                .with_models(config.models.clone()),
+        ),
+    })
+}
+
+/// Default (planner, executor) models for a provider.
+fn default_models(provider: &str) -> (&'static str, &'static str) {
+    if provider == "anthropic" {
+        ("claude-sonnet-4-5", "claude-haiku-4-5")
+    } else {
+        ("gpt-4o", "gpt-4o-mini")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    struct NamedProvider(&'static str);
+
+    /// Test providers named after a provider kind speak its API.
+    fn kind_of(name: &str) -> Option<ProviderKind> {
+        serde_json::from_value(serde_json::json!(name)).ok()
+    }
+
+    #[async_trait::async_trait]
+    impl ModelProvider for NamedProvider {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn kind(&self) -> Option<ProviderKind> {
+            kind_of(self.0)
+        }
+
+        async fn complete(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse, ModelError> {
+            Ok(CompletionResponse {
+                content: self.0.to_string(),
+                usage: crate::TokenUsage::new(10, 5),
+                model: request.model,
+                finish_reason: "stop".to_string(),
+                tool_calls: Vec::new(),
+                cached: false,
+            })
+        }
+    }
+
+    fn router(names: &[&'static str]) -> ModelRouter {
+        let providers: Vec<Arc<dyn ModelProvider>> = names
+            .iter()
+            .map(|n| Arc::new(NamedProvider(n)) as Arc<dyn ModelProvider>)
+            .collect();
+        ModelRouter::with_providers(providers).expect("router")
+    }
+
+    fn request(model: &str) -> CompletionRequest {
+        CompletionRequest {
+            model: model.to_string(),
+            messages: vec![crate::Message::user("hi")],
+            ..CompletionRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_prefers_model_provider() {
+        let router = router(&["openai", "anthropic"]);
+        let response = router
+            .complete(request("claude-sonnet-4-5"))
+            .await
+            .expect("complete");
+        assert_eq!(response.content, "anthropic");
+
+        let response = router.complete(request("gpt-4o")).await.expect("complete");
+        assert_eq!(response.content, "openai");
+
+        let budget = router.budget();
+        let budget = budget.lock().expect("budget");
+        assert_eq!(budget.tokens_by_model.get("gpt-4o"), Some(&(10, 5)));
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_priced_from_catalog() {
+        let mut pricing = PricingCatalog::new();
+        pricing.extend([crate::ModelPricing {
+            model: "gpt-4o".to_string(),
+            provider: "openai".to_string(),
+            input_cost_per_million: 100_000.0,
+            output_cost_per_million: 200_000.0,
+            context_window: 128_000,
+            max_output: 16_384,
+            supports_tools: true,
+        }]);
+        let router = router(&["openai"]).with_pricing(pricing);
+        let selection = router.select(&RoutingContext {
+            model_override: Some("gpt-4o".to_string()),
+            ..RoutingContext::default()
+        });
+        assert!((selection.estimated_cost_per_1k - 150.0).abs() < 1e-9);
+
+        router.complete(request("gpt-4o")).await.expect("complete");
+        // 10 input tokens at $0.10 and 5 output tokens at $0.20
+        let spent = router.budget().lock().expect("budget").spent_usd;
+        assert!((spent - 2.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_complete_stream_records_usage() {
+        let router = router(&["anthropic"]);
+        let events: Vec<_> = router
+            .complete_stream(request("claude-haiku-4-5"))
+            .await
+            .expect("stream")
+            .collect()
+            .await;
+
+        assert!(matches!(&events[0], Ok(StreamEvent::Delta(text)) if text == "anthropic"));
+        assert!(matches!(&events[1], Ok(StreamEvent::Done(r)) if r.usage.total_tokens == 15));
+        let budget = router.budget();
+        let budget = budget.lock().expect("budget");
+        assert_eq!(budget.tokens_by_model.get("claude-haiku-4-5"), Some(&(10, 5)));
+    }
+
+    /// Fails with `error` for its first `failures` calls.
+    struct FlakyProvider {
+        name: &'static str,
+        failures: u32,
+        error: fn() -> ModelError,
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    impl FlakyProvider {
+        fn new(name: &'static str, failures: u32, error: fn() -> ModelError) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                failures,
+                error,
+                calls: std::sync::atomic::AtomicU32::new(0),
+            })
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ModelProvider for FlakyProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn kind(&self) -> Option<ProviderKind> {
+            kind_of(self.name)
+        }
+
+        async fn complete(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse, ModelError> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if call < self.failures {
+                return Err((self.error)());
+            }
+            NamedProvider("ok").complete(request).await
+        }
+    }
+
+    fn fast_retries(router: ModelRouter) -> ModelRouter {
+        router.with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_secs(1),
+        })
+    }
+
+    fn overloaded() -> ModelError {
+        ModelError::ApiError {
+            status: 529,
+            message: "Overloaded".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_retries_transient_errors() {
+        let flaky = FlakyProvider::new("anthropic", 2, overloaded);
+        let router =
+            fast_retries(ModelRouter::with_providers(vec![flaky.clone()]).expect("router"));
+
+        let response = router.complete(request("claude-sonnet-4-5")).await;
+        assert_eq!(response.expect("complete").content, "ok");
+        assert_eq!(flaky.calls(), 3);
+
+        let flaky = FlakyProvider::new("anthropic", 1, || ModelError::ApiError {
+            status: 401,
+            message: "invalid x-api-key".to_string(),
+        });
+        let router =
+            fast_retries(ModelRouter::with_providers(vec![flaky.clone()]).expect("router"));
+        let error = router.complete(request("claude-sonnet-4-5")).await;
+        assert!(matches!(error, Err(ModelError::ApiError { status: 401, .. })));
+        assert_eq!(flaky.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_fails_over() {
+        let limited = FlakyProvider::new("openai", 1, || ModelError::RateLimited {
+            retry_after: Some(120),
+        });
+        let providers: Vec<Arc<dyn ModelProvider>> =
+            vec![limited.clone(), Arc::new(NamedProvider("openrouter"))];
+        let router = fast_retries(ModelRouter::with_providers(providers).expect("router"));
+
+        let response = router.complete(request("gpt-4o")).await.expect("complete");
+        assert_eq!(limited.calls(), 1);
+        assert_eq!(response.content, "openrouter");
+        assert_eq!(response.model, "openai/gpt-4o");
+
+        let budget = router.budget();
+        let budget = budget.lock().expect("budget");
+        assert_eq!(budget.tokens_by_model.get("openai/gpt-4o"), Some(&(10, 5)));
+    }
+
+    #[tokio::test]
+    async fn test_no_failover_to_other_model_families() {
+        let down = FlakyProvider::new("openai", 10, overloaded);
+        let providers: Vec<Arc<dyn ModelProvider>> =
+            vec![down.clone(), Arc::new(NamedProvider("anthropic"))];
+        let failing = fast_retries(ModelRouter::with_providers(providers).expect("router"));
+
+        let error = failing.complete(request("gpt-4o")).await;
+        assert!(matches!(error, Err(ModelError::ApiError { status: 529, .. })));
+        assert_eq!(down.calls(), 3);
+
+        let router = router(&["anthropic"]);
+        assert!(matches!(
+            router.complete(request("gpt-4o")).await,
+            Err(ModelError::ModelNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_skip_the_api_and_budget() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let counting = FlakyProvider::new("anthropic", 0, overloaded);
+        let router = ModelRouter::with_providers(vec![counting.clone()])
+            .expect("router")
+            .with_cache(Some(ResponseCache::new(dir.path())));
+
+        let first = router.complete(request("claude-sonnet-4-5")).await.expect("complete");
+        assert!(!first.cached);
+        let events: Vec<_> = router
+            .complete_stream(request("claude-sonnet-4-5"))
+            .await
+            .expect("stream")
+            .collect()
+            .await;
+        assert!(matches!(&events[1], Ok(StreamEvent::Done(r)) if r.cached && r.content == "ok"));
+        assert_eq!(counting.calls(), 1);
+
+        let budget = router.budget();
+        let budget = budget.lock().expect("budget");
+        assert_eq!(budget.cache_hits, 1);
+        assert_eq!(budget.tokens_by_model.get("claude-sonnet-4-5"), Some(&(10, 5)));
+        assert_eq!(budget.cached_tokens_by_model.get("claude-sonnet-4-5"), Some(&(10, 5)));
+        drop(budget);
+
+        // Without the cache every request reaches the provider
+        let uncached = router.with_cache(None);
+        uncached.complete(request("claude-sonnet-4-5")).await.expect("complete");
+        assert_eq!(counting.calls(), 2);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::default();
+        for retry in 1..=3 {
+            let delay = policy.delay(&overloaded(), retry).expect("delay");
+            let backoff = Duration::from_millis(500) * 2u32.pow(retry - 1);
+            assert!(delay >= backoff / 2 && delay <= backoff);
+        }
+        assert!(policy.delay(&overloaded(), 20).expect("delay") <= policy.max_delay);
+
+        let limited = |secs| ModelError::RateLimited {
+            retry_after: Some(secs),
+        };
+        assert_eq!(policy.delay(&limited(4), 1), Some(Duration::from_secs(4)));
+        assert_eq!(policy.delay(&limited(60), 1), None);
+    }
+
+    #[test]
+    fn test_from_config_builds_named_providers() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "providers": {
+                    "ollama": {"kind": "local", "models": ["qwen2.5-coder:14b"]},
+                    "openrouter": {"kind": "openrouter", "api_key": "sk-or-test"}
+                }
+            }"#,
+        )
+        .expect("config");
+        let mut secrets = SecretResolver::new(None);
+        let router = ModelRouter::from_config(&config, &mut secrets).expect("router");
+
+        let names = router.provider_names();
+        assert_eq!(&names[..2], ["ollama", "openrouter"]);
+        assert_eq!(router.select_model(&RoutingContext::default()), "qwen2.5-coder:14b");
+        assert_eq!(router.candidates("qwen2.5-coder:14b")[0].0.name(), "ollama");
+
+        let missing_key: Config = serde_json::from_str(
+            r#"{"version": "1.0", "providers": {"claude": {"kind": "anthropic"}}}"#,
+        )
+        .expect("config");
+        let err = ModelRouter::from_config(&missing_key, &mut secrets)
+            .err()
+            .expect("missing key");
+        assert!(err.to_string().contains("'claude' needs an api_key"));
+
+        // Built-in aliases follow the provider's kind, not its name
+        let renamed: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "providers": {
+                    "claude": {"kind": "anthropic", "api_key": "sk-ant-test"},
+                    "gateway": {"kind": "openrouter", "api_key": "sk-or-test"}
+                }
+            }"#,
+        )
+        .expect("config");
+        let router = ModelRouter::from_config(&renamed, &mut secrets).expect("router");
+        let gateway = router
+            .candidates("claude-sonnet-4-5")
+            .into_iter()
+            .find(|(p, _)| p.name() == "gateway")
+            .map(|(_, model)| model);
+        assert_eq!(gateway.as_deref(), Some("anthropic/claude-sonnet-4.5"));
+    }
+
+    #[test]
+    fn test_with_provider_restricts_and_sets_defaults() {
+        assert!(ModelRouter::with_providers(Vec::new()).is_err());
+
+        let router = router(&["openai", "anthropic"]);
+        let planning = RoutingContext {
+            task_type: TaskType::Planning,
+            ..RoutingContext::default()
+        };
+        assert_eq!(router.select_model(&planning), "gpt-4o");
+
+        let router = router.with_provider("anthropic").expect("anthropic");
+        assert_eq!(router.provider_names(), vec!["anthropic"]);
+        let selection = router.select(&planning);
+        assert_eq!(selection.model, "claude-sonnet-4-5");
+        assert_eq!(selection.provider, "anthropic");
+
+        let err = router.with_provider("openai").err().expect("missing");
+        assert!(err.to_string().contains("available: anthropic"));
+    }
+
+    #[test]
+    fn test_routing_rules_choose_configured_models() {
+        let rules: Vec<RoutingRule> = serde_json::from_str(
+            r#"[
+                {"name": "hard-tasks", "task": "execution", "min_complexity": 4,
+                 "models": ["claude-opus-4-1", "gpt-4o"]},
+                {"name": "cheap", "optimize": "cost",
+                 "models": ["claude-sonnet-4-5", "gpt-4o-mini", "claude-haiku-4-5"]}
+            ]"#,
+        )
+        .expect("rules");
+        let router = router(&["openai"]).with_rules(rules);
+
+        // Only models a configured provider serves are candidates
+        let hard = RoutingContext {
+            complexity: Some(5),
+            ..RoutingContext::default()
+        };
+        let selection = router.select(&hard);
+        assert_eq!(selection.model, "gpt-4o");
+        assert_eq!(selection.provider, "openai");
+        assert_eq!(
+            selection.reason,
+            "Routing rule 'hard-tasks': gpt-4o, the only usable model"
+        );
+
+        let cheap = RoutingContext {
+            optimize: OptimizeMode::Cost,
+            ..RoutingContext::default()
+        };
+        assert_eq!(router.select(&cheap).reason, "Routing rule 'cheap': gpt-4o-mini, the only usable model");
+
+        // No rule matches: the built-in choice for the mode
+        let selection = router.select(&RoutingContext::default());
+        assert_eq!(selection.model, "gpt-4o-mini");
+        assert!(selection.reason.starts_with("Balanced"));
+    }
+
+    #[test]
+    fn test_routing_rule_ties_use_catalog_metadata() {
+        let rule = |optimize: &str| -> Vec<RoutingRule> {
+            serde_json::from_str(&format!(
+                r#"[{{"optimize": "{optimize}", "models": ["claude-sonnet-4-5", "gpt-4o", "o1-mini"]}}]"#
+            ))
+            .expect("rules")
+        };
+        let context = |optimize| RoutingContext {
+            optimize,
+            ..RoutingContext::default()
+        };
+        let router = || router(&["openai", "anthropic"]);
+
+        let cost = router().with_rules(rule("cost")).select(&context(OptimizeMode::Cost));
+        assert_eq!(cost.model, "gpt-4o");
+        assert_eq!(cost.reason, "Routing rule '#1': gpt-4o, cheapest of 3 usable models");
+
+        let time = router().with_rules(rule("time")).select(&context(OptimizeMode::Time));
+        assert_eq!(time.model, "claude-sonnet-4-5");
+
+        let balanced = router().with_rules(rule("balanced"));
+        assert_eq!(balanced.select_model(&context(OptimizeMode::Balanced)), "claude-sonnet-4-5");
+
+        // Too much input for gpt-4o and o1-mini, and o1-mini has no tools
+        let large = RoutingContext {
+            estimated_tokens: Some(150_000),
+            ..context(OptimizeMode::Cost)
+        };
+        let router_cost = router().with_rules(rule("cost"));
+        assert_eq!(router_cost.select_model(&large), "claude-sonnet-4-5");
+        let tools = RoutingContext {
+            requires_tools: true,
+            ..context(OptimizeMode::Time)
+        };
+        let router_time = router().with_rules(rule("time"));
+        assert_eq!(router_time.select_model(&tools), "claude-sonnet-4-5");
+    }
+
+    #[test]
+    fn test_cost_and_time_modes_use_the_catalog() {
+        let router = router(&["anthropic"]);
+        let cost = router.select(&RoutingContext {
+            optimize: OptimizeMode::Cost,
+            ..RoutingContext::default()
+        });
+        assert_eq!(cost.model, "claude-3-5-haiku");
+        assert_eq!(cost.provider, "anthropic");
+        assert!(cost.reason.starts_with("Cost optimized: claude-3-5-haiku, cheapest of"));
+
+        let time = router.select(&RoutingContext {
+            optimize: OptimizeMode::Time,
+            task_type: TaskType::Planning,
+            ..RoutingContext::default()
+        });
+        assert_eq!(time.provider, "anthropic");
+        assert!(time.model.starts_with("claude"));
+    }
+
     #[test]
     fn test_select_model_cost_optimization() {
         let context = RoutingContext {
@@ -343,6 +1339,8 @@ mod tests {
             task_type: TaskType::Planning,
             estimated_tokens: None,
             model_override: None,
+            complexity: None,
+            requires_tools: false,
         };
 
         // Create a router-like selection (without providers)
@@ -362,6 +1360,8 @@ mod tests {
             task_type: TaskType::Execution,
             estimated_tokens: Some(1000),
             model_override: None,
+            complexity: None,
+            requires_tools: false,
         };
 
         let model = match (context.optimize, context.task_type) {
@@ -379,6 +1379,8 @@ mod tests {
             task_type: TaskType::Planning,
             estimated_tokens: None,
             model_override: Some("claude-3-5-sonnet".to_string()),
+            complexity: None,
+            requires_tools: false,
         };
 
         // Override should take precedence
@@ -0,0 +1,243 @@
+//! Server-sent events parsing for streaming completions.
+//!
+//! Both the Anthropic and OpenAI-compatible APIs stream completions as
+//! server-sent events. The parser here splits the raw response body into
+//! events, and [`completion_stream`] drives a provider-specific decoder over
+//! them to produce [`StreamEvent`]s.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    provider::{CompletionResponse, CompletionStream, StreamEvent, ToolCall},
+    ModelError, TokenUsage,
+};
+
+/// One server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, if present.
+    pub event: Option<String>,
+    /// The `data:` lines, joined with newlines.
+    pub data: String,
+}
+
+/// Incremental parser fed with raw response chunks.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Add a chunk and return the events it completes.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend(chunk.iter().filter(|&&b| b != b'\r'));
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            events.extend(parse_block(&String::from_utf8_lossy(&block)));
+        }
+        events
+    }
+
+    /// Flush an event left without a trailing blank line at end of stream.
+    pub fn finish(&mut self) -> Vec<SseEvent> {
+        let block = std::mem::take(&mut self.buffer);
+        parse_block(&String::from_utf8_lossy(&block))
+            .into_iter()
+            .collect()
+    }
+}
+
+fn parse_block(block: &str) -> Option<SseEvent> {
+    let mut event = SseEvent::default();
+    let mut data = Vec::new();
+    for line in block.lines() {
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event.event = Some(value.to_string()),
+            "data" => data.push(value),
+            _ => {}
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+    event.data = data.join("\n");
+    Some(event)
+}
+
+/// A streamed completion as it accumulates.
+#[derive(Debug, Default)]
+pub struct StreamState {
+    /// Text received so far.
+    pub content: String,
+    /// Model reported by the API.
+    pub model: String,
+    /// Reason the completion finished.
+    pub finish_reason: String,
+    /// Prompt tokens, once reported.
+    pub prompt_tokens: u32,
+    /// Completion tokens, once reported.
+    pub completion_tokens: u32,
+    /// Whether the API signalled the end of the stream.
+    pub done: bool,
+    /// Tool calls so far, keyed by the API's index for them.
+    pub tool_calls: BTreeMap<u64, PartialToolCall>,
+}
+
+/// A tool call whose arguments are still arriving.
+#[derive(Debug, Default)]
+pub struct PartialToolCall {
+    /// Call identifier.
+    pub id: String,
+    /// Tool name.
+    pub name: String,
+    /// Argument JSON received so far.
+    pub arguments: String,
+}
+
+impl StreamState {
+    /// The tool call at `index`, started if new.
+    pub fn tool_call(&mut self, index: u64) -> &mut PartialToolCall {
+        self.tool_calls.entry(index).or_default()
+    }
+
+    fn response(&self) -> Result<CompletionResponse, ModelError> {
+        let tool_calls = self
+            .tool_calls
+            .values()
+            .map(|call| {
+                let arguments = if call.arguments.trim().is_empty() {
+                    serde_json::Value::Object(serde_json::Map::new())
+                } else {
+                    serde_json::from_str(&call.arguments)
+                        .map_err(|e| ModelError::ParseError(format!("tool arguments: {e}")))?
+                };
+                Ok(ToolCall {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments,
+                })
+            })
+            .collect::<Result<_, ModelError>>()?;
+        Ok(CompletionResponse {
+            content: self.content.clone(),
+            usage: TokenUsage::new(self.prompt_tokens, self.completion_tokens),
+            model: self.model.clone(),
+            finish_reason: self.finish_reason.clone(),
+            tool_calls,
+            cached: false,
+        })
+    }
+}
+
+/// Decodes one event into state, returning any text delta it carries.
+pub type Decoder = fn(&mut StreamState, &SseEvent) -> Result<Option<String>, ModelError>;
+
+struct Driver {
+    response: Option<reqwest::Response>,
+    parser: SseParser,
+    state: StreamState,
+    decode: Decoder,
+    pending: VecDeque<Result<StreamEvent, ModelError>>,
+}
+
+impl Driver {
+    fn handle(&mut self, events: Vec<SseEvent>) {
+        for event in events {
+            match (self.decode)(&mut self.state, &event) {
+                Ok(Some(delta)) if !delta.is_empty() => {
+                    self.state.content.push_str(&delta);
+                    self.pending.push_back(Ok(StreamEvent::Delta(delta)));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.pending.push_back(Err(e));
+                    self.response = None;
+                    return;
+                }
+            }
+            if self.state.done {
+                self.pending
+                    .push_back(self.state.response().map(StreamEvent::Done));
+                self.response = None;
+                return;
+            }
+        }
+    }
+}
+
+/// Turn a successful SSE response into a completion stream.
+///
+/// The stream ends with [`StreamEvent::Done`] once the decoder marks the
+/// state done, or with an error if the connection closes first.
+pub fn completion_stream(response: reqwest::Response, decode: Decoder) -> CompletionStream {
+    let driver = Driver {
+        response: Some(response),
+        parser: SseParser::default(),
+        state: StreamState::default(),
+        decode,
+        pending: VecDeque::new(),
+    };
+
+    Box::pin(futures_util::stream::unfold(driver, |mut driver| async move {
+        loop {
+            if let Some(item) = driver.pending.pop_front() {
+                return Some((item, driver));
+            }
+            let response = driver.response.as_mut()?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    let events = driver.parser.push(&chunk);
+                    driver.handle(events);
+                }
+                Ok(None) => {
+                    let events = driver.parser.finish();
+                    driver.handle(events);
+                    if driver.response.take().is_some() {
+                        driver.pending.push_back(Err(ModelError::NetworkError(
+                            "Stream ended before the completion finished".to_string(),
+                        )));
+                    }
+                }
+                Err(e) => {
+                    driver.response = None;
+                    driver
+                        .pending
+                        .push_back(Err(ModelError::NetworkError(e.to_string())));
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_splits_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: ping\r\ndata: {}\r\n").is_empty());
+
+        let events = parser.push(b"\r\n: comment\ndata: a\ndata: b\n\ndata: par");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("ping".to_string()),
+                    data: "{}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "a\nb".to_string(),
+                },
+            ]
+        );
+
+        assert!(parser.push(b"tial").is_empty());
+        assert_eq!(parser.finish()[0].data, "partial");
+        assert!(parser.finish().is_empty());
+    }
+}
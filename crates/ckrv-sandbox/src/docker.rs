@@ -13,7 +13,7 @@ use bollard::models::{HostConfig, Mount, MountTypeEnum};
 use bollard::Docker;
 use futures_util::StreamExt;
 
-use crate::SandboxError;
+use crate::{ContainerRuntime, LineBuffer, OutputSink, OutputStream, SandboxError, UserMapping};
 
 /// Default Docker image for execution (contains Claude Code CLI).
 pub const DEFAULT_IMAGE: &str = "ckrv-agent:latest";
@@ -22,21 +22,37 @@ pub const DEFAULT_IMAGE: &str = "ckrv-agent:latest";
 pub struct DockerClient {
     client: Docker,
     default_image: String,
+    user_mapping: UserMapping,
 }
 
 impl DockerClient {
-    /// Create a new Docker client.
+    /// Create a client for the runtime detected from the environment.
     ///
     /// # Errors
     ///
     /// Returns an error if Docker is not available.
     pub fn new() -> Result<Self, SandboxError> {
-        let client = Docker::connect_with_local_defaults()
-            .map_err(|e| SandboxError::RuntimeNotAvailable(e.to_string()))?;
+        Self::with_runtime(&ContainerRuntime::detect())
+    }
+
+    /// Create a client for `runtime`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime's socket can't be used.
+    pub fn with_runtime(runtime: &ContainerRuntime) -> Result<Self, SandboxError> {
+        let client = runtime.connect()?;
+        tracing::debug!(
+            runtime = runtime.kind.as_str(),
+            socket = %runtime.socket,
+            rootless = runtime.rootless,
+            "Connecting to container runtime"
+        );
 
         Ok(Self {
             client,
             default_image: DEFAULT_IMAGE.to_string(),
+            user_mapping: runtime.user_mapping(),
         })
     }
 
@@ -78,6 +94,10 @@ impl DockerClient {
     }
 
     /// Execute a command in a container.
+    ///
+    /// Output is streamed line by line to `on_output` if given, otherwise
+    /// echoed to this process's stdout/stderr. On timeout the container is
+    /// killed and the output collected so far is returned.
     pub async fn execute(
         &self,
         command: Vec<String>,
@@ -87,6 +107,7 @@ impl DockerClient {
         env: HashMap<String, String>,
         timeout: Duration,
         keep_container: bool,
+        on_output: Option<OutputSink>,
     ) -> Result<ExecutionOutput, SandboxError> {
         let image = &self.default_image;
         self.ensure_image(image).await?;
@@ -147,39 +168,13 @@ impl DockerClient {
             });
         }
 
-        // Get current user UID:GID for proper permission handling
-        // Use `id` command since libc is unsafe
-        let uid_gid = std::process::Command::new("id")
-            .args(["-u"])
-            .output()
-            .ok()
-            .and_then(|o| String::from_utf8(o.stdout).ok())
-            .map(|s| s.trim().to_string())
-            .unwrap_or_else(|| "1000".to_string());
-
-        let gid = std::process::Command::new("id")
-            .args(["-g"])
-            .output()
-            .ok()
-            .and_then(|o| String::from_utf8(o.stdout).ok())
-            .map(|s| s.trim().to_string())
-            .unwrap_or_else(|| "1000".to_string());
-
-        let user_spec = format!("{}:{}", uid_gid, gid);
-
+        let config = container_config(&self.user_mapping, image, command, workdir, env_vec, mounts);
         let config = Config {
-            image: Some(image.to_string()),
-            cmd: Some(command),
-            working_dir: Some(workdir.to_string()),
-            user: Some(user_spec),
-            env: Some(env_vec),
-            host_config: Some(HostConfig {
-                mounts: Some(mounts),
-                network_mode: Some("host".to_string()), // Need network for Claude API
-                memory: Some(1024 * 1024 * 1024),       // 1GB limit for Claude
-                ..Default::default()
+            host_config: config.host_config.map(|host| HostConfig {
+                memory: Some(1024 * 1024 * 1024), // 1GB limit for Claude
+                ..host
             }),
-            ..Default::default()
+            ..config
         };
 
         let options = Some(CreateContainerOptions {
@@ -238,6 +233,9 @@ impl DockerClient {
         // Stream logs in the main task
         let mut log_stream = self.client.logs(&container.id, log_options);
         
+        let mut stdout_lines = LineBuffer::default();
+        let mut stderr_lines = LineBuffer::default();
+
         // Use a timeout for the *entire* execution, not just wait
         let log_collection = async {
             // Import Write trait for flush
@@ -246,14 +244,26 @@ impl DockerClient {
                 match log {
                     LogOutput::StdOut { message } => {
                         let s = String::from_utf8_lossy(&message);
-                        print!("{}", s); // Stream to parent stdout
-                        let _ = std::io::stdout().flush(); // Force flush
+                        if let Some(ref sink) = on_output {
+                            for line in stdout_lines.push(&s) {
+                                sink.emit(OutputStream::Stdout, &line);
+                            }
+                        } else {
+                            print!("{}", s); // Stream to parent stdout
+                            let _ = std::io::stdout().flush(); // Force flush
+                        }
                         stdout.push_str(&s);
                     }
                     LogOutput::StdErr { message } => {
                         let s = String::from_utf8_lossy(&message);
-                        eprint!("{}", s); // Stream to parent stderr
-                        let _ = std::io::stderr().flush(); // Force flush
+                        if let Some(ref sink) = on_output {
+                            for line in stderr_lines.push(&s) {
+                                sink.emit(OutputStream::Stderr, &line);
+                            }
+                        } else {
+                            eprint!("{}", s); // Stream to parent stderr
+                            let _ = std::io::stderr().flush(); // Force flush
+                        }
                         stderr.push_str(&s);
                     }
                     _ => {}
@@ -262,9 +272,20 @@ impl DockerClient {
         };
 
         // Run log collection with timeout
-        if let Err(_) = tokio::time::timeout(timeout, log_collection).await {
-             // Timeout occurred
-             let _ = self.client.kill_container::<String>(&container.id, None).await;
+        let timed_out = tokio::time::timeout(timeout, log_collection).await.is_err();
+        if timed_out {
+            tracing::warn!(container_id = %container.id, ?timeout, "Execution timed out, killing container");
+            let _ = self.client.kill_container::<String>(&container.id, None).await;
+        }
+
+        // Deliver any trailing partial lines
+        if let Some(ref sink) = on_output {
+            if let Some(line) = stdout_lines.finish() {
+                sink.emit(OutputStream::Stdout, &line);
+            }
+            if let Some(line) = stderr_lines.finish() {
+                sink.emit(OutputStream::Stderr, &line);
+            }
         }
         
         // Now wait for the exit code (it should be ready or close to ready)
@@ -304,6 +325,7 @@ impl DockerClient {
             stdout,
             stderr,
             duration_ms: duration.as_millis() as u64,
+            timed_out,
         })
     }
     pub async fn create_session(
@@ -354,18 +376,8 @@ impl DockerClient {
             });
         }
 
-        let config = Config {
-            image: Some(image.to_string()),
-            cmd: Some(vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()]), 
-            working_dir: Some(workdir.to_string()),
-            env: Some(env_vec),
-            host_config: Some(HostConfig {
-                mounts: Some(mounts),
-                network_mode: Some("host".to_string()),
-                ..Default::default()
-            }),
-            ..Default::default()
-        };
+        let command = vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()];
+        let config = container_config(&self.user_mapping, image, command, workdir, env_vec, mounts);
 
         let options = Some(CreateContainerOptions {
             name: container_name.clone(),
@@ -429,6 +441,7 @@ impl DockerClient {
             stdout,
             stderr: String::new(), // With TTY, stderr is merged into stdout
             duration_ms: duration.as_millis() as u64,
+            timed_out: false,
         })
     }
 
@@ -451,6 +464,36 @@ pub struct ExecutionOutput {
     pub stderr: String,
     /// Duration in milliseconds.
     pub duration_ms: u64,
+    /// Whether the command was killed for exceeding its timeout.
+    pub timed_out: bool,
+}
+
+/// Settings for an agent container: `command` run under `mapping` with the
+/// mounts bind-mounted and host networking (agents need their model API).
+fn container_config(
+    mapping: &UserMapping,
+    image: &str,
+    command: Vec<String>,
+    workdir: &str,
+    mut env: Vec<String>,
+    mounts: Vec<Mount>,
+) -> Config<String> {
+    env.extend(mapping.env.iter().cloned());
+    Config {
+        image: Some(image.to_string()),
+        cmd: Some(command),
+        working_dir: Some(workdir.to_string()),
+        // Run as the host user so files written to the mounts stay theirs
+        user: mapping.user.clone(),
+        env: Some(env),
+        host_config: Some(HostConfig {
+            mounts: Some(mounts),
+            network_mode: Some("host".to_string()),
+            userns_mode: mapping.userns_mode.clone(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
 }
 
 #[cfg(test)]
@@ -466,6 +509,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_agent_runs_as_mapped_root_under_rootless_docker() {
+        let runtime = ContainerRuntime {
+            kind: crate::RuntimeKind::Docker,
+            socket: "unix:///run/user/1000/docker.sock".to_string(),
+            rootless: true,
+            source: crate::RuntimeSource::Detected,
+        };
+        let command = ["claude", "-p", "hello", "--dangerously-skip-permissions"]
+            .map(String::from)
+            .to_vec();
+        let env = vec!["HOME=/home/claude".to_string()];
+
+        let config = container_config(
+            &runtime.user_mapping_for(1000, 1000),
+            DEFAULT_IMAGE,
+            command.clone(),
+            "/workspace",
+            env,
+            Vec::new(),
+        );
+
+        // Container root is the caller; Claude only skips permissions as
+        // root when told it is sandboxed
+        assert_eq!(config.cmd, Some(command));
+        assert_eq!(config.user.as_deref(), Some("0:0"));
+        let env = config.env.expect("env");
+        assert!(env.contains(&"IS_SANDBOX=1".to_string()), "{env:?}");
+        assert!(env.contains(&"HOME=/home/claude".to_string()), "{env:?}");
+    }
+
     #[test]
     fn test_execution_output_structure() {
         let output = ExecutionOutput {
@@ -473,6 +547,7 @@ mod tests {
             stdout: "success".to_string(),
             stderr: String::new(),
             duration_ms: 100,
+            timed_out: false,
         };
 
         assert_eq!(output.exit_code, 0);
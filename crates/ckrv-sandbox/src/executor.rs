@@ -5,7 +5,7 @@ use std::{collections::HashMap, path::PathBuf, time::Duration};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::{docker::DockerClient, AllowList, SandboxError};
+use crate::{docker::DockerClient, AllowList, ContainerRuntime, OutputSink, SandboxError};
 
 /// Configuration for command execution.
 #[derive(Debug, Clone)]
@@ -22,6 +22,8 @@ pub struct ExecuteConfig {
     pub timeout: Duration,
     /// Keep container after execution (for debugging).
     pub keep_container: bool,
+    /// Receives output line by line while the command runs.
+    pub on_output: Option<OutputSink>,
 }
 
 impl ExecuteConfig {
@@ -35,6 +37,7 @@ impl ExecuteConfig {
             env: HashMap::new(),
             timeout: Duration::from_secs(300),
             keep_container: false,
+            on_output: None,
         }
     }
 
@@ -65,6 +68,13 @@ impl ExecuteConfig {
         self.keep_container = keep;
         self
     }
+
+    /// Stream output line by line to `sink` instead of the parent's stdout/stderr.
+    #[must_use]
+    pub fn with_output_sink(mut self, sink: OutputSink) -> Self {
+        self.on_output = Some(sink);
+        self
+    }
 }
 
 /// Result of command execution.
@@ -78,6 +88,9 @@ pub struct ExecuteResult {
     pub stderr: String,
     /// Execution duration.
     pub duration_ms: u64,
+    /// Whether the command was killed for exceeding its timeout.
+    #[serde(default)]
+    pub timed_out: bool,
 }
 
 impl ExecuteResult {
@@ -132,6 +145,21 @@ impl DockerSandbox {
         })
     }
 
+    /// Create a sandbox on `runtime`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime's socket can't be used.
+    pub fn with_runtime(
+        allowlist: impl AllowList + 'static,
+        runtime: &ContainerRuntime,
+    ) -> Result<Self, SandboxError> {
+        Ok(Self {
+            client: DockerClient::with_runtime(runtime)?,
+            allowlist: Box::new(allowlist),
+        })
+    }
+
     /// Create with default allowlist.
     ///
     /// # Errors
@@ -168,6 +196,7 @@ impl Sandbox for DockerSandbox {
                 config.env,
                 config.timeout,
                 config.keep_container,
+                config.on_output,
             )
             .await?;
 
@@ -176,6 +205,7 @@ impl Sandbox for DockerSandbox {
             stdout: output.stdout,
             stderr: output.stderr,
             duration_ms: output.duration_ms,
+            timed_out: output.timed_out,
         })
     }
 
@@ -237,6 +267,7 @@ impl Sandbox for LocalSandbox {
             stdout: String::from_utf8_lossy(&output.stdout).to_string(),
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
             duration_ms: duration.as_millis() as u64,
+            timed_out: false,
         })
     }
 
@@ -271,6 +302,7 @@ mod tests {
             stdout: "ok".to_string(),
             stderr: String::new(),
             duration_ms: 100,
+            timed_out: false,
         };
         assert!(result.success());
     }
@@ -282,6 +314,7 @@ mod tests {
             stdout: String::new(),
             stderr: "error".to_string(),
             duration_ms: 50,
+            timed_out: false,
         };
         assert!(!result.success());
     }
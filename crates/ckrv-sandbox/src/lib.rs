@@ -7,9 +7,15 @@ pub mod docker;
 pub mod env;
 pub mod error;
 pub mod executor;
+pub mod output;
+pub mod runtime;
 
 pub use allowlist::{AllowList, DefaultAllowList};
 pub use docker::DockerClient;
 pub use env::{detect_env, EnvConfig};
 pub use error::SandboxError;
 pub use executor::{DockerSandbox, ExecuteConfig, ExecuteResult, LocalSandbox, Sandbox};
+pub use output::{LineBuffer, OutputSink, OutputStream};
+pub use runtime::{
+    ContainerRuntime, RuntimeConfig, RuntimeKind, RuntimeSource, UserMapping, RUNTIME_ENV, SOCKET_ENV,
+};
@@ -0,0 +1,103 @@
+//! Line-by-line output streaming for sandboxed commands.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// Which output stream a line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+type LineCallback = dyn Fn(OutputStream, &str) + Send + Sync;
+
+/// Callback that receives command output one line at a time, as it is produced.
+#[derive(Clone)]
+pub struct OutputSink(Arc<LineCallback>);
+
+impl OutputSink {
+    /// Create a sink from a callback.
+    pub fn new(callback: impl Fn(OutputStream, &str) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Deliver a line (without its trailing newline).
+    pub fn emit(&self, stream: OutputStream, line: &str) {
+        (self.0)(stream, line);
+    }
+}
+
+impl fmt::Debug for OutputSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OutputSink")
+    }
+}
+
+/// Splits arbitrary output chunks into complete lines.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    /// Append a chunk and return the lines it completed.
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(chunk);
+        let Some(last_newline) = self.pending.rfind('\n') else {
+            return Vec::new();
+        };
+
+        let rest = self.pending.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.pending, rest);
+        complete
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect()
+    }
+
+    /// Return any trailing partial line.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.pending))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_splits_chunks() {
+        let mut buffer = LineBuffer::default();
+
+        assert!(buffer.push("hel").is_empty());
+        assert_eq!(buffer.push("lo\nwor"), vec!["hello"]);
+        assert_eq!(buffer.push("ld\r\n\nend"), vec!["world", ""]);
+        assert_eq!(buffer.finish().as_deref(), Some("end"));
+        assert!(buffer.finish().is_none());
+    }
+
+    #[test]
+    fn test_output_sink_emits() {
+        let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let captured = Arc::clone(&lines);
+        let sink = OutputSink::new(move |stream, line| {
+            if let Ok(mut lines) = captured.lock() {
+                lines.push((stream, line.to_string()));
+            }
+        });
+
+        sink.emit(OutputStream::Stderr, "oops");
+        let lines = lines.lock().expect("lock");
+        assert_eq!(lines[0], (OutputStream::Stderr, "oops".to_string()));
+    }
+}
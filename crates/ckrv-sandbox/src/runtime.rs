@@ -0,0 +1,423 @@
+//! Container runtime selection.
+//!
+//! Sandboxes talk to Docker or Podman through the Docker Engine API. The
+//! runtime and socket come from, in order: `CKRV_CONTAINER_RUNTIME` /
+//! `CKRV_CONTAINER_SOCKET`, the project config, `DOCKER_HOST`, and finally
+//! whichever well-known socket exists on this machine. Rootless runtimes
+//! need a different user mapping for bind-mounted files to end up owned by
+//! the calling user, so the resolved [`ContainerRuntime`] also decides which
+//! user containers run as.
+
+use std::path::{Path, PathBuf};
+
+use bollard::{Docker, API_DEFAULT_VERSION};
+use serde::{Deserialize, Serialize};
+
+use crate::SandboxError;
+
+/// Environment variable selecting the runtime (`auto`, `docker` or `podman`).
+pub const RUNTIME_ENV: &str = "CKRV_CONTAINER_RUNTIME";
+
+/// Environment variable giving the runtime's API socket.
+pub const SOCKET_ENV: &str = "CKRV_CONTAINER_SOCKET";
+
+/// Read/write timeout for runtime API calls, in seconds.
+const CONNECT_TIMEOUT_SECS: u64 = 120;
+
+/// Rootful Docker's socket.
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// Rootful Podman's Docker-compatible socket.
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+/// Which container runtime to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeKind {
+    /// Use whichever runtime is found.
+    #[default]
+    Auto,
+    /// Docker, rootful or rootless.
+    Docker,
+    /// Podman through its Docker-compatible API socket.
+    Podman,
+}
+
+impl RuntimeKind {
+    /// Parse a runtime name as used in config and `CKRV_CONTAINER_RUNTIME`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Some(Self::Auto),
+            "docker" => Some(Self::Docker),
+            "podman" => Some(Self::Podman),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+        }
+    }
+}
+
+/// Container runtime settings from the project config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    /// Runtime to use.
+    #[serde(default)]
+    pub kind: RuntimeKind,
+    /// API socket, as a path or a `unix://`, `tcp://` or `http://` URI.
+    /// Found automatically when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+}
+
+/// Where the resolved runtime came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeSource {
+    /// `CKRV_CONTAINER_SOCKET`.
+    Environment,
+    /// The project config.
+    Config,
+    /// `DOCKER_HOST`.
+    DockerHost,
+    /// A well-known socket that exists on this machine.
+    Detected,
+    /// Nothing was found; the runtime's usual socket is assumed.
+    Default,
+}
+
+impl RuntimeSource {
+    /// Human-readable description.
+    #[must_use]
+    pub const fn describe(self) -> &'static str {
+        match self {
+            Self::Environment => SOCKET_ENV,
+            Self::Config => "project config",
+            Self::DockerHost => "DOCKER_HOST",
+            Self::Detected => "detected",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// User and user-namespace settings for a container.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UserMapping {
+    /// `uid:gid` to run as; `None` keeps the image's user.
+    pub user: Option<String>,
+    /// User namespace mode, such as Podman's `keep-id`.
+    pub userns_mode: Option<String>,
+    /// Extra `KEY=value` environment the container needs for this user.
+    pub env: Vec<String>,
+}
+
+/// A resolved container runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContainerRuntime {
+    /// Runtime behind the socket; never [`RuntimeKind::Auto`].
+    pub kind: RuntimeKind,
+    /// API endpoint, as a `unix://`, `tcp://` or `http://` URI.
+    pub socket: String,
+    /// Whether the daemon runs as the calling user rather than root.
+    pub rootless: bool,
+    /// Where the socket came from.
+    pub source: RuntimeSource,
+}
+
+impl ContainerRuntime {
+    /// Resolve `config` against the environment and the sockets present on
+    /// this machine.
+    #[must_use]
+    pub fn resolve(config: &RuntimeConfig) -> Self {
+        Host::current().resolve(config)
+    }
+
+    /// Resolve the runtime with no project config.
+    #[must_use]
+    pub fn detect() -> Self {
+        Self::resolve(&RuntimeConfig::default())
+    }
+
+    /// Connect to the runtime's API.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket URI is not supported.
+    pub fn connect(&self) -> Result<Docker, SandboxError> {
+        let connected = if self.socket.starts_with("unix://") {
+            Docker::connect_with_unix(&self.socket, CONNECT_TIMEOUT_SECS, API_DEFAULT_VERSION)
+        } else if self.socket.starts_with("tcp://") || self.socket.starts_with("http://") {
+            Docker::connect_with_http(&self.socket, CONNECT_TIMEOUT_SECS, API_DEFAULT_VERSION)
+        } else {
+            return Err(SandboxError::RuntimeNotAvailable(format!(
+                "unsupported socket {}",
+                self.socket
+            )));
+        };
+        connected.map_err(|e| SandboxError::RuntimeNotAvailable(e.to_string()))
+    }
+
+    /// How containers should run so files they write to bind mounts are
+    /// owned by the calling user.
+    ///
+    /// Rootful runtimes run as the caller's `uid:gid`. Rootless Docker maps
+    /// container root to the caller, so containers run as root, as they do
+    /// for a root caller; `IS_SANDBOX=1` lets agents such as Claude Code
+    /// skip permission prompts as root. Rootless Podman maps the caller into
+    /// the container with `keep-id`.
+    #[must_use]
+    pub fn user_mapping(&self) -> UserMapping {
+        let uid = nix::unistd::getuid().as_raw();
+        let gid = nix::unistd::getgid().as_raw();
+        self.user_mapping_for(uid, gid)
+    }
+
+    pub(crate) fn user_mapping_for(&self, uid: u32, gid: u32) -> UserMapping {
+        if uid == 0 || (self.rootless && self.kind == RuntimeKind::Docker) {
+            return UserMapping {
+                user: Some("0:0".to_string()),
+                userns_mode: None,
+                env: vec!["IS_SANDBOX=1".to_string()],
+            };
+        }
+        UserMapping {
+            user: Some(format!("{uid}:{gid}")),
+            userns_mode: (self.rootless && self.kind == RuntimeKind::Podman)
+                .then(|| "keep-id".to_string()),
+            env: Vec::new(),
+        }
+    }
+}
+
+/// Reads an environment variable, treating empty values as unset.
+type EnvLookup = dyn Fn(&str) -> Option<String>;
+
+/// The environment and filesystem that runtime resolution looks at.
+struct Host {
+    env: Box<EnvLookup>,
+    exists: Box<dyn Fn(&Path) -> bool>,
+    uid: u32,
+}
+
+impl Host {
+    fn current() -> Self {
+        Self {
+            env: Box::new(|key| std::env::var(key).ok().filter(|v| !v.is_empty())),
+            exists: Box::new(Path::exists),
+            uid: nix::unistd::getuid().as_raw(),
+        }
+    }
+
+    fn resolve(&self, config: &RuntimeConfig) -> ContainerRuntime {
+        let kind = (self.env)(RUNTIME_ENV)
+            .and_then(|name| RuntimeKind::parse(&name))
+            .unwrap_or(config.kind);
+
+        let explicit = (self.env)(SOCKET_ENV)
+            .map(|s| (s, RuntimeSource::Environment))
+            .or_else(|| config.socket.clone().map(|s| (s, RuntimeSource::Config)))
+            .or_else(|| {
+                let docker_host = (self.env)("DOCKER_HOST")?;
+                (kind != RuntimeKind::Podman).then_some((docker_host, RuntimeSource::DockerHost))
+            });
+        if let Some((socket, source)) = explicit {
+            return self.runtime(kind, &socket, source);
+        }
+
+        let candidates = self.candidates(kind);
+        let (kind, path, source) = candidates
+            .iter()
+            .find(|(_, path)| (self.exists)(path))
+            .map_or((candidates[0].0, &candidates[0].1, RuntimeSource::Default), |(kind, path)| {
+                (*kind, path, RuntimeSource::Detected)
+            });
+        self.runtime(kind, &path.to_string_lossy(), source)
+    }
+
+    /// Well-known sockets for `kind`, in the order they are tried.
+    fn candidates(&self, kind: RuntimeKind) -> Vec<(RuntimeKind, PathBuf)> {
+        let user_dir = self.user_runtime_dir();
+        let docker = [
+            (RuntimeKind::Docker, PathBuf::from(DOCKER_SOCKET)),
+            (RuntimeKind::Docker, user_dir.join("docker.sock")),
+        ];
+        let podman = [
+            (RuntimeKind::Podman, user_dir.join("podman/podman.sock")),
+            (RuntimeKind::Podman, PathBuf::from(PODMAN_SOCKET)),
+        ];
+        match kind {
+            RuntimeKind::Docker => docker.into(),
+            RuntimeKind::Podman => podman.into(),
+            RuntimeKind::Auto => docker.into_iter().chain(podman).collect(),
+        }
+    }
+
+    fn runtime(&self, kind: RuntimeKind, socket: &str, source: RuntimeSource) -> ContainerRuntime {
+        let socket = if socket.contains("://") {
+            socket.to_string()
+        } else {
+            format!("unix://{socket}")
+        };
+        let path = socket.strip_prefix("unix://").map(Path::new);
+        let kind = match kind {
+            RuntimeKind::Auto if socket.contains("podman") => RuntimeKind::Podman,
+            RuntimeKind::Auto => RuntimeKind::Docker,
+            kind => kind,
+        };
+        let rootless = self.uid != 0 && path.is_some_and(|p| self.is_user_owned(p));
+        ContainerRuntime {
+            kind,
+            socket,
+            rootless,
+            source,
+        }
+    }
+
+    /// Whether `socket` lives somewhere only a per-user daemon would put it.
+    fn is_user_owned(&self, socket: &Path) -> bool {
+        socket.starts_with(self.user_runtime_dir())
+            || (self.env)("HOME").is_some_and(|home| socket.starts_with(home))
+    }
+
+    fn user_runtime_dir(&self) -> PathBuf {
+        (self.env)("XDG_RUNTIME_DIR")
+            .map_or_else(|| PathBuf::from(format!("/run/user/{}", self.uid)), PathBuf::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn host(env: &[(&str, &str)], sockets: &[&str]) -> Host {
+        let env: HashMap<String, String> =
+            env.iter().map(|(k, v)| ((*k).to_string(), (*v).to_string())).collect();
+        let sockets: Vec<PathBuf> = sockets.iter().map(PathBuf::from).collect();
+        Host {
+            env: Box::new(move |key| env.get(key).cloned()),
+            exists: Box::new(move |path| sockets.iter().any(|s| s == path)),
+            uid: 1000,
+        }
+    }
+
+    #[test]
+    fn test_auto_prefers_rootful_docker() {
+        let host = host(&[], &[DOCKER_SOCKET, "/run/user/1000/podman/podman.sock"]);
+        let runtime = host.resolve(&RuntimeConfig::default());
+        assert_eq!(runtime.kind, RuntimeKind::Docker);
+        assert_eq!(runtime.socket, "unix:///var/run/docker.sock");
+        assert!(!runtime.rootless);
+        assert_eq!(runtime.source, RuntimeSource::Detected);
+    }
+
+    #[test]
+    fn test_auto_falls_back_to_rootless_podman() {
+        let host = host(
+            &[("XDG_RUNTIME_DIR", "/run/user/1000")],
+            &["/run/user/1000/podman/podman.sock"],
+        );
+        let runtime = host.resolve(&RuntimeConfig::default());
+        assert_eq!(runtime.kind, RuntimeKind::Podman);
+        assert_eq!(runtime.socket, "unix:///run/user/1000/podman/podman.sock");
+        assert!(runtime.rootless);
+        assert_eq!(
+            runtime.user_mapping_for(1000, 1000),
+            UserMapping {
+                user: Some("1000:1000".to_string()),
+                userns_mode: Some("keep-id".to_string()),
+                env: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_nothing_found_assumes_the_runtimes_usual_socket() {
+        let runtime = host(&[], &[]).resolve(&RuntimeConfig::default());
+        assert_eq!(runtime.socket, "unix:///var/run/docker.sock");
+        assert_eq!(runtime.source, RuntimeSource::Default);
+
+        let podman = RuntimeConfig {
+            kind: RuntimeKind::Podman,
+            socket: None,
+        };
+        let runtime = host(&[], &[]).resolve(&podman);
+        assert_eq!(runtime.kind, RuntimeKind::Podman);
+        assert_eq!(runtime.socket, "unix:///run/user/1000/podman/podman.sock");
+        assert!(runtime.rootless);
+    }
+
+    #[test]
+    fn test_env_overrides_config_and_docker_host() {
+        let config = RuntimeConfig {
+            kind: RuntimeKind::Docker,
+            socket: Some("/srv/docker.sock".to_string()),
+        };
+        let runtime = host(&[("DOCKER_HOST", "tcp://10.0.0.1:2375")], &[]).resolve(&config);
+        assert_eq!(runtime.socket, "unix:///srv/docker.sock");
+        assert_eq!(runtime.source, RuntimeSource::Config);
+
+        let host = host(
+            &[(RUNTIME_ENV, "podman"), (SOCKET_ENV, "/tmp/custom.sock")],
+            &[DOCKER_SOCKET],
+        );
+        let runtime = host.resolve(&config);
+        assert_eq!(runtime.kind, RuntimeKind::Podman);
+        assert_eq!(runtime.socket, "unix:///tmp/custom.sock");
+        assert_eq!(runtime.source, RuntimeSource::Environment);
+    }
+
+    #[test]
+    fn test_docker_host_is_used_in_auto_mode() {
+        let host = host(&[("DOCKER_HOST", "tcp://10.0.0.1:2375")], &[DOCKER_SOCKET]);
+        let runtime = host.resolve(&RuntimeConfig::default());
+        assert_eq!(runtime.kind, RuntimeKind::Docker);
+        assert_eq!(runtime.socket, "tcp://10.0.0.1:2375");
+        assert_eq!(runtime.source, RuntimeSource::DockerHost);
+        assert!(!runtime.rootless);
+    }
+
+    #[test]
+    fn test_user_mapping() {
+        let rootful = host(&[], &[DOCKER_SOCKET]).resolve(&RuntimeConfig::default());
+        assert_eq!(rootful.user_mapping_for(1000, 100).user.as_deref(), Some("1000:100"));
+        assert_eq!(rootful.user_mapping_for(1000, 100).userns_mode, None);
+        let root = UserMapping {
+            user: Some("0:0".to_string()),
+            userns_mode: None,
+            env: vec!["IS_SANDBOX=1".to_string()],
+        };
+        assert_eq!(rootful.user_mapping_for(0, 0), root);
+
+        let rootless_docker =
+            host(&[], &["/run/user/1000/docker.sock"]).resolve(&RuntimeConfig::default());
+        assert!(rootless_docker.rootless);
+        assert_eq!(rootless_docker.user_mapping_for(1000, 100), root);
+    }
+
+    #[test]
+    fn test_runtime_kind_parse() {
+        assert_eq!(RuntimeKind::parse("Podman"), Some(RuntimeKind::Podman));
+        assert_eq!(RuntimeKind::parse(""), Some(RuntimeKind::Auto));
+        assert_eq!(RuntimeKind::parse("lxc"), None);
+    }
+
+    #[test]
+    fn test_connect_rejects_unknown_scheme() {
+        let runtime = ContainerRuntime {
+            kind: RuntimeKind::Docker,
+            socket: "ssh://host".to_string(),
+            rootless: false,
+            source: RuntimeSource::Config,
+        };
+        assert!(runtime.connect().is_err());
+    }
+}
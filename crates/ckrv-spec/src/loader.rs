@@ -2,6 +2,7 @@
 
 use std::path::Path;
 
+use ckrv_core::interpolate;
 use ckrv_core::Spec;
 
 use crate::SpecError;
@@ -31,9 +32,18 @@ impl SpecLoader for YamlSpecLoader {
         let content =
             std::fs::read_to_string(path).map_err(|e| SpecError::ReadError(e.to_string()))?;
 
-        let mut spec: Spec =
+        let mut value: serde_yaml::Value =
             serde_yaml::from_str(&content).map_err(|e| SpecError::ParseError(e.to_string()))?;
 
+        // Only the verify block is expanded; the rest is prose for the agent
+        if let Some(verify) = value.get_mut("verify") {
+            interpolate::interpolate_yaml_field(verify, &path.display().to_string(), "verify", &[])
+                .map_err(|e| SpecError::ParseError(e.to_string()))?;
+        }
+
+        let mut spec: Spec =
+            serde_yaml::from_value(value).map_err(|e| SpecError::ParseError(e.to_string()))?;
+
         spec.source_path = Some(path.to_path_buf());
         Ok(spec)
     }
@@ -49,7 +49,7 @@ import { Switch } from '@/components/ui/switch';
 import { Label } from '@/components/ui/label';
 
 // Types
-type AgentType = 'claude' | 'claude_open_router' | 'gemini' | 'codex' | 'cursor' | 'amp' | 'qwen_code' | 'opencode' | 'factory_droid' | 'copilot';
+type AgentType = 'claude' | 'claude_open_router' | 'gemini' | 'codex' | 'cursor' | 'amp' | 'qwen_code' | 'opencode' | 'aider' | 'mock' | 'factory_droid' | 'copilot';
 
 interface OpenRouterConfig {
     api_key?: string;
@@ -139,6 +139,8 @@ const AGENT_TYPE_INFO: Record<AgentType, { label: string; icon: React.ReactNode;
     amp: { label: 'Amp', icon: <Zap size={16} />, color: 'var(--accent-amber)' },
     qwen_code: { label: 'Qwen Code', icon: <Bot size={16} />, color: 'var(--accent-cyan)' },
     opencode: { label: 'Opencode', icon: <Bot size={16} />, color: 'var(--accent-green)' },
+    aider: { label: 'Aider', icon: <Bot size={16} />, color: 'var(--accent-amber)' },
+    mock: { label: 'Mock (record/replay)', icon: <Bot size={16} />, color: 'var(--text-secondary)' },
     factory_droid: { label: 'Factory Droid', icon: <Bot size={16} />, color: 'var(--accent-purple)' },
     copilot: { label: 'GitHub Copilot', icon: <Bot size={16} />, color: 'var(--text-primary)' },
 };
@@ -2,110 +2,18 @@
 
 use axum::{extract::State, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
 use std::path::PathBuf;
 
 use crate::state::AppState;
 
-/// Agent type enumeration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum AgentType {
-    /// Default Claude Code CLI
-    Claude,
-    /// Claude Code with custom OpenRouter API
-    ClaudeOpenRouter,
-    /// Gemini CLI
-    Gemini,
-    /// OpenAI Codex CLI
-    Codex,
-    /// Cursor CLI
-    Cursor,
-    /// Amp CLI
-    Amp,
-    /// Qwen Code CLI
-    QwenCode,
-    /// Opencode CLI
-    Opencode,
-    /// Factory Droid
-    FactoryDroid,
-    /// GitHub Copilot (via CLI)
-    Copilot,
-}
-
-impl Default for AgentType {
-    fn default() -> Self {
-        Self::Claude
-    }
-}
-
-/// Model provider for OpenRouter-based agents
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OpenRouterConfig {
-    /// API key for OpenRouter
-    pub api_key: Option<String>,
-    /// Model identifier (e.g., "moonshot/kimi-k2", "minimax/minimax-m1")
-    pub model: String,
-    /// Custom base URL (default: https://openrouter.ai/api/v1)
-    pub base_url: Option<String>,
-    /// Max tokens
-    pub max_tokens: Option<u32>,
-    /// Temperature
-    pub temperature: Option<f32>,
-}
-
-/// Agent configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentConfig {
-    /// Unique identifier
-    pub id: String,
-    /// Display name
-    pub name: String,
-    /// Agent type
-    #[serde(default)]
-    pub agent_type: AgentType,
-    /// Capability level (1-5, where 5 is strongest/most capable)
-    /// Used for task-to-agent matching based on complexity
-    #[serde(default = "default_level")]
-    pub level: u8,
-    /// Whether this is the default agent
-    #[serde(default)]
-    pub is_default: bool,
-    /// Whether this agent is enabled
-    #[serde(default = "default_enabled")]
-    pub enabled: bool,
-    /// Description
-    pub description: Option<String>,
-    /// OpenRouter configuration (for ClaudeOpenRouter type)
-    pub openrouter: Option<OpenRouterConfig>,
-    /// Custom CLI binary path (if not using default)
-    pub binary_path: Option<String>,
-    /// Additional CLI arguments
-    pub extra_args: Option<Vec<String>>,
-    /// Environment variables to set
-    pub env_vars: Option<HashMap<String, String>>,
-}
-
-fn default_level() -> u8 {
-    3 // Default to mid-tier
-}
-
-fn default_enabled() -> bool {
-    true
-}
-
-/// Agents configuration file
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct AgentsFile {
-    pub agents: Vec<AgentConfig>,
-}
+pub use ckrv_core::agent_config::{AgentConfig, AgentsFile, OpenRouterConfig};
+pub use ckrv_core::AgentType;
+use ckrv_core::secrets::SecretResolver;
 
 /// Get the path to the agents config file
 fn get_agents_path(state: &AppState) -> PathBuf {
     // Proactively use global config path to avoid storing secrets in repo
-    dirs::config_dir()
-        .map(|d| d.join("chakravarti").join("agents.yaml"))
+    AgentsFile::global_path()
         .unwrap_or_else(|| state.project_root.join(".chakravarti").join("agents.yaml"))
 }
 
@@ -114,51 +22,30 @@ fn ensure_defaults(agents: &mut AgentsFile) {
     if agents.agents.is_empty() {
         // Add default Claude Code agent
         agents.agents.push(AgentConfig {
-            id: "claude-default".to_string(),
-            name: "Claude Code".to_string(),
-            agent_type: AgentType::Claude,
             level: 5, // Default Claude is strongest
             is_default: true,
-            enabled: true,
             description: Some("Default Claude Code CLI agent".to_string()),
-            openrouter: None,
-            binary_path: None,
-            extra_args: None,
-            env_vars: None,
+            ..AgentConfig::new("claude-default", "Claude Code", AgentType::Claude)
         });
     }
 }
 
-/// Load agents from config file
+/// Load agents from config file (the same file the CLI reads), keeping
+/// `${VAR}` references as written so saving doesn't bake them in
 fn load_agents(state: &AppState) -> AgentsFile {
-    let path = get_agents_path(state);
-    
-    if let Ok(content) = fs::read_to_string(&path) {
-        if let Ok(mut agents) = serde_yaml::from_str::<AgentsFile>(&content) {
-            ensure_defaults(&mut agents);
-            return agents;
-        }
-    }
-    
-    // Return default agents if file doesn't exist or is invalid
-    let mut default_agents = AgentsFile::default();
-    ensure_defaults(&mut default_agents);
-    default_agents
+    let mut agents = AgentsFile::load_for_project_raw(&state.project_root).unwrap_or_else(|e| {
+        eprintln!("Invalid agents configuration, using defaults: {}", e);
+        AgentsFile::default()
+    });
+    ensure_defaults(&mut agents);
+    agents
 }
 
 /// Save agents to config file
 fn save_agents(state: &AppState, agents: &AgentsFile) -> Result<(), String> {
-    let path = get_agents_path(state);
-    
-    // Ensure .chakravarti directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
-    }
-    
-    let yaml = serde_yaml::to_string(agents).map_err(|e| format!("Failed to serialize: {}", e))?;
-    fs::write(&path, yaml).map_err(|e| format!("Failed to write file: {}", e))?;
-    
-    Ok(())
+    agents
+        .save(&get_agents_path(state))
+        .map_err(|e| e.to_string())
 }
 
 /// List all agents
@@ -516,8 +403,17 @@ pub struct TestAgentPayload {
     pub agent: AgentConfig,
 }
 
-pub async fn test_agent(Json(payload): Json<TestAgentPayload>) -> impl IntoResponse {
-    // Test the agent configuration
+pub async fn test_agent(Json(mut payload): Json<TestAgentPayload>) -> impl IntoResponse {
+    // Test the agent configuration as it will run, with variables expanded
+    payload.agent = match payload.agent.expanded() {
+        Ok(agent) => agent,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": e.to_string()
+            }))
+        }
+    };
     let result = match payload.agent.agent_type {
         AgentType::Claude => {
             // Test Claude CLI
@@ -539,6 +435,11 @@ pub async fn test_agent(Json(payload): Json<TestAgentPayload>) -> impl IntoRespo
             if let Some(ref config) = payload.agent.openrouter {
                 if config.api_key.is_none() || config.api_key.as_ref().map(|k| k.is_empty()).unwrap_or(true) {
                     Err("OpenRouter API key is required".to_string())
+                } else if let Some(Err(e)) = config.api_key.as_deref().map(|key| {
+                    let root = std::env::current_dir().ok();
+                    SecretResolver::new(root.as_deref()).resolve(key)
+                }) {
+                    Err(e.to_string())
                 } else {
                     // For now, just validate the config exists
                     Ok(format!("OpenRouter config valid for model: {}", config.model))
@@ -554,9 +455,17 @@ pub async fn test_agent(Json(payload): Json<TestAgentPayload>) -> impl IntoRespo
                 Err(e) => Err(format!("Gemini CLI not found: {}", e)),
             }
         }
+        AgentType::Mock => Ok("Mock agent replays recorded transcripts".to_string()),
         _ => {
-            // Generic test for other agents
-            Ok(format!("{:?} agent configured", payload.agent.agent_type))
+            // Other agent CLIs: check the binary their backend would run
+            let binary = payload.agent.to_profile().binary;
+            match std::process::Command::new(&binary).arg("--version").output() {
+                Ok(output) => Ok(format!(
+                    "{binary} available: {}",
+                    String::from_utf8_lossy(&output.stdout).trim()
+                )),
+                Err(e) => Err(format!("{binary} not found: {e}")),
+            }
         }
     };
     
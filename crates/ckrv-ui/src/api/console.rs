@@ -51,6 +51,7 @@ pub async fn execute_command(
         env: env.clone(),
         timeout: std::time::Duration::from_secs(30),
         keep_container: payload.keep_container,
+        on_output: None,
     };
 
     println!("Executing command: '{}' in {}", payload.command, cwd.display());
@@ -58,7 +59,10 @@ pub async fn execute_command(
     if payload.use_sandbox {
         // Use Docker Sandbox
         let allowlist = DefaultAllowList::default();
-        let sandbox_res = DockerSandbox::new(allowlist);
+        let sandbox_res = match state.container_runtime() {
+            Ok(runtime) => DockerSandbox::with_runtime(allowlist, &runtime),
+            Err(e) => return error_response(format!("Failed to load project config: {e}")),
+        };
         
         match sandbox_res {
              Ok(sandbox) => {
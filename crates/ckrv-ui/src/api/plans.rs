@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use crate::state::AppState;
+use ckrv_metrics::pricing::{parse_openrouter_models, PricingCatalog, OPENROUTER_CACHE};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ModelAssignment {
@@ -171,23 +172,17 @@ pub async fn save_plan(
 }
 
 // OpenRouter Models & Pricing
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenRouterModelPricing {
-    prompt: String,
-    completion: String,
+/// Display names from an OpenRouter `/models` response; prices are read
+/// through the shared catalog.
+#[derive(Debug, Deserialize)]
+struct OpenRouterNames {
+    data: Vec<OpenRouterName>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenRouterModel {
+#[derive(Debug, Deserialize)]
+struct OpenRouterName {
     id: String,
     name: String,
-    pricing: Option<OpenRouterModelPricing>,
-    context_length: Option<u64>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenRouterResponse {
-    data: Vec<OpenRouterModel>,
 }
 
 #[derive(Debug, Serialize)]
@@ -205,42 +200,57 @@ pub struct ModelsResponse {
     pub models: Vec<ModelInfo>,
 }
 
-pub async fn get_openrouter_models() -> impl IntoResponse {
-    let client = reqwest::Client::new();
-    match client.get("https://openrouter.ai/api/v1/models").send().await {
-        Ok(resp) => {
-            if let Ok(data) = resp.json::<OpenRouterResponse>().await {
-                let models: Vec<ModelInfo> = data.data.into_iter().map(|m| {
-                    let prompt_cost = m.pricing.as_ref()
-                        .and_then(|p| p.prompt.parse::<f64>().ok())
-                        .unwrap_or(0.0) * 1000.0;
-                    let completion_cost = m.pricing.as_ref()
-                        .and_then(|p| p.completion.parse::<f64>().ok())
-                        .unwrap_or(0.0) * 1000.0;
-                    
-                    ModelInfo {
-                        id: m.id,
-                        name: m.name,
-                        cost_per_1k_prompt: prompt_cost,
-                        cost_per_1k_completion: completion_cost,
-                        context_length: m.context_length.unwrap_or(0),
-                    }
-                }).collect();
-
-                Json(ModelsResponse {
-                    success: true,
-                    models,
-                })
-            } else {
-                Json(ModelsResponse {
-                    success: false,
-                    models: vec![],
-                })
+/// OpenRouter models priced by the shared catalog. A fresh `/models`
+/// response is cached in the project so later cost estimates use it too.
+pub async fn get_openrouter_models(State(state): State<AppState>) -> impl IntoResponse {
+    let cache = state.project_root.join(OPENROUTER_CACHE);
+    let fetched = match reqwest::get("https://openrouter.ai/api/v1/models").await {
+        Ok(resp) => resp.text().await.ok().filter(|body| parse_openrouter_models(body).is_ok()),
+        Err(_) => None,
+    };
+    if let Some(body) = &fetched {
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = cache.parent() {
+                fs::create_dir_all(parent)?;
             }
-        },
-        Err(_) => Json(ModelsResponse {
+            fs::write(&cache, body)
+        };
+        if let Err(e) = write() {
+            eprintln!("Failed to cache OpenRouter models in {}: {}", cache.display(), e);
+        }
+    }
+    let Some(body) = fetched.or_else(|| fs::read_to_string(&cache).ok()) else {
+        return Json(ModelsResponse {
             success: false,
             models: vec![],
-        }),
-    }
+        });
+    };
+    let Ok(names) = serde_json::from_str::<OpenRouterNames>(&body) else {
+        return Json(ModelsResponse {
+            success: false,
+            models: vec![],
+        });
+    };
+
+    // Prices come from the catalog, so project overrides apply here too
+    let catalog = PricingCatalog::load(&state.project_root);
+    let models = names
+        .data
+        .into_iter()
+        .filter_map(|m| {
+            let pricing = catalog.get(&m.id)?;
+            Some(ModelInfo {
+                cost_per_1k_prompt: pricing.input_cost_per_million / 1000.0,
+                cost_per_1k_completion: pricing.output_cost_per_million / 1000.0,
+                context_length: u64::from(pricing.context_window),
+                id: m.id,
+                name: m.name,
+            })
+        })
+        .collect();
+
+    Json(ModelsResponse {
+        success: true,
+        models,
+    })
 }
@@ -16,6 +16,13 @@ static SESSIONS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| {
     Mutex::new(HashMap::new())
 });
 
+/// A client for the container runtime configured for the project.
+fn docker_client(state: &AppState) -> Result<DockerClient, String> {
+    let runtime = state.container_runtime()
+        .map_err(|e| format!("failed to load project config: {e}"))?;
+    DockerClient::with_runtime(&runtime).map_err(|e| e.to_string())
+}
+
 /// Request to start a new session
 #[derive(Debug, Deserialize)]
 pub struct StartSessionRequest {
@@ -81,7 +88,7 @@ pub async fn start_session(
     }
 
     // Create Docker client
-    let client = match DockerClient::new() {
+    let client = match docker_client(&state) {
         Ok(c) => c,
         Err(e) => {
             return Json(StartSessionResponse {
@@ -125,6 +132,7 @@ pub async fn start_session(
 
 /// Execute a command in an existing session
 pub async fn exec_in_session(
+    State(state): State<AppState>,
     Json(payload): Json<ExecRequest>,
 ) -> impl IntoResponse {
     // Look up container ID
@@ -148,7 +156,7 @@ pub async fn exec_in_session(
     };
 
     // Create Docker client
-    let client = match DockerClient::new() {
+    let client = match docker_client(&state) {
         Ok(c) => c,
         Err(e) => {
             return Json(ExecResponse {
@@ -191,6 +199,7 @@ pub async fn exec_in_session(
 
 /// Stop and clean up a session
 pub async fn stop_session(
+    State(state): State<AppState>,
     Json(payload): Json<StopSessionRequest>,
 ) -> impl IntoResponse {
     // Remove from store
@@ -210,7 +219,7 @@ pub async fn stop_session(
     };
 
     // Stop container
-    let client = match DockerClient::new() {
+    let client = match docker_client(&state) {
         Ok(c) => c,
         Err(e) => {
             return Json(StopSessionResponse {
@@ -9,7 +9,6 @@ use axum::{
     },
     response::IntoResponse,
 };
-use bollard::Docker;
 use bollard::exec::{CreateExecOptions, StartExecOptions, StartExecResults};
 use bollard::container::LogOutput;
 use futures_util::{SinkExt, StreamExt};
@@ -22,6 +21,7 @@ use tokio::io::AsyncWriteExt;
 
 use crate::state::AppState;
 use crate::api::agents::{AgentConfig, AgentType};
+use ckrv_core::secrets::SecretResolver;
 
 // Session store for container IDs
 static TERMINAL_SESSIONS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| {
@@ -68,8 +68,32 @@ pub async fn start_terminal_session(
         }
     }
 
+    // Agents arrive as written in agents.yaml; expand ${VAR} references
+    let agent = match payload.agent.as_ref().map(AgentConfig::expanded).transpose() {
+        Ok(agent) => agent,
+        Err(e) => {
+            return axum::Json(super::session::StartSessionResponse {
+                success: false,
+                session_id: payload.session_id,
+                container_id: None,
+                message: Some(format!("Invalid agent configuration: {}", e)),
+            });
+        }
+    };
+
     // Create Docker client
-    let docker = match Docker::connect_with_local_defaults() {
+    let runtime = match state.container_runtime() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            return axum::Json(super::session::StartSessionResponse {
+                success: false,
+                session_id: payload.session_id,
+                container_id: None,
+                message: Some(format!("Failed to load project config: {e}")),
+            });
+        }
+    };
+    let docker = match runtime.connect() {
         Ok(d) => d,
         Err(e) => {
             return axum::Json(super::session::StartSessionResponse {
@@ -94,38 +118,24 @@ pub async fn start_terminal_session(
     
     // Build environment variables based on agent type
     let mut env_vars = vec![format!("HOME={}", container_home)];
+    // Agent variables may hold secret: references, resolved just before launch
+    let mut agent_env: Vec<(String, String)> = Vec::new();
     
-    let is_openrouter = payload.agent.as_ref()
+    let is_openrouter = agent.as_ref()
         .map(|a| matches!(a.agent_type, AgentType::ClaudeOpenRouter))
         .unwrap_or(false);
     
     if is_openrouter {
         // OpenRouter configuration for Claude Code
         // See: https://openrouter.ai/docs/guides/guides/claude-code-integration
-        if let Some(ref agent) = payload.agent {
-            if let Some(ref openrouter_config) = agent.openrouter {
-                // Required: Set base URL to OpenRouter
-                env_vars.push("ANTHROPIC_BASE_URL=https://openrouter.ai/api".to_string());
-                
-                // Required: Set auth token to OpenRouter API key
-                if let Some(ref api_key) = openrouter_config.api_key {
-                    env_vars.push(format!("ANTHROPIC_AUTH_TOKEN={}", api_key));
-                    env_vars.push(format!("OPENROUTER_API_KEY={}", api_key));
-                }
-                
-                // Required: Explicitly blank out Anthropic API key to prevent conflicts
-                env_vars.push("ANTHROPIC_API_KEY=".to_string());
-                
-                // Optional: Set default model if specified (e.g., z-ai/glm-4.7)
-                if !openrouter_config.model.is_empty() {
-                    env_vars.push(format!("ANTHROPIC_DEFAULT_SONNET_MODEL={}", openrouter_config.model));
-                    // Also set opus and haiku to same model for consistency
-                    env_vars.push(format!("ANTHROPIC_DEFAULT_OPUS_MODEL={}", openrouter_config.model));
-                    env_vars.push(format!("ANTHROPIC_DEFAULT_HAIKU_MODEL={}", openrouter_config.model));
-                }
-                
-                println!("OpenRouter agent configured: model={}", openrouter_config.model);
+        // Same variables the runner sets (base URL, token, model tiers)
+        if let Some(ref agent) = agent {
+            let profile = agent.to_profile();
+            agent_env.extend(profile.agent_type.backend().env(&profile));
+            if let Some(ref api_key) = profile.openrouter_api_key {
+                agent_env.push(("OPENROUTER_API_KEY".to_string(), api_key.clone()));
             }
+            println!("OpenRouter agent configured: model={:?}", profile.model);
         }
         
         // For OpenRouter, we do NOT mount Claude credentials
@@ -144,24 +154,37 @@ pub async fn start_terminal_session(
     }
     
     // Add any custom env vars from agent config
-    if let Some(ref agent) = payload.agent {
+    if let Some(ref agent) = agent {
         if let Some(ref custom_env) = agent.env_vars {
             for (key, value) in custom_env {
-                env_vars.push(format!("{}={}", key, value));
+                agent_env.push((key.clone(), value.clone()));
             }
         }
     }
+    if let Err(e) = SecretResolver::new(Some(&state.project_root)).resolve_env(&mut agent_env) {
+        return axum::Json(super::session::StartSessionResponse {
+            success: false,
+            session_id: payload.session_id,
+            container_id: None,
+            message: Some(format!("Failed to resolve agent secrets: {}", e)),
+        });
+    }
+    env_vars.extend(agent_env.into_iter().map(|(key, value)| format!("{}={}", key, value)));
 
     let container_name = format!("ckrv-term-{}", uuid::Uuid::new_v4());
     
+    let user_mapping = runtime.user_mapping();
+    env_vars.extend(user_mapping.env);
     let config = bollard::container::Config {
         image: Some("ckrv-agent:latest".to_string()),
         cmd: Some(vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()]),
         working_dir: Some("/workspace".to_string()),
+        user: user_mapping.user,
         env: Some(env_vars),
         host_config: Some(bollard::models::HostConfig {
             binds: Some(binds),
             network_mode: Some("host".to_string()),
+            userns_mode: user_mapping.userns_mode,
             ..Default::default()
         }),
         tty: Some(true),
@@ -211,7 +234,7 @@ pub async fn start_terminal_session(
 }
 
 /// Handle the WebSocket connection for interactive terminal
-async fn handle_terminal(socket: WebSocket, _state: AppState, session_id: String) {
+async fn handle_terminal(socket: WebSocket, state: AppState, session_id: String) {
     // Look up container
     let container_id = {
         let sessions = TERMINAL_SESSIONS.lock().unwrap();
@@ -228,7 +251,9 @@ async fn handle_terminal(socket: WebSocket, _state: AppState, session_id: String
     };
 
     // Connect to Docker
-    let docker = match Docker::connect_with_local_defaults() {
+    let docker = match state.container_runtime().map_err(|e| e.to_string())
+        .and_then(|runtime| runtime.connect().map_err(|e| e.to_string()))
+    {
         Ok(d) => d,
         Err(e) => {
             let (mut sender, _) = socket.split();
@@ -338,6 +363,7 @@ async fn handle_terminal(socket: WebSocket, _state: AppState, session_id: String
 
 /// Stop a terminal session
 pub async fn stop_terminal_session(
+    State(state): State<AppState>,
     axum::Json(payload): axum::Json<super::session::StopSessionRequest>,
 ) -> impl IntoResponse {
     // Remove from store
@@ -357,7 +383,9 @@ pub async fn stop_terminal_session(
     };
 
     // Stop and remove container
-    let docker = match Docker::connect_with_local_defaults() {
+    let docker = match state.container_runtime().map_err(|e| e.to_string())
+        .and_then(|runtime| runtime.connect().map_err(|e| e.to_string()))
+    {
         Ok(d) => d,
         Err(e) => {
             return axum::Json(super::session::StopSessionResponse {
@@ -76,6 +76,38 @@ impl CommandService {
         }
     }
 
+    /// Run the ckrv binary, relaying stderr to the hub line by line as it
+    /// arrives (progress and streamed model output) while collecting stdout.
+    async fn output_relaying_stderr(
+        state: &AppState,
+        exe: &std::path::Path,
+        args: &[&str],
+        cwd: &std::path::Path,
+    ) -> std::io::Result<std::process::Output> {
+        let mut child = tokio::process::Command::new(exe)
+            .args(args)
+            .current_dir(cwd)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stderr_handle = child.stderr.take().map(|stderr| {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    Self::emit_stderr_line(&state, &line);
+                }
+            })
+        });
+
+        let output = child.wait_with_output().await;
+        if let Some(handle) = stderr_handle {
+            let _ = handle.await;
+        }
+        output
+    }
+
     pub async fn run_init(state: &AppState) -> Result<String, String> {
         Self::emit_step_start(state, "Initialize Repository");
         Self::emit_log(state, "Starting repository initialization...");
@@ -300,10 +332,7 @@ impl CommandService {
 
         Self::emit_log(state, &format!("Running: {} {}", exe.display(), args.join(" ")));
 
-        let output = Command::new(&exe)
-            .args(&args)
-            .current_dir(&cwd)
-            .output();
+        let output = Self::output_relaying_stderr(state, &exe, &args, &cwd).await;
 
         // Reset mode
         {
@@ -314,7 +343,6 @@ impl CommandService {
         match output {
             Ok(result) => {
                 let stdout = String::from_utf8_lossy(&result.stdout);
-                let stderr = String::from_utf8_lossy(&result.stderr);
 
                 // Log any output
                 if !stdout.is_empty() {
@@ -323,12 +351,6 @@ impl CommandService {
                     }
                 }
 
-                if !stderr.is_empty() {
-                    for line in stderr.lines() {
-                        Self::emit_stderr_line(state, line);
-                    }
-                }
-
                 if result.status.success() {
                     // Try to parse JSON output for better messaging
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(&stdout) {
@@ -398,10 +420,7 @@ impl CommandService {
 
         Self::emit_log(state, &format!("Running: {} {}", exe.display(), args.join(" ")));
 
-        let output = Command::new(&exe)
-            .args(&args)
-            .current_dir(&cwd)
-            .output();
+        let output = Self::output_relaying_stderr(state, &exe, &args, &cwd).await;
 
         // Reset mode
         {
@@ -412,7 +431,6 @@ impl CommandService {
         match output {
             Ok(result) => {
                 let stdout = String::from_utf8_lossy(&result.stdout);
-                let stderr = String::from_utf8_lossy(&result.stderr);
 
                 // Log any output
                 if !stdout.is_empty() {
@@ -421,12 +439,6 @@ impl CommandService {
                     }
                 }
 
-                if !stderr.is_empty() {
-                    for line in stderr.lines() {
-                        Self::emit_stderr_line(state, line);
-                    }
-                }
-
                 if result.status.success() {
                     // Try to parse JSON output
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(&stdout) {
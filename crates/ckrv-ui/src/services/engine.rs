@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow};
@@ -11,11 +12,16 @@ use tokio::process::Command as AsyncCommand;
 use chrono::Utc;
 
 use ckrv_git::{WorktreeManager, DefaultWorktreeManager};
-use ckrv_sandbox::{DockerSandbox, ExecuteConfig, Sandbox, DefaultAllowList};
+use ckrv_core::runner::{RunnerConfig, WorkflowRunner};
+use ckrv_core::{AgentProfile, AgentTask, AgentType, AgentsFile, Config, EventHandler, FailureKind, JobEvent};
+use ckrv_sandbox::{ContainerRuntime, DockerSandbox, ExecuteConfig, OutputStream, Sandbox, DefaultAllowList};
 
 use crate::services::history::HistoryService;
 use crate::models::history::{Run, RunStatus, HistoryBatchStatus};
 
+/// Timeout for a batch run in the Docker sandbox (15 minutes).
+const SANDBOX_TIMEOUT: Duration = Duration::from_secs(900);
+
 /// Status of a batch in the execution plan
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -61,6 +67,9 @@ pub struct Batch {
     pub status: BatchStatus,
     #[serde(default)]
     pub branch: Option<String>,
+    /// Agent that produced this batch, after any fallbacks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
     pub reasoning: String,
     pub model_assignment: ModelAssignment,
 }
@@ -354,7 +363,7 @@ impl ExecutionEngine {
                      let _ = self.sender.send(LogMessage::batch_status(&batch.id, &batch.name, "running")).await;
                      
                      // Update status to running in plan file
-                     self.update_batch_status(&plan_path, &batch.id, BatchStatus::Running, None)?;
+                     self.update_batch_status(&plan_path, &batch.id, BatchStatus::Running, None, None)?;
 
                      let batch_clone = batch.clone();
                      let task_map_clone = task_map.clone();
@@ -388,9 +397,12 @@ impl ExecutionEngine {
                  match result {
                      Ok(batch_result) => {
                          match batch_result {
-                             Ok((batch_id, branch_name)) => {
+                             Ok((batch_id, branch_name, agent)) => {
                                  // Batch succeeded
                                  self.log("batch_complete", &format!("Batch {} completed on branch {}", batch_id, branch_name)).await;
+                                 if let Some(ref agent) = agent {
+                                     self.log("info", &format!("Batch {} produced by agent '{}'", batch_id, agent)).await;
+                                 }
                                  
                                  // T012: Send explicit batch status so frontend updates counter
                                  let _ = self.sender.send(
@@ -410,7 +422,7 @@ impl ExecutionEngine {
                                          self.mark_tasks_complete(&tasks_path, tids)?;
                                      }
                                      
-                                     self.update_batch_status(&plan_path, &batch_id, BatchStatus::Completed, Some(&branch_name))?;
+                                     self.update_batch_status(&plan_path, &batch_id, BatchStatus::Completed, Some(&branch_name), agent.as_deref())?;
                                  }
                                  
                                  // T017: Update history with batch completion
@@ -487,7 +499,7 @@ impl ExecutionEngine {
         use_sandbox: bool, // NEW: Use Docker sandbox for execution
         executor_model: Option<String>,
         sender: mpsc::Sender<LogMessage>,
-    ) -> Result<(String, String)> { // Returns (batch_id, branch_name)
+    ) -> Result<(String, String, Option<String>)> { // Returns (batch_id, branch_name, agent)
         
         // Construct description
         let mut description = format!("MISSION: {}\nREASONING: {}\n\nTASKS:\n", batch.name, batch.reasoning);
@@ -500,7 +512,7 @@ impl ExecutionEngine {
         if dry_run {
             // Simulate delay
             tokio::time::sleep(Duration::from_millis(500)).await;
-            return Ok((batch.id, "dry-run-branch".to_string()));
+            return Ok((batch.id, "dry-run-branch".to_string(), None));
         }
 
         // Create Worktree using spawn_blocking to avoid blocking the async runtime
@@ -541,110 +553,93 @@ impl ExecutionEngine {
             task_args.push(m.clone());
         }
 
+        let agent_used;
         if use_sandbox {
             // Docker sandbox execution using Claude Code CLI
             let _ = sender.send(LogMessage::new("info", "Executing in Docker sandbox with Claude Code...")).await;
             
-            // Determine if this is an OpenRouter model or native Claude
-            let is_openrouter = model.as_ref().map(|m| {
-                m.contains('/') && !m.starts_with("claude")
-            }).unwrap_or(false);
+            // Resolve the agent the same way `ckrv task --agent` does; the
+            // runner falls back through its chain when it fails
+            let agents = AgentsFile::load_for_project(&root).unwrap_or_default();
+            let configured = match model.as_deref() {
+                Some(m) => agents.by_model(m),
+                None => agents.default_agent(),
+            };
+            let profile = configured.map_or_else(
+                || Self::adhoc_profile(model.as_deref()),
+                |agent| agents.profile(agent),
+            );
+            let container_runtime = Config::load_project(&root)
+                .context("Failed to load project config")?
+                .container_runtime;
             
-            // Try to create Docker sandbox, fall back to local if unavailable
-            match DockerSandbox::with_defaults() {
-                Ok(sandbox) => {
-                    // Build the Claude Code command
-                    // Use --print and --dangerously-skip-permissions for non-interactive execution
+            // Check the sandbox can be created, fall back to local if unavailable
+            let runtime = ContainerRuntime::resolve(&container_runtime);
+            match DockerSandbox::with_runtime(DefaultAllowList::default(), &runtime) {
+                Ok(_) => {
                     let claude_prompt = format!(
                         "You are implementing code changes in a project. Follow these instructions exactly:\n\n{}\n\nMake all changes to the files in /workspace. Do not ask questions - implement the code directly.",
                         description
                     );
-                    
-                    let escaped_prompt = claude_prompt.replace("\"", "\\\"");
-                    
-                    // Base command is the same for both paths
-                    let cmd = format!(
-                        "claude --print --dangerously-skip-permissions \"{}\"",
-                        escaped_prompt
-                    );
-                    
-                    let mut cfg = ExecuteConfig::new(
-                        "claude",
-                        worktree.path.clone()
-                    ).shell(&cmd)
-                     .with_timeout(Duration::from_secs(900)); // 15 minute timeout
-                    
-                    // Configure execution with claude CLI
-                    let config = if is_openrouter {
-                        // OpenRouter path: Use Claude Code CLI with OpenRouter env vars
-                        // Per https://openrouter.ai/docs/guides/guides/claude-code-integration
-                        let model_name = model.as_ref().unwrap();
-                        let _ = sender.send(LogMessage::new("info", &format!("Using OpenRouter model: {}", model_name))).await;
-                        
-                        // Get OpenRouter API key from environment or agent config
-                        let api_key = if let Ok(key) = std::env::var("OPENROUTER_API_KEY") {
-                            Some(key)
-                        } else {
-                            let agents_dir = root.join(".agents");
-                            Self::find_openrouter_key(&agents_dir, model_name)
-                        };
-                        
-                        if let Some(key) = api_key {
-                            // Required env vars for OpenRouter (same as runner.rs)
-                            cfg = cfg.env("ANTHROPIC_BASE_URL", "https://openrouter.ai/api");
-                            cfg = cfg.env("ANTHROPIC_AUTH_TOKEN", key);
-                            cfg = cfg.env("ANTHROPIC_API_KEY", ""); // Must be explicitly empty!
-                            
-                            // Set model for all tiers
-                            cfg = cfg.env("ANTHROPIC_DEFAULT_SONNET_MODEL", model_name);
-                            cfg = cfg.env("ANTHROPIC_DEFAULT_OPUS_MODEL", model_name);
-                            cfg = cfg.env("ANTHROPIC_DEFAULT_HAIKU_MODEL", model_name);
-                        } else {
-                            let _ = sender.send(LogMessage::new("warning", "No OPENROUTER_API_KEY found, execution may fail")).await;
-                        }
-                        
-                        cfg
-                    } else {
-                        // Claude subscription path: Use native Claude Code auth via ~/.claude
-                        let _ = sender.send(LogMessage::new("info", "Using Claude subscription")).await;
-                        cfg
+                    let _ = sender.send(LogMessage::new("info", &format!(
+                        "Using agent '{}' ({}{})",
+                        profile.id,
+                        profile.agent_type.backend().name(),
+                        profile.model.as_deref().map(|m| format!(", model {m}")).unwrap_or_default()
+                    ))).await;
+                    if profile.agent_type == AgentType::ClaudeOpenRouter && !profile.uses_openrouter() {
+                        let _ = sender.send(LogMessage::new("warning", "No OPENROUTER_API_KEY found, execution may fail")).await;
+                    }
+
+                    let config = RunnerConfig {
+                        step_timeout_secs: SANDBOX_TIMEOUT.as_secs(),
+                        use_sandbox: true,
+                        agents: agents.profiles(),
+                        project_root: Some(root.clone()),
+                        container_runtime,
+                        ..Default::default()
                     };
-                    
-                    // Set HOME for Claude Code config
-                    let config = config.env("HOME", "/home/claude");
-                    let config = config.env("NO_COLOR", "1");
-                    
-                    // Execute in sandbox
-                    match sandbox.execute(config).await {
-                        Ok(result) => {
-                            // Log stdout
-                            for line in result.stdout.lines() {
-                                let _ = sender.send(LogMessage::new("log", line)).await;
-                            }
-                            // Log stderr
-                            for line in result.stderr.lines() {
-                                let _ = sender.send(LogMessage::new("error", line)).await;
-                            }
-                            
-                            if !result.success() {
-                                return Err(anyhow!("Claude Code execution failed with exit code {}", result.exit_code));
-                            }
-                        }
+                    let (forwarder, forwarding) = LogForwarder::spawn(sender.clone());
+                    let runner = WorkflowRunner::new(config)
+                        .with_event_handler(Arc::new(forwarder));
+                    let result = runner
+                        .invoke(&profile, &batch.id, &claude_prompt, &worktree.path)
+                        .await;
+                    // Let queued agent output reach the log before anything after it
+                    drop(runner);
+                    if let Err(e) = forwarding.await {
+                        let _ = sender.send(LogMessage::new("warning", &format!("Agent output forwarding stopped: {e}"))).await;
+                    }
+                    let result = match result {
+                        Ok(result) => result,
                         Err(e) => {
                             let _ = sender.send(LogMessage::new("error", &format!("Sandbox execution error: {}", e))).await;
                             return Err(anyhow!("Sandbox execution failed: {}", e));
                         }
+                    };
+                    if result.timed_out {
+                        return Err(anyhow!("Claude Code execution timed out after {}s", SANDBOX_TIMEOUT.as_secs()));
+                    }
+                    if !result.success {
+                        return Err(anyhow!(
+                            "Agent '{}' failed: {}",
+                            result.agent.id,
+                            result.failure.unwrap_or(FailureKind::Crash)
+                        ));
                     }
+                    agent_used = Some(result.agent.id);
                 }
                 Err(e) => {
                     // Fall back to local execution if Docker is not available
                     let _ = sender.send(LogMessage::new("warning", &format!("Docker unavailable ({}), falling back to local execution", e))).await;
                     Self::execute_local(&exe, &task_args, &sender).await?;
+                    agent_used = Self::task_agent(&root, &batch_run_id);
                 }
             }
         } else {
             // Local execution (no sandbox) - uses ckrv task
             Self::execute_local(&exe, &task_args, &sender).await?;
+            agent_used = Self::task_agent(&root, &batch_run_id);
         }
         
         // Commit changes inside the worktree
@@ -659,7 +654,7 @@ impl ExecutionEngine {
             .current_dir(&worktree.path)
             .status().await?;
 
-        Ok((batch.id, branch_name))
+        Ok((batch.id, branch_name, agent_used))
     }
     
     /// Execute command locally (no sandbox)
@@ -778,7 +773,11 @@ impl ExecutionEngine {
 
         // Run Claude Code in Sandbox
         // We use ckrv-sandbox here
-        let sandbox = DockerSandbox::new(DefaultAllowList::default())
+        let container_runtime = Config::load_project(&self.project_root)
+            .context("Failed to load project config")?
+            .container_runtime;
+        let runtime = ContainerRuntime::resolve(&container_runtime);
+        let sandbox = DockerSandbox::with_runtime(DefaultAllowList::default(), &runtime)
              .context("Failed to create sandbox")?;
              
         let escaped_prompt = shell_escape::escape(prompt.into());
@@ -811,7 +810,7 @@ impl ExecutionEngine {
         Ok(())
     }
 
-    fn update_batch_status(&self, plan_path: &Path, batch_id: &str, status: BatchStatus, branch: Option<&str>) -> Result<()> {
+    fn update_batch_status(&self, plan_path: &Path, batch_id: &str, status: BatchStatus, branch: Option<&str>, agent: Option<&str>) -> Result<()> {
         let content = std::fs::read_to_string(plan_path)?;
         let mut plan: ExecutionPlan = serde_yaml::from_str(&content)?;
         
@@ -821,6 +820,9 @@ impl ExecutionEngine {
                 if let Some(b) = branch {
                     batch.branch = Some(b.to_string());
                 }
+                if let Some(a) = agent {
+                    batch.agent = Some(a.to_string());
+                }
             }
         }
         
@@ -849,61 +851,72 @@ impl ExecutionEngine {
         Ok(())
     }
     
-    /// Find OpenRouter API key from agent config files
-    /// Checks global config at ~/.config/chakravarti/agents.yaml
-    fn find_openrouter_key(_agents_dir: &Path, model: &str) -> Option<String> {
-        // Agent config structures matching the actual format
-        #[derive(serde::Deserialize)]
-        struct AgentsFile {
-            agents: Vec<AgentEntry>,
-        }
-        
-        #[derive(serde::Deserialize)]
-        struct AgentEntry {
-            #[allow(dead_code)]
-            id: String,
-            #[allow(dead_code)]
-            agent_type: String,
-            openrouter: Option<OpenRouterConfig>,
-        }
-        
-        #[derive(serde::Deserialize)]
-        struct OpenRouterConfig {
-            api_key: Option<String>,
-            model: Option<String>,
+    /// Agent that `ckrv task` recorded as producing the batch run, after any
+    /// fallbacks.
+    fn task_agent(root: &Path, batch_run_id: &str) -> Option<String> {
+        AgentTask::load(root, batch_run_id).ok().and_then(|task| task.agent)
+    }
+
+    /// Profile for a model string that no configured agent claims.
+    ///
+    /// Model IDs like `minimax/minimax-m2.1` run Claude Code through
+    /// `OpenRouter` with the key from `OPENROUTER_API_KEY`; anything else runs
+    /// Claude Code on its own subscription.
+    fn adhoc_profile(model: Option<&str>) -> AgentProfile {
+        match model {
+            Some(m) if m.contains('/') && !m.starts_with("claude") => {
+                AgentProfile::new(m, "claude")
+                    .with_agent_type(AgentType::ClaudeOpenRouter)
+                    .with_model(m)
+                    .with_openrouter(std::env::var("OPENROUTER_API_KEY").ok(), None)
+            }
+            _ => AgentProfile::new("claude", "claude"),
         }
-        
-        // Check global config path first (same as task.rs)
-        let agents_path = dirs::config_dir()
-            .map(|d| d.join("chakravarti").join("agents.yaml"))
-            .filter(|p| p.exists());
-        
-        if let Some(path) = agents_path {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                if let Ok(file) = serde_yaml::from_str::<AgentsFile>(&content) {
-                    // First try to find the specific agent matching the model
-                    for agent in &file.agents {
-                        if let Some(ref or) = agent.openrouter {
-                            if or.model.as_ref().map(|m| m == model).unwrap_or(false) {
-                                if let Some(ref key) = or.api_key {
-                                    return Some(key.clone());
-                                }
-                            }
-                        }
-                    }
-                    
-                    // If no specific match, return any OpenRouter key we find
-                    for agent in &file.agents {
-                        if let Some(ref or) = agent.openrouter {
-                            if let Some(ref key) = or.api_key {
-                                return Some(key.clone());
-                            }
-                        }
-                    }
+    }
+}
+
+/// Forwards a runner's agent output and fallbacks to the UI log.
+///
+/// Events queue without bound and are sent on in order by a task that waits
+/// for room in the log channel, so a slow UI delays lines rather than
+/// losing them.
+struct LogForwarder(mpsc::UnboundedSender<LogMessage>);
+
+impl LogForwarder {
+    /// Start forwarding to `sender`. The task ends once the forwarder is
+    /// dropped and its queue is drained.
+    fn spawn(sender: mpsc::Sender<LogMessage>) -> (Self, tokio::task::JoinHandle<()>) {
+        let (queue, mut pending) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            while let Some(msg) = pending.recv().await {
+                if sender.send(msg).await.is_err() {
+                    break;
                 }
             }
-        }
-        
-        None
+        });
+        (Self(queue), task)
+    }
+}
+
+impl EventHandler for LogForwarder {
+    fn handle(&self, event: JobEvent) {
+        let msg = match event {
+            JobEvent::AgentOutput { stream, line, .. } => {
+                let (type_, stream) = match stream {
+                    OutputStream::Stdout => ("log", "stdout"),
+                    OutputStream::Stderr => ("error", "stderr"),
+                };
+                let mut msg = LogMessage::new(type_, &line);
+                msg.stream = Some(stream.to_string());
+                msg
+            }
+            JobEvent::AgentFallback { from, to, failure, .. } => LogMessage::new(
+                "warning",
+                &format!("Agent '{from}' failed ({failure}), falling back to '{to}'"),
+            ),
+            _ => return,
+        };
+        // Fails only once the UI log has closed, with no one left to show it
+        self.0.send(msg).ok();
     }
 }
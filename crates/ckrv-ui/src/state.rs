@@ -20,6 +20,9 @@ pub enum SystemMode {
 use std::sync::Arc;
 use std::path::PathBuf;
 
+use ckrv_core::{Config, CoreError};
+use ckrv_sandbox::ContainerRuntime;
+
 #[derive(Clone)]
 pub struct AppState {
     pub status: Arc<tokio::sync::RwLock<SystemStatus>>,
@@ -27,6 +30,19 @@ pub struct AppState {
     pub project_root: PathBuf,
 }
 
+impl AppState {
+    /// The container runtime from the project's `config.json`, resolved
+    /// against this machine.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project config can't be loaded.
+    pub fn container_runtime(&self) -> Result<ContainerRuntime, CoreError> {
+        let config = Config::load_project(&self.project_root)?;
+        Ok(ContainerRuntime::resolve(&config.container_runtime))
+    }
+}
+
 impl Default for SystemStatus {
     fn default() -> Self {
         Self {